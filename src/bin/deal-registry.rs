@@ -0,0 +1,35 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+#![recursion_limit = "256"]
+// Coding conventions
+#![deny(
+    non_upper_case_globals,
+    non_camel_case_types,
+    non_snake_case,
+    unused_mut,
+    unused_imports,
+    dead_code,
+    missing_docs
+)]
+
+//! Main executable for deal-registry: reference HTTP registry the Farcaster nodes publish their
+//! open deals to.
+
+#[macro_use]
+extern crate log;
+
+use clap::Parser;
+
+use farcaster_node::registry::{self, Opts};
+
+fn main() {
+    let mut opts = Opts::parse();
+    trace!("Command-line arguments: {:?}", &opts);
+    opts.process();
+
+    registry::server::run(opts.listen).expect("Error running the deal registry");
+}
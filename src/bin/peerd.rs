@@ -85,15 +85,17 @@ extern crate amplify_derive;
 
 use clap::Parser;
 use internet2::addr::InetSocketAddr;
-use nix::unistd::{fork, ForkResult};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{fork, ForkResult, Pid};
 use std::convert::TryFrom;
 use std::net::TcpListener;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::time::Duration;
 
-use farcaster_node::peerd::{self, Opts};
-use farcaster_node::LogStyle;
+use farcaster_node::bus::ctl::CtlMsg;
+use farcaster_node::peerd::{self, InboundLimiter, InboundLimits, Opts};
 use farcaster_node::ServiceConfig;
+use farcaster_node::{Error, LogStyle, ServiceId};
 use internet2::addr::NodeAddr;
 use internet2::session;
 use microservices::peer::PeerConnection;
@@ -158,11 +160,16 @@ fn main() {
 
     let mut local_socket: Option<InetSocketAddr> = None;
     let remote_node_addr: Option<NodeAddr> = None;
-    let connection = match peer_socket {
+    let (connection, remote_static) = match peer_socket {
         PeerSocket::Listen(inet_addr) => {
             debug!("Running in LISTEN mode");
 
             local_socket = Some(inet_addr);
+            let mut limiter = InboundLimiter::new(InboundLimits {
+                max_connections: opts.max_inbound_connections,
+                max_connections_per_ip: opts.max_inbound_connections_per_ip,
+                max_handshakes_per_minute: opts.max_handshakes_per_minute,
+            });
 
             debug!("Binding TCP socket {}", inet_addr);
             if let Ok(listener) = TcpListener::bind(
@@ -176,11 +183,28 @@ fn main() {
                         .expect("Error accepting incoming peer connection");
                     debug!("New connection from {}", remote_socket_addr);
 
+                    // Release the connections whose child process exited
+                    for pid in limiter.pids() {
+                        match waitpid(Pid::from_raw(pid), Some(WaitPidFlag::WNOHANG)) {
+                            Ok(WaitStatus::StillAlive) => {}
+                            _ => limiter.release(pid),
+                        }
+                    }
+                    if let Err(rejection) = limiter.admit(remote_socket_addr.ip()) {
+                        warn!(
+                            "Refusing connection from {}: {}",
+                            remote_socket_addr, rejection
+                        );
+                        drop(stream);
+                        continue;
+                    }
+
                     // TODO: Support multithread mode
                     debug!("Forking child process");
-                    if let ForkResult::Child =
-                        unsafe { fork().expect("Unable to fork child process") }
-                    {
+                    let fork_result = unsafe { fork().expect("Unable to fork child process") };
+                    if let ForkResult::Parent { child } = fork_result {
+                        limiter.register(child.as_raw(), remote_socket_addr.ip());
+                    } else {
                         stream
                             .set_read_timeout(Some(Duration::from_secs(30)))
                             .expect("Unable to set up timeout for TCP connection");
@@ -198,7 +222,44 @@ fn main() {
                             remote_socket_addr
                         );
 
-                        break PeerConnection::with(session);
+                        // The transport authenticated the static key of the remote node
+                        let remote_static = session.remote_id();
+                        match peerd::is_banned(&service_config, remote_static) {
+                            Ok(false) => {}
+                            Ok(true) => {
+                                warn!(
+                                    "Refusing connection from {}: peer {} is banned",
+                                    remote_socket_addr, remote_static
+                                );
+                                std::process::exit(0);
+                            }
+                            Err(err) => {
+                                error!(
+                                    "Unable to check whether peer {} is banned: {}",
+                                    remote_static, err
+                                );
+                                std::process::exit(1);
+                            }
+                        }
+                        // A single peerd process owns the connection to a remote node
+                        match peerd::claim_connection(&service_config, remote_static) {
+                            Ok(true) => {}
+                            Ok(false) => {
+                                warn!(
+                                    "Refusing connection from {}: peer {} is already connected",
+                                    remote_socket_addr, remote_static
+                                );
+                                std::process::exit(0);
+                            }
+                            Err(err) => {
+                                error!(
+                                    "Unable to claim the connection to peer {}: {}",
+                                    remote_static, err
+                                );
+                                std::process::exit(1);
+                            }
+                        }
+                        break (PeerConnection::with(session), remote_static);
                     }
                     debug!("Child forked; returning into main listener event loop");
                     continue;
@@ -211,8 +272,19 @@ fn main() {
         PeerSocket::Connect(remote_node) => {
             debug!("Peerd running in CONNECT mode");
             debug!("Connecting to {}", &remote_node.addr());
-            peerd::run_from_connect(service_config, remote_node, local_socket, local_node)
-                .expect("Error running peerd runtime");
+            if let Err(err) = peerd::run_from_connect(
+                service_config.clone(),
+                remote_node,
+                local_socket,
+                local_node,
+            ) {
+                exit_with_error(
+                    service_config,
+                    ServiceId::Peer(remote_node.id),
+                    CtlMsg::ConnectFailed,
+                    err,
+                );
+            }
             unreachable!()
         }
     };
@@ -228,14 +300,30 @@ fn main() {
         remote_node_addr: full internet2 remote node address
         local_socket: None
         connect: true */
-    peerd::run_from_listener(
-        service_config,
+    if let Err(err) = peerd::run_from_listener(
+        service_config.clone(),
         connection,
         remote_node_addr,
         local_socket,
         local_node,
-    )
-    .expect("Error running peerd runtime");
+        remote_static,
+    ) {
+        exit_with_error(
+            service_config,
+            ServiceId::Peer(remote_static),
+            CtlMsg::PeerdTerminated(std::process::id()),
+            err,
+        );
+    }
 
     unreachable!()
 }
+
+/// Logs the error the runtime stopped on, reports it to farcasterd and exits with a non-zero code
+fn exit_with_error(config: ServiceConfig, identity: ServiceId, report: CtlMsg, err: Error) -> ! {
+    error!("Error running peerd runtime: {}", err);
+    if let Err(err) = peerd::report_failure(config, identity, report) {
+        error!("Unable to report the failure to farcasterd: {}", err);
+    }
+    std::process::exit(1);
+}
@@ -13,7 +13,6 @@ use clap::Parser;
 
 use farcaster_node::cli::Opts;
 use farcaster_node::client::Client;
-use farcaster_node::LogStyle;
 use farcaster_node::ServiceConfig;
 use microservices::shell::Exec;
 
@@ -29,11 +28,12 @@ fn main() {
     debug!("CTL RPC socket {}", &service_config.ctl_endpoint);
 
     let mut client = Client::with(service_config).expect("Error initializing client");
+    client.set_output_format(opts.format);
 
     trace!("Executing command: {:?}", opts.command);
 
     if let Err(err) = opts.command.exec(&mut client) {
-        eprintln!("{} {}", "error:".err(), err.err());
+        client.report_error(&err);
         std::process::exit(1);
     }
 }
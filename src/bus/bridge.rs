@@ -10,6 +10,8 @@ use crate::bus::p2p::PeerMsg;
 use crate::bus::sync::SyncMsg;
 use crate::bus::ServiceId;
 
+use farcaster_core::swap::{btcxmr::Deal, SwapId};
+use farcaster_core::Uuid;
 use strict_encoding::{NetworkDecode, NetworkEncode};
 
 #[derive(Clone, Debug, Display, From, NetworkEncode, NetworkDecode)]
@@ -37,4 +39,21 @@ pub enum BridgeMsg {
     },
     #[display("Grpc Server Terminated")]
     GrpcServerTerminated,
+    #[display("Service Exited {service_id}")]
+    ServiceExited {
+        service_id: ServiceId,
+        code: Option<i32>,
+    },
+    #[display("Approval Expired {swap_id}")]
+    ApprovalExpired { swap_id: SwapId },
+    #[display("Deal Expired {deal}")]
+    DealExpired { deal: Deal },
+    #[display("Quote Timeout {request_id}")]
+    QuoteTimeout { request_id: Uuid },
+    #[display("Reference Price {price}")]
+    ReferencePrice { price: bitcoin::Amount },
+    #[display("Shutdown Timeout {shutdown_id} stage {stage}")]
+    ShutdownTimeout { shutdown_id: Uuid, stage: u8 },
+    #[display("Deal Gossip Refresh")]
+    DealGossipRefresh,
 }
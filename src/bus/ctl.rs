@@ -8,24 +8,29 @@ use std::fmt::{self, Debug};
 use std::io;
 use std::str::FromStr;
 
+use farcaster_core::bitcoin::timelock::CSVTimelock;
 use farcaster_core::blockchain::Network;
 use farcaster_core::consensus::{self, Decodable, Encodable};
 use farcaster_core::impl_strict_encoding;
 use farcaster_core::swap::btcxmr::KeyManager;
+use farcaster_core::trade::DealId;
+use farcaster_core::transaction::TxLabel;
 use farcaster_core::{
     blockchain::Blockchain,
     swap::btcxmr::{Deal, DealParameters, Parameters},
     swap::SwapId,
+    Uuid,
 };
 
 use bitcoin::secp256k1::SecretKey;
 use bitcoin::Transaction;
-use internet2::addr::{InetSocketAddr, NodeAddr};
+use internet2::addr::{InetSocketAddr, NodeAddr, NodeId};
 use strict_encoding::{NetworkDecode, NetworkEncode};
 
-use crate::bus::p2p::{PeerMsg, TakerCommit};
+use crate::bus::p2p::{QuoteRequest, TakerCommit, WatchtowerSwap};
 use crate::bus::{
-    AddressSecretKey, CheckpointEntry, DealInfo, Failure, OptionDetails, Outcome, Progress,
+    AddressSecretKey, CheckpointEntry, DealInfo, Failure, Misbehavior, OptionDetails, Outcome,
+    PeerBan, Progress, SignedDeal, StartedSwap,
 };
 use crate::swapd::CheckpointSwapd;
 use crate::syncerd::{Health, SweepAddressAddendum};
@@ -80,8 +85,10 @@ pub enum CtlMsg {
     #[display("peerd_reconnected({0})")]
     PeerdReconnected(ServiceId),
 
-    #[display("peerd_terminated()")]
-    PeerdTerminated,
+    /// Sent by a peerd process on termination with its process id, ignored if the process no
+    /// longer owns the connection
+    #[display("peerd_terminated({0})")]
+    PeerdTerminated(u32),
 
     #[display("disconnected")]
     Disconnected,
@@ -145,6 +152,12 @@ pub enum CtlMsg {
     #[from]
     Checkpoint(Checkpoint),
 
+    /// A message sent from database to swapd once its checkpoint is saved, swapd then sends the
+    /// receipts of the peer messages the checkpoint contains, and ShutdownReady if it was
+    /// preparing a shutdown
+    #[display("checkpoint_saved()")]
+    CheckpointSaved,
+
     #[display("remove_checkpoint")]
     RemoveCheckpoint(SwapId),
 
@@ -170,9 +183,6 @@ pub enum CtlMsg {
     #[display("funding_canceled({0})")]
     FundingCanceled(Blockchain),
 
-    #[display("failed peer message")]
-    FailedPeerMessage(PeerMsg),
-
     #[display("connect failed")]
     ConnectFailed,
 
@@ -181,6 +191,168 @@ pub enum CtlMsg {
 
     #[display("health_result({0})")]
     HealthResult(Health),
+
+    /// A message sent from swapd to farcasterd when a race-sensitive broadcast is withheld
+    /// because the syncer data cannot be trusted
+    #[display("stale_syncer_data({0})")]
+    StaleSyncerData(StaleSyncerData),
+
+    /// A message sent from swapd to farcasterd once the transactions protecting the locked funds
+    /// are signed, farcasterd registers them with the configured watchtowers
+    #[display("watchtower_register({0})")]
+    WatchtowerRegister(WatchtowerSwap),
+
+    /// A message sent from a watchtower farcasterd to database to persist a watched swap
+    #[display("set_watched_swap({0})")]
+    SetWatchedSwap(WatchedSwap),
+
+    #[display("remove_watched_swap({0})")]
+    RemoveWatchedSwap(SwapId),
+
+    /// A message sent from a watchtower farcasterd to database on startup, database replies with
+    /// a RestoreWatchedSwap message for each persisted watched swap
+    #[display("retrieve_watched_swaps()")]
+    RetrieveWatchedSwaps,
+
+    #[display("restore_watched_swap({0})")]
+    RestoreWatchedSwap(WatchedSwap),
+
+    /// A message sent from farcasterd to database to persist a swap registered with our
+    /// watchtowers, so it can still be revoked after a restart
+    #[display("set_watchtower_registration({0})")]
+    SetWatchtowerRegistration(WatchtowerSwap),
+
+    #[display("remove_watchtower_registration({0})")]
+    RemoveWatchtowerRegistration(SwapId),
+
+    /// A message sent from farcasterd to database on startup, database replies with a
+    /// RestoreWatchtowerRegistration message for each persisted registration
+    #[display("retrieve_watchtower_registrations()")]
+    RetrieveWatchtowerRegistrations,
+
+    #[display("restore_watchtower_registration({0})")]
+    RestoreWatchtowerRegistration(WatchtowerSwap),
+
+    /// A message sent from a client to farcasterd to register the swap with the configured
+    /// watchtowers again, using the transactions swapd handed over for it
+    #[display("register_with_watchtowers({0})")]
+    RegisterWithWatchtowers(SwapId),
+
+    /// A message sent from a client to farcasterd to revoke the swap from the configured
+    /// watchtowers
+    #[display("revoke_from_watchtowers({0})")]
+    RevokeFromWatchtowers(SwapId),
+
+    /// A message sent from farcasterd to the running swapds once a crashed syncer has been
+    /// relaunched, swapd re-registers its live tasks with the new syncer instance
+    #[display("syncer_restarted({0})")]
+    SyncerRestarted(Blockchain),
+
+    /// A message sent from a client to farcasterd to ban a remote node id, its connections are
+    /// dropped and it can't reconnect until unbanned
+    #[display("ban_peer({0})")]
+    BanPeer(NodeId),
+
+    #[display("unban_peer({0})")]
+    UnbanPeer(NodeId),
+
+    /// A message sent from farcasterd to database to persist a peer ban
+    #[display("set_peer_ban({0})")]
+    SetPeerBan(PeerBan),
+
+    #[display("remove_peer_ban({0})")]
+    RemovePeerBan(NodeId),
+
+    /// A message sent from farcasterd to database on startup, database replies with a
+    /// RestorePeerBan message for each persisted ban
+    #[display("retrieve_peer_bans()")]
+    RetrievePeerBans,
+
+    #[display("restore_peer_ban({0})")]
+    RestorePeerBan(PeerBan),
+
+    /// A message sent from peerd to farcasterd when the remote peer misbehaved, farcasterd adds
+    /// to the peer's ban score
+    #[display("peer_misbehaved({0})")]
+    PeerMisbehaved(Misbehavior),
+
+    /// A message sent from a client to farcasterd to accept a taker awaiting manual approval,
+    /// the other takers pending on the same deal are rejected
+    #[display("accept_taker({0})")]
+    AcceptTaker(SwapId),
+
+    /// A message sent from a client to farcasterd to reject a taker awaiting manual approval
+    #[display("reject_taker({0})")]
+    RejectTaker(SwapId),
+
+    /// A message sent from a client to farcasterd to add a deal template, deals are then issued
+    /// from it on the quote requests of the takers
+    #[display("add_deal_template({0})")]
+    AddDealTemplate(DealTemplate),
+
+    /// A message sent from a client to farcasterd to remove a deal template, the deals already
+    /// issued from it remain takeable until they expire
+    #[display("remove_deal_template({0})")]
+    RemoveDealTemplate(Uuid),
+
+    /// A message sent from a client to farcasterd to request a quote from a remote maker, the
+    /// client is answered with the quoted deal once the maker replied
+    #[display("request_quote({0})")]
+    RequestQuote(ProtoQuoteRequest),
+
+    /// A message sent from a client to farcasterd to turn the maintenance mode on or off, the
+    /// client is answered with the maintenance status
+    #[display("set_maintenance({0})")]
+    SetMaintenance(bool),
+
+    /// A message sent from a client to farcasterd to shut the node down once the running swaps
+    /// checkpointed, refused if a swap is inside a race window unless forced
+    #[display("shutdown(force: {0})")]
+    Shutdown(bool),
+
+    /// A message sent from farcasterd to the running swapds before a shutdown, swapd checkpoints
+    /// and replies ShutdownReady once database saved the checkpoint, or ShutdownRefused if inside
+    /// a race window and not forced
+    #[display("prepare_shutdown(force: {0})")]
+    PrepareShutdown(bool),
+
+    #[display("shutdown_ready()")]
+    ShutdownReady,
+
+    #[display("shutdown_refused({0})")]
+    ShutdownRefused(String),
+
+    /// A message sent from farcasterd to database to persist a swap counted in the daily volume
+    #[display("set_started_swap({0})")]
+    SetStartedSwap(StartedSwap),
+
+    #[display("remove_started_swap({0})")]
+    RemoveStartedSwap(DealId),
+
+    /// A message sent from farcasterd to database on startup, database replies with a
+    /// RestoreStartedSwap message for each persisted swap
+    #[display("retrieve_started_swaps()")]
+    RetrieveStartedSwaps,
+
+    #[display("restore_started_swap({0})")]
+    RestoreStartedSwap(StartedSwap),
+
+    /// A message sent from farcasterd to database to persist the maintenance mode across restarts
+    #[display("set_maintenance_mode({0})")]
+    SetMaintenanceMode(bool),
+
+    /// A message sent from farcasterd to database on startup, database replies with
+    /// RestoreMaintenanceMode
+    #[display("retrieve_maintenance_mode()")]
+    RetrieveMaintenanceMode,
+
+    #[display("restore_maintenance_mode({0})")]
+    RestoreMaintenanceMode(bool),
+
+    /// A message sent by a service to farcasterd right before exiting on Terminate, farcasterd
+    /// waits for it to stop the next services on shutdown
+    #[display("terminated()")]
+    Terminated,
 }
 
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
@@ -198,16 +370,179 @@ pub struct ProtoDeal {
     pub public_addr: InetSocketAddr,
     pub arbitrating_addr: bitcoin::Address,
     pub accordant_addr: monero::Address,
+    pub approval: DealApproval,
+    /// Seconds the deal remains takeable, the deal is shared signed and automatically revoked
+    /// once expired if set
+    pub lifetime: Option<u64>,
+}
+
+/// How the maker accepts the takers committing to one of its deals
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+pub enum DealApproval {
+    /// Any taker is accepted
+    #[display("auto")]
+    Auto,
+    /// Only the takers with one of the listed node ids are accepted
+    #[display("allowlist")]
+    Allowlist(Vec<NodeId>),
+    /// Takers are held until accepted or rejected by the user
+    #[display("manual")]
+    Manual,
+}
+
+/// A maker deal template: deals are issued on demand for the arbitrating amount requested by a
+/// taker, at the price of the template and within its amount and timelock bounds
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{id}")]
+pub struct DealTemplate {
+    pub id: Uuid,
+    /// Parameters of the largest deal the template issues, the quoted deals are scaled down
+    /// from it at the same price and use its timelocks unless the taker requests others
+    pub deal_parameters: DealParameters,
+    pub min_arbitrating_amount: bitcoin::Amount,
+    pub min_cancel_timelock: u32,
+    pub max_cancel_timelock: u32,
+    pub min_punish_timelock: u32,
+    pub max_punish_timelock: u32,
+    /// Total arbitrating amount the template can issue deals for, unlimited if not set
+    pub inventory: Option<bitcoin::Amount>,
+    pub public_addr: InetSocketAddr,
+    pub arbitrating_addr: bitcoin::Address,
+    pub accordant_addr: monero::Address,
+}
+
+impl DealTemplate {
+    /// The price of the template in BTC per XMR
+    pub fn price(&self) -> f64 {
+        self.deal_parameters.arbitrating_amount.as_btc()
+            / self.deal_parameters.accordant_amount.as_xmr()
+    }
+
+    /// Accordant amount exchanged for the arbitrating amount at the price of the template
+    pub fn accordant_amount(&self, arbitrating_amount: bitcoin::Amount) -> monero::Amount {
+        let params = &self.deal_parameters;
+        monero::Amount::from_pico(
+            (arbitrating_amount.as_sat() as u128 * params.accordant_amount.as_pico() as u128
+                / params.arbitrating_amount.as_sat() as u128) as u64,
+        )
+    }
+
+    /// Checks the bounds of the template are consistent with its deal parameters
+    pub fn validate(&self) -> Result<(), Error> {
+        let params = &self.deal_parameters;
+        let cancel_timelock = params.cancel_timelock.as_u32();
+        let punish_timelock = params.punish_timelock.as_u32();
+        if self.min_arbitrating_amount.as_sat() == 0
+            || self.min_arbitrating_amount > params.arbitrating_amount
+        {
+            Err(Error::Farcaster(format!(
+                "The minimum amount {} must be positive and at most the maximum amount {}",
+                self.min_arbitrating_amount, params.arbitrating_amount
+            )))
+        } else if params.accordant_amount.as_pico() == 0 {
+            Err(Error::Farcaster(
+                "The accordant amount must be positive".to_string(),
+            ))
+        } else if cancel_timelock < self.min_cancel_timelock
+            || cancel_timelock > self.max_cancel_timelock
+        {
+            Err(Error::Farcaster(format!(
+                "The cancel timelock {} is out of the bounds [{}, {}]",
+                cancel_timelock, self.min_cancel_timelock, self.max_cancel_timelock
+            )))
+        } else if punish_timelock < self.min_punish_timelock
+            || punish_timelock > self.max_punish_timelock
+        {
+            Err(Error::Farcaster(format!(
+                "The punish timelock {} is out of the bounds [{}, {}]",
+                punish_timelock, self.min_punish_timelock, self.max_punish_timelock
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Parameters of the deal quoted for the request, fails if the request is out of the bounds
+    /// of the template
+    pub fn quote(&self, request: &QuoteRequest) -> Result<DealParameters, Error> {
+        let params = &self.deal_parameters;
+        let amount = request.arbitrating_amount;
+        let cancel_timelock = request
+            .cancel_timelock
+            .unwrap_or_else(|| params.cancel_timelock.as_u32());
+        let punish_timelock = request
+            .punish_timelock
+            .unwrap_or_else(|| params.punish_timelock.as_u32());
+        if request.network != params.network {
+            return Err(Error::Farcaster(format!(
+                "Template {} is not on {}",
+                self.id, request.network
+            )));
+        }
+        if amount < self.min_arbitrating_amount || amount > params.arbitrating_amount {
+            return Err(Error::Farcaster(format!(
+                "Amount {} is out of the range [{}, {}]",
+                amount, self.min_arbitrating_amount, params.arbitrating_amount
+            )));
+        }
+        if cancel_timelock < self.min_cancel_timelock
+            || cancel_timelock > self.max_cancel_timelock
+            || punish_timelock < self.min_punish_timelock
+            || punish_timelock > self.max_punish_timelock
+        {
+            return Err(Error::Farcaster(format!(
+                "Timelocks {}/{} are out of the bounds [{}, {}]/[{}, {}]",
+                cancel_timelock,
+                punish_timelock,
+                self.min_cancel_timelock,
+                self.max_cancel_timelock,
+                self.min_punish_timelock,
+                self.max_punish_timelock
+            )));
+        }
+        Ok(DealParameters {
+            uuid: Uuid::new().into(),
+            arbitrating_amount: amount,
+            accordant_amount: self.accordant_amount(amount),
+            cancel_timelock: CSVTimelock::new(cancel_timelock),
+            punish_timelock: CSVTimelock::new(punish_timelock),
+            ..params.clone()
+        })
+    }
+}
+
+/// A quote request for a remote maker
+#[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{request}, {maker}")]
+pub struct ProtoQuoteRequest {
+    pub maker: NodeAddr,
+    pub request: QuoteRequest,
 }
 
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
 #[display("{deal}, ..")]
 pub struct PubDeal {
     pub deal: Deal,
+    pub origin: DealOrigin,
     pub bitcoin_address: bitcoin::Address,
     pub monero_address: monero::Address,
 }
 
+/// Where a taken deal comes from, farcasterd verifies the deal accordingly before connecting to
+/// its maker
+#[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+pub enum DealOrigin {
+    /// An encoded deal, only checked against the revocations gossiped by its maker
+    #[display("encoded")]
+    Encoded,
+    /// A deal signed by its maker, the signature must match the deal and not be expired
+    #[display("signed")]
+    Signed(SignedDeal),
+    /// A deal announced by a remote maker, the announcement must still be known and not expired
+    #[display("announced")]
+    Announced,
+}
+
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
 #[display("{0}, ..")]
 pub struct ReconnectPeer(pub NodeAddr, pub Option<SecretKey>);
@@ -406,6 +741,21 @@ pub enum Tx {
     Punish(Transaction),
 }
 
+#[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{blockchain} syncer data is stale, withholding {tx_label}: {reason}")]
+pub struct StaleSyncerData {
+    pub blockchain: Blockchain,
+    pub tx_label: TxLabel,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{swap} for {client}")]
+pub struct WatchedSwap {
+    pub client: NodeId,
+    pub swap: WatchtowerSwap,
+}
+
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
 #[display("taker_commited")]
 pub struct TakerCommitted {
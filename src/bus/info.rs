@@ -10,14 +10,20 @@ use std::time::Duration;
 use amplify::ToYamlString;
 use farcaster_core::role::{SwapRole, TradeRole};
 use farcaster_core::trade::DealId;
-use farcaster_core::{blockchain::Blockchain, swap::btcxmr::Deal, swap::SwapId};
-use internet2::addr::{InetSocketAddr, NodeAddr, NodeId};
+use farcaster_core::{
+    blockchain::{Blockchain, Network},
+    swap::btcxmr::Deal,
+    swap::SwapId,
+    transaction::TxLabel,
+    Uuid,
+};
+use internet2::addr::{InetSocketAddr, NodeId};
 #[cfg(feature = "serde")]
 use serde_with::{DisplayFromStr, DurationSeconds};
 use strict_encoding::{NetworkDecode, NetworkEncode};
 
 use crate::bus::{
-    AddressSecretKey, CheckpointEntry, DealInfo, Failure, List, OptionDetails, Progress,
+    AddressSecretKey, CheckpointEntry, DealInfo, Failure, List, OptionDetails, PeerBan, Progress,
 };
 use crate::cli::DealSelector;
 use crate::farcasterd::stats::Stats;
@@ -52,6 +58,32 @@ pub enum InfoMsg {
     #[display("list_listens()")]
     ListListens,
 
+    #[display("list_bans()")]
+    ListBans,
+
+    /// Sent by a listener-forked peerd process before registering the connection to the remote
+    /// node, refused while another live peerd process owns it
+    #[display("claim_connection({0}, {1})")]
+    ClaimConnection(NodeId, u32),
+
+    #[display("list_approvals()")]
+    ListApprovals,
+
+    #[display("list_remote_deals()")]
+    ListRemoteDeals,
+
+    #[display("list_deal_templates()")]
+    ListDealTemplates,
+
+    #[display("get_market_maker()")]
+    GetMarketMaker,
+
+    #[display("get_reference_price()")]
+    GetReferencePrice,
+
+    #[display("query_deal_registry({0})")]
+    QueryDealRegistry(RegistryQuery),
+
     #[display("retrieve_all_checkpoint_info")]
     RetrieveAllCheckpointInfo,
 
@@ -104,6 +136,9 @@ pub enum InfoMsg {
     #[display(inner)]
     TookDeal(TookDeal),
 
+    #[display(inner)]
+    QuotedDeal(QuotedDeal),
+
     // - GetInfo section
     #[display("syncer_info(..)")]
     #[from]
@@ -125,7 +160,7 @@ pub enum InfoMsg {
     // - ListPeers section
     #[display(inner)]
     #[from]
-    PeerList(List<NodeAddr>),
+    PeerList(List<NodeId>),
     // - End ListPeers section
 
     // - ListSwap section
@@ -154,6 +189,58 @@ pub enum InfoMsg {
     #[from]
     ListenList(List<String>),
     // - End ListListen section
+
+    // - ListBans section
+    #[display(inner)]
+    #[from]
+    PeerBanList(List<PeerBan>),
+    // - End ListBans section
+
+    // - ClaimConnection section
+    #[display("connection_claim({0})")]
+    ConnectionClaim(bool),
+    // - End ClaimConnection section
+
+    // - ListApprovals section
+    #[display(inner)]
+    #[from]
+    ApprovalList(List<PendingApproval>),
+    // - End ListApprovals section
+
+    // - ListRemoteDeals section
+    #[display(inner)]
+    #[from]
+    RemoteDealList(List<RemoteDealInfo>),
+    // - End ListRemoteDeals section
+
+    // - QueryDealRegistry section
+    #[display(inner)]
+    RegistryDealList(List<RemoteDealInfo>),
+    // - End QueryDealRegistry section
+
+    // - ListDealTemplates section
+    #[display(inner)]
+    #[from]
+    DealTemplateList(List<DealTemplateInfo>),
+    // - End ListDealTemplates section
+
+    // - GetMarketMaker section
+    #[display(inner)]
+    #[from]
+    MarketMakerInfo(MarketMakerInfo),
+    // - End GetMarketMaker section
+
+    // - GetReferencePrice section
+    #[display(inner)]
+    #[from]
+    ReferencePrice(ReferencePrice),
+    // - End GetReferencePrice section
+
+    // - SetMaintenance section
+    #[display(inner)]
+    #[from]
+    MaintenanceStatus(MaintenanceStatus),
+    // - End SetMaintenance section
     #[display(inner)]
     CheckpointList(List<CheckpointEntry>),
 
@@ -231,6 +318,213 @@ pub struct TookDeal {
     pub message: String,
 }
 
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(PendingApproval::to_yaml_string)]
+pub struct PendingApproval {
+    pub swap_id: SwapId,
+    pub deal_id: DealId,
+    pub taker: NodeId,
+    pub since: u64,
+}
+
+/// A deal template of this node and the arbitrating amount it can still issue deals for
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(DealTemplateInfo::to_yaml_string)]
+pub struct DealTemplateInfo {
+    #[serde_as(as = "DisplayFromStr")]
+    pub template_id: Uuid,
+    #[serde_as(as = "DisplayFromStr")]
+    pub network: Network,
+    pub maker_role: SwapRole,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub min_amount: bitcoin::Amount,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub max_amount: bitcoin::Amount,
+    pub price: String,
+    pub cancel_timelock: String,
+    pub punish_timelock: String,
+    /// Remaining arbitrating amount, unlimited if not set
+    #[serde(with = "bitcoin::util::amount::serde::as_btc::opt")]
+    pub available: Option<bitcoin::Amount>,
+    pub issued_deals: u64,
+}
+
+/// State of the market maker, its inventory is used by the open deals and by the swaps of the
+/// taken deals
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(MarketMakerInfo::to_yaml_string)]
+pub struct MarketMakerInfo {
+    /// The concurrent swaps limit of the node, unlimited if not set
+    pub max_concurrent_swaps: Option<u32>,
+    pub running_swaps: u32,
+    /// Bitcoin inventory, unlimited if not set
+    #[serde(with = "bitcoin::util::amount::serde::as_btc::opt")]
+    pub btc_inventory: Option<bitcoin::Amount>,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub btc_used: bitcoin::Amount,
+    /// Monero inventory, unlimited if not set
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub xmr_inventory: Option<monero::Amount>,
+    #[serde(with = "monero::util::amount::serde::as_xmr")]
+    pub xmr_used: monero::Amount,
+    pub markets: Vec<MarketInfo>,
+}
+
+/// The deals kept open by the market maker on a network
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(MarketInfo::to_yaml_string)]
+pub struct MarketInfo {
+    #[serde_as(as = "DisplayFromStr")]
+    pub network: Network,
+    pub maker_role: SwapRole,
+    pub status: String,
+    pub price: String,
+    pub target_open_deals: u32,
+    pub open_deals: Vec<DealId>,
+}
+
+/// The reference price of one XMR in BTC of the price oracle and the time it was fetched at
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(ReferencePrice::to_yaml_string)]
+pub struct ReferencePrice {
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub price: bitcoin::Amount,
+    pub since: u64,
+}
+
+impl ReferencePrice {
+    /// Accordant amount exchanged for the arbitrating amount at the reference price moved by the
+    /// spread, in percent
+    pub fn accordant_amount(
+        &self,
+        arbitrating_amount: bitcoin::Amount,
+        spread: f64,
+    ) -> monero::Amount {
+        accordant_amount_at(arbitrating_amount, price_with_spread(self.price, spread))
+    }
+}
+
+/// The price of one monero in bitcoin moved by the spread in percent, above the price if the
+/// spread is positive and below it if negative
+pub fn price_with_spread(price: bitcoin::Amount, spread: f64) -> bitcoin::Amount {
+    bitcoin::Amount::from_sat((price.as_sat() as f64 * (1.0 + spread / 100.0)).round() as u64)
+}
+
+/// Monero amount exchanged for the bitcoin amount at the price of one monero in bitcoin
+pub fn accordant_amount_at(
+    arbitrating_amount: bitcoin::Amount,
+    price: bitcoin::Amount,
+) -> monero::Amount {
+    monero::Amount::from_pico(
+        (arbitrating_amount.as_sat() as u128 * 1_000_000_000_000 / price.as_sat().max(1) as u128)
+            as u64,
+    )
+}
+
+/// A deal issued by a remote maker for our quote request
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(QuotedDeal::to_yaml_string)]
+pub struct QuotedDeal {
+    pub message: String,
+    pub expiry: u64,
+    pub viewable_deal: ViewableDeal,
+}
+
+/// A deal announced by a remote maker, relayed to us by our peers or fetched from a deal registry
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(RemoteDealInfo::to_yaml_string)]
+pub struct RemoteDealInfo {
+    pub deal_id: DealId,
+    pub maker: NodeId,
+    pub price: String,
+    pub since: u64,
+    pub serialized_deal: String,
+    pub deal: Deal,
+}
+
+impl RemoteDealInfo {
+    pub fn new(deal: Deal, since: u64) -> Self {
+        RemoteDealInfo {
+            deal_id: deal.id(),
+            maker: NodeId::from(deal.node_id),
+            price: format!("{} BTC/XMR", deal_price(&deal)),
+            since,
+            serialized_deal: deal.to_string(),
+            deal,
+        }
+    }
+}
+
+/// The price of the deal in BTC per XMR
+pub fn deal_price(deal: &Deal) -> f64 {
+    deal.parameters.arbitrating_amount.as_btc() / deal.parameters.accordant_amount.as_xmr()
+}
+
+/// Filters the deals of a deal registry, amounts are in satoshis and prices in satoshis per XMR
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{network}")]
+pub struct RegistryQuery {
+    pub network: Network,
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    pub min_price: Option<u64>,
+    pub max_price: Option<u64>,
+}
+
+impl RegistryQuery {
+    /// Returns if the deal is on the queried network and within the queried amount and price
+    /// ranges
+    pub fn matches(&self, deal: &Deal) -> bool {
+        let amount = deal.parameters.arbitrating_amount.as_sat();
+        let price = (deal_price(deal) * 100_000_000.0) as u64;
+        deal.parameters.network == self.network
+            && self.min_amount.map_or(true, |min| amount >= min)
+            && self.max_amount.map_or(true, |max| amount <= max)
+            && self.min_price.map_or(true, |min| price >= min)
+            && self.max_price.map_or(true, |max| price <= max)
+    }
+}
+
 #[cfg_attr(feature = "serde", serde_as)]
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
 #[cfg_attr(
@@ -261,12 +555,97 @@ pub struct NodeInfo {
     pub uptime: Duration,
     pub since: u64,
     #[serde_as(as = "Vec<DisplayFromStr>")]
-    pub peers: Vec<NodeAddr>,
+    pub peers: Vec<NodeId>,
     pub swaps: Vec<SwapId>,
     #[serde_as(as = "Vec<DisplayFromStr>")]
     pub deals: Vec<Deal>,
     #[serde(alias = "statistics")]
     pub stats: Stats,
+    pub swap_limits: SwapLimitsUsage,
+    pub maintenance: MaintenanceStatus,
+    pub syncer_alerts: Vec<SyncerAlert>,
+}
+
+/// Raised when a swap withholds a race-sensitive broadcast because the syncer data of the
+/// blockchain cannot be trusted, the most recent alerts are kept in the node info.
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display("{swap_id}: {blockchain} syncer data is stale, withholding {tx_label}: {reason}")]
+pub struct SyncerAlert {
+    #[serde_as(as = "DisplayFromStr")]
+    pub swap_id: SwapId,
+    pub blockchain: Blockchain,
+    pub tx_label: TxLabel,
+    pub reason: String,
+    pub since: u64,
+}
+
+/// Whether the node is in maintenance mode and the swaps still running. No new swap starts in
+/// maintenance mode, the node can be shut down once the running swaps are drained.
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(MaintenanceStatus::to_yaml_string)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    pub draining_swaps: u32,
+    pub safe_to_shutdown: bool,
+}
+
+/// Usage of the swap limits configured, the swaps running count against the concurrency and in
+/// flight limits
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(SwapLimitsUsage::to_yaml_string)]
+pub struct SwapLimitsUsage {
+    /// Maximum number of swaps running at once, unlimited if not set
+    pub max_concurrent_swaps: Option<u32>,
+    pub running_swaps: u32,
+    pub networks: Vec<VolumeUsage>,
+}
+
+/// Amounts swapped on a network against their limits, unlimited if not set
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(VolumeUsage::to_yaml_string)]
+pub struct VolumeUsage {
+    #[serde_as(as = "DisplayFromStr")]
+    pub network: Network,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub btc_in_flight: bitcoin::Amount,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc::opt")]
+    pub max_btc_in_flight: Option<bitcoin::Amount>,
+    #[serde(with = "monero::util::amount::serde::as_xmr")]
+    pub xmr_in_flight: monero::Amount,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub max_xmr_in_flight: Option<monero::Amount>,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub btc_daily_volume: bitcoin::Amount,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc::opt")]
+    pub max_btc_daily_volume: Option<bitcoin::Amount>,
+    #[serde(with = "monero::util::amount::serde::as_xmr")]
+    pub xmr_daily_volume: monero::Amount,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub max_xmr_daily_volume: Option<monero::Amount>,
 }
 
 #[cfg_attr(feature = "serde", serde_as)]
@@ -291,6 +670,8 @@ pub struct PeerInfo {
     pub messages_received: usize,
     pub forked_from_listener: bool,
     pub awaits_pong: bool,
+    pub protocol_version: u16,
+    pub features: Vec<String>,
 }
 
 #[cfg_attr(feature = "serde", serde_as)]
@@ -304,7 +685,7 @@ pub struct PeerInfo {
 pub struct SwapInfo {
     pub swap_id: SwapId,
     #[serde_as(as = "Option<DisplayFromStr>")]
-    pub connection: Option<NodeAddr>,
+    pub connection: Option<NodeId>,
     pub connected: bool,
     #[serde_as(as = "DurationSeconds")]
     pub uptime: Duration,
@@ -461,6 +842,26 @@ impl ToYamlString for MadeDeal {}
 #[cfg(feature = "serde")]
 impl ToYamlString for TookDeal {}
 #[cfg(feature = "serde")]
+impl ToYamlString for PendingApproval {}
+#[cfg(feature = "serde")]
+impl ToYamlString for DealTemplateInfo {}
+#[cfg(feature = "serde")]
+impl ToYamlString for QuotedDeal {}
+#[cfg(feature = "serde")]
+impl ToYamlString for MarketMakerInfo {}
+#[cfg(feature = "serde")]
+impl ToYamlString for SwapLimitsUsage {}
+#[cfg(feature = "serde")]
+impl ToYamlString for MaintenanceStatus {}
+#[cfg(feature = "serde")]
+impl ToYamlString for VolumeUsage {}
+#[cfg(feature = "serde")]
+impl ToYamlString for ReferencePrice {}
+#[cfg(feature = "serde")]
+impl ToYamlString for MarketInfo {}
+#[cfg(feature = "serde")]
+impl ToYamlString for RemoteDealInfo {}
+#[cfg(feature = "serde")]
 impl ToYamlString for CheckpointEntry {}
 #[cfg(feature = "serde")]
 impl ToYamlString for SwapProgress {}
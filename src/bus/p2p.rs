@@ -4,7 +4,11 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1, SecretKey};
+use bitcoin::Txid;
 use farcaster_core::{
+    blockchain::Network,
     protocol::message::Abort,
     swap::btcxmr::message::{
         BuyProcedureSignature, CommitAliceParameters, CommitBobParameters, CoreArbitratingSetup,
@@ -12,10 +16,17 @@ use farcaster_core::{
     },
     swap::btcxmr::Deal,
     swap::SwapId,
+    trade::DealId,
+    Uuid,
 };
-use internet2::Api;
+use internet2::addr::NodeId;
+use internet2::{Api, TypedEnum};
 use strict_encoding::{StrictDecode, StrictEncode};
 
+use crate::bus::{unix_timestamp, SignedDeal};
+use crate::swapd::{TemporalSafety, WrappedEncryptedSignature};
+use crate::Error;
+
 #[derive(Clone, Debug, Display, Api, StrictDecode, StrictEncode)]
 #[api(encoding = "strict")]
 #[display(inner)]
@@ -49,6 +60,38 @@ pub enum PeerMsg {
     #[display("buy procedure signature")]
     BuyProcedureSignature(BuyProcedureSignature),
 
+    #[api(type = 33740)]
+    #[display("{0} cooperative refund request")]
+    CooperativeRefundRequest(SwapId),
+
+    #[api(type = 33741)]
+    #[display("cooperative refund signature")]
+    CooperativeRefundSignature(CooperativeRefundSignature),
+
+    #[api(type = 33750)]
+    #[display("{0} watchtower request")]
+    WatchtowerRequest(SignedWatchtowerRequest),
+
+    #[api(type = 33760)]
+    #[display("deal_announcement({0})")]
+    DealAnnouncement(DealAnnouncement),
+
+    #[api(type = 33761)]
+    #[display("deal_revocation({0})")]
+    DealRevocation(DealRevocation),
+
+    #[api(type = 33770)]
+    #[display("quote_request({0})")]
+    QuoteRequest(QuoteRequest),
+
+    #[api(type = 33771)]
+    #[display("quote({0})")]
+    Quote(Quote),
+
+    #[api(type = 33772)]
+    #[display("quote_rejected({0})")]
+    QuoteRejected(QuoteRejection),
+
     #[api(type = 18)]
     #[display("ping({0})")]
     Ping(u16),
@@ -57,9 +100,17 @@ pub enum PeerMsg {
     #[display("pong(..)")]
     Pong(Vec<u8>),
 
+    #[api(type = 33796)]
+    #[display("init({0})")]
+    Init(PeerInit),
+
+    #[api(type = 33797)]
+    #[display("identity_challenge({0})")]
+    IdentityChallenge(IdentityChallenge),
+
     #[api(type = 33798)]
-    #[display("identity(..)")]
-    Identity(internet2::addr::NodeId),
+    #[display("identity_proof({0})")]
+    IdentityProof(IdentityProof),
 
     #[api(type = 33799)]
     #[display("abort(..)")]
@@ -76,6 +127,10 @@ pub enum PeerMsg {
     #[api(type = 33802)]
     #[display("msg_receipt {0}")]
     MsgReceipt(Receipt),
+
+    #[api(type = 33803)]
+    #[display("malformed_message()")]
+    MalformedMessage,
 }
 
 impl PeerMsg {
@@ -91,19 +146,38 @@ impl PeerMsg {
             PeerMsg::Abort(Abort { swap_id, .. }) => *swap_id,
             PeerMsg::CoreArbitratingSetup(CoreArbitratingSetup { swap_id, .. }) => *swap_id,
             PeerMsg::BuyProcedureSignature(BuyProcedureSignature { swap_id, .. }) => *swap_id,
+            PeerMsg::CooperativeRefundRequest(swap_id) => *swap_id,
+            PeerMsg::CooperativeRefundSignature(CooperativeRefundSignature { swap_id, .. }) => {
+                *swap_id
+            }
+            PeerMsg::WatchtowerRequest(req) => req.request.swap_id(),
             PeerMsg::MsgReceipt(Receipt { swap_id, .. }) => *swap_id,
             PeerMsg::Ping(_)
             | PeerMsg::Pong(_)
             | PeerMsg::PingPeer
             | PeerMsg::PeerReceiverRuntimeShutdown
-            | PeerMsg::Identity(_) => {
+            | PeerMsg::MalformedMessage
+            | PeerMsg::DealAnnouncement(_)
+            | PeerMsg::DealRevocation(_)
+            | PeerMsg::QuoteRequest(_)
+            | PeerMsg::Quote(_)
+            | PeerMsg::QuoteRejected(_)
+            | PeerMsg::Init(_)
+            | PeerMsg::IdentityChallenge(_)
+            | PeerMsg::IdentityProof(_) => {
                 unreachable!(
-                    "Ping, Pong, PingPeer, PeerdShutdown and Identity do not contain swapid"
+                    "Ping, Pong, PingPeer, PeerdShutdown, gossip, quote and handshake messages do not contain swapid"
                 )
             }
         }
     }
 
+    /// Identifies a message by its content, used to acknowledge and to deduplicate protocol
+    /// messages
+    pub fn msg_id(&self) -> sha256::Hash {
+        sha256::Hash::hash(&self.serialize())
+    }
+
     pub fn on_receiver_whitelist(&self) -> bool {
         matches!(
             self,
@@ -113,13 +187,37 @@ impl PeerMsg {
                 | PeerMsg::RefundProcedureSignatures(_)
                 | PeerMsg::CoreArbitratingSetup(_)
                 | PeerMsg::BuyProcedureSignature(_)
+                | PeerMsg::CooperativeRefundRequest(_)
+                | PeerMsg::CooperativeRefundSignature(_)
+                | PeerMsg::WatchtowerRequest(_)
                 | PeerMsg::Ping(_)
                 | PeerMsg::Pong(_)
                 | PeerMsg::MsgReceipt(_)
                 | PeerMsg::DealNotFound(_)
+                | PeerMsg::Abort(_)
+                | PeerMsg::DealAnnouncement(_)
+                | PeerMsg::DealRevocation(_)
+                | PeerMsg::QuoteRequest(_)
+                | PeerMsg::Quote(_)
+                | PeerMsg::QuoteRejected(_)
         )
     }
 
+    /// The optional feature both peers must have negotiated to exchange the message
+    pub fn required_feature(&self) -> Option<PeerFeatures> {
+        match self {
+            PeerMsg::MsgReceipt(_) => Some(PeerFeatures::MSG_RECEIPTS),
+            PeerMsg::CooperativeRefundRequest(_) | PeerMsg::CooperativeRefundSignature(_) => {
+                Some(PeerFeatures::COOPERATIVE_REFUND)
+            }
+            PeerMsg::WatchtowerRequest(_) => Some(PeerFeatures::WATCHTOWER),
+            PeerMsg::DealAnnouncement(_) | PeerMsg::DealRevocation(_) => {
+                Some(PeerFeatures::DEAL_GOSSIP)
+            }
+            _ => None,
+        }
+    }
+
     pub fn is_protocol(&self) -> bool {
         matches!(
             self,
@@ -129,6 +227,9 @@ impl PeerMsg {
                 | PeerMsg::RefundProcedureSignatures(_)
                 | PeerMsg::CoreArbitratingSetup(_)
                 | PeerMsg::BuyProcedureSignature(_)
+                | PeerMsg::CooperativeRefundRequest(_)
+                | PeerMsg::CooperativeRefundSignature(_)
+                | PeerMsg::WatchtowerRequest(_)
                 | PeerMsg::DealNotFound(_)
         )
     }
@@ -181,9 +282,412 @@ impl Commit {
     }
 }
 
+/// Alice's adaptor signature on a transaction spending the lock output straight back to Bob,
+/// encrypted with Bob's adaptor key. Publishing the transaction reveals Bob's Monero key share
+/// to Alice.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("cooperative_refund_signature")]
+pub struct CooperativeRefundSignature {
+    pub swap_id: SwapId,
+    pub refund_tx: bitcoin::Transaction,
+    pub refund_adaptor_sig: WrappedEncryptedSignature,
+}
+
+/// The presigned transactions and timelock parameters a watchtower needs to protect the funds
+/// locked in a swap while the node running it is offline. Alice registers her cancel and punish
+/// transactions, Bob his cancel and refund transactions. The buy transaction is watched once
+/// known to end the job when the swap completes.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{swap_id}")]
+pub struct WatchtowerSwap {
+    pub swap_id: SwapId,
+    pub network: Network,
+    pub lock_txid: Txid,
+    pub refund_txid: Txid,
+    pub buy_txid: Option<Txid>,
+    pub cancel_tx: bitcoin::Transaction,
+    pub refund_tx: Option<bitcoin::Transaction>,
+    pub punish_tx: Option<bitcoin::Transaction>,
+    pub temporal_safety: TemporalSafety,
+}
+
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+pub enum WatchtowerRequest {
+    #[display("register({0})")]
+    Register(WatchtowerSwap),
+    #[display("revoke({0})")]
+    Revoke(SwapId),
+}
+
+impl WatchtowerRequest {
+    pub fn swap_id(&self) -> SwapId {
+        match self {
+            Self::Register(swap) => swap.swap_id,
+            Self::Revoke(swap_id) => *swap_id,
+        }
+    }
+}
+
+/// A watchtower request signed with the node key of the client, the watchtower only accepts
+/// requests signed by one of its authorized clients and sent over a connection with the same
+/// node. The nonce is the signing time in milliseconds, strictly increasing per client, so a
+/// request cannot be replayed.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{request}")]
+pub struct SignedWatchtowerRequest {
+    pub request: WatchtowerRequest,
+    pub nonce: u64,
+    pub node_id: NodeId,
+    pub signature: Signature,
+}
+
+impl SignedWatchtowerRequest {
+    pub fn sign(
+        request: WatchtowerRequest,
+        nonce: u64,
+        node_secret_key: &SecretKey,
+    ) -> Result<Self, Error> {
+        let secp = Secp256k1::new();
+        let msg = Self::message(&request, nonce)?;
+        Ok(SignedWatchtowerRequest {
+            node_id: NodeId::from(PublicKey::from_secret_key(&secp, node_secret_key)),
+            signature: secp.sign_ecdsa(&msg, node_secret_key),
+            request,
+            nonce,
+        })
+    }
+
+    pub fn verify(&self) -> Result<(), Error> {
+        let msg = Self::message(&self.request, self.nonce)?;
+        Secp256k1::verification_only().verify_ecdsa(
+            &msg,
+            &self.signature,
+            &self.node_id.public_key(),
+        )?;
+        Ok(())
+    }
+
+    fn message(request: &WatchtowerRequest, nonce: u64) -> Result<Message, Error> {
+        let mut encoded = vec![];
+        request.strict_encode(&mut encoded)?;
+        nonce.strict_encode(&mut encoded)?;
+        Ok(Message::from_slice(&sha256::Hash::hash(&encoded))?)
+    }
+}
+
+/// A deal announced to the remote order books, signed with the node key of its maker so the
+/// relaying peers can neither forge nor alter it. The announcement is forgotten at its expiry,
+/// makers announce their open deals again before.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{deal}")]
+pub struct DealAnnouncement {
+    pub deal: Deal,
+    /// Unix timestamp after which the announcement is dropped
+    pub expiry: u64,
+    pub signature: Signature,
+}
+
+impl DealAnnouncement {
+    pub fn sign(deal: Deal, expiry: u64, node_secret_key: &SecretKey) -> Result<Self, Error> {
+        let secp = Secp256k1::new();
+        if PublicKey::from_secret_key(&secp, node_secret_key) != deal.node_id {
+            return Err(Error::Farcaster(format!(
+                "Deal {} was not made by this node",
+                deal.id()
+            )));
+        }
+        let msg = Self::message(&deal, expiry)?;
+        Ok(DealAnnouncement {
+            signature: secp.sign_ecdsa(&msg, node_secret_key),
+            deal,
+            expiry,
+        })
+    }
+
+    /// Checks the announcement was signed by the maker of the deal
+    pub fn verify(&self) -> Result<(), Error> {
+        let msg = Self::message(&self.deal, self.expiry)?;
+        Secp256k1::verification_only().verify_ecdsa(&msg, &self.signature, &self.deal.node_id)?;
+        Ok(())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        unix_timestamp() >= self.expiry
+    }
+
+    fn message(deal: &Deal, expiry: u64) -> Result<Message, Error> {
+        let mut engine = sha256::Hash::engine();
+        engine.input(b"farcaster deal announcement");
+        deal.strict_encode(&mut engine)?;
+        expiry.strict_encode(&mut engine)?;
+        Ok(Message::from_slice(&sha256::Hash::from_engine(engine))?)
+    }
+}
+
+/// The revocation of a deal, signed with the node key of its maker. It only applies to the deals
+/// of that node and is forgotten at its expiry, once every announcement of the deal signed
+/// before the revocation expired as well.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{deal_id}")]
+pub struct DealRevocation {
+    pub deal_id: DealId,
+    pub node_id: NodeId,
+    /// Unix timestamp after which the revocation is dropped
+    pub expiry: u64,
+    pub signature: Signature,
+}
+
+impl DealRevocation {
+    pub fn sign(deal_id: DealId, expiry: u64, node_secret_key: &SecretKey) -> Result<Self, Error> {
+        let secp = Secp256k1::new();
+        let node_id = NodeId::from(PublicKey::from_secret_key(&secp, node_secret_key));
+        let msg = Self::message(&deal_id, &node_id, expiry)?;
+        Ok(DealRevocation {
+            signature: secp.sign_ecdsa(&msg, node_secret_key),
+            deal_id,
+            node_id,
+            expiry,
+        })
+    }
+
+    /// Checks the revocation was signed by the node it claims
+    pub fn verify(&self) -> Result<(), Error> {
+        let msg = Self::message(&self.deal_id, &self.node_id, self.expiry)?;
+        Secp256k1::verification_only().verify_ecdsa(
+            &msg,
+            &self.signature,
+            &self.node_id.public_key(),
+        )?;
+        Ok(())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        unix_timestamp() >= self.expiry
+    }
+
+    fn message(deal_id: &DealId, node_id: &NodeId, expiry: u64) -> Result<Message, Error> {
+        let mut engine = sha256::Hash::engine();
+        engine.input(b"farcaster deal revocation");
+        deal_id.strict_encode(&mut engine)?;
+        engine.input(&node_id.public_key().serialize());
+        expiry.strict_encode(&mut engine)?;
+        Ok(Message::from_slice(&sha256::Hash::from_engine(engine))?)
+    }
+}
+
+/// Asks a maker for a deal of the given arbitrating amount, issued from one of its deal
+/// templates
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{id}")]
+pub struct QuoteRequest {
+    pub id: Uuid,
+    /// The template to quote from, any template covering the amount on the network otherwise
+    pub template_id: Option<Uuid>,
+    pub network: Network,
+    pub arbitrating_amount: bitcoin::Amount,
+    /// The timelocks requested by the taker, the defaults of the template are quoted otherwise
+    pub cancel_timelock: Option<u32>,
+    pub punish_timelock: Option<u32>,
+}
+
+/// The deal issued for a quote request, signed by the maker and only takeable by the requesting
+/// taker until its expiry.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{request_id}")]
+pub struct Quote {
+    pub request_id: Uuid,
+    pub signed_deal: SignedDeal,
+}
+
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{request_id}, {reason}")]
+pub struct QuoteRejection {
+    pub request_id: Uuid,
+    pub reason: String,
+}
+
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
 #[display("receipt")]
 pub struct Receipt {
     pub swap_id: SwapId,
     pub msg_type: internet2::TypeId,
+    pub msg_id: sha256::Hash,
+}
+
+/// Opens the identity handshake: announces the node id the sender claims to own and a fresh
+/// nonce the remote peer has to sign with its own node key.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{node_id}")]
+pub struct IdentityChallenge {
+    pub node_id: NodeId,
+    pub nonce: Vec<u8>,
+}
+
+/// Answers an identity challenge with a signature of the remote nonce, proving ownership of the
+/// node key. The signature commits to the static keys of both ends of the encrypted transport
+/// session, a proof cannot be relayed into another session.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{node_id}")]
+pub struct IdentityProof {
+    pub node_id: NodeId,
+    pub signature: Signature,
+}
+
+impl IdentityProof {
+    /// Signs the remote nonce, `remote_static` is the static key of the remote end of the
+    /// transport session as authenticated by the local node
+    pub fn sign(
+        nonce: &[u8],
+        node_secret_key: &SecretKey,
+        remote_static: &NodeId,
+    ) -> Result<Self, Error> {
+        let secp = Secp256k1::new();
+        let node_id = NodeId::from(PublicKey::from_secret_key(&secp, node_secret_key));
+        let msg = Self::message(nonce, &node_id, remote_static)?;
+        Ok(IdentityProof {
+            node_id,
+            signature: secp.sign_ecdsa(&msg, node_secret_key),
+        })
+    }
+
+    /// Verifies the proof of the local nonce, `local_static` is the static key of the local end
+    /// of the transport session
+    pub fn verify(&self, nonce: &[u8], local_static: &NodeId) -> Result<(), Error> {
+        let msg = Self::message(nonce, &self.node_id, local_static)?;
+        Secp256k1::verification_only().verify_ecdsa(
+            &msg,
+            &self.signature,
+            &self.node_id.public_key(),
+        )?;
+        Ok(())
+    }
+
+    fn message(nonce: &[u8], node_id: &NodeId, verifier_static: &NodeId) -> Result<Message, Error> {
+        let mut engine = sha256::Hash::engine();
+        engine.input(b"farcaster peer identity");
+        engine.input(nonce);
+        engine.input(&node_id.public_key().serialize());
+        engine.input(&verifier_static.public_key().serialize());
+        Ok(Message::from_slice(&sha256::Hash::from_engine(engine))?)
+    }
+}
+
+/// Version of the peer protocol, bumped on every incompatible change to the peer messages.
+pub const PEER_PROTOCOL_VERSION: u16 = 1;
+
+/// Bitmask of the optional parts of the peer protocol a node supports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, StrictEncode, StrictDecode)]
+pub struct PeerFeatures(pub u64);
+
+impl PeerFeatures {
+    /// Protocol messages are acknowledged with a receipt
+    pub const MSG_RECEIPTS: PeerFeatures = PeerFeatures(1 << 0);
+    /// Cooperative refund request and signature messages
+    pub const COOPERATIVE_REFUND: PeerFeatures = PeerFeatures(1 << 1);
+    /// Watchtower registration requests
+    pub const WATCHTOWER: PeerFeatures = PeerFeatures(1 << 2);
+    /// Deal announcements and revocations
+    pub const DEAL_GOSSIP: PeerFeatures = PeerFeatures(1 << 3);
+
+    const NAMES: [(PeerFeatures, &'static str); 4] = [
+        (Self::MSG_RECEIPTS, "msg_receipts"),
+        (Self::COOPERATIVE_REFUND, "cooperative_refund"),
+        (Self::WATCHTOWER, "watchtower"),
+        (Self::DEAL_GOSSIP, "deal_gossip"),
+    ];
+
+    /// Features supported by this node
+    pub fn supported() -> Self {
+        PeerFeatures(
+            Self::MSG_RECEIPTS.0
+                | Self::COOPERATIVE_REFUND.0
+                | Self::WATCHTOWER.0
+                | Self::DEAL_GOSSIP.0,
+        )
+    }
+
+    /// Features this node requires from its peers
+    pub fn required() -> Self {
+        Self::MSG_RECEIPTS
+    }
+
+    pub fn contains(self, other: PeerFeatures) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersection(self, other: PeerFeatures) -> Self {
+        PeerFeatures(self.0 & other.0)
+    }
+
+    pub fn difference(self, other: PeerFeatures) -> Self {
+        PeerFeatures(self.0 & !other.0)
+    }
+
+    /// Names of the features set in the bitmask, unknown bits are reported by position
+    pub fn names(self) -> Vec<String> {
+        (0..64)
+            .map(|bit| PeerFeatures(1 << bit))
+            .filter(|feature| self.contains(*feature))
+            .map(|feature| {
+                Self::NAMES
+                    .iter()
+                    .find(|(known, _)| *known == feature)
+                    .map(|(_, name)| name.to_string())
+                    .unwrap_or_else(|| format!("unknown_{}", feature.0.trailing_zeros()))
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for PeerFeatures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 == 0 {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", self.names().join(", "))
+        }
+    }
+}
+
+/// Exchanged right after the identity handshake so that both nodes can refuse each other before
+/// any swap starts if their peer protocols are incompatible.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("v{protocol_version}, features: {features}")]
+pub struct PeerInit {
+    pub protocol_version: u16,
+    pub features: PeerFeatures,
+    pub required_features: PeerFeatures,
+}
+
+impl PeerInit {
+    pub fn local() -> Self {
+        PeerInit {
+            protocol_version: PEER_PROTOCOL_VERSION,
+            features: PeerFeatures::supported(),
+            required_features: PeerFeatures::required(),
+        }
+    }
+
+    /// Checks the remote init against this one and returns the features both nodes support.
+    pub fn negotiate(&self, remote: &PeerInit) -> Result<PeerFeatures, Error> {
+        if remote.protocol_version != self.protocol_version {
+            return Err(Error::IncompatiblePeer(format!(
+                "remote peer speaks protocol version {}, local version is {}",
+                remote.protocol_version, self.protocol_version
+            )));
+        }
+        if !remote.features.contains(self.required_features) {
+            return Err(Error::IncompatiblePeer(format!(
+                "remote peer lacks required features: {}",
+                self.required_features.difference(remote.features)
+            )));
+        }
+        if !self.features.contains(remote.required_features) {
+            return Err(Error::IncompatiblePeer(format!(
+                "remote peer requires unsupported features: {}",
+                remote.required_features.difference(self.features)
+            )));
+        }
+        Ok(self.features.intersection(remote.features))
+    }
 }
@@ -7,22 +7,32 @@
 use std::{
     fmt::{self, Debug, Display, Formatter},
     str::FromStr,
+    time::SystemTime,
 };
 
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1, SecretKey};
+use bitcoin::util::base58;
+
 use farcaster_core::{
     blockchain::Network,
     role::TradeRole,
     swap::{btcxmr::Deal, SwapId},
+    trade::DealId,
 };
 
 use amplify::{ToYamlString, Wrapper};
 use internet2::addr::NodeId;
 use microservices::rpc;
 use serde_with::DisplayFromStr;
-use strict_encoding::{NetworkDecode, NetworkEncode};
+use strict_encoding::{
+    strict_deserialize, strict_serialize, NetworkDecode, NetworkEncode, StrictDecode, StrictEncode,
+};
 
+use crate::bus::info::deal_price;
 use crate::swapd::StateReport;
 use crate::syncerd::Health;
+use crate::Error;
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
 #[display("{swap_id}, {deal}")]
@@ -106,22 +116,147 @@ pub enum DealStatus {
 
 #[derive(Clone, Debug, Eq, PartialEq, Display, NetworkEncode, NetworkDecode)]
 #[display("{deal}, {status}")]
-#[cfg_attr(
-    feature = "serde",
-    derive(Serialize, Deserialize),
-    serde(crate = "serde_crate")
-)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(crate = "serde_crate"))]
 #[display(DealInfo::to_yaml_string)]
 pub struct DealInfo {
     pub deal: Deal,
     pub serialized_deal: String,
     pub status: DealStatus,
     pub local_trade_role: TradeRole,
+    /// Unix timestamp after which the deal is automatically revoked, if any
+    pub expiry: Option<u64>,
+}
+
+// Serialized with the price implied by the amounts of the deal, ignored when deserialized
+#[cfg(feature = "serde")]
+impl serde::Serialize for DealInfo {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        #[serde(crate = "serde_crate")]
+        struct PricedDealInfo<'a> {
+            deal: &'a Deal,
+            serialized_deal: &'a str,
+            price: String,
+            status: &'a DealStatus,
+            local_trade_role: &'a TradeRole,
+            expiry: Option<u64>,
+        }
+        serde::Serialize::serialize(
+            &PricedDealInfo {
+                deal: &self.deal,
+                serialized_deal: &self.serialized_deal,
+                price: format!("{} BTC/XMR", deal_price(&self.deal)),
+                status: &self.status,
+                local_trade_role: &self.local_trade_role,
+                expiry: self.expiry,
+            },
+            serializer,
+        )
+    }
 }
 
 #[cfg(feature = "serde")]
 impl ToYamlString for DealInfo {}
 
+const SIGNED_DEAL_PREFIX: &str = "SignedDeal:";
+
+/// A deal signed by the node key of its maker and only valid until its expiry, protects takers
+/// from tampered or stale deals.
+#[derive(Clone, PartialEq, Eq, Debug, StrictEncode, StrictDecode)]
+pub struct SignedDeal {
+    pub deal: Deal,
+    /// Unix timestamp after which the deal can no longer be taken
+    pub expiry: u64,
+    pub signature: Signature,
+}
+
+impl SignedDeal {
+    pub fn sign(deal: Deal, expiry: u64, node_secret_key: &SecretKey) -> Result<Self, Error> {
+        let secp = Secp256k1::new();
+        if PublicKey::from_secret_key(&secp, node_secret_key) != deal.node_id {
+            return Err(Error::Farcaster(format!(
+                "Deal {} was not made by this node",
+                deal.id()
+            )));
+        }
+        let msg = Self::message(&deal, expiry)?;
+        Ok(SignedDeal {
+            signature: secp.sign_ecdsa(&msg, node_secret_key),
+            deal,
+            expiry,
+        })
+    }
+
+    /// Checks the deal was signed by its maker and has not expired yet
+    pub fn verify(&self) -> Result<(), Error> {
+        let msg = Self::message(&self.deal, self.expiry)?;
+        Secp256k1::verification_only().verify_ecdsa(&msg, &self.signature, &self.deal.node_id)?;
+        if self.is_expired() {
+            return Err(Error::DealExpired(self.deal.id()));
+        }
+        Ok(())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        unix_timestamp() >= self.expiry
+    }
+
+    fn message(deal: &Deal, expiry: u64) -> Result<Message, Error> {
+        let mut engine = sha256::Hash::engine();
+        engine.input(b"farcaster signed deal");
+        deal.strict_encode(&mut engine)?;
+        expiry.strict_encode(&mut engine)?;
+        Ok(Message::from_slice(&sha256::Hash::from_engine(engine))?)
+    }
+}
+
+impl Display for SignedDeal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let encoded = strict_serialize(self).map_err(|_| fmt::Error)?;
+        write!(
+            f,
+            "{}{}",
+            SIGNED_DEAL_PREFIX,
+            base58::check_encode_slice(&encoded)
+        )
+    }
+}
+
+impl FromStr for SignedDeal {
+    type Err = SignedDealParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoded = s
+            .strip_prefix(SIGNED_DEAL_PREFIX)
+            .ok_or(SignedDealParseError::WrongPrefix)?;
+        let encoded = base58::from_check(encoded).map_err(|_| SignedDealParseError::Invalid)?;
+        strict_deserialize(&encoded).map_err(|_| SignedDealParseError::Invalid)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SignedDealParseError {
+    /// The provided value is not prefixed as a signed deal
+    WrongPrefix,
+    /// The provided value can't be decoded as a signed deal
+    Invalid,
+}
+
+/// Returns the current unix timestamp in seconds
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Display, NetworkEncode, NetworkDecode)]
 #[cfg_attr(
     feature = "serde",
@@ -137,9 +272,18 @@ pub enum Outcome {
     FailurePunish,
     #[display("Failure Abort")]
     FailureAbort,
+    /// Aborted before the lock as the swap did not progress in time, e.g. the counterparty
+    /// became unresponsive
+    #[display("Failure Timeout")]
+    FailureTimeout,
 }
 
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
 #[display(inner)]
 pub enum Progress {
     Message(String),
@@ -214,21 +358,119 @@ pub struct Failure {
 )]
 #[display(Debug)]
 pub enum FailureCode {
-    /// Catch-all: TODO: Expand
-    Unknown = 0xFFF,
+    /// The request or one of its arguments is malformed or invalid
+    InvalidRequest = 0x001,
+
+    /// The deal is invalid, expired, or rejected by the configuration or the policies
+    InvalidDeal = 0x002,
+
+    /// An amount is out of the configured tradeable range
+    AmountOutOfRange = 0x003,
+
+    /// No deal matches the request
+    UnknownDeal = 0x004,
+
+    /// No swap matches the request
+    UnknownSwap = 0x005,
+
+    /// The requested resource does not exist, e.g. a checkpoint, a deal template or a taker
+    /// awaiting approval
+    NotFound = 0x006,
+
+    /// The request is not possible in the current state, e.g. aborting a locked swap
+    InvalidState = 0x007,
+
+    /// A configured limit on the swaps is reached
+    LimitReached = 0x008,
+
+    /// The node cannot serve the request for now, e.g. in maintenance mode, shutting down, with
+    /// the feature disabled or a service crashed
+    Unavailable = 0x009,
+
+    /// The remote peer cannot be reached or did not answer in time
+    PeerUnreachable = 0x00A,
+
+    /// The syncer is unhealthy, crashed or failed to query the blockchain
+    SyncerUnhealthy = 0x00B,
+
+    /// The wallet refused the request, its token is invalid
+    WalletLocked = 0x00C,
+
+    /// Not enough funds are available for the request
+    InsufficientFunds = 0x00D,
 
     TargetServiceNotFound = 0xFFE,
+
+    /// Catch-all for the failures not covered by another code
+    Unknown = 0xFFF,
 }
 
 impl From<u16> for FailureCode {
     fn from(value: u16) -> Self {
         match value {
+            0x001 => FailureCode::InvalidRequest,
+            0x002 => FailureCode::InvalidDeal,
+            0x003 => FailureCode::AmountOutOfRange,
+            0x004 => FailureCode::UnknownDeal,
+            0x005 => FailureCode::UnknownSwap,
+            0x006 => FailureCode::NotFound,
+            0x007 => FailureCode::InvalidState,
+            0x008 => FailureCode::LimitReached,
+            0x009 => FailureCode::Unavailable,
+            0x00A => FailureCode::PeerUnreachable,
+            0x00B => FailureCode::SyncerUnhealthy,
+            0x00C => FailureCode::WalletLocked,
+            0x00D => FailureCode::InsufficientFunds,
             0xFFE => FailureCode::TargetServiceNotFound,
             _ => FailureCode::Unknown,
         }
     }
 }
 
+/// Classify a node error for the clients to branch on
+impl From<&Error> for FailureCode {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::Failure(failure) => failure.code,
+            Error::AmountOutOfRange(_) => FailureCode::AmountOutOfRange,
+            Error::InvalidDeal(_) | Error::DealPolicy(_) | Error::DealExpired(_) => {
+                FailureCode::InvalidDeal
+            }
+            Error::SwapLimit(_) => FailureCode::LimitReached,
+            Error::Maintenance | Error::DealRegistry(_) | Error::PriceOracle(_) => {
+                FailureCode::Unavailable
+            }
+            Error::NotResponding
+            | Error::Peer(_)
+            | Error::PeerIdentityMismatch { .. }
+            | Error::PeerIdentityUnproven(_)
+            | Error::IncompatiblePeer(_) => FailureCode::PeerUnreachable,
+            Error::Syncer(_) => FailureCode::SyncerUnhealthy,
+            Error::InvalidToken => FailureCode::WalletLocked,
+            Error::Config(_)
+            | Error::BitcoinAddress(_)
+            | Error::BitcoinAmount(_)
+            | Error::MoneroAddress(_)
+            | Error::MoneroAmount(_)
+            | Error::Uuid(_)
+            | Error::Inet2AddrParseError(_) => FailureCode::InvalidRequest,
+            _ => FailureCode::Unknown,
+        }
+    }
+}
+
+impl From<&Error> for Failure {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::Failure(failure) => failure.clone(),
+            err => Failure {
+                code: err.into(),
+                info: err.to_string(),
+            },
+        }
+    }
+}
+
 impl From<FailureCode> for u16 {
     fn from(code: FailureCode) -> Self {
         code as u16
@@ -315,3 +557,56 @@ impl ToYamlString for DefaultHealthReport {}
 impl ToYamlString for CompleteHealthReport {}
 #[cfg(feature = "serde")]
 impl ToYamlString for ReducedHealthReport {}
+
+/// A node id banned from connecting to this node, either manually or after accumulating too
+/// much ban score
+#[derive(Clone, Debug, Display, Eq, PartialEq, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display("{node_id}: {reason}")]
+pub struct PeerBan {
+    pub node_id: NodeId,
+    pub reason: String,
+    pub since: u64,
+}
+
+/// A swap started by this node, counted in its daily volume for 24 hours
+#[derive(Clone, Debug, Display, Eq, PartialEq, NetworkEncode, NetworkDecode)]
+#[display("{deal_id}: {btc}, {xmr} on {network}")]
+pub struct StartedSwap {
+    pub deal_id: DealId,
+    pub since: u64,
+    pub network: Network,
+    pub btc: bitcoin::Amount,
+    pub xmr: monero::Amount,
+}
+
+/// Misbehavior of a remote peer contributing to its ban score
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Hash, NetworkEncode, NetworkDecode)]
+pub enum Misbehavior {
+    #[display("malformed message")]
+    MalformedMessage,
+    #[display("commit to unknown deal")]
+    UnknownDeal,
+    #[display("abort of unknown swap")]
+    UnknownSwapAbort,
+    #[display("invalid deal gossip")]
+    InvalidGossip,
+    #[display("quote request flood")]
+    QuoteFlood,
+}
+
+impl Misbehavior {
+    pub fn ban_score(&self) -> u32 {
+        match self {
+            Misbehavior::MalformedMessage => 20,
+            Misbehavior::UnknownDeal => 10,
+            Misbehavior::UnknownSwapAbort => 10,
+            Misbehavior::InvalidGossip => 20,
+            Misbehavior::QuoteFlood => 10,
+        }
+    }
+}
@@ -8,8 +8,9 @@ use farcaster_core::swap::btcxmr::{Deal, DealParameters};
 use farcaster_core::Uuid;
 use std::io::{self, Read};
 use std::str::FromStr;
+use std::time::Duration;
 
-use internet2::addr::{InetSocketAddr, NodeAddr};
+use internet2::addr::{InetSocketAddr, NodeAddr, NodeId};
 use microservices::shell::Exec;
 
 use clap::IntoApp;
@@ -19,19 +20,25 @@ use farcaster_core::{
     blockchain::{Blockchain, Network},
     role::SwapRole,
     swap::SwapId,
+    trade::DealId,
 };
 
 use super::Command;
 use crate::bus::{
     ctl::{self, CtlMsg},
-    info::{Address, InfoMsg},
+    info::{Address, InfoMsg, RegistryQuery},
+    p2p::QuoteRequest,
     AddressSecretKey,
 };
 use crate::bus::{
     BusMsg, CompleteHealthReport, DefaultHealthReport, Failure, FailureCode, HealthCheckSelector,
     ReducedHealthReport,
 };
-use crate::cli::opts::CheckpointSelector;
+use crate::cli::opts::{
+    ApprovalMode, ApprovalsCommand, CheckpointSelector, MaintenanceCommand, TemplatesCommand,
+    WatchtowerCommand,
+};
+use crate::cli::{Dashboard, Output, OutputFormat};
 use crate::client::Client;
 use crate::syncerd::{Health, SweepAddressAddendum, SweepBitcoinAddress, SweepMoneroAddress};
 use crate::{Error, LogStyle, ServiceId};
@@ -56,10 +63,13 @@ impl Exec for Command {
                     }
                     1 => {
                         let subj = subject.get(0).expect("vec of lenght 1");
-                        if let Ok(node_addr) = NodeAddr::from_str(subj) {
-                            runtime
-                                .request_info(ServiceId::Peer(0, node_addr), InfoMsg::GetInfo)?;
-                            ServiceId::Peer(0, node_addr)
+                        if let Some(node_id) = NodeAddr::from_str(subj)
+                            .map(|node_addr| node_addr.id)
+                            .ok()
+                            .or_else(|| NodeId::from_str(subj).ok())
+                        {
+                            runtime.request_info(ServiceId::Peer(node_id), InfoMsg::GetInfo)?;
+                            ServiceId::Peer(node_id)
                         } else if let Ok(swap_id) = Uuid::from_str(subj).map(SwapId) {
                             runtime.request_info(ServiceId::Swap(swap_id), InfoMsg::GetInfo)?;
                             ServiceId::Swap(swap_id)
@@ -82,18 +92,20 @@ impl Exec for Command {
                     }
                 };
                 match runtime.response()? {
-                    BusMsg::Info(InfoMsg::NodeInfo(info)) => println!("{}", info),
-                    BusMsg::Info(InfoMsg::PeerInfo(info)) => println!("{}", info),
-                    BusMsg::Info(InfoMsg::SwapInfo(info)) => println!("{}", info),
-                    BusMsg::Info(InfoMsg::SyncerInfo(info)) => println!("{}", info),
+                    BusMsg::Info(InfoMsg::NodeInfo(info)) => runtime.report(Output::NodeInfo(info)),
+                    BusMsg::Info(InfoMsg::PeerInfo(info)) => runtime.report(Output::PeerInfo(info)),
+                    BusMsg::Info(InfoMsg::SwapInfo(info)) => runtime.report(Output::SwapInfo(info)),
+                    BusMsg::Info(InfoMsg::SyncerInfo(info)) => {
+                        runtime.report(Output::SyncerInfo(info))
+                    }
                     BusMsg::Ctl(CtlMsg::Failure(Failure { code, .. }))
                         if code == FailureCode::TargetServiceNotFound =>
                     {
                         match target_service_id {
-                            ServiceId::Peer(_, node_addr) => {
+                            ServiceId::Peer(node_id) => {
                                 return Err(Error::Farcaster(format!(
-                                    "No connected peerd with address {}",
-                                    node_addr
+                                    "No connected peerd with node id {}",
+                                    node_id
                                 )));
                             }
                             ServiceId::Swap(swap_id) => {
@@ -134,6 +146,29 @@ impl Exec for Command {
                 runtime.report_response_or_fail()?;
             }
 
+            Command::ListRemoteDeals => {
+                runtime.request_info(ServiceId::Farcasterd, InfoMsg::ListRemoteDeals)?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::QueryRegistry {
+                network,
+                min_amount,
+                max_amount,
+                min_price,
+                max_price,
+            } => {
+                let query = RegistryQuery {
+                    network,
+                    min_amount: min_amount.map(|amount| amount.as_sat()),
+                    max_amount: max_amount.map(|amount| amount.as_sat()),
+                    min_price: min_price.map(|price| price.as_sat()),
+                    max_price: max_price.map(|price| price.as_sat()),
+                };
+                runtime.request_info(ServiceId::Farcasterd, InfoMsg::QueryDealRegistry(query))?;
+                runtime.report_response_or_fail()?;
+            }
+
             Command::ListDeals { select } => {
                 runtime.request_info(ServiceId::Farcasterd, InfoMsg::ListDeals(select.into()))?;
                 runtime.report_response_or_fail()?;
@@ -209,15 +244,12 @@ impl Exec for Command {
                     let monero_testnet_health = self.check_health(runtime, Monero, Testnet)?;
                     let monero_mainnet_health = self.check_health(runtime, Monero, Mainnet)?;
 
-                    println!(
-                        "{}",
-                        DefaultHealthReport {
-                            bitcoin_testnet_health,
-                            bitcoin_mainnet_health,
-                            monero_testnet_health,
-                            monero_mainnet_health,
-                        }
-                    );
+                    runtime.report(Output::DefaultHealthReport(DefaultHealthReport {
+                        bitcoin_testnet_health,
+                        bitcoin_mainnet_health,
+                        monero_testnet_health,
+                        monero_mainnet_health,
+                    }));
                 }
                 // user selected a specific network
                 Some(HealthCheckSelector::Network(network)) => {
@@ -225,13 +257,10 @@ impl Exec for Command {
 
                     let bitcoin_health = self.check_health(runtime, Bitcoin, *network)?;
                     let monero_health = self.check_health(runtime, Monero, *network)?;
-                    println!(
-                        "{}",
-                        ReducedHealthReport {
-                            bitcoin_health,
-                            monero_health,
-                        }
-                    );
+                    runtime.report(Output::ReducedHealthReport(ReducedHealthReport {
+                        bitcoin_health,
+                        monero_health,
+                    }));
                 }
                 // check all networks
                 Some(HealthCheckSelector::All) => {
@@ -246,17 +275,14 @@ impl Exec for Command {
                     let monero_mainnet_health = self.check_health(runtime, Monero, Mainnet)?;
                     let monero_local_health = self.check_health(runtime, Monero, Local)?;
 
-                    println!(
-                        "{}",
-                        CompleteHealthReport {
-                            bitcoin_testnet_health,
-                            bitcoin_mainnet_health,
-                            bitcoin_local_health,
-                            monero_testnet_health,
-                            monero_mainnet_health,
-                            monero_local_health,
-                        }
-                    );
+                    runtime.report(Output::CompleteHealthReport(CompleteHealthReport {
+                        bitcoin_testnet_health,
+                        bitcoin_mainnet_health,
+                        bitcoin_local_health,
+                        monero_testnet_health,
+                        monero_mainnet_health,
+                        monero_local_health,
+                    }));
                 }
             },
 
@@ -266,6 +292,7 @@ impl Exec for Command {
                 accordant_blockchain,
                 arbitrating_amount,
                 accordant_amount,
+                oracle_spread,
                 arbitrating_addr,
                 accordant_addr,
                 cancel_timelock,
@@ -274,7 +301,47 @@ impl Exec for Command {
                 maker_role,
                 public_ip_addr,
                 public_port,
+                approval,
+                allowed_takers,
+                lifetime,
             } => {
+                let accordant_amount = match accordant_amount {
+                    Some(accordant_amount) => accordant_amount,
+                    None => {
+                        runtime.request_info(ServiceId::Farcasterd, InfoMsg::GetReferencePrice)?;
+                        match runtime.report_failure()? {
+                            BusMsg::Info(InfoMsg::ReferencePrice(reference)) => {
+                                let accordant_amount = reference.accordant_amount(
+                                    arbitrating_amount,
+                                    oracle_spread.unwrap_or_default(),
+                                );
+                                runtime.note(format!(
+                                    "Reference price {} BTC/XMR, pricing the deal at {} for {}",
+                                    reference.price.as_btc(),
+                                    accordant_amount,
+                                    arbitrating_amount
+                                ));
+                                accordant_amount
+                            }
+                            _ => {
+                                return Err(Error::Farcaster(
+                                    "Received unexpected response".to_string(),
+                                ))
+                            }
+                        }
+                    }
+                };
+                let approval = match approval {
+                    ApprovalMode::Auto => ctl::DealApproval::Auto,
+                    ApprovalMode::Allowlist if allowed_takers.is_empty() => {
+                        return Err(Error::Farcaster(
+                            "The allowlist approval mode requires at least one --allow-taker"
+                                .to_string(),
+                        ));
+                    }
+                    ApprovalMode::Allowlist => ctl::DealApproval::Allowlist(allowed_takers),
+                    ApprovalMode::Manual => ctl::DealApproval::Manual,
+                };
                 let deal_parameters = DealParameters {
                     uuid: Uuid::new().into(),
                     network,
@@ -293,42 +360,92 @@ impl Exec for Command {
                     public_addr,
                     arbitrating_addr,
                     accordant_addr,
+                    approval,
+                    lifetime,
                 };
                 runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::MakeDeal(proto_deal))?;
                 // report success or failure of the request to cli
                 runtime.report_response_or_fail()?;
             }
 
-            Command::DealInfo { deal } => {
-                println!("\n Trading {}\n", deal_buy_information(&deal.parameters));
-                println!("{}", serde_yaml::to_string(&deal).expect("already parsed"));
-            }
+            Command::DealInfo { deal } => match runtime.output_format() {
+                OutputFormat::Text => {
+                    println!("\n Trading {}\n", deal_buy_information(&deal.parameters));
+                    println!("{}", serde_yaml::to_string(&deal).expect("already parsed"));
+                }
+                _ => runtime.report(Output::Deal(deal)),
+            },
 
             Command::Take {
                 deal,
+                deal_id,
+                signed_deal,
                 bitcoin_address,
                 monero_address,
                 without_validation,
             } => {
+                let (deal, origin) = match (deal, deal_id, signed_deal) {
+                    (Some(deal), _, _) => (deal, ctl::DealOrigin::Encoded),
+                    (None, _, Some(signed_deal)) => {
+                        signed_deal.verify()?;
+                        runtime.note(format!(
+                            "Deal signed by its maker, expires at {}",
+                            signed_deal.expiry
+                        ));
+                        (
+                            signed_deal.deal.clone(),
+                            ctl::DealOrigin::Signed(signed_deal),
+                        )
+                    }
+                    (None, Some(deal_id), None) => {
+                        runtime.request_info(ServiceId::Farcasterd, InfoMsg::ListRemoteDeals)?;
+                        match runtime.report_failure()? {
+                            BusMsg::Info(InfoMsg::RemoteDealList(remote_deals)) => remote_deals
+                                .iter()
+                                .find(|remote| remote.deal_id == DealId::from(deal_id))
+                                .map(|remote| (remote.deal.clone(), ctl::DealOrigin::Announced))
+                                .ok_or_else(|| {
+                                    Error::Farcaster(format!(
+                                        "Deal {} not found in the remote deals",
+                                        deal_id
+                                    ))
+                                })?,
+                            _ => {
+                                return Err(Error::Farcaster(
+                                    "Received unexpected response".to_string(),
+                                ))
+                            }
+                        }
+                    }
+                    (None, None, None) => {
+                        return Err(Error::Farcaster(
+                            "Either a deal, a deal id or a signed deal is required".to_string(),
+                        ))
+                    }
+                };
                 let Deal {
                     node_id,
                     peer_address,
                     ..
                 } = deal;
                 if !without_validation {
-                    println!(
+                    runtime.note(format!(
                         "\nWant to buy {}?\n\nCarefully validate the deal!\n",
                         deal_buy_information(&deal.parameters)
-                    );
-                    println!("Trade counterparty: {}@{}\n", &node_id, peer_address);
-                    println!("{}", serde_yaml::to_string(&deal).expect("already parsed"));
+                    ));
+                    runtime.note(format!(
+                        "Trade counterparty: {}@{}\n",
+                        &node_id, peer_address
+                    ));
+                    runtime.note(serde_yaml::to_string(&deal).expect("already parsed"));
                 }
-                if without_validation || take_deal() {
+                if without_validation || take_deal(runtime) {
                     // pass deal to farcasterd to initiate the swap
                     runtime.request_ctl(
                         ServiceId::Farcasterd,
                         CtlMsg::TakeDeal(ctl::PubDeal {
                             deal,
+                            origin,
                             bitcoin_address,
                             monero_address,
                         }),
@@ -338,6 +455,114 @@ impl Exec for Command {
                 }
             }
 
+            Command::Templates { command } => match command {
+                TemplatesCommand::Add {
+                    arbitrating_addr,
+                    accordant_addr,
+                    network,
+                    arbitrating_blockchain,
+                    accordant_blockchain,
+                    min_arbitrating_amount,
+                    max_arbitrating_amount,
+                    price,
+                    inventory,
+                    maker_role,
+                    cancel_timelock,
+                    punish_timelock,
+                    min_cancel_timelock,
+                    max_cancel_timelock,
+                    min_punish_timelock,
+                    max_punish_timelock,
+                    fee_strategy,
+                    public_ip_addr,
+                    public_port,
+                } => {
+                    if price.as_sat() == 0 {
+                        return Err(Error::Farcaster("The price must be positive".to_string()));
+                    }
+                    // accordant amount of the largest deal at the price, in piconero
+                    let accordant_amount = monero::Amount::from_pico(
+                        (max_arbitrating_amount.as_sat() as u128 * 1_000_000_000_000
+                            / price.as_sat() as u128) as u64,
+                    );
+                    let template = ctl::DealTemplate {
+                        id: Uuid::new(),
+                        deal_parameters: DealParameters {
+                            uuid: Uuid::new().into(),
+                            network,
+                            arbitrating_blockchain,
+                            accordant_blockchain,
+                            arbitrating_amount: max_arbitrating_amount,
+                            accordant_amount,
+                            cancel_timelock,
+                            punish_timelock,
+                            fee_strategy,
+                            maker_role,
+                        },
+                        min_arbitrating_amount,
+                        min_cancel_timelock: min_cancel_timelock
+                            .unwrap_or_else(|| cancel_timelock.as_u32()),
+                        max_cancel_timelock: max_cancel_timelock
+                            .unwrap_or_else(|| cancel_timelock.as_u32()),
+                        min_punish_timelock: min_punish_timelock
+                            .unwrap_or_else(|| punish_timelock.as_u32()),
+                        max_punish_timelock: max_punish_timelock
+                            .unwrap_or_else(|| punish_timelock.as_u32()),
+                        inventory,
+                        public_addr: InetSocketAddr::socket(public_ip_addr, public_port),
+                        arbitrating_addr,
+                        accordant_addr,
+                    };
+                    runtime
+                        .request_ctl(ServiceId::Farcasterd, CtlMsg::AddDealTemplate(template))?;
+                    runtime.report_response_or_fail()?;
+                }
+                TemplatesCommand::List => {
+                    runtime.request_info(ServiceId::Farcasterd, InfoMsg::ListDealTemplates)?;
+                    runtime.report_response_or_fail()?;
+                }
+                TemplatesCommand::Remove { template_id } => {
+                    runtime.request_ctl(
+                        ServiceId::Farcasterd,
+                        CtlMsg::RemoveDealTemplate(template_id),
+                    )?;
+                    runtime.report_response_or_fail()?;
+                }
+            },
+
+            Command::RequestQuote {
+                maker,
+                network,
+                arbitrating_amount,
+                template,
+                cancel_timelock,
+                punish_timelock,
+            } => {
+                let request = QuoteRequest {
+                    id: Uuid::new(),
+                    template_id: template,
+                    network,
+                    arbitrating_amount,
+                    cancel_timelock,
+                    punish_timelock,
+                };
+                runtime.request_ctl(
+                    ServiceId::Farcasterd,
+                    CtlMsg::RequestQuote(ctl::ProtoQuoteRequest { maker, request }),
+                )?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::ReferencePrice => {
+                runtime.request_info(ServiceId::Farcasterd, InfoMsg::GetReferencePrice)?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::MarketMaker => {
+                runtime.request_info(ServiceId::Farcasterd, InfoMsg::GetMarketMaker)?;
+                runtime.report_response_or_fail()?;
+            }
+
             Command::RevokeDeal { deal } => {
                 runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::RevokeDeal(deal))?;
                 runtime.report_response_or_fail()?;
@@ -348,6 +573,64 @@ impl Exec for Command {
                 runtime.report_response_or_fail()?;
             }
 
+            Command::Ban { node_id } => {
+                runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::BanPeer(node_id))?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::Unban { node_id } => {
+                runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::UnbanPeer(node_id))?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::ListBans => {
+                runtime.request_info(ServiceId::Farcasterd, InfoMsg::ListBans)?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::Approvals { command } => match command {
+                ApprovalsCommand::List => {
+                    runtime.request_info(ServiceId::Farcasterd, InfoMsg::ListApprovals)?;
+                    runtime.report_response_or_fail()?;
+                }
+                ApprovalsCommand::Accept { swap_id } => {
+                    runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::AcceptTaker(swap_id))?;
+                    runtime.report_response_or_fail()?;
+                }
+                ApprovalsCommand::Reject { swap_id } => {
+                    runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::RejectTaker(swap_id))?;
+                    runtime.report_response_or_fail()?;
+                }
+            },
+
+            Command::Watchtower { command } => match command {
+                WatchtowerCommand::Register { swap_id } => {
+                    runtime.request_ctl(
+                        ServiceId::Farcasterd,
+                        CtlMsg::RegisterWithWatchtowers(swap_id),
+                    )?;
+                    runtime.report_response_or_fail()?;
+                }
+                WatchtowerCommand::Revoke { swap_id } => {
+                    runtime.request_ctl(
+                        ServiceId::Farcasterd,
+                        CtlMsg::RevokeFromWatchtowers(swap_id),
+                    )?;
+                    runtime.report_response_or_fail()?;
+                }
+            },
+
+            Command::Maintenance { command } => {
+                let enable = command == MaintenanceCommand::On;
+                runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::SetMaintenance(enable))?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::Shutdown { force } => {
+                runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::Shutdown(force))?;
+                runtime.report_response_or_fail()?;
+            }
+
             Command::Progress { swapid, follow } => {
                 if follow {
                     // subscribe to progress event and loop until Finish event is received or user
@@ -455,6 +738,10 @@ impl Exec for Command {
                 }
             }
 
+            Command::Dashboard { refresh } => {
+                Dashboard::new(Duration::from_secs(refresh)).run(runtime)?;
+            }
+
             Command::Completion { shell } => {
                 let mut app = super::Opts::command();
                 let name = app.get_name().to_string();
@@ -498,20 +785,20 @@ impl Command {
     }
 }
 
-fn take_deal() -> bool {
-    println!("Deal or No Deal? [y/n]");
+fn take_deal(runtime: &Client) -> bool {
+    runtime.note("Deal or No Deal? [y/n]");
     let mut input = [0u8; 1];
     std::io::stdin().read_exact(&mut input).unwrap_or(());
     match std::str::from_utf8(&input[..]) {
         Ok("y") | Ok("Y") => {
-            println!("Deal!");
+            runtime.note("Deal!");
             true
         }
         Ok("n") | Ok("N") => {
-            println!("No Deal!");
+            runtime.note("No Deal!");
             false
         }
-        _ => take_deal(),
+        _ => take_deal(runtime),
     }
 }
 
@@ -0,0 +1,666 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use amplify::Wrapper;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use farcaster_core::blockchain::{Blockchain, Network};
+use farcaster_core::swap::SwapId;
+use internet2::addr::NodeId;
+use tui::backend::{Backend, CrosstermBackend};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap};
+use tui::{Frame, Terminal};
+
+use crate::bus::ctl::{CtlMsg, FundingInfo};
+use crate::bus::info::{DealStatusSelector, InfoMsg, NodeInfo};
+use crate::bus::{BusMsg, DealInfo, Failure, FailureCode, Progress};
+use crate::client::Client;
+use crate::swapd::StateReport;
+use crate::syncerd::Health;
+use crate::{Error, ServiceConfig, ServiceId};
+
+/// Time waited for a key press before the dashboard is drawn again
+const TICK: Duration = Duration::from_millis(250);
+
+/// Interactive terminal dashboard of the node. The node information is refreshed periodically,
+/// the state of the running swaps is followed through their progress subscription.
+pub struct Dashboard {
+    refresh: Duration,
+    last_refresh: Option<Instant>,
+    node_info: Option<NodeInfo>,
+    deals: Vec<DealInfo>,
+    swap_ids: Vec<SwapId>,
+    swaps: HashMap<SwapId, SwapView>,
+    syncers: Vec<(Blockchain, Network, Health)>,
+    funding: Vec<FundingInfo>,
+    // Progress of the subscribed swaps, received by a dedicated client per swap
+    progress_sender: Sender<(SwapId, BusMsg)>,
+    progress_receiver: Receiver<(SwapId, BusMsg)>,
+    focus: Panel,
+    deal_state: TableState,
+    swap_state: TableState,
+    funding_state: TableState,
+    // The swap whose log is displayed, if any
+    log_view: Option<SwapId>,
+    status: String,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Panel {
+    Deals,
+    Swaps,
+    Funding,
+}
+
+/// The latest state report of a swap and the log of its progress
+#[derive(Default)]
+struct SwapView {
+    report: Option<StateReport>,
+    log: Vec<String>,
+}
+
+impl Dashboard {
+    pub fn new(refresh: Duration) -> Self {
+        let (progress_sender, progress_receiver) = mpsc::channel();
+        Dashboard {
+            refresh,
+            last_refresh: None,
+            node_info: None,
+            deals: vec![],
+            swap_ids: vec![],
+            swaps: HashMap::new(),
+            syncers: vec![],
+            funding: vec![],
+            progress_sender,
+            progress_receiver,
+            focus: Panel::Swaps,
+            deal_state: TableState::default(),
+            swap_state: TableState::default(),
+            funding_state: TableState::default(),
+            log_view: None,
+            status: s!("Press q to quit"),
+        }
+    }
+
+    /// Take over the terminal until the user quits, the terminal is restored even on error
+    pub fn run(mut self, runtime: &mut Client) -> Result<(), Error> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        let res = self.event_loop(runtime, &mut terminal);
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+        res
+    }
+
+    fn event_loop<B: Backend>(
+        &mut self,
+        runtime: &mut Client,
+        terminal: &mut Terminal<B>,
+    ) -> Result<(), Error> {
+        loop {
+            if self
+                .last_refresh
+                .map_or(true, |last_refresh| last_refresh.elapsed() >= self.refresh)
+            {
+                if let Err(err) = self.refresh(runtime) {
+                    self.status = format!("Refresh failed: {}", err);
+                }
+                self.last_refresh = Some(Instant::now());
+            }
+            self.receive_progress();
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(TICK)? {
+                if let Event::Key(key) = event::read()? {
+                    if self.handle_key(runtime, key) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn refresh(&mut self, runtime: &mut Client) -> Result<(), Error> {
+        if let BusMsg::Info(InfoMsg::NodeInfo(info)) =
+            request_info(runtime, ServiceId::Farcasterd, InfoMsg::GetInfo)?
+        {
+            self.swap_ids = info.swaps.clone();
+            self.swaps.retain(|swap_id, _| info.swaps.contains(swap_id));
+            for swap_id in info.swaps.iter() {
+                if !self.swaps.contains_key(swap_id) {
+                    self.swaps.insert(*swap_id, SwapView::default());
+                    subscribe_progress(
+                        runtime.config().clone(),
+                        *swap_id,
+                        self.progress_sender.clone(),
+                    );
+                }
+            }
+            self.node_info = Some(info);
+        }
+        if let BusMsg::Info(InfoMsg::DealList(deals)) = request_info(
+            runtime,
+            ServiceId::Farcasterd,
+            InfoMsg::ListDeals(DealStatusSelector::Open),
+        )? {
+            self.deals = deals.into_inner();
+        }
+        self.syncers.clear();
+        for network in [Network::Mainnet, Network::Testnet, Network::Local] {
+            for blockchain in [Blockchain::Bitcoin, Blockchain::Monero] {
+                let syncer = ServiceId::Syncer(blockchain, network);
+                match request_info(runtime, syncer, InfoMsg::GetInfo) {
+                    Err(Error::Failure(Failure {
+                        code: FailureCode::TargetServiceNotFound,
+                        ..
+                    })) => continue,
+                    res => res?,
+                };
+                runtime.request_ctl(
+                    ServiceId::Farcasterd,
+                    CtlMsg::HealthCheck(blockchain, network),
+                )?;
+                if let BusMsg::Ctl(CtlMsg::HealthResult(health)) = runtime.report_failure()? {
+                    self.syncers.push((blockchain, network, health));
+                }
+            }
+        }
+        self.funding.clear();
+        for blockchain in [Blockchain::Bitcoin, Blockchain::Monero] {
+            if let BusMsg::Info(InfoMsg::FundingInfos(infos)) = request_info(
+                runtime,
+                ServiceId::Farcasterd,
+                InfoMsg::NeedsFunding(blockchain),
+            )? {
+                self.funding.extend(infos.swaps_need_funding);
+            }
+        }
+        clamp_selection(&mut self.deal_state, self.deals.len());
+        clamp_selection(&mut self.swap_state, self.swap_ids.len());
+        clamp_selection(&mut self.funding_state, self.funding.len());
+        Ok(())
+    }
+
+    fn receive_progress(&mut self) {
+        while let Ok((swap_id, msg)) = self.progress_receiver.try_recv() {
+            let view = match self.swaps.get_mut(&swap_id) {
+                Some(view) => view,
+                None => continue,
+            };
+            match msg {
+                BusMsg::Info(InfoMsg::Progress(Progress::StateUpdate(report))) => {
+                    view.report = Some(report);
+                }
+                BusMsg::Info(InfoMsg::Progress(Progress::StateTransition(transition))) => {
+                    view.log.push(format!(
+                        "{} -> {}",
+                        transition.old_state.state, transition.new_state.state
+                    ));
+                    view.report = Some(transition.new_state);
+                }
+                BusMsg::Info(InfoMsg::Progress(Progress::Message(message))) => {
+                    view.log.push(message);
+                }
+                BusMsg::Info(InfoMsg::Success(details)) => {
+                    view.log.push(format!("Swap ended: {}", details));
+                }
+                BusMsg::Info(InfoMsg::Failure(failure)) | BusMsg::Ctl(CtlMsg::Failure(failure)) => {
+                    view.log.push(format!("Failure: {}", failure.info));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Handle a key press, returns true once the user quits
+    fn handle_key(&mut self, runtime: &mut Client, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Char('q') | KeyCode::Esc if self.log_view.is_some() => self.log_view = None,
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Panel::Deals => Panel::Swaps,
+                    Panel::Swaps => Panel::Funding,
+                    Panel::Funding => Panel::Deals,
+                }
+            }
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Char('a') => self.abort_swap(runtime),
+            KeyCode::Char('r') => self.revoke_deal(runtime),
+            KeyCode::Char('c') => self.copy_funding_address(),
+            KeyCode::Char('l') | KeyCode::Enter => {
+                self.log_view = match self.log_view {
+                    Some(_) => None,
+                    None => self.selected_swap(),
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.focus {
+            Panel::Deals => (&mut self.deal_state, self.deals.len()),
+            Panel::Swaps => (&mut self.swap_state, self.swap_ids.len()),
+            Panel::Funding => (&mut self.funding_state, self.funding.len()),
+        };
+        if len == 0 {
+            state.select(None);
+            return;
+        }
+        let selected = state.selected().unwrap_or(0) as isize + delta;
+        state.select(Some(selected.rem_euclid(len as isize) as usize));
+    }
+
+    fn selected_swap(&self) -> Option<SwapId> {
+        self.swap_state
+            .selected()
+            .and_then(|i| self.swap_ids.get(i))
+            .copied()
+    }
+
+    fn abort_swap(&mut self, runtime: &mut Client) {
+        let swap_id = match self.selected_swap() {
+            Some(swap_id) => swap_id,
+            None => {
+                self.status = s!("Select a swap to abort");
+                return;
+            }
+        };
+        let res = runtime
+            .request_ctl(ServiceId::Swap(swap_id), CtlMsg::AbortSwap)
+            .and_then(|_| runtime.report_failure());
+        self.status = match res {
+            Ok(resp) => format!("Abort swap {}: {}", swap_id, resp),
+            Err(err) => format!("Abort swap {} failed: {}", swap_id, err),
+        };
+    }
+
+    fn revoke_deal(&mut self, runtime: &mut Client) {
+        let deal = match self.deal_state.selected().and_then(|i| self.deals.get(i)) {
+            Some(deal_info) => deal_info.deal.clone(),
+            None => {
+                self.status = s!("Select a deal to revoke");
+                return;
+            }
+        };
+        let res = runtime
+            .request_ctl(ServiceId::Farcasterd, CtlMsg::RevokeDeal(deal.clone()))
+            .and_then(|_| runtime.report_failure());
+        self.status = match res {
+            Ok(resp) => format!("Revoke deal {}: {}", deal.id(), resp),
+            Err(err) => format!("Revoke deal {} failed: {}", deal.id(), err),
+        };
+    }
+
+    /// Copy the selected funding address to the clipboard of the terminal with an OSC 52 escape
+    /// sequence, it works over ssh as long as the terminal supports it
+    fn copy_funding_address(&mut self) {
+        let address = match self
+            .funding_state
+            .selected()
+            .and_then(|i| self.funding.get(i))
+        {
+            Some(FundingInfo::Bitcoin(info)) => info.address.to_string(),
+            Some(FundingInfo::Monero(info)) => info.address.to_string(),
+            None => {
+                self.status = s!("Select a funding address to copy");
+                return;
+            }
+        };
+        let mut stdout = io::stdout();
+        let res = write!(stdout, "\x1b]52;c;{}\x07", base64::encode(&address))
+            .and_then(|_| stdout.flush());
+        self.status = match res {
+            Ok(_) => format!("Copied {}", address),
+            Err(err) => format!("Copy failed: {}", err),
+        };
+    }
+
+    fn draw<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Min(10),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(frame.size());
+        self.draw_header(frame, rows[0]);
+        match self.log_view {
+            Some(swap_id) => self.draw_log(frame, rows[1], swap_id),
+            None => {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(65), Constraint::Percentage(35)].as_ref())
+                    .split(rows[1]);
+                let left = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+                    .split(columns[0]);
+                let right = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(
+                        [
+                            Constraint::Percentage(30),
+                            Constraint::Percentage(30),
+                            Constraint::Percentage(40),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(columns[1]);
+                self.draw_deals(frame, left[0]);
+                self.draw_swaps(frame, left[1]);
+                self.draw_peers(frame, right[0]);
+                self.draw_syncers(frame, right[1]);
+                self.draw_funding(frame, right[2]);
+            }
+        }
+        let help = match self.log_view {
+            Some(_) => "q/l: back",
+            None => {
+                "q: quit  tab: focus  up/down: select  a: abort swap  r: revoke deal  \
+                 c: copy funding address  l: swap log"
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(Span::styled(help, Style::default().fg(Color::DarkGray))),
+            rows[2],
+        );
+    }
+
+    fn draw_header<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let summary = match &self.node_info {
+            Some(info) => format!(
+                "uptime {}s | {} peers | {} swaps | {} deals | maintenance {}",
+                info.uptime.as_secs(),
+                info.peers.len(),
+                info.swaps.len(),
+                info.deals.len(),
+                if info.maintenance.enabled {
+                    "on"
+                } else {
+                    "off"
+                },
+            ),
+            None => s!("Awaiting the node information"),
+        };
+        let header = Paragraph::new(vec![
+            Spans::from(Span::styled(
+                summary,
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Spans::from(self.status.as_str()),
+        ])
+        .block(Block::default().borders(Borders::BOTTOM).title("Farcaster"));
+        frame.render_widget(header, area);
+    }
+
+    fn draw_deals<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let rows = self.deals.iter().map(|deal_info| {
+            let parameters = &deal_info.deal.parameters;
+            Row::new(vec![
+                Cell::from(deal_info.deal.id().to_string()),
+                Cell::from(deal_info.local_trade_role.to_string()),
+                Cell::from(parameters.arbitrating_amount.to_string()),
+                Cell::from(parameters.accordant_amount.to_string()),
+                Cell::from(parameters.network.to_string()),
+            ])
+        });
+        let widths = [
+            Constraint::Percentage(30),
+            Constraint::Percentage(10),
+            Constraint::Percentage(22),
+            Constraint::Percentage(23),
+            Constraint::Percentage(15),
+        ];
+        let table = Table::new(rows)
+            .header(header_row(&[
+                "Deal", "Role", "Bitcoin", "Monero", "Network",
+            ]))
+            .block(panel_block("Open deals", self.focus == Panel::Deals))
+            .widths(&widths)
+            .highlight_style(highlight_style());
+        frame.render_stateful_widget(table, area, &mut self.deal_state);
+    }
+
+    fn draw_swaps<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let rows = self.swap_ids.iter().map(|swap_id| {
+            let report = self
+                .swaps
+                .get(swap_id)
+                .and_then(|view| view.report.as_ref());
+            let optional = |value: Option<String>| value.unwrap_or_else(|| s!("-"));
+            Row::new(vec![
+                Cell::from(swap_id.to_string()),
+                Cell::from(optional(report.map(|report| report.state.clone()))),
+                Cell::from(optional(report.and_then(|report| {
+                    report.arb_lock_confirmations.map(|c| c.to_string())
+                }))),
+                Cell::from(optional(report.and_then(|report| {
+                    report.acc_lock_confirmations.map(|c| c.to_string())
+                }))),
+                Cell::from(optional(report.and_then(|report| {
+                    report.blocks_until_cancel_possible.map(|b| b.to_string())
+                }))),
+                Cell::from(optional(report.and_then(|report| {
+                    report.blocks_until_punish_possible.map(|b| b.to_string())
+                }))),
+            ])
+        });
+        let widths = [
+            Constraint::Percentage(30),
+            Constraint::Percentage(30),
+            Constraint::Percentage(10),
+            Constraint::Percentage(10),
+            Constraint::Percentage(10),
+            Constraint::Percentage(10),
+        ];
+        let table = Table::new(rows)
+            .header(header_row(&[
+                "Swap",
+                "State",
+                "BTC conf",
+                "XMR conf",
+                "To cancel",
+                "To punish",
+            ]))
+            .block(panel_block("Running swaps", self.focus == Panel::Swaps))
+            .widths(&widths)
+            .highlight_style(highlight_style());
+        frame.render_stateful_widget(table, area, &mut self.swap_state);
+    }
+
+    fn draw_peers<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let peers: Vec<Spans> = self
+            .node_info
+            .iter()
+            .flat_map(|info| info.peers.iter())
+            .map(|peer: &NodeId| Spans::from(peer.to_string()))
+            .collect();
+        let paragraph = Paragraph::new(peers)
+            .block(panel_block("Peers", false))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_syncers<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let rows = self.syncers.iter().map(|(blockchain, network, health)| {
+            // the syncer height as last seen by the swaps on its network
+            let height = self
+                .swaps
+                .values()
+                .filter_map(|view| view.report.as_ref())
+                .map(|report| match blockchain {
+                    Blockchain::Bitcoin => report.arb_block_height,
+                    Blockchain::Monero => report.acc_block_height,
+                })
+                .max()
+                .map_or_else(|| s!("-"), |height| height.to_string());
+            let color = match health {
+                Health::Healthy => Color::Green,
+                _ => Color::Red,
+            };
+            Row::new(vec![
+                Cell::from(format!("{} {}", blockchain, network)),
+                Cell::from(height),
+                Cell::from(Span::styled(health.to_string(), Style::default().fg(color))),
+            ])
+        });
+        let widths = [
+            Constraint::Percentage(35),
+            Constraint::Percentage(20),
+            Constraint::Percentage(45),
+        ];
+        let table = Table::new(rows)
+            .header(header_row(&["Syncer", "Height", "Health"]))
+            .block(panel_block("Syncers", false))
+            .widths(&widths);
+        frame.render_widget(table, area);
+    }
+
+    fn draw_funding<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let rows = self.funding.iter().map(|funding_info| {
+            let (swap_id, amount, address) = match funding_info {
+                FundingInfo::Bitcoin(info) => (
+                    info.swap_id,
+                    info.amount.to_string(),
+                    info.address.to_string(),
+                ),
+                FundingInfo::Monero(info) => (
+                    info.swap_id,
+                    info.amount.to_string(),
+                    info.address.to_string(),
+                ),
+            };
+            Row::new(vec![
+                Cell::from(swap_id.to_string()),
+                Cell::from(amount),
+                Cell::from(address),
+            ])
+        });
+        let widths = [
+            Constraint::Percentage(30),
+            Constraint::Percentage(25),
+            Constraint::Percentage(45),
+        ];
+        let table = Table::new(rows)
+            .header(header_row(&["Swap", "Amount", "Address"]))
+            .block(panel_block("Funding needs", self.focus == Panel::Funding))
+            .widths(&widths)
+            .highlight_style(highlight_style());
+        frame.render_stateful_widget(table, area, &mut self.funding_state);
+    }
+
+    fn draw_log<B: Backend>(&self, frame: &mut Frame<B>, area: Rect, swap_id: SwapId) {
+        let log: Vec<Spans> = self
+            .swaps
+            .get(&swap_id)
+            .map(|view| {
+                view.log
+                    .iter()
+                    .map(|line| Spans::from(line.as_str()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // keep the latest lines in view
+        let visible = area.height.saturating_sub(2) as usize;
+        let scroll = log.len().saturating_sub(visible) as u16;
+        let paragraph = Paragraph::new(log)
+            .block(panel_block(&format!("Swap {} log", swap_id), true))
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0));
+        frame.render_widget(paragraph, area);
+    }
+}
+
+/// Request an info from the service and wait for its response
+fn request_info(runtime: &mut Client, service: ServiceId, req: InfoMsg) -> Result<BusMsg, Error> {
+    runtime.request_info(service, req)?;
+    runtime.report_failure()
+}
+
+/// Follow the progress of the swap with a dedicated client, the progress notifications do not
+/// carry the swap they belong to. The client stops once the swap ends or the dashboard quits.
+fn subscribe_progress(config: ServiceConfig, swap_id: SwapId, sender: Sender<(SwapId, BusMsg)>) {
+    std::thread::spawn(move || {
+        let mut client = match Client::with(config) {
+            Ok(client) => client,
+            Err(err) => {
+                let _ = sender.send((swap_id, BusMsg::from(err)));
+                return;
+            }
+        };
+        if let Err(err) =
+            client.request_info(ServiceId::Farcasterd, InfoMsg::SubscribeProgress(swap_id))
+        {
+            let _ = sender.send((swap_id, BusMsg::from(err)));
+            return;
+        }
+        while let Ok(msg) = client.response() {
+            let ended = matches!(
+                msg,
+                BusMsg::Info(InfoMsg::Success(_))
+                    | BusMsg::Info(InfoMsg::Failure(_))
+                    | BusMsg::Ctl(CtlMsg::Failure(_))
+            );
+            if sender.send((swap_id, msg)).is_err() || ended {
+                break;
+            }
+        }
+    });
+}
+
+fn clamp_selection(state: &mut TableState, len: usize) {
+    match state.selected() {
+        _ if len == 0 => state.select(None),
+        Some(selected) if selected >= len => state.select(Some(len - 1)),
+        None => state.select(Some(0)),
+        _ => {}
+    }
+}
+
+fn panel_block(title: &str, focused: bool) -> Block<'static> {
+    let style = if focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(Span::styled(
+            title.to_string(),
+            style.add_modifier(Modifier::BOLD),
+        ))
+}
+
+fn header_row(titles: &[&'static str]) -> Row<'static> {
+    Row::new(titles.iter().copied()).style(Style::default().add_modifier(Modifier::BOLD))
+}
+
+fn highlight_style() -> Style {
+    Style::default().add_modifier(Modifier::REVERSED)
+}
@@ -5,6 +5,13 @@
 // https://opensource.org/licenses/MIT.
 
 mod command;
+mod dashboard;
 mod opts;
+mod output;
 
-pub use opts::{Command, DealSelector, Opts};
+pub use dashboard::Dashboard;
+pub use opts::{
+    ApprovalsCommand, Command, DealSelector, MaintenanceCommand, Opts, TemplatesCommand,
+    WatchtowerCommand,
+};
+pub use output::{Output, OutputFormat, OutputFormatParseError};
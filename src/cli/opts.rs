@@ -6,6 +6,7 @@
 
 use bitcoin::Address as BtcAddress;
 use clap_complete::shells::Shell;
+use internet2::addr::{NodeAddr, NodeId};
 use monero::Address as XmrAddress;
 use std::net::IpAddr;
 use std::str::FromStr;
@@ -15,13 +16,15 @@ use farcaster_core::{
     blockchain::{Blockchain, FeeStrategy, Network},
     role::SwapRole,
     swap::{btcxmr::Deal, SwapId},
+    Uuid,
 };
 
 use crate::bus::info::Address;
-use crate::bus::HealthCheckSelector;
+use crate::bus::{HealthCheckSelector, SignedDeal};
+use crate::cli::OutputFormat;
 
 /// Command-line tool for working with Farcaster node
-#[derive(Parser, Clone, PartialEq, Eq, Debug)]
+#[derive(Parser, Clone, PartialEq, Debug)]
 #[clap(name = "swap-cli", bin_name = "swap-cli", author, version)]
 pub struct Opts {
     /// These params can be read also from the configuration file, not just
@@ -29,6 +32,15 @@ pub struct Opts {
     #[clap(flatten)]
     pub shared: crate::opts::Opts,
 
+    /// Format of the output: human readable text, or json and yaml documents for scripts
+    #[clap(
+        long,
+        global = true,
+        default_value = "text",
+        possible_values = &["text", "json", "yaml"]
+    )]
+    pub format: OutputFormat,
+
     /// Command to execute
     #[clap(subcommand)]
     pub command: Command,
@@ -41,12 +53,12 @@ impl Opts {
 }
 
 /// Command-line commands:
-#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+#[derive(Subcommand, Clone, PartialEq, Debug, Display)]
 pub enum Command {
     /// General information about the running node
     #[display("info<{subject:?}>")]
     Info {
-        /// Remote peer address, swap id, or blockchain and network. If absent, returns information
+        /// Remote peer address or node id, swap id, or blockchain and network. If absent, returns information
         /// about the node itself
         subject: Vec<String>,
     },
@@ -70,6 +82,40 @@ pub enum Command {
         select: DealSelector,
     },
 
+    /// Lists the deals announced by remote makers and relayed by the connected peers
+    #[clap(aliases = &["lrd"])]
+    ListRemoteDeals,
+
+    /// Queries the configured deal registry for the deals matching the network, amount range and
+    /// price
+    #[clap(aliases = &["qr"])]
+    QueryRegistry {
+        /// Network of the deals.
+        #[clap(
+            short,
+            long,
+            default_value = "testnet",
+            possible_values = &["Testnet", "testnet", "Mainnet", "mainnet", "Local", "local"]
+        )]
+        network: Network,
+
+        /// Minimum amount of arbitrating assets exchanged.
+        #[clap(long = "min-btc-amount")]
+        min_amount: Option<bitcoin::Amount>,
+
+        /// Maximum amount of arbitrating assets exchanged.
+        #[clap(long = "max-btc-amount")]
+        max_amount: Option<bitcoin::Amount>,
+
+        /// Minimum price, in BTC per XMR.
+        #[clap(long)]
+        min_price: Option<bitcoin::Amount>,
+
+        /// Maximum price, in BTC per XMR.
+        #[clap(long)]
+        max_price: Option<bitcoin::Amount>,
+    },
+
     /// Gives information on an open deal
     #[clap(aliases = &["di"])]
     #[display("deal-info<{deal}>")]
@@ -178,8 +224,18 @@ pub enum Command {
         arbitrating_amount: bitcoin::Amount,
 
         /// Amount of accordant assets to exchanged.
-        #[clap(long = "xmr-amount")]
-        accordant_amount: monero::Amount,
+        #[clap(
+            long = "xmr-amount",
+            required_unless_present = "oracle-spread",
+            conflicts_with = "oracle-spread"
+        )]
+        accordant_amount: Option<monero::Amount>,
+
+        /// Prices the deal relatively to the reference price of the price oracle instead of
+        /// giving the accordant amount: the spread in percent added to the reference price of
+        /// one XMR in BTC, e.g. 1.5 sells XMR 1.5% above it or -1.5 buys XMR 1.5% below it.
+        #[clap(long, allow_hyphen_values = true)]
+        oracle_spread: Option<f64>,
 
         /// The future maker swap role, either Alice of Bob. This will dictate with asset will be
         /// exchanged for which asset. Alice will sell accordant assets for arbitrating ones and
@@ -211,6 +267,26 @@ pub enum Command {
         /// {farcasterd.bind_ip}:{farcasterd.bind_port}
         #[clap(short = 'p', long, default_value = "7067")]
         public_port: u16,
+
+        /// How takers are accepted: any taker (auto), only the node ids given with
+        /// --allow-taker (allowlist), or once approved with `swap-cli approvals accept`
+        /// (manual).
+        #[clap(
+            long,
+            default_value = "auto",
+            possible_values = &["auto", "allowlist", "manual"]
+        )]
+        approval: ApprovalMode,
+
+        /// Node id of a taker allowed to take the deal, can be repeated. Requires the allowlist
+        /// approval mode.
+        #[clap(long = "allow-taker")]
+        allowed_takers: Vec<NodeId>,
+
+        /// Number of seconds the deal remains takeable. The deal is then shared signed with its
+        /// expiry and automatically revoked once expired.
+        #[clap(long = "expires-in")]
+        lifetime: Option<u64>,
     },
 
     /// Taker accepts deal and connects to maker's daemon to start the trade.
@@ -224,14 +300,80 @@ pub enum Command {
         monero_address: XmrAddress,
 
         /// An encoded deal.
-        #[clap(short = 'D', long = "deal")]
-        deal: Deal,
+        #[clap(
+            short = 'D',
+            long = "deal",
+            required_unless_present_any = &["deal-id", "signed-deal"]
+        )]
+        deal: Option<Deal>,
+
+        /// The id of a deal announced by a remote maker, as listed by list-remote-deals.
+        #[clap(long = "deal-id", conflicts_with = "deal")]
+        deal_id: Option<Uuid>,
+
+        /// An encoded deal signed by its maker, the signature and the expiry of the deal are
+        /// verified before connecting to the maker.
+        #[clap(long = "signed-deal", conflicts_with_all = &["deal", "deal-id"])]
+        signed_deal: Option<SignedDeal>,
 
         /// Accept the deal without validation.
         #[clap(short, long)]
         without_validation: bool,
     },
 
+    /// Adds, lists or removes the deal templates from which deals are issued on the quote
+    /// requests of the takers
+    #[display("templates<{command}>")]
+    Templates {
+        #[clap(subcommand)]
+        command: TemplatesCommand,
+    },
+
+    /// Requests a deal for the given amount from the deal templates of a remote maker, the
+    /// quoted deal is signed and can be taken with `take --signed-deal`
+    #[clap(aliases = &["rq"])]
+    #[display("request-quote<{maker}>")]
+    RequestQuote {
+        /// The maker to request the quote from, as node_id@ip:port
+        maker: NodeAddr,
+
+        /// Network of the deal.
+        #[clap(
+            short,
+            long,
+            default_value = "testnet",
+            possible_values = &["Testnet", "testnet", "Mainnet", "mainnet", "Local", "local"]
+        )]
+        network: Network,
+
+        /// Amount of arbitrating assets to exchange.
+        #[clap(long = "btc-amount")]
+        arbitrating_amount: bitcoin::Amount,
+
+        /// The deal template to quote from, any template of the maker covering the amount
+        /// otherwise.
+        #[clap(long)]
+        template: Option<Uuid>,
+
+        /// The cancel timelock requested, the template default otherwise.
+        #[clap(long)]
+        cancel_timelock: Option<u32>,
+
+        /// The punish timelock requested, the template default otherwise.
+        #[clap(long)]
+        punish_timelock: Option<u32>,
+    },
+
+    /// Shows the reference price of one XMR in BTC of the price oracle configured in
+    /// farcasterd.toml
+    #[clap(aliases = &["price"])]
+    ReferencePrice,
+
+    /// Shows the state of the market maker configured in farcasterd.toml: its inventory, its
+    /// running swaps and the deals it keeps open on each network
+    #[clap(aliases = &["mm"])]
+    MarketMaker,
+
     /// Revoke deal accepts a deal and revokes it within the runtime.
     #[display("revoke-deal<{deal}>")]
     RevokeDeal {
@@ -246,6 +388,55 @@ pub enum Command {
         swap_id: SwapId,
     },
 
+    /// Ban a remote node, its connections are dropped and it can't reconnect until unbanned
+    #[display("ban<{node_id}>")]
+    Ban {
+        /// The node id of the remote peer to ban
+        node_id: NodeId,
+    },
+
+    /// Lift the ban of a remote node
+    #[display("unban<{node_id}>")]
+    Unban {
+        /// The node id of the remote peer to unban
+        node_id: NodeId,
+    },
+
+    /// Lists the banned remote nodes
+    ListBans,
+
+    /// Lists, accepts or rejects the takers awaiting approval for deals made with manual
+    /// approval
+    #[display("approvals<{command}>")]
+    Approvals {
+        #[clap(subcommand)]
+        command: ApprovalsCommand,
+    },
+
+    /// Registers a swap with the configured watchtowers again, or revokes it from them
+    #[display("watchtower<{command}>")]
+    Watchtower {
+        #[clap(subcommand)]
+        command: WatchtowerCommand,
+    },
+
+    /// Turns the maintenance mode on or off. In maintenance mode no new swap starts, our open
+    /// deals are hidden without being revoked and the running swaps drain
+    #[display("maintenance<{command}>")]
+    Maintenance {
+        #[clap(subcommand)]
+        command: MaintenanceCommand,
+    },
+
+    /// Shuts the node down once the running swaps checkpointed, stopping the services in
+    /// dependency order. Refused if a swap is inside a race window, unless forced
+    #[display("shutdown")]
+    Shutdown {
+        /// Shut down even if swaps are inside a race window or did not checkpoint in time
+        #[clap(long)]
+        force: bool,
+    },
+
     /// Request swap progress report.
     #[display("progress<{swapid}>")]
     Progress {
@@ -296,6 +487,16 @@ pub enum Command {
         address: Address,
     },
 
+    /// Opens an interactive dashboard of the node in the terminal: the open deals, the running
+    /// swaps, the peers, the syncers and the funding needs
+    #[clap(aliases = &["dash"])]
+    #[display("dashboard")]
+    Dashboard {
+        /// Seconds between two refreshes of the node information.
+        #[clap(short, long, default_value = "5")]
+        refresh: u64,
+    },
+
     /// Output shell completion code for the specified shell (bash, zsh or fish)
     ///
     /// The shell code must be evaluated to provide interactive completion of swap-cli commands.
@@ -314,6 +515,198 @@ pub enum Command {
     },
 }
 
+/// Maintenance commands:
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum MaintenanceCommand {
+    /// Stops accepting new swaps, `swap-cli info` shows when the running swaps are drained
+    #[display("on")]
+    On,
+
+    /// Accepts new swaps again and announces our open deals
+    #[display("off")]
+    Off,
+}
+
+/// Watchtower commands:
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum WatchtowerCommand {
+    /// Registers the swap with the watchtowers again, once its cancel transaction is signed
+    #[display("register<{swap_id}>")]
+    Register {
+        /// The swap id to register
+        swap_id: SwapId,
+    },
+
+    /// Revokes the swap from the watchtowers, they stop protecting its funds
+    #[display("revoke<{swap_id}>")]
+    Revoke {
+        /// The swap id to revoke
+        swap_id: SwapId,
+    },
+}
+
+/// Approval commands:
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum ApprovalsCommand {
+    /// Lists the takers awaiting approval
+    #[display("list")]
+    List,
+
+    /// Accepts the taker of the swap, the other takers of the same deal are rejected
+    #[display("accept<{swap_id}>")]
+    Accept {
+        /// The swap id of the taker to accept
+        swap_id: SwapId,
+    },
+
+    /// Rejects the taker of the swap
+    #[display("reject<{swap_id}>")]
+    Reject {
+        /// The swap id of the taker to reject
+        swap_id: SwapId,
+    },
+}
+
+/// Deal template commands:
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum TemplatesCommand {
+    /// Adds a deal template, takers request quotes for an amount within its range and are
+    /// issued deals at its price
+    #[display("add")]
+    Add {
+        /// Bitcoin address used as destination or refund address.
+        #[clap(long = "btc-addr")]
+        arbitrating_addr: BtcAddress,
+
+        /// Monero address used as destination or refund address.
+        #[clap(long = "xmr-addr")]
+        accordant_addr: XmrAddress,
+
+        /// Network to use to execute the swaps between the chosen blockchains.
+        #[clap(
+            short,
+            long,
+            default_value = "testnet",
+            possible_values = &["Testnet", "testnet", "Mainnet", "mainnet", "Local", "local"]
+        )]
+        network: Network,
+
+        /// The chosen arbitrating blockchain.
+        #[clap(
+            long = "arb-blockchain",
+            default_value = "bitcoin",
+            possible_values = &["Bitcoin", "bitcoin"])
+        ]
+        arbitrating_blockchain: Blockchain,
+
+        /// The chosen accordant blockchain.
+        #[clap(
+            long = "acc-blockchain",
+            default_value = "monero",
+            possible_values = &["Monero", "monero"])
+        ]
+        accordant_blockchain: Blockchain,
+
+        /// Minimum amount of arbitrating assets a deal is issued for.
+        #[clap(long = "min-btc-amount")]
+        min_arbitrating_amount: bitcoin::Amount,
+
+        /// Maximum amount of arbitrating assets a deal is issued for.
+        #[clap(long = "max-btc-amount")]
+        max_arbitrating_amount: bitcoin::Amount,
+
+        /// Price of one XMR in BTC.
+        #[clap(long)]
+        price: bitcoin::Amount,
+
+        /// Total amount of arbitrating assets the template issues deals for, unlimited if not
+        /// set.
+        #[clap(long)]
+        inventory: Option<bitcoin::Amount>,
+
+        /// The future maker swap role, either Alice of Bob.
+        #[clap(short = 'r', long, default_value = "Bob", possible_values = &["Alice", "Bob"])]
+        maker_role: SwapRole,
+
+        /// The cancel timelock quoted when the taker does not request one.
+        #[clap(long, default_value = "4")]
+        cancel_timelock: CSVTimelock,
+
+        /// The punish timelock quoted when the taker does not request one.
+        #[clap(long, default_value = "5")]
+        punish_timelock: CSVTimelock,
+
+        /// Lowest cancel timelock a taker can request, the default cancel timelock if not set.
+        #[clap(long)]
+        min_cancel_timelock: Option<u32>,
+
+        /// Highest cancel timelock a taker can request, the default cancel timelock if not set.
+        #[clap(long)]
+        max_cancel_timelock: Option<u32>,
+
+        /// Lowest punish timelock a taker can request, the default punish timelock if not set.
+        #[clap(long)]
+        min_punish_timelock: Option<u32>,
+
+        /// Highest punish timelock a taker can request, the default punish timelock if not set.
+        #[clap(long)]
+        max_punish_timelock: Option<u32>,
+
+        /// The chosen fee for the arbitrating transactions.
+        #[clap(long, default_value = "1000 satoshi/kvB")]
+        fee_strategy: FeeStrategy<SatPerKvB>,
+
+        /// Public IPv4 or IPv6 address to advertise in the issued deals; defaults to 127.0.0.1.
+        #[clap(short = 'I', long, default_value = "127.0.0.1")]
+        public_ip_addr: IpAddr,
+
+        /// Public port to advertise in the issued deals; defaults to the FC port 7067.
+        #[clap(short = 'p', long, default_value = "7067")]
+        public_port: u16,
+    },
+
+    /// Lists the deal templates and their remaining inventory
+    #[display("list")]
+    List,
+
+    /// Removes a deal template, the deals already issued from it remain takeable until they
+    /// expire
+    #[display("remove<{template_id}>")]
+    Remove {
+        /// The id of the template to remove
+        template_id: Uuid,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+pub enum ApprovalMode {
+    #[display("auto")]
+    Auto,
+    #[display("allowlist")]
+    Allowlist,
+    #[display("manual")]
+    Manual,
+}
+
+impl FromStr for ApprovalMode {
+    type Err = ApprovalModeParseError;
+    fn from_str(input: &str) -> Result<ApprovalMode, Self::Err> {
+        match input {
+            "auto" | "Auto" => Ok(ApprovalMode::Auto),
+            "allowlist" | "Allowlist" => Ok(ApprovalMode::Allowlist),
+            "manual" | "Manual" => Ok(ApprovalMode::Manual),
+            _ => Err(ApprovalModeParseError::Invalid),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ApprovalModeParseError {
+    /// The provided value can't be parsed as an approval mode
+    Invalid,
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, From)]
 pub enum DealSelector {
     #[display("Open")]
@@ -0,0 +1,183 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::str::FromStr;
+
+use farcaster_core::swap::{btcxmr::Deal, SwapId};
+use internet2::addr::NodeId;
+
+use crate::bus::ctl::CtlMsg;
+use crate::bus::info::{
+    AddressBalance, BitcoinAddressSwapIdPair, DealTemplateInfo, FundingInfos, InfoMsg, MadeDeal,
+    MaintenanceStatus, MarketMakerInfo, MoneroAddressSwapIdPair, NodeInfo, PeerInfo,
+    PendingApproval, QuotedDeal, ReferencePrice, RemoteDealInfo, SwapInfo, SwapProgress,
+    SyncerInfo, TookDeal,
+};
+use crate::bus::{
+    BusMsg, CheckpointEntry, CompleteHealthReport, DealInfo, DefaultHealthReport, Failure, List,
+    OptionDetails, PeerBan, Progress, ReducedHealthReport,
+};
+use crate::syncerd::runtime::SyncerdTask;
+
+/// Format of the swap-cli output
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+pub enum OutputFormat {
+    /// Human readable output, not meant to be parsed
+    #[display("text")]
+    Text,
+    /// One json document per line
+    #[display("json")]
+    Json,
+    /// Yaml documents separated by `---`
+    #[display("yaml")]
+    Yaml,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Text
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = OutputFormatParseError;
+    fn from_str(input: &str) -> Result<OutputFormat, Self::Err> {
+        match input {
+            "text" | "Text" => Ok(OutputFormat::Text),
+            "json" | "Json" | "JSON" => Ok(OutputFormat::Json),
+            "yaml" | "Yaml" | "YAML" => Ok(OutputFormat::Yaml),
+            _ => Err(OutputFormatParseError::Invalid),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum OutputFormatParseError {
+    /// The provided value can't be parsed as an output format, use text, json or yaml
+    Invalid,
+}
+
+/// A document written by swap-cli. In the json and yaml formats the document is an object with
+/// its kind under `type` and its content under `data`, e.g.
+/// `{"type":"failure","data":{"code":"InvalidDeal","info":"..."}}`. The kinds and the fields of
+/// their content are stable, new kinds and new fields may be added.
+#[derive(Clone, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize),
+    serde(
+        crate = "serde_crate",
+        tag = "type",
+        content = "data",
+        rename_all = "snake_case"
+    )
+)]
+#[display(inner)]
+pub enum Output {
+    /// The request succeeded, with an optional message
+    Success(OptionDetails),
+    /// The request failed, with the failure code and its description. Written as the last
+    /// document before exiting with a non-zero status
+    Failure(Failure),
+    /// A free-form message, the text representation of the responses without a dedicated kind
+    Message(String),
+    NodeInfo(NodeInfo),
+    PeerInfo(PeerInfo),
+    SwapInfo(SwapInfo),
+    SyncerInfo(SyncerInfo),
+    Peers(List<NodeId>),
+    Swaps(List<SwapId>),
+    Tasks(List<SyncerdTask>),
+    Deals(List<DealInfo>),
+    Listens(List<String>),
+    Bans(List<PeerBan>),
+    Approvals(List<PendingApproval>),
+    RemoteDeals(List<RemoteDealInfo>),
+    DealTemplates(List<DealTemplateInfo>),
+    MarketMaker(MarketMakerInfo),
+    ReferencePrice(ReferencePrice),
+    Maintenance(MaintenanceStatus),
+    Checkpoints(List<CheckpointEntry>),
+    BitcoinAddresses(List<BitcoinAddressSwapIdPair>),
+    MoneroAddresses(List<MoneroAddressSwapIdPair>),
+    FundingInfos(FundingInfos),
+    AddressBalance(AddressBalance),
+    /// The progress made so far by a swap
+    SwapProgress(SwapProgress),
+    /// An event of the `--follow` progress stream, terminated by a success or a failure
+    Progress(Progress),
+    MadeDeal(MadeDeal),
+    TookDeal(TookDeal),
+    QuotedDeal(QuotedDeal),
+    Deal(Deal),
+    DefaultHealthReport(DefaultHealthReport),
+    ReducedHealthReport(ReducedHealthReport),
+    CompleteHealthReport(CompleteHealthReport),
+}
+
+impl From<BusMsg> for Output {
+    fn from(msg: BusMsg) -> Self {
+        match msg {
+            BusMsg::Ctl(CtlMsg::Success(details)) | BusMsg::Info(InfoMsg::Success(details)) => {
+                Output::Success(details)
+            }
+            BusMsg::Ctl(CtlMsg::Failure(failure)) | BusMsg::Info(InfoMsg::Failure(failure)) => {
+                Output::Failure(failure)
+            }
+            BusMsg::Ctl(CtlMsg::Progress(progress)) | BusMsg::Info(InfoMsg::Progress(progress)) => {
+                Output::Progress(progress)
+            }
+            BusMsg::Info(info) => match info {
+                InfoMsg::String(message) => Output::Message(message),
+                InfoMsg::NodeInfo(info) => Output::NodeInfo(info),
+                InfoMsg::PeerInfo(info) => Output::PeerInfo(info),
+                InfoMsg::SwapInfo(info) => Output::SwapInfo(info),
+                InfoMsg::SyncerInfo(info) => Output::SyncerInfo(info),
+                InfoMsg::PeerList(peers) => Output::Peers(peers),
+                InfoMsg::SwapList(swaps) => Output::Swaps(swaps),
+                InfoMsg::TaskList(tasks) => Output::Tasks(tasks),
+                InfoMsg::DealList(deals) | InfoMsg::DealInfoList(deals) => Output::Deals(deals),
+                InfoMsg::ListenList(listens) => Output::Listens(listens),
+                InfoMsg::PeerBanList(bans) => Output::Bans(bans),
+                InfoMsg::ApprovalList(approvals) => Output::Approvals(approvals),
+                InfoMsg::RemoteDealList(deals) | InfoMsg::RegistryDealList(deals) => {
+                    Output::RemoteDeals(deals)
+                }
+                InfoMsg::DealTemplateList(templates) => Output::DealTemplates(templates),
+                InfoMsg::MarketMakerInfo(info) => Output::MarketMaker(info),
+                InfoMsg::ReferencePrice(price) => Output::ReferencePrice(price),
+                InfoMsg::MaintenanceStatus(status) => Output::Maintenance(status),
+                InfoMsg::CheckpointList(checkpoints) => Output::Checkpoints(checkpoints),
+                InfoMsg::BitcoinAddressList(addresses) => Output::BitcoinAddresses(addresses),
+                InfoMsg::MoneroAddressList(addresses) => Output::MoneroAddresses(addresses),
+                InfoMsg::FundingInfos(infos) => Output::FundingInfos(infos),
+                InfoMsg::AddressBalance(balance) => Output::AddressBalance(balance),
+                InfoMsg::SwapProgress(progress) => Output::SwapProgress(progress),
+                InfoMsg::MadeDeal(deal) => Output::MadeDeal(deal),
+                InfoMsg::TookDeal(deal) => Output::TookDeal(deal),
+                InfoMsg::QuotedDeal(deal) => Output::QuotedDeal(deal),
+                info => Output::Message(info.to_string()),
+            },
+            msg => Output::Message(msg.to_string()),
+        }
+    }
+}
+
+impl Output {
+    /// Serialize the document in the format, the text format is its display
+    pub fn format(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Text => self.to_string(),
+            OutputFormat::Json => {
+                serde_json::to_string(self).expect("internal JSON serialization error")
+            }
+            OutputFormat::Yaml => {
+                serde_yaml::to_string(self).expect("internal YAML serialization error")
+            }
+        }
+    }
+}
@@ -4,13 +4,15 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::fmt::Display;
 use std::thread::sleep;
 use std::time::Duration;
 
 use internet2::ZmqSocketType;
 use microservices::esb;
 
-use crate::bus::{ctl::CtlMsg, info::InfoMsg, BusMsg, ServiceBus};
+use crate::bus::{ctl::CtlMsg, info::InfoMsg, BusMsg, Failure, ServiceBus};
+use crate::cli::{Output, OutputFormat};
 use crate::service::Endpoints;
 use crate::service::ServiceConfig;
 use crate::{Error, LogStyle, ServiceId};
@@ -20,21 +22,24 @@ pub struct Client {
     identity: ServiceId,
     response_queue: std::collections::VecDeque<BusMsg>,
     esb: esb::Controller<ServiceBus, BusMsg, Handler>,
+    config: ServiceConfig,
+    output_format: OutputFormat,
 }
 
 impl Client {
     pub fn with(config: ServiceConfig) -> Result<Self, Error> {
         debug!("Setting up RPC client...");
         let identity = ServiceId::client();
+        let esb_config = config.clone();
         let esb = esb::Controller::with(
             map! {
                 ServiceBus::Ctl => esb::BusConfig::with_addr(
-                    config.ctl_endpoint,
+                    esb_config.ctl_endpoint,
                     ZmqSocketType::RouterConnect,
                     Some(ServiceId::router())
                 ),
                 ServiceBus::Info => esb::BusConfig::with_addr(
-                    config.info_endpoint,
+                    esb_config.info_endpoint,
                     ZmqSocketType::RouterConnect,
                     Some(ServiceId::router()),
                 )
@@ -51,6 +56,8 @@ impl Client {
             identity,
             response_queue: empty!(),
             esb,
+            config,
+            output_format: OutputFormat::default(),
         })
     }
 
@@ -58,6 +65,19 @@ impl Client {
         self.identity.clone()
     }
 
+    /// The configuration of the client, to connect other clients to the same node
+    pub fn config(&self) -> &ServiceConfig {
+        &self.config
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    pub fn set_output_format(&mut self, output_format: OutputFormat) {
+        self.output_format = output_format;
+    }
+
     pub fn request_info(&mut self, daemon: ServiceId, req: InfoMsg) -> Result<(), Error> {
         debug!("Executing {}", req);
         self.esb
@@ -86,18 +106,41 @@ impl Client {
 
     pub fn report_failure(&mut self) -> Result<BusMsg, Error> {
         match self.response()? {
-            BusMsg::Ctl(CtlMsg::Failure(fail)) => Err(Error::Farcaster(fail.info)),
+            BusMsg::Ctl(CtlMsg::Failure(fail)) | BusMsg::Info(InfoMsg::Failure(fail)) => {
+                Err(Error::Failure(fail))
+            }
             resp => Ok(resp),
         }
     }
 
     pub fn report_response_or_fail(&mut self) -> Result<(), Error> {
         let resp = self.report_failure()?;
-        // note: this triggers the yaml formatting when implemented
-        println!("{}", resp);
+        self.report(resp);
         Ok(())
     }
 
+    /// Write a document in the output format on stdout
+    pub fn report(&self, output: impl Into<Output>) {
+        println!("{}", output.into().format(self.output_format));
+    }
+
+    /// Write the error as a failure document, on stderr in the text format
+    pub fn report_error(&self, err: &Error) {
+        match self.output_format {
+            OutputFormat::Text => eprintln!("{} {}", "error:".err(), err.err()),
+            _ => self.report(Output::Failure(Failure::from(err))),
+        }
+    }
+
+    /// Write a message meant for the user only, on stderr unless in the text format to keep the
+    /// json and yaml documents on stdout parseable
+    pub fn note(&self, message: impl Display) {
+        match self.output_format {
+            OutputFormat::Text => println!("{}", message),
+            _ => eprintln!("{}", message),
+        }
+    }
+
     /// Print the stream of received requests until progress fails or succeed
     pub fn report_progress(&mut self) -> Result<(), Error> {
         // loop on all requests received until a progress termination condition is recieved
@@ -111,11 +154,14 @@ impl Client {
                     break Err(e)
                 }
                 Ok(BusMsg::Ctl(CtlMsg::Success(s))) => {
-                    println!("{}", s.bright_green_bold());
+                    match self.output_format {
+                        OutputFormat::Text => println!("{}", s.bright_green_bold()),
+                        _ => self.report(Output::Success(s)),
+                    }
                     // terminate on success
                     break Ok(());
                 }
-                Ok(req) => println!("{}", req),
+                Ok(req) => self.report(req),
             }
         }
     }
@@ -5,9 +5,11 @@
 // https://opensource.org/licenses/MIT.
 
 use config::ConfigError::Message;
-use farcaster_core::blockchain::Network;
+use farcaster_core::bitcoin::fee::SatPerKvB;
+use farcaster_core::blockchain::{FeeStrategy, Network};
+use farcaster_core::role::SwapRole;
 use farcaster_core::swap::btcxmr::DealParameters;
-use internet2::addr::InetSocketAddr;
+use internet2::addr::{InetSocketAddr, NodeAddr, NodeId};
 use serde::{Deserialize, Serialize};
 use serde_with::DisplayFromStr;
 
@@ -17,7 +19,9 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
+use crate::bus::info::{accordant_amount_at, price_with_spread};
 use crate::{AccordantBlockchain, ArbitratingBlockchain, Error};
 
 pub const FARCASTER_MAINNET_ELECTRUM_SERVER: &str = "ssl://blockstream.info:700";
@@ -31,6 +35,16 @@ pub const FARCASTER_TESTNET_MONERO_RPC_WALLET: &str = "http://localhost:38083";
 pub const FARCASTER_BIND_PORT: u16 = 7067;
 pub const FARCASTER_BIND_IP: &str = "0.0.0.0";
 
+pub const FARCASTER_MAX_SERVICE_RESTARTS: u32 = 3;
+
+pub const FARCASTER_MAX_INBOUND_CONNECTIONS: u32 = 64;
+pub const FARCASTER_MAX_INBOUND_CONNECTIONS_PER_IP: u32 = 4;
+pub const FARCASTER_MAX_HANDSHAKES_PER_MINUTE: u32 = 30;
+pub const FARCASTER_BAN_SCORE_THRESHOLD: u32 = 100;
+pub const FARCASTER_APPROVAL_TIMEOUT: u64 = 600;
+pub const FARCASTER_PRICE_REFRESH_INTERVAL: u64 = 60;
+pub const FARCASTER_MAX_PRICE_DEVIATION: f64 = 5.0;
+
 pub const GRPC_BIND_IP_ADDRESS: &str = "127.0.0.1";
 
 pub const SWAP_MAINNET_BITCOIN_SAFETY: u8 = 7;
@@ -45,6 +59,14 @@ pub const SWAP_TESTNET_BITCOIN_SAFETY: u8 = 3;
 pub const SWAP_TESTNET_BITCOIN_FINALITY: u8 = 1;
 pub const SWAP_TESTNET_MONERO_FINALITY: u8 = 1;
 
+pub const SWAP_BITCOIN_MAX_SYNCER_DATA_AGE: u64 = 120;
+pub const SWAP_MONERO_MAX_SYNCER_DATA_AGE: u64 = 120;
+
+pub const SWAP_COMMIT_TIMEOUT: u64 = 300;
+pub const SWAP_REVEAL_TIMEOUT: u64 = 600;
+pub const SWAP_FUNDING_TIMEOUT: u64 = 3600;
+pub const SWAP_LOCK_TIMEOUT: u64 = 3600;
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(crate = "serde_crate")]
 pub struct Config {
@@ -56,6 +78,9 @@ pub struct Config {
     pub grpc: Option<GrpcConfig>,
     /// Syncer configuration
     pub syncers: Option<Networked<Option<SyncerServers>>>,
+    /// Watchtower configuration, both for serving as a watchtower and for registering swaps with
+    /// remote watchtowers
+    pub watchtower: Option<WatchtowerConfig>,
 }
 
 impl Config {
@@ -100,6 +125,171 @@ impl Config {
         }
     }
 
+    /// Returns the maximum number of times a crashed swapd or syncerd is relaunched within an hour
+    /// before giving up on it, if not set return the default value
+    pub fn max_service_restarts(&self) -> u32 {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                max_service_restarts: Some(max),
+                ..
+            }) => *max,
+            _ => FARCASTER_MAX_SERVICE_RESTARTS,
+        }
+    }
+
+    /// Returns the maximum number of inbound peer connections the listener accepts, if not set
+    /// return the default value
+    pub fn max_inbound_connections(&self) -> u32 {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                max_inbound_connections: Some(max),
+                ..
+            }) => *max,
+            _ => FARCASTER_MAX_INBOUND_CONNECTIONS,
+        }
+    }
+
+    /// Returns the maximum number of inbound peer connections the listener accepts from a single
+    /// ip address, if not set return the default value
+    pub fn max_inbound_connections_per_ip(&self) -> u32 {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                max_inbound_connections_per_ip: Some(max),
+                ..
+            }) => *max,
+            _ => FARCASTER_MAX_INBOUND_CONNECTIONS_PER_IP,
+        }
+    }
+
+    /// Returns the maximum number of inbound handshakes the listener starts per minute, if not
+    /// set return the default value
+    pub fn max_handshakes_per_minute(&self) -> u32 {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                max_handshakes_per_minute: Some(max),
+                ..
+            }) => *max,
+            _ => FARCASTER_MAX_HANDSHAKES_PER_MINUTE,
+        }
+    }
+
+    /// Returns the misbehavior score at which a peer is automatically banned, if not set return
+    /// the default value
+    pub fn ban_score_threshold(&self) -> u32 {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                ban_score_threshold: Some(threshold),
+                ..
+            }) => *threshold,
+            _ => FARCASTER_BAN_SCORE_THRESHOLD,
+        }
+    }
+
+    /// Returns the time in seconds a taker awaits the manual approval of the maker before being
+    /// rejected, if not set return the default value
+    pub fn approval_timeout(&self) -> Duration {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                approval_timeout: Some(timeout),
+                ..
+            }) => Duration::from_secs(*timeout),
+            _ => Duration::from_secs(FARCASTER_APPROVAL_TIMEOUT),
+        }
+    }
+
+    /// Returns the url of the deal registry the open deals are published to and fetched from, if
+    /// any
+    pub fn deal_registry(&self) -> Option<String> {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                deal_registry: Some(url),
+                ..
+            }) => Some(url.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns if this node serves as a watchtower for its authorized clients
+    pub fn is_watchtower_enable(&self) -> bool {
+        match &self.watchtower {
+            Some(WatchtowerConfig { enable, .. }) => *enable,
+            _ => false,
+        }
+    }
+
+    /// Returns the node ids allowed to register swaps with this node when serving as a watchtower
+    pub fn watchtower_authorized_clients(&self) -> Vec<NodeId> {
+        match &self.watchtower {
+            Some(WatchtowerConfig {
+                authorized_clients, ..
+            }) => authorized_clients.clone(),
+            _ => vec![],
+        }
+    }
+
+    /// Returns the remote watchtowers this node registers its swaps with, empty if none
+    pub fn watchtowers(&self) -> Vec<NodeAddr> {
+        match &self.watchtower {
+            Some(WatchtowerConfig { towers, .. }) => towers.clone(),
+            _ => vec![],
+        }
+    }
+
+    /// Returns the price oracle configuration, if any
+    pub fn price_oracle(&self) -> Option<PriceOracleConfig> {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                price_oracle: Some(price_oracle),
+                ..
+            }) => Some(price_oracle.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the deal policy for the given network and our role in the swap, if any
+    pub fn deal_policy(&self, network: Network, local_role: SwapRole) -> Option<DealPolicy> {
+        let policies = self
+            .farcasterd
+            .as_ref()?
+            .policy
+            .as_ref()?
+            .get_for_network(network)?;
+        match local_role {
+            SwapRole::Alice => policies.alice,
+            SwapRole::Bob => policies.bob,
+        }
+    }
+
+    /// Returns the maximum number of swaps running at once, if any
+    pub fn max_concurrent_swaps(&self) -> Option<u32> {
+        self.farcasterd
+            .as_ref()?
+            .swap_limits
+            .as_ref()?
+            .max_concurrent_swaps
+    }
+
+    /// Returns the in flight and daily volume limits for the given network, if any
+    pub fn volume_limits(&self, network: Network) -> Option<VolumeLimits> {
+        self.farcasterd
+            .as_ref()?
+            .swap_limits
+            .as_ref()?
+            .networks
+            .get_for_network(network)
+    }
+
+    /// Returns the market maker configuration if enable
+    pub fn market_maker(&self) -> Option<MarketMakerConfig> {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                market_maker: Some(market_maker),
+                ..
+            }) if market_maker.enable => Some(market_maker.clone()),
+            _ => None,
+        }
+    }
+
     /// Returns the auto-funding configuration for a given network if enable, if None no
     /// configuration is found
     pub fn get_auto_funding_config(&self, network: Network) -> Option<AutoFundingServers> {
@@ -181,6 +371,7 @@ impl Config {
                 Ok(ParsedSwapConfig {
                     arbitrating,
                     accordant,
+                    timeouts: swap.timeouts.clone().unwrap_or_default(),
                 })
             }
             None => {
@@ -191,20 +382,59 @@ impl Config {
                 Ok(ParsedSwapConfig {
                     arbitrating,
                     accordant,
+                    timeouts: PreLockTimeouts::default(),
                 })
             }
         }
     }
 
-    /// Validate a deal against user configuration (farcasterd.toml) and user provided addresses
+    /// Validate a deal against user configuration (farcasterd.toml), user provided addresses and,
+    /// if given, the reference price of the price oracle
     pub fn validate_deal_parameters(
         &self,
         deal: &DealParameters,
         arb_addr: &bitcoin::Address,
         acc_addr: &monero::Address,
+        reference_price: Option<bitcoin::Amount>,
     ) -> Result<(), Error> {
         self.validate_deal_addresses(deal, arb_addr, acc_addr)?;
-        self.validate_deal_amounts(deal)
+        self.validate_deal_amounts(deal)?;
+        if let Some(reference_price) = reference_price {
+            self.validate_deal_price(deal, reference_price)?;
+        }
+        Ok(())
+    }
+
+    /// Validate the price of a deal is within the maximum deviation from the reference price of
+    /// one monero in bitcoin
+    pub fn validate_deal_price(
+        &self,
+        deal: &DealParameters,
+        reference_price: bitcoin::Amount,
+    ) -> Result<(), Error> {
+        let max_deviation = self
+            .price_oracle()
+            .and_then(|oracle| oracle.max_deviation)
+            .unwrap_or(FARCASTER_MAX_PRICE_DEVIATION);
+        if deal.accordant_amount.as_pico() == 0 {
+            return Err(Error::InvalidDeal(
+                "The accordant amount must be positive".to_string(),
+            ));
+        }
+        let price =
+            deal.arbitrating_amount.as_sat() as f64 * 1e12 / deal.accordant_amount.as_pico() as f64;
+        let deviation =
+            (price - reference_price.as_sat() as f64).abs() / reference_price.as_sat() as f64;
+        if deviation * 100.0 > max_deviation {
+            return Err(Error::InvalidDeal(format!(
+                "The deal price {} BTC/XMR is {:.2}% off the reference price {} BTC/XMR, more than {}%",
+                price / 1e8,
+                deviation * 100.0,
+                reference_price.as_btc(),
+                max_deviation
+            )));
+        }
+        Ok(())
     }
 
     /// Validate deal amounts against user configuration (farcasterd.toml)
@@ -332,6 +562,7 @@ impl Default for Config {
                 }),
                 local: None,
             }),
+            watchtower: None,
         }
     }
 }
@@ -347,6 +578,30 @@ pub struct FarcasterdConfig {
     pub bind_ip: Option<String>,
     /// Whether checkpoints should be auto restored at start-up, or not
     pub auto_restore: Option<bool>,
+    /// Sets how many times a crashed swapd or syncerd is relaunched within an hour
+    pub max_service_restarts: Option<u32>,
+    /// Sets the maximum number of inbound peer connections
+    pub max_inbound_connections: Option<u32>,
+    /// Sets the maximum number of inbound peer connections from a single ip address
+    pub max_inbound_connections_per_ip: Option<u32>,
+    /// Sets the maximum number of inbound handshakes per minute
+    pub max_handshakes_per_minute: Option<u32>,
+    /// Sets the misbehavior score at which a peer is automatically banned
+    pub ban_score_threshold: Option<u32>,
+    /// Sets the time in seconds a taker awaits the manual approval of the maker
+    pub approval_timeout: Option<u64>,
+    /// Sets the url of the HTTP deal registry the open deals are published to
+    pub deal_registry: Option<String>,
+    /// Sets the market maker keeping deals open, default to no market maker
+    pub market_maker: Option<MarketMakerConfig>,
+    /// Sets the price oracle the taken deals are validated against, default to no price oracle
+    pub price_oracle: Option<PriceOracleConfig>,
+    /// Sets the policies the made and taken deals must comply with, per network and role,
+    /// default to no policy
+    pub policy: Option<Networked<Option<PolicyConfig>>>,
+    /// Sets the limits on the swaps running at once and on the volume swapped, default to no
+    /// limits
+    pub swap_limits: Option<SwapLimitsConfig>,
 }
 
 /// This struct holds all swap config, for all chains and all networks
@@ -357,6 +612,9 @@ pub struct SwapConfig {
     pub bitcoin: Networked<Option<ChainSwapConfig<ArbConfig, bitcoin::Amount>>>,
     /// Swap parameters for the Monero blockchain per network
     pub monero: Networked<Option<ChainSwapConfig<AccConfig, monero::Amount>>>,
+    /// Timeouts of the swap states before the lock, default to the timeouts of
+    /// [`PreLockTimeouts::default`]
+    pub timeouts: Option<PreLockTimeouts>,
 }
 
 /// This struct holds the complete swap config for a chain
@@ -385,6 +643,38 @@ pub struct ParsedSwapConfig {
     pub arbitrating: ArbConfig,
     /// Swap parameters for an accordant blockchain
     pub accordant: AccConfig,
+    /// Timeouts of the swap states before the lock
+    pub timeouts: PreLockTimeouts,
+}
+
+/// Holds the time, in seconds, a swap waits in a state before the bitcoin lock before it is
+/// aborted. Zero disables the timeout.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+#[serde(default)]
+pub struct PreLockTimeouts {
+    /// Awaiting the commit and the reveal of the counterparty once the swap started, default to
+    /// 300 seconds
+    pub commit: u64,
+    /// Awaiting the next protocol step once the parameters are revealed, Bob the fee estimation
+    /// and Alice the core arbitrating setup of Bob, default to 600 seconds
+    pub reveal: u64,
+    /// Bob awaiting the funding of the swap, default to 3600 seconds
+    pub funding: u64,
+    /// Awaiting the lock, Bob the refund procedure signatures of Alice to broadcast it and Alice
+    /// the lock of Bob, default to 3600 seconds
+    pub lock: u64,
+}
+
+impl Default for PreLockTimeouts {
+    fn default() -> Self {
+        PreLockTimeouts {
+            commit: SWAP_COMMIT_TIMEOUT,
+            reveal: SWAP_REVEAL_TIMEOUT,
+            funding: SWAP_FUNDING_TIMEOUT,
+            lock: SWAP_LOCK_TIMEOUT,
+        }
+    }
 }
 
 /// Holds the parameters needed for an arbitrating asset in a swap, e.g. Bitcoin
@@ -396,9 +686,19 @@ pub struct ArbConfig {
     pub safety: u8,
     /// Number of confirmations required to consider a transaction final
     pub finality: u8,
+    /// Maximum age in seconds of the syncer's view of the chain before race-sensitive
+    /// transactions are withheld, default to 120 seconds
+    #[serde(default)]
+    pub max_syncer_data_age: Option<u64>,
 }
 
 impl ArbConfig {
+    /// Returns the maximum age in seconds of the syncer data, if not set return the default value
+    pub fn max_syncer_data_age(&self) -> u64 {
+        self.max_syncer_data_age
+            .unwrap_or(SWAP_BITCOIN_MAX_SYNCER_DATA_AGE)
+    }
+
     fn get(blockchain: ArbitratingBlockchain, network: Network) -> Option<Self> {
         match blockchain {
             ArbitratingBlockchain::Bitcoin => match network {
@@ -413,6 +713,7 @@ impl ArbConfig {
         ArbConfig {
             safety: SWAP_MAINNET_BITCOIN_SAFETY,
             finality: SWAP_MAINNET_BITCOIN_FINALITY,
+            max_syncer_data_age: None,
         }
     }
 
@@ -420,6 +721,7 @@ impl ArbConfig {
         ArbConfig {
             safety: SWAP_TESTNET_BITCOIN_SAFETY,
             finality: SWAP_TESTNET_BITCOIN_FINALITY,
+            max_syncer_data_age: None,
         }
     }
 }
@@ -430,9 +732,19 @@ impl ArbConfig {
 pub struct AccConfig {
     /// Number of confirmations required to consider a transaction final
     pub finality: u8,
+    /// Maximum age in seconds of the syncer's view of the chain before race-sensitive
+    /// decisions are withheld, default to 120 seconds
+    #[serde(default)]
+    pub max_syncer_data_age: Option<u64>,
 }
 
 impl AccConfig {
+    /// Returns the maximum age in seconds of the syncer data, if not set return the default value
+    pub fn max_syncer_data_age(&self) -> u64 {
+        self.max_syncer_data_age
+            .unwrap_or(SWAP_MONERO_MAX_SYNCER_DATA_AGE)
+    }
+
     fn get(blockchain: AccordantBlockchain, network: Network) -> Option<Self> {
         match blockchain {
             AccordantBlockchain::Monero => match network {
@@ -446,12 +758,14 @@ impl AccConfig {
     fn xmr_mainnet_default() -> Self {
         AccConfig {
             finality: SWAP_MAINNET_MONERO_FINALITY,
+            max_syncer_data_age: None,
         }
     }
 
     fn xmr_testnet_default() -> Self {
         AccConfig {
             finality: SWAP_TESTNET_MONERO_FINALITY,
+            max_syncer_data_age: None,
         }
     }
 }
@@ -461,6 +775,7 @@ impl From<ArbConfig> for AccConfig {
     fn from(arb: ArbConfig) -> Self {
         Self {
             finality: arb.finality,
+            max_syncer_data_age: arb.max_syncer_data_age,
         }
     }
 }
@@ -512,12 +827,18 @@ where
     {
         if let Some(min) = self.min_amount {
             if amount < min {
-                return Err(Message(format!("{} is smaller than {}", amount, min)))?;
+                return Err(Error::AmountOutOfRange(format!(
+                    "{} is smaller than {}",
+                    amount, min
+                )));
             }
         }
         if let Some(max) = self.max_amount {
             if amount > max {
-                return Err(Message(format!("{} is greater than {}", amount, max)))?;
+                return Err(Error::AmountOutOfRange(format!(
+                    "{} is greater than {}",
+                    amount, max
+                )));
             }
         }
         Ok(())
@@ -543,6 +864,209 @@ pub struct GrpcConfig {
     pub bind_ip: Option<String>,
 }
 
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct WatchtowerConfig {
+    /// Serve as a watchtower for the authorized clients
+    pub enable: bool,
+    /// Node ids allowed to register swaps when serving as a watchtower
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    pub authorized_clients: Vec<NodeId>,
+    /// Watchtowers to register our swaps with, as `<node_id>@<ip>:<port>`
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    pub towers: Vec<NodeAddr>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct PriceOracleConfig {
+    /// Url of the HTTP price source, answering a JSON document
+    pub url: Option<String>,
+    /// JSON pointer to the price of one monero in bitcoin in the answer of the HTTP price source,
+    /// the whole answer if not set
+    pub json_pointer: Option<String>,
+    /// File containing the price of one monero in bitcoin, used if no url is set
+    pub file: Option<String>,
+    /// Seconds between two price updates, default to 60 seconds
+    pub refresh_interval: Option<u64>,
+    /// Maximum deviation in percent of the price of a taken deal from the reference price,
+    /// default to 5%
+    pub max_deviation: Option<f64>,
+}
+
+impl PriceOracleConfig {
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.refresh_interval
+                .unwrap_or(FARCASTER_PRICE_REFRESH_INTERVAL),
+        )
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct SwapLimitsConfig {
+    /// Maximum number of swaps running at once on all networks, unlimited if not set
+    pub max_concurrent_swaps: Option<u32>,
+    /// Limits on the amounts swapped per network
+    #[serde(flatten)]
+    pub networks: Networked<Option<VolumeLimits>>,
+}
+
+/// Limits on the amounts swapped on a network, each unset limit is not enforced
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct VolumeLimits {
+    /// Maximum bitcoin of the swaps running at once
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub max_btc_in_flight: Option<bitcoin::Amount>,
+    /// Maximum monero of the swaps running at once
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub max_xmr_in_flight: Option<monero::Amount>,
+    /// Maximum bitcoin of the swaps started over the last 24 hours
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub max_btc_daily_volume: Option<bitcoin::Amount>,
+    /// Maximum monero of the swaps started over the last 24 hours
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub max_xmr_daily_volume: Option<monero::Amount>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct PolicyConfig {
+    /// Policy of the deals where we are Alice, selling monero
+    pub alice: Option<DealPolicy>,
+    /// Policy of the deals where we are Bob, selling bitcoin
+    pub bob: Option<DealPolicy>,
+}
+
+/// Bounds on the parameters of a deal, each unset bound is not enforced
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+#[cfg_attr(test, derive(Default))]
+pub struct DealPolicy {
+    /// Minimum cancel timelock in blocks
+    pub min_cancel_timelock: Option<u32>,
+    /// Maximum cancel timelock in blocks
+    pub max_cancel_timelock: Option<u32>,
+    /// Minimum punish timelock in blocks
+    pub min_punish_timelock: Option<u32>,
+    /// Maximum punish timelock in blocks
+    pub max_punish_timelock: Option<u32>,
+    /// Minimum number of blocks left to buy once the lock transaction is final and before the
+    /// cancel transaction can race it
+    pub min_buy_window: Option<u32>,
+    /// Minimum number of blocks left to refund once the cancel transaction is final and before
+    /// the punish transaction can race it
+    pub min_refund_window: Option<u32>,
+    /// Minimum fee rate of the arbitrating transactions
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub min_fee_rate: Option<SatPerKvB>,
+    /// Maximum fee rate of the arbitrating transactions
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub max_fee_rate: Option<SatPerKvB>,
+    /// Node ids of the makers whose deals can be taken, any maker if empty
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    pub allowed_makers: Vec<NodeId>,
+    /// Maximum number of swaps running at once on the network with this role
+    pub max_concurrent_swaps: Option<u32>,
+}
+
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct MarketMakerConfig {
+    /// Run the market maker
+    pub enable: bool,
+    /// Bitcoin sold by the market maker deals where we are Bob, unlimited if not set
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub btc_inventory: Option<bitcoin::Amount>,
+    /// Monero sold by the market maker deals where we are Alice, unlimited if not set
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub xmr_inventory: Option<monero::Amount>,
+    /// The deals kept open, per network
+    #[serde(default)]
+    pub deals: Vec<MarketMakerDeals>,
+}
+
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct MarketMakerDeals {
+    /// Network of the deals
+    #[serde_as(as = "DisplayFromStr")]
+    pub network: Network,
+    /// Number of deals kept open
+    pub open_deals: u32,
+    /// Bitcoin amounts of the deals, used in turn
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub sizes: Vec<bitcoin::Amount>,
+    /// Reference price of one monero in bitcoin, the price of the price oracle if not set
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub price: Option<bitcoin::Amount>,
+    /// Spread in percent applied to the reference price in our favor, default to 0
+    #[serde(default)]
+    pub spread: f64,
+    /// Our role in the swaps, Alice sells monero and Bob sells bitcoin
+    #[serde_as(as = "DisplayFromStr")]
+    pub maker_role: SwapRole,
+    /// Cancel timelock of the deals in blocks
+    pub cancel_timelock: u32,
+    /// Punish timelock of the deals in blocks
+    pub punish_timelock: u32,
+    /// Fee strategy of the arbitrating transactions
+    #[serde_as(as = "DisplayFromStr")]
+    pub fee_strategy: FeeStrategy<SatPerKvB>,
+    /// Address receiving or refunding the bitcoin of the swaps
+    #[serde_as(as = "DisplayFromStr")]
+    pub bitcoin_address: bitcoin::Address,
+    /// Address receiving or refunding the monero of the swaps
+    #[serde_as(as = "DisplayFromStr")]
+    pub monero_address: monero::Address,
+    /// Public address of the node announced in the deals
+    #[serde_as(as = "DisplayFromStr")]
+    pub public_addr: InetSocketAddr,
+}
+
+impl MarketMakerDeals {
+    /// Our price of one monero in bitcoin, the configured price or else the price of the oracle,
+    /// moved by the spread in our favor: Alice sells monero above it and Bob buys monero below it
+    pub fn price(&self, oracle_price: Option<bitcoin::Amount>) -> Option<bitcoin::Amount> {
+        let spread = match self.maker_role {
+            SwapRole::Alice => self.spread,
+            SwapRole::Bob => -self.spread,
+        };
+        self.price
+            .or(oracle_price)
+            .map(|price| price_with_spread(price, spread))
+    }
+
+    /// Monero amount exchanged for the bitcoin amount at our price, None if no price is known
+    pub fn accordant_amount(
+        &self,
+        arbitrating_amount: bitcoin::Amount,
+        oracle_price: Option<bitcoin::Amount>,
+    ) -> Option<monero::Amount> {
+        self.price(oracle_price)
+            .map(|price| accordant_amount_at(arbitrating_amount, price))
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(crate = "serde_crate")]
 pub struct AutoFundingConfig {
@@ -636,6 +1160,7 @@ impl Default for SwapConfig {
                 }),
                 local: None,
             },
+            timeouts: None,
         }
     }
 }
@@ -650,6 +1175,17 @@ impl Default for FarcasterdConfig {
             // write the default port and ip in the generated config
             bind_port: Some(FARCASTER_BIND_PORT),
             bind_ip: Some(FARCASTER_BIND_IP.to_string()),
+            max_service_restarts: Some(FARCASTER_MAX_SERVICE_RESTARTS),
+            max_inbound_connections: Some(FARCASTER_MAX_INBOUND_CONNECTIONS),
+            max_inbound_connections_per_ip: Some(FARCASTER_MAX_INBOUND_CONNECTIONS_PER_IP),
+            max_handshakes_per_minute: Some(FARCASTER_MAX_HANDSHAKES_PER_MINUTE),
+            ban_score_threshold: Some(FARCASTER_BAN_SCORE_THRESHOLD),
+            approval_timeout: Some(FARCASTER_APPROVAL_TIMEOUT),
+            deal_registry: None,
+            market_maker: None,
+            price_oracle: None,
+            policy: None,
+            swap_limits: None,
         }
     }
 }
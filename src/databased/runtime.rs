@@ -6,18 +6,21 @@
 
 use farcaster_core::swap::btcxmr::Deal;
 use farcaster_core::swap::SwapId;
+use farcaster_core::trade::DealId;
 use farcaster_core::{blockchain::Blockchain, role::TradeRole};
+use internet2::addr::NodeId;
 use lmdb::{Cursor, Transaction as LMDBTransaction};
 use std::io::Cursor as IoCursor;
 use std::path::PathBuf;
 use strict_encoding::{StrictDecode, StrictEncode};
 
 use crate::bus::{
-    ctl::{Checkpoint, CtlMsg},
+    ctl::{Checkpoint, CtlMsg, WatchedSwap},
     info::{Address, InfoMsg},
     info::{BitcoinAddressSwapIdPair, DealStatusSelector, MoneroAddressSwapIdPair},
+    p2p::WatchtowerSwap,
     AddressSecretKey, BitcoinSecretKeyInfo, BusMsg, CheckpointEntry, DealInfo, DealStatus, Failure,
-    FailureCode, MoneroSecretKeyInfo, Outcome, ServiceBus,
+    FailureCode, MoneroSecretKeyInfo, Outcome, PeerBan, ServiceBus, StartedSwap,
 };
 use crate::{swapd::CheckpointSwapd, Endpoints};
 use crate::{CtlServer, Error, LogStyle, Service, ServiceConfig, ServiceId};
@@ -86,6 +89,17 @@ impl Runtime {
                 debug!("Received Hello from {}", source);
             }
 
+            CtlMsg::Terminate if source == ServiceId::Farcasterd => {
+                info!("Terminating {}", self.identity().label());
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::Ctl(CtlMsg::Terminated),
+                )?;
+                std::process::exit(0);
+            }
+
             CtlMsg::Checkpoint(Checkpoint { swap_id, state }) => {
                 let info = CheckpointEntry {
                     swap_id,
@@ -99,12 +113,21 @@ impl Runtime {
                 debug!("{} | setting swap checkpoint", swap_id.swap_id());
                 let key = CheckpointKey {
                     swap_id,
-                    service_id: source,
+                    service_id: source.clone(),
                 };
                 let mut state_encoded = vec![];
                 state.strict_encode(&mut state_encoded)?;
                 self.database.set_checkpoint_state(&key, &state_encoded)?;
                 debug!("{} | checkpoint set", swap_id.swap_id());
+                // swapd acknowledges the checkpointed peer messages once they are saved
+                if let Err(err) = endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    source,
+                    BusMsg::Ctl(CtlMsg::CheckpointSaved),
+                ) {
+                    debug!("{} | Could not confirm the checkpoint: {}", swap_id, err);
+                }
             }
 
             CtlMsg::RestoreCheckpoint(CheckpointEntry { swap_id, .. }) => {
@@ -112,21 +135,19 @@ impl Runtime {
                     swap_id,
                     service_id: ServiceId::Swap(swap_id),
                 }) {
-                    Ok(raw_state) => {
-                        match CheckpointSwapd::strict_decode(IoCursor::new(raw_state)) {
-                            Ok(state) => {
-                                endpoints.send_to(
-                                    ServiceBus::Ctl,
-                                    self.identity(),
-                                    ServiceId::Swap(swap_id),
-                                    BusMsg::Ctl(CtlMsg::Checkpoint(Checkpoint { swap_id, state })),
-                                )?;
-                            }
-                            Err(err) => {
-                                error!("Decoding the checkpoint failed: {}", err);
-                            }
+                    Ok(raw_state) => match CheckpointSwapd::strict_decode_with_legacy(&raw_state) {
+                        Ok(state) => {
+                            endpoints.send_to(
+                                ServiceBus::Ctl,
+                                self.identity(),
+                                ServiceId::Swap(swap_id),
+                                BusMsg::Ctl(CtlMsg::Checkpoint(Checkpoint { swap_id, state })),
+                            )?;
                         }
-                    }
+                        Err(err) => {
+                            error!("Decoding the checkpoint failed: {}", err);
+                        }
+                    },
                     Err(err) => {
                         error!(
                             "Failed to retrieve checkpointed state for swap {}: {}",
@@ -212,6 +233,118 @@ impl Runtime {
                     })?;
             }
 
+            CtlMsg::SetWatchedSwap(watched_swap) => {
+                debug!(
+                    "{} | setting watched swap",
+                    watched_swap.swap.swap_id.swap_id()
+                );
+                self.database.set_watched_swap(&watched_swap)?;
+            }
+
+            CtlMsg::RemoveWatchedSwap(swap_id) => {
+                if let Err(err) = self.database.delete_watched_swap(swap_id) {
+                    debug!("{} | Did not delete watched swap: {}", swap_id, err);
+                }
+            }
+
+            CtlMsg::SetWatchtowerRegistration(swap) => {
+                debug!(
+                    "{} | setting watchtower registration",
+                    swap.swap_id.swap_id()
+                );
+                self.database.set_watchtower_registration(&swap)?;
+            }
+
+            CtlMsg::RemoveWatchtowerRegistration(swap_id) => {
+                if let Err(err) = self.database.delete_watchtower_registration(swap_id) {
+                    debug!(
+                        "{} | Did not delete watchtower registration: {}",
+                        swap_id, err
+                    );
+                }
+            }
+
+            CtlMsg::RetrieveWatchtowerRegistrations => {
+                for swap in self.database.get_all_watchtower_registrations()? {
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        source.clone(),
+                        BusMsg::Ctl(CtlMsg::RestoreWatchtowerRegistration(swap)),
+                    )?;
+                }
+            }
+
+            CtlMsg::SetPeerBan(ban) => {
+                debug!("setting peer ban {}", ban);
+                self.database.set_peer_ban(&ban)?;
+            }
+
+            CtlMsg::RemovePeerBan(node_id) => {
+                if let Err(err) = self.database.delete_peer_ban(node_id) {
+                    debug!("Did not delete peer ban for {}: {}", node_id, err);
+                }
+            }
+
+            CtlMsg::RetrievePeerBans => {
+                for ban in self.database.get_all_peer_bans()? {
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        source.clone(),
+                        BusMsg::Ctl(CtlMsg::RestorePeerBan(ban)),
+                    )?;
+                }
+            }
+
+            CtlMsg::SetStartedSwap(swap) => {
+                debug!("setting started swap {}", swap);
+                self.database.set_started_swap(&swap)?;
+            }
+
+            CtlMsg::RemoveStartedSwap(deal_id) => {
+                if let Err(err) = self.database.delete_started_swap(deal_id) {
+                    debug!("Did not delete started swap of deal {}: {}", deal_id, err);
+                }
+            }
+
+            CtlMsg::RetrieveStartedSwaps => {
+                for swap in self.database.get_all_started_swaps()? {
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        source.clone(),
+                        BusMsg::Ctl(CtlMsg::RestoreStartedSwap(swap)),
+                    )?;
+                }
+            }
+
+            CtlMsg::SetMaintenanceMode(enable) => {
+                debug!("setting maintenance mode {}", enable);
+                self.database.set_maintenance_mode(enable)?;
+            }
+
+            CtlMsg::RetrieveMaintenanceMode => {
+                let enable = self.database.get_maintenance_mode()?;
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    source,
+                    BusMsg::Ctl(CtlMsg::RestoreMaintenanceMode(enable)),
+                )?;
+            }
+
+            CtlMsg::RetrieveWatchedSwaps => {
+                for watched_swap in self.database.get_all_watched_swaps()? {
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        source.clone(),
+                        BusMsg::Ctl(CtlMsg::RestoreWatchedSwap(watched_swap)),
+                    )?;
+                }
+            }
+
             _ => {
                 error!("BusMsg {} is not supported by the CTL interface", request);
             }
@@ -247,7 +380,7 @@ impl Runtime {
                             endpoints,
                             source,
                             CtlMsg::Failure(Failure {
-                                code: FailureCode::Unknown,
+                                code: FailureCode::NotFound,
                                 info: "Failed to retrieve checkpoint list".to_string(),
                             }),
                         )?;
@@ -266,7 +399,7 @@ impl Runtime {
                             endpoints,
                             source,
                             CtlMsg::Failure(Failure {
-                                code: FailureCode::Unknown,
+                                code: FailureCode::NotFound,
                                 info: format!(
                                     "Could not retrieve checkpoint entry for {}",
                                     swap_id
@@ -284,7 +417,7 @@ impl Runtime {
                             endpoints,
                             source,
                             CtlMsg::Failure(Failure {
-                                code: FailureCode::Unknown,
+                                code: FailureCode::NotFound,
                                 info: format!(
                                     "Could not retrieve secret key for address {}",
                                     address
@@ -311,7 +444,7 @@ impl Runtime {
                         endpoints,
                         source,
                         CtlMsg::Failure(Failure {
-                            code: FailureCode::Unknown,
+                            code: FailureCode::NotFound,
                             info: format!("Could not retrieve secret key for address {}", address),
                         }),
                     )?,
@@ -400,6 +533,13 @@ const LMDB_CHECKPOINT_INFOS: &str = "checkpoint_infos";
 const LMDB_BITCOIN_ADDRESSES: &str = "bitcoin_addresses";
 const LMDB_MONERO_ADDRESSES: &str = "monero_addresses";
 const LMDB_DEAL_HISTORY: &str = "deal_history";
+const LMDB_WATCHED_SWAPS: &str = "watched_swaps";
+const LMDB_PEER_BANS: &str = "peer_bans";
+const LMDB_STARTED_SWAPS: &str = "started_swaps";
+const LMDB_NODE_SETTINGS: &str = "node_settings";
+
+const MAINTENANCE_MODE_KEY: &[u8] = b"maintenance_mode";
+const LMDB_WATCHTOWER_REGISTRATIONS: &str = "watchtower_registrations";
 
 impl Database {
     fn new(path: PathBuf) -> Result<Database, lmdb::Error> {
@@ -412,6 +552,14 @@ impl Database {
         env.create_db(Some(LMDB_BITCOIN_ADDRESSES), lmdb::DatabaseFlags::empty())?;
         env.create_db(Some(LMDB_DEAL_HISTORY), lmdb::DatabaseFlags::empty())?;
         env.create_db(Some(LMDB_MONERO_ADDRESSES), lmdb::DatabaseFlags::empty())?;
+        env.create_db(Some(LMDB_WATCHED_SWAPS), lmdb::DatabaseFlags::empty())?;
+        env.create_db(Some(LMDB_PEER_BANS), lmdb::DatabaseFlags::empty())?;
+        env.create_db(Some(LMDB_STARTED_SWAPS), lmdb::DatabaseFlags::empty())?;
+        env.create_db(Some(LMDB_NODE_SETTINGS), lmdb::DatabaseFlags::empty())?;
+        env.create_db(
+            Some(LMDB_WATCHTOWER_REGISTRATIONS),
+            lmdb::DatabaseFlags::empty(),
+        )?;
         Ok(Database(env))
     }
 
@@ -464,6 +612,8 @@ impl Database {
                             deal,
                             status: filtered_status,
                             local_trade_role,
+                            // The expiry only matters to the open deals, it is not persisted
+                            expiry: None,
                         })
                         .map_err(Error::from),
                 )
@@ -673,6 +823,185 @@ impl Database {
         tx.commit()?;
         Ok(())
     }
+
+    fn set_watched_swap(&mut self, watched_swap: &WatchedSwap) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_WATCHED_SWAPS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        watched_swap.swap.swap_id.strict_encode(&mut key)?;
+        if tx.get(db, &key).is_ok() {
+            tx.del(db, &key, None)?;
+        }
+        let mut val = vec![];
+        watched_swap.strict_encode(&mut val)?;
+        tx.put(db, &key, &val, lmdb::WriteFlags::empty())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_all_watched_swaps(&mut self) -> Result<Vec<WatchedSwap>, Error> {
+        let db = self.0.open_db(Some(LMDB_WATCHED_SWAPS))?;
+        let tx = self.0.begin_ro_txn()?;
+        let mut cursor = tx.open_ro_cursor(db)?;
+        let res = cursor
+            .iter()
+            .map(|(_, value)| Ok(WatchedSwap::strict_decode(IoCursor::new(value.to_vec()))?))
+            .collect();
+        drop(cursor);
+        tx.abort();
+        res
+    }
+
+    fn delete_watched_swap(&mut self, swap_key: SwapId) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_WATCHED_SWAPS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        swap_key.strict_encode(&mut key)?;
+        tx.del(db, &key, None)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_watchtower_registration(&mut self, swap: &WatchtowerSwap) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_WATCHTOWER_REGISTRATIONS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        swap.swap_id.strict_encode(&mut key)?;
+        if tx.get(db, &key).is_ok() {
+            tx.del(db, &key, None)?;
+        }
+        let mut val = vec![];
+        swap.strict_encode(&mut val)?;
+        tx.put(db, &key, &val, lmdb::WriteFlags::empty())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_all_watchtower_registrations(&mut self) -> Result<Vec<WatchtowerSwap>, Error> {
+        let db = self.0.open_db(Some(LMDB_WATCHTOWER_REGISTRATIONS))?;
+        let tx = self.0.begin_ro_txn()?;
+        let mut cursor = tx.open_ro_cursor(db)?;
+        let res = cursor
+            .iter()
+            .map(|(_, value)| {
+                Ok(WatchtowerSwap::strict_decode(IoCursor::new(
+                    value.to_vec(),
+                ))?)
+            })
+            .collect();
+        drop(cursor);
+        tx.abort();
+        res
+    }
+
+    fn delete_watchtower_registration(&mut self, swap_key: SwapId) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_WATCHTOWER_REGISTRATIONS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        swap_key.strict_encode(&mut key)?;
+        tx.del(db, &key, None)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_peer_ban(&mut self, ban: &PeerBan) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_PEER_BANS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        ban.node_id.strict_encode(&mut key)?;
+        if tx.get(db, &key).is_ok() {
+            tx.del(db, &key, None)?;
+        }
+        let mut val = vec![];
+        ban.strict_encode(&mut val)?;
+        tx.put(db, &key, &val, lmdb::WriteFlags::empty())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_all_peer_bans(&mut self) -> Result<Vec<PeerBan>, Error> {
+        let db = self.0.open_db(Some(LMDB_PEER_BANS))?;
+        let tx = self.0.begin_ro_txn()?;
+        let mut cursor = tx.open_ro_cursor(db)?;
+        let res = cursor
+            .iter()
+            .map(|(_, value)| Ok(PeerBan::strict_decode(IoCursor::new(value.to_vec()))?))
+            .collect();
+        drop(cursor);
+        tx.abort();
+        res
+    }
+
+    fn delete_peer_ban(&mut self, node_id: NodeId) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_PEER_BANS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        node_id.strict_encode(&mut key)?;
+        tx.del(db, &key, None)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_started_swap(&mut self, swap: &StartedSwap) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_STARTED_SWAPS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        swap.deal_id.strict_encode(&mut key)?;
+        if tx.get(db, &key).is_ok() {
+            tx.del(db, &key, None)?;
+        }
+        let mut val = vec![];
+        swap.strict_encode(&mut val)?;
+        tx.put(db, &key, &val, lmdb::WriteFlags::empty())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_all_started_swaps(&mut self) -> Result<Vec<StartedSwap>, Error> {
+        let db = self.0.open_db(Some(LMDB_STARTED_SWAPS))?;
+        let tx = self.0.begin_ro_txn()?;
+        let mut cursor = tx.open_ro_cursor(db)?;
+        let res = cursor
+            .iter()
+            .map(|(_, value)| Ok(StartedSwap::strict_decode(IoCursor::new(value.to_vec()))?))
+            .collect();
+        drop(cursor);
+        tx.abort();
+        res
+    }
+
+    fn set_maintenance_mode(&mut self, enable: bool) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_NODE_SETTINGS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut val = vec![];
+        enable.strict_encode(&mut val)?;
+        tx.put(db, &MAINTENANCE_MODE_KEY, &val, lmdb::WriteFlags::empty())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The persisted maintenance mode, off if never set
+    fn get_maintenance_mode(&mut self) -> Result<bool, Error> {
+        let db = self.0.open_db(Some(LMDB_NODE_SETTINGS))?;
+        let tx = self.0.begin_ro_txn()?;
+        let enable = match tx.get(db, &MAINTENANCE_MODE_KEY) {
+            Ok(val) => bool::strict_decode(IoCursor::new(val.to_vec()))?,
+            Err(lmdb::Error::NotFound) => false,
+            Err(err) => return Err(err.into()),
+        };
+        tx.abort();
+        Ok(enable)
+    }
+
+    fn delete_started_swap(&mut self, deal_id: DealId) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_STARTED_SWAPS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        deal_id.strict_encode(&mut key)?;
+        tx.del(db, &key, None)?;
+        tx.commit()?;
+        Ok(())
+    }
 }
 
 #[test]
@@ -818,14 +1147,105 @@ fn test_lmdb_state() {
         deal: deal_1,
         status: DealStatus::Ended(Outcome::SuccessSwap),
         local_trade_role: TradeRole::Maker,
+        expiry: None,
     };
     let status_2 = DealInfo {
         serialized_deal: deal_2.to_string(),
         deal: deal_2,
         status: DealStatus::Open,
         local_trade_role: TradeRole::Maker,
+        expiry: None,
     };
     assert!(deals_retrieved.len() == 2);
     assert!(deals_retrieved.contains(&status_1));
     assert!(deals_retrieved.contains(&status_2));
+
+    let ban = PeerBan {
+        node_id: NodeId::from(bitcoin::secp256k1::PublicKey::from_secret_key(
+            bitcoin::secp256k1::SECP256K1,
+            &sk,
+        )),
+        reason: "manual ban".to_string(),
+        since: 0,
+    };
+    database.set_peer_ban(&ban).unwrap();
+    let bans = database.get_all_peer_bans().unwrap();
+    assert!(bans.contains(&ban));
+    database.delete_peer_ban(ban.node_id).unwrap();
+    let bans = database.get_all_peer_bans().unwrap();
+    assert!(!bans.contains(&ban));
+
+    let swap = StartedSwap {
+        deal_id: status_2.deal.id(),
+        since: 0,
+        network: farcaster_core::blockchain::Network::Testnet,
+        btc: bitcoin::Amount::from_sat(100_000),
+        xmr: monero::Amount::from_pico(1_000_000_000_000),
+    };
+    database.set_started_swap(&swap).unwrap();
+    let swaps = database.get_all_started_swaps().unwrap();
+    assert!(swaps.contains(&swap));
+    database.delete_started_swap(swap.deal_id).unwrap();
+    let swaps = database.get_all_started_swaps().unwrap();
+    assert!(!swaps.contains(&swap));
+
+    database.set_maintenance_mode(true).unwrap();
+    assert!(database.get_maintenance_mode().unwrap());
+    database.set_maintenance_mode(false).unwrap();
+    assert!(!database.get_maintenance_mode().unwrap());
+}
+
+#[test]
+fn test_legacy_checkpoint_decode() {
+    use crate::bus::Outcome;
+    use crate::swapd::{LegacyCheckpointSwapd, LegacyServiceId, SwapStateMachine, TemporalSafety};
+    use farcaster_core::role::TradeRole;
+    use internet2::addr::NodeAddr;
+    use std::str::FromStr;
+
+    let legacy = LegacyCheckpointSwapd {
+        state: SwapStateMachine::SwapEnd(Outcome::SuccessSwap),
+        pending_msg: None,
+        enquirer: Some(LegacyServiceId::Farcasterd),
+        xmr_addr_addendum: None,
+        temporal_safety: TemporalSafety {
+            cancel_timelock: 10,
+            punish_timelock: 20,
+            safety: 3,
+            arb_finality: 1,
+            acc_finality: 1,
+        },
+        txids: vec![],
+        pending_broadcasts: vec![],
+        local_trade_role: TradeRole::Maker,
+        connected_counterparty_node_id: None,
+        deal: Deal::from_str("Deal:Cke4ftrP5A781Vq85dgBQJNwYgBS4nuUV1LQM2fvVdFMNR4h5TrWhRR11111uMFuZTAsNgpdK8DiK11111TB9zym113GTvtvqfD1111114A4TTfFfmZoWyvpcjDBtTZCdWFSUWcRKYfEC3Y17hqaXZ3dWz11111111111111111111111111111111111111111AfZ113SEBTEspU3a").unwrap(),
+    };
+    let mut encoded = vec![];
+    legacy.strict_encode(&mut encoded).unwrap();
+    let checkpoint = CheckpointSwapd::strict_decode_with_legacy(&encoded).unwrap();
+    assert!(checkpoint.outbox.is_empty());
+    assert!(checkpoint.received_msgs.is_empty());
+    assert_eq!(checkpoint.enquirer, Some(ServiceId::Farcasterd));
+    assert_eq!(checkpoint.deal, legacy.deal);
+
+    let mut encoded = vec![];
+    checkpoint.strict_encode(&mut encoded).unwrap();
+    let decoded = CheckpointSwapd::strict_decode_with_legacy(&encoded).unwrap();
+    assert_eq!(decoded.enquirer, checkpoint.enquirer);
+    assert_eq!(decoded.deal, checkpoint.deal);
+
+    // the legacy peer services map onto the connection to the remote node
+    let node_addr = NodeAddr::from_str(
+        "02e77b779cdc2c713823f7a19147392606ed0d9b7bd8a2d1f3e3c0e4c7a6e9bc1b@127.0.0.1:9735",
+    )
+    .unwrap();
+    let legacy = LegacyCheckpointSwapd {
+        enquirer: Some(LegacyServiceId::Peer(42, node_addr)),
+        ..legacy
+    };
+    let mut encoded = vec![];
+    legacy.strict_encode(&mut encoded).unwrap();
+    let checkpoint = CheckpointSwapd::strict_decode_with_legacy(&encoded).unwrap();
+    assert_eq!(checkpoint.enquirer, Some(ServiceId::Peer(node_addr.id)));
 }
@@ -7,12 +7,14 @@
 use std::io;
 
 use amplify::IoError;
+use farcaster_core::trade::DealId;
+use internet2::addr::NodeId;
 use internet2::{presentation, transport};
 #[cfg(feature = "_rpc")]
 use microservices::esb;
 
 #[cfg(feature = "_rpc")]
-use crate::bus::ServiceBus;
+use crate::bus::{Failure, ServiceBus};
 use crate::service::ServiceId;
 
 #[derive(Debug, Display, From, Error)]
@@ -52,6 +54,11 @@ pub enum Error {
     #[from]
     Bridge(transport::Error),
 
+    /// Failure returned by a service, with its failure code
+    #[cfg(feature = "_rpc")]
+    #[display("{0:#}")]
+    Failure(Failure),
+
     /// Provided RPC request is not supported for the used type of endpoint
     #[cfg(feature = "_rpc")]
     #[display("RPC request error, not supported on {0}: {1}")]
@@ -63,9 +70,42 @@ pub enum Error {
     /// Peer has misbehaved peer protocol rules
     Misbehaving,
 
+    /// Peer identity mismatch: expected node {expected}, remote peer proved {found}
+    PeerIdentityMismatch { expected: NodeId, found: NodeId },
+
+    /// Peer failed to prove ownership of the node key {0}
+    PeerIdentityUnproven(NodeId),
+
+    /// Incompatible peer: {0}
+    IncompatiblePeer(String),
+
     /// Unrecoverable error: {0}
     Terminate(String),
 
+    /// Deal registry error: {0}
+    DealRegistry(String),
+
+    /// Price oracle error: {0}
+    PriceOracle(String),
+
+    /// Invalid deal: {0}
+    InvalidDeal(String),
+
+    /// Amount out of range: {0}
+    AmountOutOfRange(String),
+
+    /// Deal rejected by policy: {0}
+    DealPolicy(String),
+
+    /// Swap limit reached: {0}
+    SwapLimit(String),
+
+    /// The node is in maintenance mode, no new swap can start
+    Maintenance,
+
+    /// Deal {0} has expired
+    DealExpired(DealId),
+
     /// Other error type with string explanation
     #[display(inner)]
     #[from(internet2::addr::NoOnionSupportError)]
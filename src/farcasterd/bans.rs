@@ -0,0 +1,187 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use farcaster_core::protocol::message::Abort;
+use internet2::addr::NodeId;
+use microservices::esb::Handler;
+
+use crate::bus::ctl::CtlMsg;
+use crate::bus::p2p::PeerMsg;
+use crate::bus::{BusMsg, Misbehavior, OptionDetails, PeerBan, ServiceBus};
+use crate::farcasterd::runtime::Runtime;
+use crate::service::Endpoints;
+use crate::{CtlServer, Error, LogStyle, ServiceId};
+
+/// The banned remote nodes and the ban score accumulated by the misbehaving ones.
+#[derive(Default)]
+pub struct PeerBans {
+    banned: HashMap<NodeId, PeerBan>,
+    scores: HashMap<NodeId, u32>,
+}
+
+impl PeerBans {
+    pub fn is_banned(&self, node_id: &NodeId) -> bool {
+        self.banned.contains_key(node_id)
+    }
+
+    /// Returns if the service is a connection to a banned remote node
+    pub fn is_banned_connection(&self, service: &ServiceId) -> bool {
+        service
+            .node_id()
+            .map_or(false, |node_id| self.is_banned(&node_id))
+    }
+
+    pub fn list(&self) -> Vec<PeerBan> {
+        self.banned.values().cloned().collect()
+    }
+}
+
+impl Runtime {
+    /// Handle a ban request from a client
+    pub fn handle_ban_peer(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        node_id: NodeId,
+    ) -> Result<(), Error> {
+        self.ban_peer(endpoints, node_id, "manual ban".to_string())?;
+        self.send_client_ctl(
+            endpoints,
+            source,
+            CtlMsg::Success(OptionDetails::with(format!("Banned peer {}", node_id))),
+        )
+    }
+
+    /// Handle an unban request from a client
+    pub fn handle_unban_peer(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        node_id: NodeId,
+    ) -> Result<(), Error> {
+        self.bans.scores.remove(&node_id);
+        let msg = if self.bans.banned.remove(&node_id).is_some() {
+            info!("{} peer {}", "Unbanned".bright_green_bold(), node_id);
+            endpoints.send_to(
+                ServiceBus::Ctl,
+                self.identity(),
+                ServiceId::Database,
+                BusMsg::Ctl(CtlMsg::RemovePeerBan(node_id)),
+            )?;
+            format!("Unbanned peer {}", node_id)
+        } else {
+            format!("Peer {} is not banned", node_id)
+        };
+        self.send_client_ctl(endpoints, source, CtlMsg::Success(OptionDetails::with(msg)))
+    }
+
+    /// Add the misbehavior of the remote node behind the connection to its ban score, and ban it
+    /// once the score reaches the configured threshold
+    pub fn handle_peer_misbehavior(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: &ServiceId,
+        misbehavior: Misbehavior,
+    ) -> Result<(), Error> {
+        let node_id = match source.node_id() {
+            Some(node_id) => node_id,
+            None => return Ok(()),
+        };
+        if self.bans.is_banned(&node_id) {
+            return Ok(());
+        }
+        let score = self.bans.scores.entry(node_id).or_insert(0);
+        *score += misbehavior.ban_score();
+        let score = *score;
+        let threshold = self.config.ban_score_threshold();
+        warn!(
+            "Peer {} misbehaved ({}), ban score is now {}/{}",
+            node_id.bright_blue_italic(),
+            misbehavior,
+            score,
+            threshold
+        );
+        if score >= threshold {
+            self.ban_peer(
+                endpoints,
+                node_id,
+                format!("ban score {} reached after {}", score, misbehavior),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Route the abort of a remote node to the swap it runs with that node; aborts of swaps the
+    /// node is not part of count towards its ban score
+    pub fn handle_peer_abort(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        abort: Abort,
+    ) -> Result<(), Error> {
+        let swap_id = abort.swap_id;
+        if self
+            .trade_state_machines
+            .iter()
+            .any(|tsm| tsm.get_swap_id_with_matching_connection(&source) == Some(swap_id))
+        {
+            debug!("Forwarding abort from {} to swap {}", source, swap_id);
+            endpoints.send_to(
+                ServiceBus::Msg,
+                source,
+                ServiceId::Swap(swap_id),
+                BusMsg::P2p(PeerMsg::Abort(abort)),
+            )?;
+            Ok(())
+        } else {
+            self.handle_peer_misbehavior(endpoints, &source, Misbehavior::UnknownSwapAbort)
+        }
+    }
+
+    /// Restore a ban persisted by databased
+    pub fn restore_peer_ban(&mut self, ban: PeerBan) {
+        debug!("Restoring ban of peer {}", ban);
+        self.bans.banned.insert(ban.node_id, ban);
+    }
+
+    fn ban_peer(
+        &mut self,
+        endpoints: &mut Endpoints,
+        node_id: NodeId,
+        reason: String,
+    ) -> Result<(), Error> {
+        let ban = PeerBan {
+            node_id,
+            reason,
+            since: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_else(|_| Duration::from_secs(0))
+                .as_secs(),
+        };
+        warn!("{} peer {}", "Banning".err(), ban);
+        self.bans.scores.remove(&node_id);
+        self.bans.banned.insert(node_id, ban.clone());
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(CtlMsg::SetPeerBan(ban)),
+        )?;
+        let connections: Vec<_> = self
+            .registered_services
+            .iter()
+            .filter(|service| service.node_id() == Some(node_id))
+            .cloned()
+            .collect();
+        for connection in connections {
+            self.handle_failed_connection(endpoints, connection)?;
+        }
+        Ok(())
+    }
+}
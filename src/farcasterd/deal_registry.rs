@@ -0,0 +1,119 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use farcaster_core::swap::btcxmr::Deal;
+
+use crate::bus::bridge::BridgeMsg;
+use crate::bus::ctl::CtlMsg;
+use crate::bus::info::{InfoMsg, RegistryQuery, RemoteDealInfo};
+use crate::bus::{Failure, FailureCode};
+use crate::farcasterd::runtime::Runtime;
+use crate::farcasterd::supervisor::report_over_bridge;
+use crate::registry::{RegistryAction, RegistryClient, RegistryDeal};
+use crate::service::Endpoints;
+use crate::{CtlServer, Error, ServiceId};
+
+impl Runtime {
+    /// Publish a deal made by this node to the deal registry, if one is configured
+    pub fn publish_deal(&self, deal: &Deal) {
+        self.push_to_registry(deal, RegistryAction::Publish);
+    }
+
+    /// Revoke a deal made by this node from the deal registry, if one is configured
+    pub fn unpublish_deal(&self, deal: &Deal) {
+        self.push_to_registry(deal, RegistryAction::Revoke);
+    }
+
+    /// Fetch the deals matching the query from the deal registry, the client is answered once the
+    /// registry replied
+    pub fn handle_query_deal_registry(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        query: RegistryQuery,
+    ) -> Result<(), Error> {
+        let url = match self.config.deal_registry() {
+            Some(url) => url,
+            None => {
+                return self.send_client_ctl(
+                    endpoints,
+                    source,
+                    CtlMsg::Failure(Failure {
+                        code: FailureCode::Unavailable,
+                        info: "No deal registry configured".to_string(),
+                    }),
+                )
+            }
+        };
+        std::thread::spawn(move || {
+            let res = RegistryClient::new(&url).and_then(|client| client.query(&query));
+            let request = match res {
+                Ok(deals) => BridgeMsg::Info {
+                    request: InfoMsg::RegistryDealList(
+                        deals
+                            .into_iter()
+                            .map(|(deal, since)| RemoteDealInfo::new(deal, since))
+                            .collect(),
+                    ),
+                    service_id: source,
+                },
+                Err(err) => BridgeMsg::Ctl {
+                    request: CtlMsg::Failure(Failure {
+                        code: FailureCode::Unavailable,
+                        info: format!("Failed to query the deal registry: {}", err),
+                    }),
+                    service_id: source,
+                },
+            };
+            report_over_bridge(request);
+        });
+        Ok(())
+    }
+
+    fn push_to_registry(&self, deal: &Deal, action: RegistryAction) {
+        let url = match self.config.deal_registry() {
+            Some(url) => url,
+            None => return,
+        };
+        let signed_deal = match self
+            .peer_keys_ready()
+            .and_then(|(sk, _)| RegistryDeal::sign(deal, action, &sk))
+        {
+            Ok(signed_deal) => signed_deal,
+            Err(err) => {
+                warn!(
+                    "Cannot {} deal {} on the registry: {}",
+                    action,
+                    deal.id(),
+                    err
+                );
+                return;
+            }
+        };
+        let deal = deal.clone();
+        std::thread::spawn(move || {
+            let res = RegistryClient::new(&url).and_then(|client| match action {
+                RegistryAction::Publish => client.publish(signed_deal),
+                RegistryAction::Revoke => client.revoke(&deal, signed_deal),
+            });
+            match res {
+                Ok(()) => debug!(
+                    "Registry {} of deal {} succeeded on {}",
+                    action,
+                    deal.id(),
+                    url
+                ),
+                Err(err) => warn!(
+                    "Failed to {} deal {} on the registry {}: {}",
+                    action,
+                    deal.id(),
+                    url,
+                    err
+                ),
+            }
+        });
+    }
+}
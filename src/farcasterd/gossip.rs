@@ -0,0 +1,385 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use farcaster_core::swap::btcxmr::Deal;
+use farcaster_core::trade::DealId;
+use internet2::addr::NodeId;
+use microservices::esb::Handler;
+
+use crate::bus::bridge::BridgeMsg;
+use crate::bus::ctl::DealOrigin;
+use crate::bus::info::RemoteDealInfo;
+use crate::bus::p2p::{DealAnnouncement, DealRevocation, PeerMsg};
+use crate::bus::{unix_timestamp, BusMsg, Misbehavior, ServiceBus};
+use crate::farcasterd::runtime::Runtime;
+use crate::farcasterd::supervisor::report_over_bridge;
+use crate::service::Endpoints;
+use crate::{Error, LogStyle, ServiceId};
+
+/// Announcements are dropped once this many remote deals are known
+const MAX_REMOTE_DEALS: usize = 1000;
+
+/// The oldest revocations are forgotten once this many are known
+const MAX_REVOKED_DEALS: usize = 1000;
+
+/// Announcements and revocations signed by this node expire after this many seconds
+const DEAL_GOSSIP_LIFETIME: u64 = 60 * 60;
+
+/// Interval at which the open deals are announced again, well within their announcement lifetime
+const DEAL_GOSSIP_REFRESH_INTERVAL: Duration = Duration::from_secs(20 * 60);
+
+struct RemoteDeal {
+    announcement: DealAnnouncement,
+    since: SystemTime,
+}
+
+/// The remote order book: the deals announced by the other makers and relayed by our peers, and
+/// the deals known to be revoked by their maker, each until its signed expiry.
+#[derive(Default)]
+pub struct DealGossip {
+    remote_deals: HashMap<DealId, RemoteDeal>,
+    revoked: HashMap<DealId, DealRevocation>,
+}
+
+impl DealGossip {
+    pub fn list(&self) -> Vec<RemoteDealInfo> {
+        self.remote_deals
+            .values()
+            .filter(|remote| !remote.announcement.is_expired())
+            .map(|remote| {
+                RemoteDealInfo::new(
+                    remote.announcement.deal.clone(),
+                    remote
+                        .since
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                )
+            })
+            .collect()
+    }
+
+    /// Forget the expired announcements and revocations
+    fn prune(&mut self) {
+        self.remote_deals
+            .retain(|_, remote| !remote.announcement.is_expired());
+        self.revoked
+            .retain(|_, revocation| !revocation.is_expired());
+    }
+
+    fn is_revoked(&self, deal_id: &DealId, maker: &NodeId) -> bool {
+        self.revoked
+            .get(deal_id)
+            .map_or(false, |revocation| revocation.node_id == *maker)
+    }
+
+    fn insert_revocation(&mut self, revocation: DealRevocation) {
+        if self.revoked.len() >= MAX_REVOKED_DEALS {
+            if let Some(oldest) = self
+                .revoked
+                .values()
+                .min_by_key(|revocation| revocation.expiry)
+                .map(|revocation| revocation.deal_id)
+            {
+                self.revoked.remove(&oldest);
+            }
+        }
+        self.revoked.insert(revocation.deal_id, revocation);
+    }
+}
+
+/// Wake the runtime at every refresh interval to announce its open deals again and forget the
+/// expired gossip
+pub fn spawn_deal_gossip_refresh() {
+    std::thread::spawn(|| loop {
+        std::thread::sleep(DEAL_GOSSIP_REFRESH_INTERVAL);
+        report_over_bridge(BridgeMsg::DealGossipRefresh);
+    });
+}
+
+impl Runtime {
+    /// Announce a deal made by this node to the connected peers and to the deal registry
+    pub fn announce_deal(&mut self, endpoints: &mut Endpoints, deal: &Deal) {
+        debug!("Announcing deal {} to the connected peers", deal.id());
+        // the deal may be announced again after its revocation, e.g. after the maintenance mode
+        self.gossip.revoked.remove(&deal.id());
+        if let Some(announcement) = self.sign_deal_announcement(deal) {
+            self.broadcast_gossip(endpoints, PeerMsg::DealAnnouncement(announcement), None);
+        }
+        self.publish_deal(deal);
+    }
+
+    /// Announce that a deal made by this node is no longer available
+    pub fn announce_deal_revocation(&mut self, endpoints: &mut Endpoints, deal: &Deal) {
+        debug!(
+            "Announcing revocation of deal {} to the connected peers",
+            deal.id()
+        );
+        match self.peer_keys_ready().and_then(|(sk, _)| {
+            DealRevocation::sign(deal.id(), unix_timestamp() + DEAL_GOSSIP_LIFETIME, &sk)
+        }) {
+            Ok(revocation) => {
+                self.gossip.insert_revocation(revocation.clone());
+                self.broadcast_gossip(endpoints, PeerMsg::DealRevocation(revocation), None);
+            }
+            Err(err) => warn!(
+                "Failed to sign the revocation of deal {}: {}",
+                deal.id(),
+                err
+            ),
+        }
+        self.unpublish_deal(deal);
+    }
+
+    /// Send our open deals, unless in maintenance mode, and the known remote deals to a newly
+    /// connected peer
+    pub fn sync_deal_gossip(&mut self, endpoints: &mut Endpoints, connection: &ServiceId) {
+        self.gossip.prune();
+        let announcements: Vec<DealAnnouncement> = self
+            .open_deals_to_announce()
+            .iter()
+            .filter_map(|deal| self.sign_deal_announcement(deal))
+            .chain(
+                self.gossip
+                    .remote_deals
+                    .values()
+                    .map(|remote| remote.announcement.clone()),
+            )
+            .collect();
+        for announcement in announcements {
+            self.send_gossip(
+                endpoints,
+                connection.clone(),
+                PeerMsg::DealAnnouncement(announcement),
+            );
+        }
+    }
+
+    /// Announce the open deals again before their previous announcement expires and forget the
+    /// expired gossip
+    pub fn handle_deal_gossip_refresh(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        self.gossip.prune();
+        for deal in self.open_deals_to_announce() {
+            if let Some(announcement) = self.sign_deal_announcement(&deal) {
+                self.broadcast_gossip(endpoints, PeerMsg::DealAnnouncement(announcement), None);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn handle_deal_announcement(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        announcement: DealAnnouncement,
+    ) -> Result<(), Error> {
+        let deal_id = announcement.deal.id();
+        if let Err(err) = announcement.verify() {
+            warn!(
+                "Invalid announcement of deal {} from {}: {}",
+                deal_id, source, err
+            );
+            return self.handle_peer_misbehavior(endpoints, &source, Misbehavior::InvalidGossip);
+        }
+        self.gossip.prune();
+        let known_expiry = self
+            .gossip
+            .remote_deals
+            .get(&deal_id)
+            .map(|remote| remote.announcement.expiry);
+        let maker = NodeId::from(announcement.deal.node_id);
+        // Announcements and revocations share their lifetime, an announcement expiring after the
+        // revocation was signed after it, e.g. once its maker left the maintenance mode
+        let revoked_until = self
+            .gossip
+            .revoked
+            .get(&deal_id)
+            .filter(|revocation| revocation.node_id == maker)
+            .map(|revocation| revocation.expiry);
+        if announcement.is_expired()
+            || known_expiry.map_or(false, |expiry| expiry >= announcement.expiry)
+            || revoked_until.map_or(false, |expiry| expiry >= announcement.expiry)
+            || self.deals.contains(&announcement.deal)
+        {
+            trace!("Ignoring known deal {} announced by {}", deal_id, source);
+            return Ok(());
+        }
+        if revoked_until.is_some() {
+            debug!("Deal {} announced again by its maker", deal_id);
+            self.gossip.revoked.remove(&deal_id);
+        }
+        if known_expiry.is_none() {
+            if self.gossip.remote_deals.len() >= MAX_REMOTE_DEALS {
+                debug!(
+                    "Dropping deal {} announced by {}, the remote order book is full",
+                    deal_id, source
+                );
+                return Ok(());
+            }
+            info!(
+                "Received deal {} from {}",
+                deal_id.bright_yellow_bold(),
+                source.bright_blue_italic()
+            );
+        }
+        // A new announcement of a known deal extends its expiry
+        let since = self
+            .gossip
+            .remote_deals
+            .get(&deal_id)
+            .map_or_else(SystemTime::now, |remote| remote.since);
+        self.gossip.remote_deals.insert(
+            deal_id,
+            RemoteDeal {
+                announcement: announcement.clone(),
+                since,
+            },
+        );
+        self.broadcast_gossip(
+            endpoints,
+            PeerMsg::DealAnnouncement(announcement),
+            Some(&source),
+        );
+        Ok(())
+    }
+
+    pub fn handle_deal_revocation(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        revocation: DealRevocation,
+    ) -> Result<(), Error> {
+        let deal_id = revocation.deal_id;
+        if let Err(err) = revocation.verify() {
+            warn!(
+                "Invalid revocation of deal {} from {}: {}",
+                deal_id, source, err
+            );
+            return self.handle_peer_misbehavior(endpoints, &source, Misbehavior::InvalidGossip);
+        }
+        self.gossip.prune();
+        if revocation.is_expired() || self.gossip.is_revoked(&deal_id, &revocation.node_id) {
+            trace!("Ignoring known revocation of deal {}", deal_id);
+            return Ok(());
+        }
+        // Only the maker of the deal can revoke it
+        if self
+            .gossip
+            .remote_deals
+            .get(&deal_id)
+            .map_or(false, |remote| {
+                NodeId::from(remote.announcement.deal.node_id) == revocation.node_id
+            })
+        {
+            self.gossip.remote_deals.remove(&deal_id);
+            info!(
+                "Deal {} was revoked, removing it from the remote deals",
+                deal_id.bright_yellow_bold()
+            );
+        }
+        self.gossip.insert_revocation(revocation.clone());
+        self.broadcast_gossip(
+            endpoints,
+            PeerMsg::DealRevocation(revocation),
+            Some(&source),
+        );
+        Ok(())
+    }
+
+    /// Verify a deal before taking it: a signed deal must be signed by its maker for this deal
+    /// and not be expired, an announced deal must still be announced, and no deal may have been
+    /// revoked or announced differently by its maker
+    pub fn verify_taken_deal(&mut self, deal: &Deal, origin: &DealOrigin) -> Result<(), Error> {
+        self.gossip.prune();
+        let deal_id = deal.id();
+        if let DealOrigin::Signed(signed_deal) = origin {
+            if signed_deal.deal != *deal {
+                return Err(Error::InvalidDeal(format!(
+                    "the signed deal does not match deal {}",
+                    deal_id
+                )));
+            }
+            signed_deal.verify()?;
+        }
+        if self
+            .gossip
+            .is_revoked(&deal_id, &NodeId::from(deal.node_id))
+        {
+            return Err(Error::InvalidDeal(format!(
+                "deal {} was revoked by its maker",
+                deal_id
+            )));
+        }
+        match self.gossip.remote_deals.get(&deal_id) {
+            Some(remote) if remote.announcement.deal != *deal => Err(Error::InvalidDeal(format!(
+                "deal {} does not match the deal announced by its maker",
+                deal_id
+            ))),
+            None if matches!(origin, DealOrigin::Announced) => Err(Error::DealExpired(deal_id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The deals made by this node that are announced, none in maintenance mode
+    fn open_deals_to_announce(&self) -> Vec<Deal> {
+        if self.maintenance {
+            return vec![];
+        }
+        self.trade_state_machines
+            .iter()
+            .filter_map(|tsm| tsm.announced_deal())
+            .collect()
+    }
+
+    fn sign_deal_announcement(&self, deal: &Deal) -> Option<DealAnnouncement> {
+        match self.peer_keys_ready().and_then(|(sk, _)| {
+            DealAnnouncement::sign(deal.clone(), unix_timestamp() + DEAL_GOSSIP_LIFETIME, &sk)
+        }) {
+            Ok(announcement) => Some(announcement),
+            Err(err) => {
+                warn!(
+                    "Failed to sign the announcement of deal {}: {}",
+                    deal.id(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// Send the gossip message to every connected peer except the one it was received from
+    fn broadcast_gossip(
+        &mut self,
+        endpoints: &mut Endpoints,
+        msg: PeerMsg,
+        except: Option<&ServiceId>,
+    ) {
+        let connections: Vec<_> = self
+            .registered_services
+            .iter()
+            .filter(|service| matches!(service, ServiceId::Peer(..)))
+            .filter(|service| except != Some(*service))
+            .cloned()
+            .collect();
+        for connection in connections {
+            self.send_gossip(endpoints, connection, msg.clone());
+        }
+    }
+
+    fn send_gossip(&mut self, endpoints: &mut Endpoints, connection: ServiceId, msg: PeerMsg) {
+        if let Err(err) = endpoints.send_to(
+            ServiceBus::Msg,
+            self.identity(),
+            connection.clone(),
+            BusMsg::P2p(msg),
+        ) {
+            debug!("Failed to send gossip to {}: {}", connection, err);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use farcaster_core::swap::btcxmr::Deal;
+use microservices::esb::Handler;
+
+use crate::bus::ctl::CtlMsg;
+use crate::bus::info::{InfoMsg, MaintenanceStatus};
+use crate::bus::{BusMsg, ServiceBus};
+use crate::farcasterd::runtime::Runtime;
+use crate::service::Endpoints;
+use crate::{CtlServer, Error, LogStyle, ServiceId};
+
+impl Runtime {
+    /// Turn the maintenance mode on or off and persist it across restarts
+    pub fn handle_set_maintenance(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        enable: bool,
+    ) -> Result<(), Error> {
+        self.set_maintenance(endpoints, enable)?;
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(CtlMsg::SetMaintenanceMode(enable)),
+        )?;
+        let status = self.maintenance_status();
+        self.send_client_info(endpoints, source, InfoMsg::MaintenanceStatus(status))
+    }
+
+    /// While in maintenance mode no new swap starts and our open deals are hidden: revoked from
+    /// the peers and the deal registry, and no longer listed. They are announced again once the
+    /// maintenance mode is turned off.
+    pub fn set_maintenance(
+        &mut self,
+        endpoints: &mut Endpoints,
+        enable: bool,
+    ) -> Result<(), Error> {
+        if enable != self.maintenance {
+            self.maintenance = enable;
+            let deals: Vec<Deal> = self
+                .trade_state_machines
+                .iter()
+                .filter_map(|tsm| tsm.announced_deal())
+                .collect();
+            if enable {
+                info!(
+                    "{}, draining {} swaps",
+                    "Maintenance mode on".bright_yellow_bold(),
+                    self.maintenance_status().draining_swaps
+                );
+                for deal in deals.iter() {
+                    self.announce_deal_revocation(endpoints, deal);
+                }
+            } else {
+                info!("{}", "Maintenance mode off".bright_green_bold());
+                for deal in deals.iter() {
+                    self.announce_deal(endpoints, deal);
+                }
+                self.handle_market_maker(endpoints)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if the node is in maintenance mode, no new swap can start
+    pub fn check_maintenance(&self) -> Result<(), Error> {
+        if self.maintenance {
+            Err(Error::Maintenance)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn maintenance_status(&self) -> MaintenanceStatus {
+        let draining_swaps = self
+            .trade_state_machines
+            .iter()
+            .filter(|tsm| tsm.consumed_deal().is_some())
+            .count() as u32;
+        MaintenanceStatus {
+            enabled: self.maintenance,
+            draining_swaps,
+            safe_to_shutdown: draining_swaps == 0,
+        }
+    }
+}
@@ -0,0 +1,385 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::{HashMap, HashSet};
+
+use farcaster_core::bitcoin::timelock::CSVTimelock;
+use farcaster_core::blockchain::Blockchain;
+use farcaster_core::role::SwapRole;
+use farcaster_core::swap::btcxmr::{Deal, DealParameters};
+use farcaster_core::trade::DealId;
+use farcaster_core::Uuid;
+
+use crate::bus::ctl::{CtlMsg, DealApproval};
+use crate::bus::info::{InfoMsg, MarketInfo, MarketMakerInfo};
+use crate::bus::{Failure, FailureCode, Outcome};
+use crate::config::{MarketMakerConfig, MarketMakerDeals};
+use crate::farcasterd::runtime::Runtime;
+use crate::farcasterd::trade_state_machine::make_internal_deal;
+use crate::service::Endpoints;
+use crate::{CtlServer, Error, LogStyle, ServiceId};
+
+/// The market maker keeps the configured number of deals open on each network and makes a new
+/// deal whenever one is taken, revoked or expires, as long as its inventory covers the next deal
+/// and a swap of the deal stays within the swap limits of the node.
+pub struct MarketMaker {
+    config: MarketMakerConfig,
+    // Open deals made by the market maker and the inventory they use
+    open_deals: HashMap<DealId, MarketDeal>,
+    // Deals of the market maker consumed by a taker, kept while their swap runs. Their inventory
+    // is only spent if the swap succeeds.
+    taken_deals: HashMap<DealId, MarketDeal>,
+    btc_used: bitcoin::Amount,
+    xmr_used: monero::Amount,
+    // Index in the configured sizes of the next deal made on each market
+    next_size: Vec<usize>,
+    status: Vec<MarketStatus>,
+}
+
+struct MarketDeal {
+    // Index of the market in the configured deals
+    market: usize,
+    btc: bitcoin::Amount,
+    xmr: monero::Amount,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+enum MarketStatus {
+    #[display("starting")]
+    Starting,
+    #[display("running")]
+    Running,
+    #[display("paused, swap limit reached: {0}")]
+    SwapLimit(String),
+    #[display("stopped, inventory exhausted")]
+    InventoryExhausted,
+    #[display("failed to make a deal: {0}")]
+    Failed(String),
+}
+
+impl MarketMaker {
+    pub fn new(config: MarketMakerConfig) -> Self {
+        let markets = config.deals.len();
+        MarketMaker {
+            config,
+            open_deals: none!(),
+            taken_deals: none!(),
+            btc_used: bitcoin::Amount::from_sat(0),
+            xmr_used: monero::Amount::from_pico(0),
+            next_size: vec![0; markets],
+            status: vec![MarketStatus::Starting; markets],
+        }
+    }
+
+    pub fn info(
+        &self,
+        oracle_price: Option<bitcoin::Amount>,
+        max_concurrent_swaps: Option<u32>,
+    ) -> MarketMakerInfo {
+        MarketMakerInfo {
+            max_concurrent_swaps,
+            running_swaps: self.taken_deals.len() as u32,
+            btc_inventory: self.config.btc_inventory,
+            btc_used: self.btc_used,
+            xmr_inventory: self.config.xmr_inventory,
+            xmr_used: self.xmr_used,
+            markets: self
+                .config
+                .deals
+                .iter()
+                .enumerate()
+                .map(|(index, market)| MarketInfo {
+                    network: market.network,
+                    maker_role: market.maker_role,
+                    status: self.status[index].to_string(),
+                    price: market.price(oracle_price).map_or_else(
+                        || "unknown".to_string(),
+                        |price| format!("{} BTC/XMR", price.as_btc()),
+                    ),
+                    target_open_deals: market.open_deals,
+                    open_deals: self
+                        .open_deals
+                        .iter()
+                        .filter(|(_, deal)| deal.market == index)
+                        .map(|(deal_id, _)| *deal_id)
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Sort the deals that are no longer open into the taken ones, their swap is running, and the
+    /// revoked or expired ones, their inventory is released. The taken deals no longer consumed
+    /// without a successful swap release their inventory as well.
+    fn update(&mut self, open: &HashSet<DealId>, consumed: &HashSet<DealId>) {
+        let closed: Vec<DealId> = self
+            .open_deals
+            .keys()
+            .filter(|deal_id| !open.contains(deal_id))
+            .copied()
+            .collect();
+        for deal_id in closed {
+            let deal = self
+                .open_deals
+                .remove(&deal_id)
+                .expect("closed deal is open");
+            if consumed.contains(&deal_id) {
+                info!(
+                    "{} {} taken, a swap is running",
+                    "Market maker deal".bright_green_bold(),
+                    deal_id.bright_yellow_bold()
+                );
+                self.taken_deals.insert(deal_id, deal);
+            } else {
+                debug!(
+                    "Market maker deal {} closed, releasing its inventory",
+                    deal_id
+                );
+                self.release(&deal);
+            }
+        }
+        let ended: Vec<DealId> = self
+            .taken_deals
+            .keys()
+            .filter(|deal_id| !consumed.contains(deal_id))
+            .copied()
+            .collect();
+        for deal_id in ended {
+            self.swap_ended(&deal_id, None);
+        }
+    }
+
+    /// Forget the taken deal once its swap ended, its inventory is spent only if the swap
+    /// succeeded
+    fn swap_ended(&mut self, deal_id: &DealId, outcome: Option<&Outcome>) {
+        if let Some(deal) = self.taken_deals.remove(deal_id) {
+            if outcome == Some(&Outcome::SuccessSwap) {
+                debug!("Market maker deal {} swapped", deal_id);
+            } else {
+                info!(
+                    "Swap of market maker deal {} failed, releasing its inventory",
+                    deal_id
+                );
+                self.release(&deal);
+            }
+        }
+    }
+
+    fn release(&mut self, deal: &MarketDeal) {
+        self.btc_used -= deal.btc;
+        self.xmr_used -= deal.xmr;
+    }
+
+    /// The amounts of the next deal on the market and the inventory it uses, fails if the price
+    /// is not known or if the inventory does not cover it
+    fn next_deal(
+        &self,
+        index: usize,
+        oracle_price: Option<bitcoin::Amount>,
+    ) -> Result<(bitcoin::Amount, monero::Amount, MarketDeal), MarketStatus> {
+        let market = &self.config.deals[index];
+        let btc = *market
+            .sizes
+            .get(self.next_size[index] % market.sizes.len().max(1))
+            .ok_or_else(|| MarketStatus::Failed("no deal sizes configured".to_string()))?;
+        let xmr = market
+            .accordant_amount(btc, oracle_price)
+            .ok_or_else(|| MarketStatus::Failed("no reference price available".to_string()))?;
+        let deal = match market.maker_role {
+            SwapRole::Alice => MarketDeal {
+                market: index,
+                btc: bitcoin::Amount::from_sat(0),
+                xmr,
+            },
+            SwapRole::Bob => MarketDeal {
+                market: index,
+                btc,
+                xmr: monero::Amount::from_pico(0),
+            },
+        };
+        let btc_covered = self
+            .config
+            .btc_inventory
+            .map_or(true, |inventory| self.btc_used + deal.btc <= inventory);
+        let xmr_covered = self
+            .config
+            .xmr_inventory
+            .map_or(true, |inventory| self.xmr_used + deal.xmr <= inventory);
+        if btc_covered && xmr_covered {
+            Ok((btc, xmr, deal))
+        } else {
+            Err(MarketStatus::InventoryExhausted)
+        }
+    }
+
+    fn set_status(&mut self, index: usize, status: MarketStatus) {
+        if self.status[index] != status {
+            let market = &self.config.deals[index];
+            match &status {
+                MarketStatus::Running => info!(
+                    "Market maker as {} on {} is {}",
+                    market.maker_role, market.network, status
+                ),
+                _ => warn!(
+                    "Market maker as {} on {} is {}",
+                    market.maker_role, market.network, status
+                ),
+            }
+            self.status[index] = status;
+        }
+    }
+}
+
+impl Runtime {
+    /// Top up the open deals of the market maker, called once the services are ready and after
+    /// each request processed by the trade state machines
+    pub fn handle_market_maker(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        let mut market_maker = match self.market_maker.take() {
+            Some(market_maker) => market_maker,
+            None => return Ok(()),
+        };
+        let res = self.run_market_maker(endpoints, &mut market_maker);
+        self.market_maker = Some(market_maker);
+        res
+    }
+
+    pub fn handle_get_market_maker(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+    ) -> Result<(), Error> {
+        match &self.market_maker {
+            Some(market_maker) => {
+                let info = market_maker.info(
+                    self.reference_price().ok().flatten(),
+                    self.config.max_concurrent_swaps(),
+                );
+                self.send_client_info(endpoints, source, InfoMsg::MarketMakerInfo(info))
+            }
+            None => self.send_client_ctl(
+                endpoints,
+                source,
+                CtlMsg::Failure(Failure {
+                    code: FailureCode::Unavailable,
+                    info: "The market maker is not enabled".to_string(),
+                }),
+            ),
+        }
+    }
+
+    /// Record the outcome of the swap of a deal, releasing its inventory if it was made by the
+    /// market maker and the swap failed
+    pub fn handle_market_maker_outcome(&mut self, deal: &Deal, outcome: &Outcome) {
+        if let Some(market_maker) = self.market_maker.as_mut() {
+            market_maker.swap_ended(&deal.id(), Some(outcome));
+        }
+    }
+
+    fn run_market_maker(
+        &mut self,
+        endpoints: &mut Endpoints,
+        market_maker: &mut MarketMaker,
+    ) -> Result<(), Error> {
+        let open: HashSet<DealId> = self
+            .trade_state_machines
+            .iter()
+            .filter_map(|tsm| tsm.open_deal())
+            .map(|deal| deal.id())
+            .collect();
+        let consumed: HashSet<DealId> = self
+            .trade_state_machines
+            .iter()
+            .filter_map(|tsm| tsm.consumed_deal())
+            .map(|(deal, _)| deal.id())
+            .collect();
+        market_maker.update(&open, &consumed);
+        if self.services_ready().is_err() || self.peer_keys_ready().is_err() || self.maintenance {
+            return Ok(());
+        }
+        let oracle_price = self.reference_price().ok().flatten();
+        for (index, market) in market_maker.config.deals.clone().iter().enumerate() {
+            let status = loop {
+                let open_deals = market_maker
+                    .open_deals
+                    .values()
+                    .filter(|deal| deal.market == index)
+                    .count();
+                if open_deals >= market.open_deals as usize {
+                    break MarketStatus::Running;
+                }
+                let (arbitrating_amount, accordant_amount, market_deal) =
+                    match market_maker.next_deal(index, oracle_price) {
+                        Ok(next_deal) => next_deal,
+                        Err(status) => break status,
+                    };
+                let deal = match self.make_market_deal(
+                    endpoints,
+                    market,
+                    arbitrating_amount,
+                    accordant_amount,
+                ) {
+                    Ok(deal) => deal,
+                    Err(Error::SwapLimit(reason)) => break MarketStatus::SwapLimit(reason),
+                    Err(err) => break MarketStatus::Failed(err.to_string()),
+                };
+                info!(
+                    "{}: {}",
+                    "Market maker deal made".bright_green_bold(),
+                    deal.id().bright_yellow_bold()
+                );
+                market_maker.btc_used += market_deal.btc;
+                market_maker.xmr_used += market_deal.xmr;
+                market_maker.open_deals.insert(deal.id(), market_deal);
+                market_maker.next_size[index] += 1;
+            };
+            market_maker.set_status(index, status);
+        }
+        Ok(())
+    }
+
+    fn make_market_deal(
+        &mut self,
+        endpoints: &mut Endpoints,
+        market: &MarketMakerDeals,
+        arbitrating_amount: bitcoin::Amount,
+        accordant_amount: monero::Amount,
+    ) -> Result<Deal, Error> {
+        let deal_parameters = DealParameters {
+            uuid: Uuid::new().into(),
+            network: market.network,
+            arbitrating_blockchain: Blockchain::Bitcoin,
+            accordant_blockchain: Blockchain::Monero,
+            arbitrating_amount,
+            accordant_amount,
+            cancel_timelock: CSVTimelock::new(market.cancel_timelock),
+            punish_timelock: CSVTimelock::new(market.punish_timelock),
+            fee_strategy: market.fee_strategy,
+            maker_role: market.maker_role,
+        };
+        self.config.validate_deal_parameters(
+            &deal_parameters,
+            &market.bitcoin_address,
+            &market.monero_address,
+            None,
+        )?;
+        self.validate_deal_policy(&deal_parameters, deal_parameters.maker_role, None)?;
+        self.check_swap_limits(&deal_parameters)?;
+        let bind_addr = self.config.get_bind_addr()?;
+        let node_id = self.listen(bind_addr)?;
+        let deal = deal_parameters.to_v1(node_id.public_key(), market.public_addr);
+        let (tsm, _) = make_internal_deal(
+            self,
+            endpoints,
+            deal.clone(),
+            market.bitcoin_address.clone(),
+            market.monero_address,
+            DealApproval::Auto,
+            None,
+        )?;
+        self.trade_state_machines.push(tsm);
+        Ok(deal)
+    }
+}
@@ -4,12 +4,24 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+mod bans;
+mod deal_registry;
+mod gossip;
+mod maintenance;
+mod market_maker;
 #[cfg(feature = "shell")]
 mod opts;
+mod policy;
+mod price_oracle;
+mod quotes;
 mod runtime;
+mod shutdown;
 pub mod stats;
+mod supervisor;
+mod swap_limits;
 mod syncer_state_machine;
 mod trade_state_machine;
+mod watchtower;
 
 #[cfg(feature = "shell")]
 pub use opts::Opts;
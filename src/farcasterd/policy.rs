@@ -0,0 +1,312 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::convert::TryInto;
+
+use farcaster_core::blockchain::FeeStrategy;
+use farcaster_core::role::SwapRole;
+use farcaster_core::swap::btcxmr::DealParameters;
+use internet2::addr::NodeId;
+
+use crate::config::DealPolicy;
+use crate::farcasterd::runtime::Runtime;
+use crate::swapd::TemporalSafety;
+use crate::Error;
+
+impl Runtime {
+    /// Validate a deal against the policy configured for its network and our role in the swap.
+    /// The maker is only given when taking a deal.
+    pub fn validate_deal_policy(
+        &self,
+        deal: &DealParameters,
+        local_role: SwapRole,
+        maker: Option<NodeId>,
+    ) -> Result<(), Error> {
+        let policy = match self.config.deal_policy(deal.network, local_role) {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+        validate_timelocks(&policy, &self.temporal_safety(deal)?)?;
+        validate_fee_strategy(deal, &policy)?;
+        if let Some(maker) = maker {
+            validate_maker(deal, &policy, maker)?;
+        }
+        if let Some(max_concurrent_swaps) = policy.max_concurrent_swaps {
+            let running_swaps = self.count_swaps(deal, local_role);
+            if running_swaps >= max_concurrent_swaps as usize {
+                return Err(Error::DealPolicy(format!(
+                    "{} swaps as {} already running on {}, the maximum is {}",
+                    running_swaps, local_role, deal.network, max_concurrent_swaps
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // The temporal safety of a swap of the deal with the configured finality and safety
+    fn temporal_safety(&self, deal: &DealParameters) -> Result<TemporalSafety, Error> {
+        let swap_config = self.config.get_swap_config(
+            deal.arbitrating_blockchain.try_into()?,
+            deal.accordant_blockchain.try_into()?,
+            deal.network,
+        )?;
+        Ok(TemporalSafety {
+            cancel_timelock: deal.cancel_timelock.as_u32(),
+            punish_timelock: deal.punish_timelock.as_u32(),
+            safety: swap_config.arbitrating.safety.into(),
+            arb_finality: swap_config.arbitrating.finality.into(),
+            acc_finality: swap_config.accordant.finality.into(),
+        })
+    }
+
+    // Number of swaps taken or running on the network of the deal with the same local role
+    fn count_swaps(&self, deal: &DealParameters, local_role: SwapRole) -> usize {
+        self.trade_state_machines
+            .iter()
+            .filter(|tsm| {
+                tsm.consumed_deal().map_or(false, |(deal_taken, _)| {
+                    deal_taken.parameters.network == deal.network
+                }) && tsm.swap_role() == Some(local_role)
+            })
+            .count()
+    }
+}
+
+fn validate_timelocks(policy: &DealPolicy, temporal_safety: &TemporalSafety) -> Result<(), Error> {
+    let cancel = temporal_safety.cancel_timelock;
+    let punish = temporal_safety.punish_timelock;
+    check_bounds(
+        "cancel timelock",
+        cancel,
+        policy.min_cancel_timelock,
+        policy.max_cancel_timelock,
+    )?;
+    check_bounds(
+        "punish timelock",
+        punish,
+        policy.min_punish_timelock,
+        policy.max_punish_timelock,
+    )?;
+    temporal_safety
+        .valid_params()
+        .map_err(|err| Error::DealPolicy(err.to_string()))?;
+    // valid parameters guarantee the timelocks are above the finality and the safety
+    let margin = temporal_safety.arb_finality + temporal_safety.safety;
+    check_bounds(
+        "buy window",
+        cancel.saturating_sub(margin),
+        policy.min_buy_window,
+        None,
+    )?;
+    check_bounds(
+        "refund window",
+        punish.saturating_sub(margin),
+        policy.min_refund_window,
+        None,
+    )
+}
+
+fn validate_maker(deal: &DealParameters, policy: &DealPolicy, maker: NodeId) -> Result<(), Error> {
+    if !policy.allowed_makers.is_empty() && !policy.allowed_makers.contains(&maker) {
+        return Err(Error::DealPolicy(format!(
+            "maker {} is not allowed on {}",
+            maker, deal.network
+        )));
+    }
+    Ok(())
+}
+// Only fixed fee rates are supported, a new fee strategy must be given its own bounds here
+fn validate_fee_strategy(deal: &DealParameters, policy: &DealPolicy) -> Result<(), Error> {
+    match &deal.fee_strategy {
+        FeeStrategy::Fixed(fee_rate) => {
+            if let Some(min_fee_rate) = policy.min_fee_rate {
+                if fee_rate.as_sat() < min_fee_rate.as_sat() {
+                    return Err(Error::DealPolicy(format!(
+                        "fee rate {} is below the minimum {}",
+                        fee_rate, min_fee_rate
+                    )));
+                }
+            }
+            if let Some(max_fee_rate) = policy.max_fee_rate {
+                if fee_rate.as_sat() > max_fee_rate.as_sat() {
+                    return Err(Error::DealPolicy(format!(
+                        "fee rate {} is above the maximum {}",
+                        fee_rate, max_fee_rate
+                    )));
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn check_bounds(name: &str, value: u32, min: Option<u32>, max: Option<u32>) -> Result<(), Error> {
+    if let Some(min) = min {
+        if value < min {
+            return Err(Error::DealPolicy(format!(
+                "{} of {} blocks is below the minimum of {} blocks",
+                name, value, min
+            )));
+        }
+    }
+    if let Some(max) = max {
+        if value > max {
+            return Err(Error::DealPolicy(format!(
+                "{} of {} blocks is above the maximum of {} blocks",
+                name, value, max
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use farcaster_core::bitcoin::fee::SatPerKvB;
+    use farcaster_core::bitcoin::timelock::CSVTimelock;
+    use farcaster_core::blockchain::{Blockchain, Network};
+    use farcaster_core::Uuid;
+
+    fn deal(fee_rate: u64) -> DealParameters {
+        DealParameters {
+            uuid: Uuid::new().into(),
+            network: Network::Testnet,
+            arbitrating_blockchain: Blockchain::Bitcoin,
+            accordant_blockchain: Blockchain::Monero,
+            arbitrating_amount: bitcoin::Amount::from_sat(100_000),
+            accordant_amount: monero::Amount::from_pico(1_000_000_000_000),
+            cancel_timelock: CSVTimelock::new(10),
+            punish_timelock: CSVTimelock::new(20),
+            fee_strategy: FeeStrategy::Fixed(SatPerKvB::from_sat(fee_rate)),
+            maker_role: SwapRole::Bob,
+        }
+    }
+
+    fn temporal_safety(cancel_timelock: u32, punish_timelock: u32) -> TemporalSafety {
+        TemporalSafety {
+            cancel_timelock,
+            punish_timelock,
+            safety: 3,
+            arb_finality: 2,
+            acc_finality: 1,
+        }
+    }
+
+    fn node_id(byte: u8) -> NodeId {
+        let secret_key = SecretKey::from_slice(&[byte; 32]).unwrap();
+        NodeId::from(PublicKey::from_secret_key(&Secp256k1::new(), &secret_key))
+    }
+
+    fn rejection(res: Result<(), Error>) -> String {
+        match res {
+            Err(Error::DealPolicy(reason)) => reason,
+            res => panic!("expected a policy rejection, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        assert!(check_bounds("cancel timelock", 10, None, None).is_ok());
+        assert!(check_bounds("cancel timelock", 10, Some(10), Some(10)).is_ok());
+        assert_eq!(
+            rejection(check_bounds("cancel timelock", 9, Some(10), None)),
+            "cancel timelock of 9 blocks is below the minimum of 10 blocks"
+        );
+        assert_eq!(
+            rejection(check_bounds("punish timelock", 11, None, Some(10))),
+            "punish timelock of 11 blocks is above the maximum of 10 blocks"
+        );
+    }
+
+    #[test]
+    fn timelocks() {
+        let policy = DealPolicy {
+            min_cancel_timelock: Some(10),
+            max_punish_timelock: Some(20),
+            ..Default::default()
+        };
+        assert!(validate_timelocks(&policy, &temporal_safety(10, 20)).is_ok());
+        assert_eq!(
+            rejection(validate_timelocks(&policy, &temporal_safety(9, 20))),
+            "cancel timelock of 9 blocks is below the minimum of 10 blocks"
+        );
+        assert_eq!(
+            rejection(validate_timelocks(&policy, &temporal_safety(10, 21))),
+            "punish timelock of 21 blocks is above the maximum of 20 blocks"
+        );
+        // the cancel timelock must be above the safety
+        assert_eq!(
+            rejection(validate_timelocks(
+                &DealPolicy::default(),
+                &temporal_safety(3, 20)
+            )),
+            "Unsafe or invalid temporal parameters between: timelocks, finality, and safety"
+        );
+    }
+
+    #[test]
+    fn safety_margins() {
+        // the finality and the safety take 5 blocks off the timelocks
+        let policy = DealPolicy {
+            min_buy_window: Some(5),
+            min_refund_window: Some(15),
+            ..Default::default()
+        };
+        assert!(validate_timelocks(&policy, &temporal_safety(10, 20)).is_ok());
+        assert_eq!(
+            rejection(validate_timelocks(&policy, &temporal_safety(9, 20))),
+            "buy window of 4 blocks is below the minimum of 5 blocks"
+        );
+        assert_eq!(
+            rejection(validate_timelocks(&policy, &temporal_safety(10, 19))),
+            "refund window of 14 blocks is below the minimum of 15 blocks"
+        );
+    }
+
+    #[test]
+    fn fee_range() {
+        let policy = DealPolicy {
+            min_fee_rate: Some(SatPerKvB::from_sat(1_000)),
+            max_fee_rate: Some(SatPerKvB::from_sat(50_000)),
+            ..Default::default()
+        };
+        assert!(validate_fee_strategy(&deal(1_000), &policy).is_ok());
+        assert!(validate_fee_strategy(&deal(50_000), &policy).is_ok());
+        assert_eq!(
+            rejection(validate_fee_strategy(&deal(999), &policy)),
+            format!(
+                "fee rate {} is below the minimum {}",
+                SatPerKvB::from_sat(999),
+                SatPerKvB::from_sat(1_000)
+            )
+        );
+        assert_eq!(
+            rejection(validate_fee_strategy(&deal(50_001), &policy)),
+            format!(
+                "fee rate {} is above the maximum {}",
+                SatPerKvB::from_sat(50_001),
+                SatPerKvB::from_sat(50_000)
+            )
+        );
+    }
+
+    #[test]
+    fn allowed_makers() {
+        let deal = deal(1_000);
+        assert!(validate_maker(&deal, &DealPolicy::default(), node_id(1)).is_ok());
+        let policy = DealPolicy {
+            allowed_makers: vec![node_id(1)],
+            ..Default::default()
+        };
+        assert!(validate_maker(&deal, &policy, node_id(1)).is_ok());
+        assert_eq!(
+            rejection(validate_maker(&deal, &policy, node_id(2))),
+            format!("maker {} is not allowed on Testnet", node_id(2))
+        );
+    }
+}
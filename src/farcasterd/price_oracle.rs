@@ -0,0 +1,117 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::time::Duration;
+
+use crate::bus::bridge::BridgeMsg;
+use crate::bus::ctl::CtlMsg;
+use crate::bus::info::{InfoMsg, ReferencePrice};
+use crate::bus::{unix_timestamp, Failure, FailureCode};
+use crate::config::PriceOracleConfig;
+use crate::farcasterd::runtime::Runtime;
+use crate::farcasterd::supervisor::report_over_bridge;
+use crate::oracle::price_source;
+use crate::service::Endpoints;
+use crate::{CtlServer, Error, ServiceId};
+
+/// Number of missed price updates after which the reference price is considered stale
+const STALE_PRICE_UPDATES: u32 = 5;
+
+/// The latest reference price reported by the price oracle thread
+pub struct PriceOracle {
+    price: Option<ReferencePrice>,
+    stale_after: Duration,
+}
+
+impl PriceOracle {
+    pub fn new(config: &PriceOracleConfig) -> Self {
+        PriceOracle {
+            price: None,
+            stale_after: config.refresh_interval() * STALE_PRICE_UPDATES,
+        }
+    }
+
+    /// The latest reference price, fails if none was fetched yet or if it is stale
+    pub fn price(&self) -> Result<ReferencePrice, Error> {
+        match &self.price {
+            Some(price) if price.since + self.stale_after.as_secs() >= unix_timestamp() => {
+                Ok(price.clone())
+            }
+            Some(price) => Err(Error::PriceOracle(format!(
+                "the reference price is stale, last updated at {}",
+                price.since
+            ))),
+            None => Err(Error::PriceOracle(
+                "no reference price fetched yet".to_string(),
+            )),
+        }
+    }
+}
+
+/// Fetch the reference price from the configured price source every refresh interval and report
+/// it to the runtime
+pub fn spawn_price_oracle(config: &PriceOracleConfig) -> Result<(), Error> {
+    let source = price_source(config)?;
+    let refresh_interval = config.refresh_interval();
+    std::thread::spawn(move || loop {
+        match source.fetch() {
+            Ok(price) => report_over_bridge(BridgeMsg::ReferencePrice { price }),
+            Err(err) => warn!("Failed to fetch the reference price: {}", err),
+        }
+        std::thread::sleep(refresh_interval);
+    });
+    Ok(())
+}
+
+impl Runtime {
+    pub fn handle_reference_price(
+        &mut self,
+        endpoints: &mut Endpoints,
+        price: bitcoin::Amount,
+    ) -> Result<(), Error> {
+        if let Some(oracle) = self.price_oracle.as_mut() {
+            debug!("Reference price updated to {} BTC/XMR", price.as_btc());
+            oracle.price = Some(ReferencePrice {
+                price,
+                since: unix_timestamp(),
+            });
+        }
+        // the market maker may wait for a reference price to price its deals
+        self.handle_market_maker(endpoints)
+    }
+
+    /// The reference price the taken deals are validated against: None if no price oracle is
+    /// configured, fails if the price is not known or stale
+    pub fn reference_price(&self) -> Result<Option<bitcoin::Amount>, Error> {
+        self.price_oracle
+            .as_ref()
+            .map(|oracle| oracle.price().map(|reference| reference.price))
+            .transpose()
+    }
+
+    pub fn handle_get_reference_price(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+    ) -> Result<(), Error> {
+        let res = self
+            .price_oracle
+            .as_ref()
+            .ok_or_else(|| Error::PriceOracle("no price oracle configured".to_string()))
+            .and_then(|oracle| oracle.price());
+        match res {
+            Ok(price) => self.send_client_info(endpoints, source, InfoMsg::ReferencePrice(price)),
+            Err(err) => self.send_client_ctl(
+                endpoints,
+                source,
+                CtlMsg::Failure(Failure {
+                    code: FailureCode::from(&err),
+                    info: err.to_string(),
+                }),
+            ),
+        }
+    }
+}
@@ -0,0 +1,650 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use farcaster_core::swap::btcxmr::Deal;
+use farcaster_core::trade::DealId;
+use farcaster_core::Uuid;
+use internet2::addr::{NodeAddr, NodeId};
+use microservices::esb::Handler;
+
+use crate::bus::bridge::BridgeMsg;
+use crate::bus::ctl::{CtlMsg, DealApproval, DealTemplate};
+use crate::bus::info::{DealTemplateInfo, InfoMsg, QuotedDeal, ViewableDeal};
+use crate::bus::p2p::{PeerMsg, Quote, QuoteRejection, QuoteRequest};
+use crate::bus::{BusMsg, Failure, FailureCode, Misbehavior, OptionDetails, ServiceBus};
+use crate::farcasterd::runtime::Runtime;
+use crate::farcasterd::supervisor::report_over_bridge;
+use crate::farcasterd::trade_state_machine::make_internal_deal;
+use crate::service::Endpoints;
+use crate::{CtlServer, Error, LogStyle, ServiceId};
+
+/// Time a deal issued for a quote request remains takeable
+const QUOTED_DEAL_LIFETIME: Duration = Duration::from_secs(300);
+/// Time the taker waits for the maker to answer a quote request
+const QUOTE_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Quote requests a taker may send within the rate limit window, every further request is
+/// rejected and counts as misbehavior
+const MAX_QUOTE_REQUESTS_PER_WINDOW: usize = 10;
+const QUOTE_REQUEST_WINDOW: Duration = Duration::from_secs(60);
+/// Deals quoted to a taker that may be open at once, further requests are rejected until the
+/// taker takes them or they expire
+const MAX_OPEN_QUOTED_DEALS_PER_TAKER: usize = 3;
+
+/// Negotiated deals: on the maker side the deal templates and the deals issued from them, on the
+/// taker side the quote requests waiting for a connection or for the answer of the maker.
+#[derive(Default)]
+pub struct DealQuotes {
+    templates: HashMap<Uuid, TemplateInventory>,
+    issued_deals: HashMap<DealId, IssuedDeal>,
+    quote_requests: HashMap<NodeId, Vec<Instant>>,
+    pending_requests: HashMap<NodeId, Vec<QuoteRequest>>,
+    awaiting_quotes: HashMap<Uuid, AwaitingQuote>,
+}
+
+struct IssuedDeal {
+    template_id: Uuid,
+    amount: bitcoin::Amount,
+    taker: NodeId,
+}
+
+struct TemplateInventory {
+    template: DealTemplate,
+    // Arbitrating amount the template can still issue deals for, unlimited if None
+    available: Option<bitcoin::Amount>,
+    issued_deals: u64,
+}
+
+struct AwaitingQuote {
+    client: ServiceId,
+    maker: NodeId,
+    request: QuoteRequest,
+}
+
+impl DealQuotes {
+    /// Returns if quote requests are waiting for the connection to complete
+    pub fn awaits_connection(&self, service: &ServiceId) -> bool {
+        service
+            .node_id()
+            .map_or(false, |id| self.pending_requests.contains_key(&id))
+    }
+
+    pub fn list_templates(&self) -> Vec<DealTemplateInfo> {
+        self.templates
+            .values()
+            .map(|inventory| {
+                let template = &inventory.template;
+                let params = &template.deal_parameters;
+                DealTemplateInfo {
+                    template_id: template.id,
+                    network: params.network,
+                    maker_role: params.maker_role,
+                    min_amount: template.min_arbitrating_amount,
+                    max_amount: params.arbitrating_amount,
+                    price: format!("{} BTC/XMR", template.price()),
+                    cancel_timelock: format!(
+                        "{} [{}, {}]",
+                        params.cancel_timelock.as_u32(),
+                        template.min_cancel_timelock,
+                        template.max_cancel_timelock
+                    ),
+                    punish_timelock: format!(
+                        "{} [{}, {}]",
+                        params.punish_timelock.as_u32(),
+                        template.min_punish_timelock,
+                        template.max_punish_timelock
+                    ),
+                    available: inventory.available,
+                    issued_deals: inventory.issued_deals,
+                }
+            })
+            .collect()
+    }
+
+    /// The template quoting the request: the requested one, or the first one covering the
+    /// requested amount on the network
+    fn quoting_template(&self, request: &QuoteRequest) -> Result<&TemplateInventory, Error> {
+        let covers = |inventory: &&TemplateInventory| {
+            inventory
+                .available
+                .map_or(true, |available| available >= request.arbitrating_amount)
+        };
+        match request.template_id {
+            Some(template_id) => self
+                .templates
+                .get(&template_id)
+                .ok_or_else(|| Error::Farcaster(format!("Unknown deal template {}", template_id)))
+                .and_then(|inventory| {
+                    Some(inventory).filter(covers).ok_or_else(|| {
+                        Error::Farcaster(format!(
+                            "Deal template {} has not enough inventory left",
+                            template_id
+                        ))
+                    })
+                }),
+            None => self
+                .templates
+                .values()
+                .filter(covers)
+                .find(|inventory| inventory.template.quote(request).is_ok())
+                .ok_or_else(|| {
+                    Error::Farcaster(format!(
+                        "No deal template for {} on {}",
+                        request.arbitrating_amount, request.network
+                    ))
+                }),
+        }
+    }
+
+    /// Record a quote request of the taker, returns false if it exceeds the rate limit
+    fn record_quote_request(&mut self, taker: NodeId) -> bool {
+        let now = Instant::now();
+        self.quote_requests.retain(|_, requests| {
+            requests.retain(|at| now.duration_since(*at) < QUOTE_REQUEST_WINDOW);
+            !requests.is_empty()
+        });
+        let requests = self.quote_requests.entry(taker).or_default();
+        requests.push(now);
+        requests.len() <= MAX_QUOTE_REQUESTS_PER_WINDOW
+    }
+
+    fn queue_request(&mut self, maker: NodeAddr, request: QuoteRequest) {
+        self.pending_requests
+            .entry(maker.id)
+            .or_default()
+            .push(request);
+    }
+
+    fn take_pending_requests(&mut self, service: &ServiceId) -> Vec<QuoteRequest> {
+        service
+            .node_id()
+            .and_then(|id| self.pending_requests.remove(&id))
+            .unwrap_or_default()
+    }
+}
+
+impl Runtime {
+    /// Add a deal template, starting the listener the takers connect to
+    pub fn handle_add_deal_template(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        template: DealTemplate,
+    ) -> Result<(), Error> {
+        let res = template
+            .validate()
+            .and_then(|_| {
+                self.config.validate_deal_parameters(
+                    &template.deal_parameters,
+                    &template.arbitrating_addr,
+                    &template.accordant_addr,
+                    None,
+                )
+            })
+            .and_then(|_| self.config.get_bind_addr())
+            .and_then(|bind_addr| self.listen(bind_addr));
+        if let Err(err) = res {
+            warn!("Deal template validation error: {}", err);
+            return self.send_client_ctl(
+                endpoints,
+                source,
+                CtlMsg::Failure(Failure {
+                    code: FailureCode::from(&err),
+                    info: err.to_string(),
+                }),
+            );
+        }
+        info!(
+            "{}: {}",
+            "Deal template added".bright_green_bold(),
+            template.id.bright_yellow_bold()
+        );
+        let id = template.id;
+        self.quotes.templates.insert(
+            id,
+            TemplateInventory {
+                available: template.inventory,
+                template,
+                issued_deals: 0,
+            },
+        );
+        self.send_client_ctl(
+            endpoints,
+            source,
+            CtlMsg::Success(OptionDetails::with(format!(
+                "Deal template {} added, takers can request quotes from it",
+                id
+            ))),
+        )
+    }
+
+    /// Remove a deal template, the deals issued from it remain takeable until they expire
+    pub fn handle_remove_deal_template(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        template_id: Uuid,
+    ) -> Result<(), Error> {
+        let reply = if self.quotes.templates.remove(&template_id).is_some() {
+            info!("Deal template {} removed", template_id.bright_yellow_bold());
+            CtlMsg::Success(OptionDetails::with(format!(
+                "Deal template {} removed",
+                template_id
+            )))
+        } else {
+            CtlMsg::Failure(Failure {
+                code: FailureCode::NotFound,
+                info: format!("Unknown deal template {}", template_id),
+            })
+        };
+        self.send_client_ctl(endpoints, source, reply)
+    }
+
+    pub fn handle_list_deal_templates(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+    ) -> Result<(), Error> {
+        self.send_client_info(
+            endpoints,
+            source,
+            InfoMsg::DealTemplateList(self.quotes.list_templates().into()),
+        )
+    }
+
+    /// Return the amount of a deal issued from a template to its inventory, called when the deal
+    /// is revoked or expires without being taken
+    pub fn release_quoted_deal(&mut self, deal: &Deal) {
+        if let Some(issued) = self.quotes.issued_deals.remove(&deal.id()) {
+            if let Some(inventory) = self.quotes.templates.get_mut(&issued.template_id) {
+                inventory.available = inventory
+                    .available
+                    .map(|available| available + issued.amount);
+            }
+        }
+    }
+
+    /// The deals quoted to the taker that are still open, the issued deals taken since are
+    /// forgotten
+    fn open_quoted_deals(&mut self, taker: NodeId) -> usize {
+        let open_deals: Vec<DealId> = self
+            .trade_state_machines
+            .iter()
+            .filter_map(|tsm| tsm.open_deal())
+            .map(|deal| deal.id())
+            .collect();
+        self.quotes
+            .issued_deals
+            .retain(|deal_id, _| open_deals.contains(deal_id));
+        self.quotes
+            .issued_deals
+            .values()
+            .filter(|issued| issued.taker == taker)
+            .count()
+    }
+
+    /// Issue a deal from one of our templates for the quote request of a taker
+    pub fn handle_quote_request(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        request: QuoteRequest,
+    ) -> Result<(), Error> {
+        let taker = match source.node_id() {
+            Some(taker) => taker,
+            None => return Ok(()),
+        };
+        if !self.quotes.record_quote_request(taker) {
+            info!(
+                "Rejecting quote request {} from {}: too many requests",
+                request.id, taker
+            );
+            self.send_quote_rejection(
+                endpoints,
+                source.clone(),
+                request.id,
+                Error::Farcaster("Too many quote requests, retry later".to_string()),
+            )?;
+            return self.handle_peer_misbehavior(endpoints, &source, Misbehavior::QuoteFlood);
+        }
+        if self.open_quoted_deals(taker) >= MAX_OPEN_QUOTED_DEALS_PER_TAKER {
+            info!(
+                "Rejecting quote request {} from {}: too many open quoted deals",
+                request.id, taker
+            );
+            return self.send_quote_rejection(
+                endpoints,
+                source,
+                request.id,
+                Error::Farcaster(format!(
+                    "At most {} quoted deals can be open at once, take or let them expire first",
+                    MAX_OPEN_QUOTED_DEALS_PER_TAKER
+                )),
+            );
+        }
+        let issued = self
+            .check_maintenance()
+            .and_then(|_| self.quotes.quoting_template(&request))
+            .and_then(|inventory| {
+                let template = &inventory.template;
+                let parameters = template.quote(&request)?;
+                self.config.validate_deal_parameters(
+                    &parameters,
+                    &template.arbitrating_addr,
+                    &template.accordant_addr,
+                    None,
+                )?;
+                self.validate_deal_policy(&parameters, parameters.maker_role, None)?;
+                self.check_swap_limits(&parameters)?;
+                Ok((template.clone(), parameters))
+            });
+        let (template, parameters) = match issued {
+            Ok(issued) => issued,
+            Err(err) => {
+                info!(
+                    "Rejecting quote request {} from {}: {}",
+                    request.id, taker, err
+                );
+                return self.send_quote_rejection(endpoints, source, request.id, err);
+            }
+        };
+        let deal = match self
+            .config
+            .get_bind_addr()
+            .and_then(|bind_addr| self.listen(bind_addr))
+            .map(|node_id| parameters.to_v1(node_id.public_key(), template.public_addr))
+        {
+            Ok(deal) => deal,
+            Err(err) => return self.send_quote_rejection(endpoints, source, request.id, err),
+        };
+        // The quoted deal is signed with its expiry, only the taker can take it and it is not
+        // announced
+        let (tsm, signed_deal) = match make_internal_deal(
+            self,
+            endpoints,
+            deal.clone(),
+            template.arbitrating_addr.clone(),
+            template.accordant_addr,
+            DealApproval::Allowlist(vec![taker]),
+            Some(QUOTED_DEAL_LIFETIME),
+        ) {
+            Ok((tsm, Some(signed_deal))) => (tsm, signed_deal),
+            Ok((_, None)) => unreachable!("deals with a lifetime are signed"),
+            Err(err) => return self.send_quote_rejection(endpoints, source, request.id, err),
+        };
+        self.trade_state_machines.push(tsm);
+        if let Some(inventory) = self.quotes.templates.get_mut(&template.id) {
+            inventory.available = inventory
+                .available
+                .map(|available| available - request.arbitrating_amount);
+            inventory.issued_deals += 1;
+        }
+        self.quotes.issued_deals.insert(
+            deal.id(),
+            IssuedDeal {
+                template_id: template.id,
+                amount: request.arbitrating_amount,
+                taker,
+            },
+        );
+        info!(
+            "{}: {} for {} from template {}",
+            "Deal quoted".bright_green_bold(),
+            deal.id().bright_yellow_bold(),
+            taker.bright_blue_italic(),
+            template.id
+        );
+        endpoints.send_to(
+            ServiceBus::Msg,
+            self.identity(),
+            source,
+            BusMsg::P2p(PeerMsg::Quote(Quote {
+                request_id: request.id,
+                signed_deal,
+            })),
+        )?;
+        Ok(())
+    }
+
+    fn send_quote_rejection(
+        &mut self,
+        endpoints: &mut Endpoints,
+        peerd: ServiceId,
+        request_id: Uuid,
+        err: Error,
+    ) -> Result<(), Error> {
+        endpoints.send_to(
+            ServiceBus::Msg,
+            self.identity(),
+            peerd,
+            BusMsg::P2p(PeerMsg::QuoteRejected(QuoteRejection {
+                request_id,
+                reason: err.to_string(),
+            })),
+        )?;
+        Ok(())
+    }
+
+    /// Send a quote request to a remote maker, the client is answered once the maker replied
+    pub fn handle_request_quote(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        maker: NodeAddr,
+        request: QuoteRequest,
+    ) -> Result<(), Error> {
+        match self.connect_peer(&maker) {
+            Ok((true, peerd)) => {
+                endpoints.send_to(
+                    ServiceBus::Msg,
+                    self.identity(),
+                    peerd,
+                    BusMsg::P2p(PeerMsg::QuoteRequest(request.clone())),
+                )?;
+            }
+            Ok((false, _)) => {
+                debug!(
+                    "Waiting for the connection to {} to request quote {}",
+                    maker, request.id
+                );
+                self.quotes.queue_request(maker, request.clone());
+            }
+            Err(err) => {
+                return self.send_client_ctl(
+                    endpoints,
+                    source,
+                    CtlMsg::Failure(Failure {
+                        code: FailureCode::PeerUnreachable,
+                        info: format!("Failed to connect to maker {}: {}", maker, err),
+                    }),
+                );
+            }
+        }
+        let request_id = request.id;
+        self.quotes.awaiting_quotes.insert(
+            request_id,
+            AwaitingQuote {
+                client: source,
+                maker: maker.id,
+                request,
+            },
+        );
+        std::thread::spawn(move || {
+            std::thread::sleep(QUOTE_REQUEST_TIMEOUT);
+            report_over_bridge(BridgeMsg::QuoteTimeout { request_id });
+        });
+        Ok(())
+    }
+
+    /// Send the quote requests waiting for the connection to the maker, or fail them if the
+    /// connection failed
+    pub fn handle_quote_connection(
+        &mut self,
+        endpoints: &mut Endpoints,
+        peerd: ServiceId,
+        connected: bool,
+    ) -> Result<(), Error> {
+        let pending_requests = self.quotes.take_pending_requests(&peerd);
+        if !connected {
+            for request in pending_requests {
+                self.fail_quote_request(
+                    endpoints,
+                    request.id,
+                    FailureCode::PeerUnreachable,
+                    format!("Connection to maker {} failed", peerd),
+                )?;
+            }
+            return self.handle_failed_connection(endpoints, peerd);
+        }
+        for request in pending_requests {
+            endpoints.send_to(
+                ServiceBus::Msg,
+                self.identity(),
+                peerd.clone(),
+                BusMsg::P2p(PeerMsg::QuoteRequest(request)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Check the deal quoted by the maker matches our request and pass it on to the client
+    pub fn handle_quote(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        quote: Quote,
+    ) -> Result<(), Error> {
+        let awaiting = match self.quotes.awaiting_quotes.get(&quote.request_id) {
+            Some(awaiting) if Some(awaiting.maker) == source.node_id() => awaiting,
+            _ => {
+                debug!("Ignoring unexpected quote {} from {}", quote, source);
+                return Ok(());
+            }
+        };
+        let request = &awaiting.request;
+        let deal = &quote.signed_deal.deal;
+        let params = &deal.parameters;
+        let res = quote.signed_deal.verify().and_then(|_| {
+            if NodeId::from(deal.node_id) != awaiting.maker
+                || params.network != request.network
+                || params.arbitrating_amount != request.arbitrating_amount
+                || request.cancel_timelock.map_or(false, |timelock| {
+                    params.cancel_timelock.as_u32() != timelock
+                })
+                || request.punish_timelock.map_or(false, |timelock| {
+                    params.punish_timelock.as_u32() != timelock
+                })
+            {
+                Err(Error::Farcaster(format!(
+                    "The quoted deal {} does not match the request",
+                    deal.id()
+                )))
+            } else {
+                Ok(())
+            }
+        });
+        let awaiting = self
+            .quotes
+            .awaiting_quotes
+            .remove(&quote.request_id)
+            .expect("checked above");
+        match res {
+            Ok(()) => {
+                info!(
+                    "{}: {} from {}",
+                    "Received quote".bright_green_bold(),
+                    deal.id().bright_yellow_bold(),
+                    source.bright_blue_italic()
+                );
+                self.send_client_info(
+                    endpoints,
+                    awaiting.client,
+                    InfoMsg::QuotedDeal(QuotedDeal {
+                        message: format!(
+                            "Deal quoted, take it with the signed deal before {}.",
+                            quote.signed_deal.expiry
+                        ),
+                        expiry: quote.signed_deal.expiry,
+                        viewable_deal: ViewableDeal {
+                            deal: quote.signed_deal.to_string(),
+                            details: deal.clone(),
+                        },
+                    }),
+                )
+            }
+            Err(err) => {
+                warn!("Rejecting quote from {}: {}", source, err);
+                self.send_client_ctl(
+                    endpoints,
+                    awaiting.client,
+                    CtlMsg::Failure(Failure {
+                        code: FailureCode::InvalidDeal,
+                        info: err.to_string(),
+                    }),
+                )
+            }
+        }
+    }
+
+    pub fn handle_quote_rejected(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        rejection: QuoteRejection,
+    ) -> Result<(), Error> {
+        match self.quotes.awaiting_quotes.get(&rejection.request_id) {
+            Some(awaiting) if Some(awaiting.maker) == source.node_id() => self.fail_quote_request(
+                endpoints,
+                rejection.request_id,
+                FailureCode::InvalidDeal,
+                format!("Quote rejected by the maker: {}", rejection.reason),
+            ),
+            _ => {
+                debug!(
+                    "Ignoring unexpected quote rejection {} from {}",
+                    rejection, source
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Fail the quote request if the maker did not answer in time
+    pub fn handle_quote_timeout(
+        &mut self,
+        endpoints: &mut Endpoints,
+        request_id: Uuid,
+    ) -> Result<(), Error> {
+        self.fail_quote_request(
+            endpoints,
+            request_id,
+            FailureCode::PeerUnreachable,
+            "The maker did not answer the quote request in time".to_string(),
+        )
+    }
+
+    fn fail_quote_request(
+        &mut self,
+        endpoints: &mut Endpoints,
+        request_id: Uuid,
+        code: FailureCode,
+        info: String,
+    ) -> Result<(), Error> {
+        match self.quotes.awaiting_quotes.remove(&request_id) {
+            Some(awaiting) => {
+                warn!("Quote request {} failed: {}", request_id, info);
+                self.send_client_ctl(
+                    endpoints,
+                    awaiting.client,
+                    CtlMsg::Failure(Failure { code, info }),
+                )
+            }
+            None => Ok(()),
+        }
+    }
+}
@@ -4,21 +4,31 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use crate::bus::ctl::{CtlMsg, FundingInfo, GetKeys, SwapKeys};
+use crate::bus::bridge::BridgeMsg;
+use crate::bus::ctl::{CtlMsg, FundingInfo, GetKeys, ProtoQuoteRequest, StaleSyncerData, SwapKeys};
 use crate::bus::info::FundingInfos;
 use crate::bus::p2p::{PeerMsg, TakerCommit};
 use crate::bus::sync::SyncMsg;
-use crate::bus::{BusMsg, DealInfo, DealStatus, List, ServiceBus};
+use crate::bus::{unix_timestamp, BusMsg, DealInfo, DealStatus, List, Misbehavior, ServiceBus};
 use crate::event::StateMachineExecutor;
+use crate::farcasterd::bans::PeerBans;
+use crate::farcasterd::gossip::{spawn_deal_gossip_refresh, DealGossip};
+use crate::farcasterd::market_maker::MarketMaker;
+use crate::farcasterd::price_oracle::{spawn_price_oracle, PriceOracle};
+use crate::farcasterd::quotes::DealQuotes;
+use crate::farcasterd::shutdown::Shutdown;
 use crate::farcasterd::stats::Stats;
+use crate::farcasterd::supervisor::{supervise, Supervisor, SUPERVISOR_BRIDGE};
+use crate::farcasterd::swap_limits::SwapVolume;
 use crate::farcasterd::syncer_state_machine::{SyncerStateMachine, SyncerStateMachineExecutor};
 use crate::farcasterd::trade_state_machine::{TradeStateMachine, TradeStateMachineExecutor};
+use crate::farcasterd::watchtower::{Watchtower, WatchtowerClient};
 use crate::farcasterd::Opts;
 use crate::syncerd::{AddressBalance, TaskAborted};
 use crate::syncerd::{Event as SyncerEvent, HealthResult, SweepSuccess, TaskId};
 use crate::{
     bus::ctl::{Keys, ProgressStack, Token},
-    bus::info::{DealStatusSelector, InfoMsg, NodeInfo, ProgressEvent, SwapProgress},
+    bus::info::{DealStatusSelector, InfoMsg, NodeInfo, ProgressEvent, SwapProgress, SyncerAlert},
     bus::{Failure, FailureCode, Progress},
     clap::Parser,
     config::ParsedSwapConfig,
@@ -46,6 +56,10 @@ use farcaster_core::{
 use internet2::addr::NodeId;
 use internet2::{addr::InetSocketAddr, addr::NodeAddr};
 use microservices::esb::{self, Handler};
+use microservices::ZMQ_CONTEXT;
+
+/// Number of stale syncer data alerts kept in the node info
+const MAX_SYNCER_ALERTS: usize = 50;
 
 pub fn run(
     service_config: ServiceConfig,
@@ -53,6 +67,10 @@ pub fn run(
     _opts: Opts,
     wallet_token: Token,
 ) -> Result<(), Error> {
+    // Bind the bridge over which the supervising threads report exited swapd and syncerd
+    let rx = ZMQ_CONTEXT.socket(zmq::PULL)?;
+    rx.bind(SUPERVISOR_BRIDGE)?;
+
     let _walletd = launch("walletd", ["--token", &wallet_token.to_string()])?;
     if config.is_grpc_enable() {
         let _grpcd = launch(
@@ -76,6 +94,11 @@ pub fn run(
         );
     }
 
+    if let Some(price_oracle) = config.price_oracle() {
+        spawn_price_oracle(&price_oracle)?;
+    }
+    spawn_deal_gossip_refresh();
+
     let runtime = Runtime {
         identity: ServiceId::Farcasterd,
         node_secret_key: None,
@@ -85,19 +108,38 @@ pub fn run(
         auto_restored: false,
         spawning_services: none!(),
         registered_services: none!(),
+        peer_processes: none!(),
         deals: none!(),
         wallet_token,
         progress: none!(),
         progress_subscriptions: none!(),
         stats: none!(),
-        config,
+        syncer_alerts: none!(),
         syncer_task_counter: 0,
         trade_state_machines: vec![],
         syncer_state_machines: none!(),
+        watchtower: if config.is_watchtower_enable() {
+            Some(Watchtower::new(config.watchtower_authorized_clients()))
+        } else {
+            None
+        },
+        watchtower_client: WatchtowerClient::new(config.watchtowers()),
+        supervisor: none!(),
+        bans: none!(),
+        gossip: none!(),
+        quotes: none!(),
+        market_maker: config.market_maker().map(MarketMaker::new),
+        price_oracle: config.price_oracle().as_ref().map(PriceOracle::new),
+        swap_volume: none!(),
+        maintenance: false,
+        shutdown: none!(),
+        config,
     };
 
-    let broker = true;
-    Service::run(service_config, runtime, broker)
+    let mut service = Service::broker(service_config, runtime)?;
+    service.add_bridge_service_bus(rx)?;
+    service.run_loop()?;
+    unreachable!()
 }
 
 pub struct Runtime {
@@ -110,6 +152,7 @@ pub struct Runtime {
     pub listens: HashSet<InetSocketAddr>, // Set by MakeDeal, contains unique socket addresses of the binding peerd listeners.
     pub spawning_services: HashSet<ServiceId>, // Services that have been launched, but have not replied with Hello yet
     pub registered_services: HashSet<ServiceId>, // Services that have announced themselves with Hello
+    peer_processes: HashMap<NodeId, u32>, // The id of the peerd process owning the connection to each remote node
     pub deals: HashSet<Deal>, // The set of all known deals. Includes open, consumed and ended deals includes open, consumed and ended deals
     progress: HashMap<ServiceId, VecDeque<ProgressStack>>, // A mapping from Swap ServiceId to its sent and received progress messages (Progress, Success, Failure)
     progress_subscriptions: HashMap<ServiceId, HashSet<ServiceId>>, // A mapping from a Client ServiceId to its subsribed swap progresses
@@ -118,6 +161,18 @@ pub struct Runtime {
     pub syncer_task_counter: u32, // A strictly incrementing counter of issued syncer tasks
     pub trade_state_machines: Vec<TradeStateMachine>, // New trade state machines are inserted on creation and destroyed upon state machine end transitions
     syncer_state_machines: HashMap<TaskId, SyncerStateMachine>, // New syncer state machines are inserted by their syncer task id when sending a syncer request and destroyed upon matching syncer request receival
+    syncer_alerts: VecDeque<SyncerAlert>, // The most recent broadcasts withheld by the swaps on stale syncer data
+    pub watchtower: Option<Watchtower>, // Set if this node serves as a watchtower, holds the swaps watched for the clients
+    pub watchtower_client: WatchtowerClient, // The remote watchtowers our swaps are registered with
+    pub supervisor: Supervisor, // Restarts of the crashed swapd and syncerd, and the crashed swaps being relaunched
+    pub bans: PeerBans, // The banned remote nodes, restored from databased on startup, and the ban scores of misbehaving ones
+    pub gossip: DealGossip, // The deals announced by remote makers and relayed by our peers
+    pub quotes: DealQuotes, // Our deal templates and the quote requests sent to remote makers
+    pub market_maker: Option<MarketMaker>, // Set if the market maker is enabled, holds the deals it keeps open
+    pub price_oracle: Option<PriceOracle>, // Set if a price oracle is configured, holds the latest reference price
+    pub swap_volume: SwapVolume, // The swaps started over the last 24 hours, checked against the volume limits
+    pub maintenance: bool, // Set in maintenance mode, no new swap starts while the running ones drain
+    pub shutdown: Option<Shutdown>, // Set while a shutdown checkpoints the swaps and stops the services
 }
 
 impl CtlServer for Runtime {}
@@ -146,6 +201,53 @@ impl esb::Handler<ServiceBus> for Runtime {
             (ServiceBus::Info, BusMsg::Info(req)) => self.handle_info(endpoints, source, req),
             // Syncer event bus for blockchain tasks and events, only accept Sync message
             (ServiceBus::Sync, BusMsg::Sync(req)) => self.handle_sync(endpoints, source, req),
+            // Supervisor bridge reporting exited child processes
+            (ServiceBus::Bridge, BusMsg::Bridge(BridgeMsg::ServiceExited { service_id, code })) => {
+                self.handle_service_exited(endpoints, service_id, code)
+            }
+            // Timer bridge reporting expired approvals of takers and expired deals
+            (
+                ServiceBus::Bridge,
+                BusMsg::Bridge(
+                    req @ (BridgeMsg::ApprovalExpired { .. } | BridgeMsg::DealExpired { .. }),
+                ),
+            ) => self.process_request_with_state_machines(
+                BusMsg::Bridge(req),
+                ServiceId::Farcasterd,
+                endpoints,
+            ),
+            // Timer bridge reporting unanswered quote requests
+            (ServiceBus::Bridge, BusMsg::Bridge(BridgeMsg::QuoteTimeout { request_id })) => {
+                self.handle_quote_timeout(endpoints, request_id)
+            }
+            // Price oracle thread reporting the reference price
+            (ServiceBus::Bridge, BusMsg::Bridge(BridgeMsg::ReferencePrice { price })) => {
+                self.handle_reference_price(endpoints, price)
+            }
+            // Timer bridge reporting the services not confirming a stage of the shutdown
+            (
+                ServiceBus::Bridge,
+                BusMsg::Bridge(BridgeMsg::ShutdownTimeout { shutdown_id, stage }),
+            ) => self.handle_shutdown_timeout(endpoints, shutdown_id, stage),
+            // Timer bridge refreshing the deal gossip
+            (ServiceBus::Bridge, BusMsg::Bridge(BridgeMsg::DealGossipRefresh)) => {
+                self.handle_deal_gossip_refresh(endpoints)
+            }
+            // Deal registry client threads answering the clients
+            (
+                ServiceBus::Bridge,
+                BusMsg::Bridge(BridgeMsg::Info {
+                    request,
+                    service_id,
+                }),
+            ) => self.send_client_info(endpoints, service_id, request),
+            (
+                ServiceBus::Bridge,
+                BusMsg::Bridge(BridgeMsg::Ctl {
+                    request,
+                    service_id,
+                }),
+            ) => self.send_client_ctl(endpoints, service_id, request),
             // All other pairs are not supported
             (_, request) => Err(Error::NotSupported(bus, request.to_string())),
         }
@@ -204,6 +306,34 @@ impl Runtime {
         source: ServiceId,
         request: PeerMsg,
     ) -> Result<(), Error> {
+        if self.bans.is_banned_connection(&source) {
+            debug!("Ignoring {} from banned peer {}", request, source);
+            return Ok(());
+        }
+        match request {
+            PeerMsg::WatchtowerRequest(signed_request) => {
+                return self.handle_watchtower_request(endpoints, source, signed_request);
+            }
+            PeerMsg::DealAnnouncement(announcement) => {
+                return self.handle_deal_announcement(endpoints, source, announcement);
+            }
+            PeerMsg::DealRevocation(revocation) => {
+                return self.handle_deal_revocation(endpoints, source, revocation);
+            }
+            PeerMsg::QuoteRequest(request) => {
+                return self.handle_quote_request(endpoints, source, request);
+            }
+            PeerMsg::Quote(quote) => {
+                return self.handle_quote(endpoints, source, quote);
+            }
+            PeerMsg::QuoteRejected(rejection) => {
+                return self.handle_quote_rejected(endpoints, source, rejection);
+            }
+            PeerMsg::Abort(abort) => {
+                return self.handle_peer_abort(endpoints, source, abort);
+            }
+            _ => {}
+        }
         debug!(
             "{} received {} from peer - processing with trade state machine",
             self.identity, request
@@ -218,6 +348,14 @@ impl Runtime {
         request: CtlMsg,
     ) -> Result<(), Error> {
         match request {
+            CtlMsg::Hello if self.bans.is_banned_connection(&source) => {
+                warn!(
+                    "Connection {} is from a banned peer, terminating it",
+                    source.bright_blue_italic()
+                );
+                self.handle_failed_connection(endpoints, source)?;
+            }
+
             CtlMsg::Hello => {
                 // Ignoring; this is used to set remote identity at ZMQ level
                 info!(
@@ -241,7 +379,33 @@ impl Runtime {
                             ServiceId::Database,
                             BusMsg::Ctl(CtlMsg::CleanDanglingDeals),
                         )?;
+                        endpoints.send_to(
+                            ServiceBus::Ctl,
+                            self.identity(),
+                            ServiceId::Database,
+                            BusMsg::Ctl(CtlMsg::RetrievePeerBans),
+                        )?;
+                        endpoints.send_to(
+                            ServiceBus::Ctl,
+                            self.identity(),
+                            ServiceId::Database,
+                            BusMsg::Ctl(CtlMsg::RetrieveStartedSwaps),
+                        )?;
+                        endpoints.send_to(
+                            ServiceBus::Ctl,
+                            self.identity(),
+                            ServiceId::Database,
+                            BusMsg::Ctl(CtlMsg::RetrieveMaintenanceMode),
+                        )?;
+                        endpoints.send_to(
+                            ServiceBus::Ctl,
+                            self.identity(),
+                            ServiceId::Database,
+                            BusMsg::Ctl(CtlMsg::RetrieveWatchtowerRegistrations),
+                        )?;
                         self.handle_auto_restore(endpoints)?;
+                        self.handle_watchtower_start(endpoints)?;
+                        self.handle_market_maker(endpoints)?;
                     }
                     ServiceId::Wallet => {
                         self.registered_services.insert(source.clone());
@@ -253,20 +417,20 @@ impl Runtime {
                             BusMsg::Ctl(CtlMsg::GetKeys(wallet_token)),
                         )?;
                     }
-                    ServiceId::Peer(_, addr) => {
+                    ServiceId::Peer(node_id) => {
                         // If this is a connecting peerd, only process the
                         // connection once ConnectSuccess / ConnectFailure is
                         // received
                         let awaiting_swaps: Vec<_> = self
                             .trade_state_machines
                             .iter()
-                            .filter(|tsm| tsm.awaiting_connect_from() == Some(*addr))
+                            .filter(|tsm| tsm.awaiting_connect_from() == Some(*node_id))
                             .map(|tsm| tsm.swap_id().map_or("…".to_string(), |s| s.to_string()))
                             .collect();
                         if !awaiting_swaps.is_empty() {
                             debug!("Received hello from awaited peerd connection {}, will continue processing once swaps {:?} are connected.", source, awaiting_swaps);
                         } else {
-                            self.handle_new_connection(source.clone());
+                            self.handle_new_connection(endpoints, source.clone());
                         }
                     }
                     ServiceId::Swap(_) => {
@@ -280,6 +444,8 @@ impl Runtime {
                                 source,
                                 self.count_syncers().bright_blue_bold()
                             );
+                            self.handle_watchtower_syncer_up(endpoints, &source)?;
+                            self.handle_syncer_relaunched(endpoints, &source)?;
                         } else {
                             error!(
                                 "Syncer {} was already registered; the service probably was relaunched\\
@@ -332,9 +498,20 @@ impl Runtime {
                 self.node_secret_key = Some(sk);
                 self.node_public_key = Some(pk);
                 self.handle_auto_restore(endpoints)?;
+                self.handle_watchtower_start(endpoints)?;
+                self.handle_market_maker(endpoints)?;
+            }
+
+            CtlMsg::PeerdTerminated(pid)
+                if matches!(source, ServiceId::Peer(..)) && !self.owns_connection(&source, pid) =>
+            {
+                debug!(
+                    "Ignoring the termination of peerd process {} not owning {} anymore",
+                    pid, source
+                );
             }
 
-            CtlMsg::PeerdTerminated if matches!(source, ServiceId::Peer(..)) => {
+            CtlMsg::PeerdTerminated(_) if matches!(source, ServiceId::Peer(..)) => {
                 self.handle_failed_connection(endpoints, source.clone())?;
 
                 // log a message if a swap running over this connection
@@ -344,6 +521,127 @@ impl Runtime {
                 }
             }
 
+            CtlMsg::StaleSyncerData(stale_syncer_data) if matches!(source, ServiceId::Swap(..)) => {
+                warn!(
+                    "{} | {}",
+                    source.bright_blue_italic(),
+                    stale_syncer_data.err()
+                );
+                self.stats.incr_withheld_broadcast();
+                if let ServiceId::Swap(swap_id) = source {
+                    self.raise_syncer_alert(swap_id, stale_syncer_data);
+                }
+            }
+
+            CtlMsg::WatchtowerRegister(watchtower_swap)
+                if matches!(source, ServiceId::Swap(..)) =>
+            {
+                self.register_with_watchtowers(endpoints, watchtower_swap)?;
+            }
+
+            CtlMsg::RestoreWatchedSwap(watched_swap) if source == ServiceId::Database => {
+                self.watch_swap(endpoints, watched_swap)?;
+            }
+
+            CtlMsg::RestoreWatchtowerRegistration(watchtower_swap)
+                if source == ServiceId::Database =>
+            {
+                self.watchtower_client.restore_registration(watchtower_swap);
+            }
+
+            CtlMsg::RegisterWithWatchtowers(swap_id) => {
+                self.handle_register_with_watchtowers(endpoints, source, swap_id)?;
+            }
+
+            CtlMsg::RevokeFromWatchtowers(swap_id) => {
+                self.handle_revoke_from_watchtowers(endpoints, source, swap_id)?;
+            }
+
+            CtlMsg::RestorePeerBan(ban) if source == ServiceId::Database => {
+                self.restore_peer_ban(ban);
+            }
+
+            CtlMsg::RestoreStartedSwap(swap) if source == ServiceId::Database => {
+                self.handle_restore_started_swap(endpoints, swap)?;
+            }
+
+            CtlMsg::RestoreMaintenanceMode(enable) if source == ServiceId::Database => {
+                self.set_maintenance(endpoints, enable)?;
+            }
+
+            CtlMsg::BanPeer(node_id) => {
+                self.handle_ban_peer(endpoints, source, node_id)?;
+            }
+
+            CtlMsg::UnbanPeer(node_id) => {
+                self.handle_unban_peer(endpoints, source, node_id)?;
+            }
+
+            CtlMsg::PeerMisbehaved(misbehavior) if matches!(source, ServiceId::Peer(..)) => {
+                self.handle_peer_misbehavior(endpoints, &source, misbehavior)?;
+            }
+
+            CtlMsg::ConnectSuccess if self.watchtower_client.awaits_connection(&source) => {
+                self.handle_watchtower_connection(endpoints, source, true)?;
+            }
+
+            CtlMsg::ConnectFailed if self.watchtower_client.awaits_connection(&source) => {
+                self.handle_watchtower_connection(endpoints, source, false)?;
+            }
+
+            // The watchtower did not negotiate the watchtower feature, peerd dropped the request
+            CtlMsg::Failure(failure) if self.watchtower_client.is_watchtower(&source) => {
+                error!(
+                    "Watchtower {} cannot watch our swaps: {}",
+                    source.bright_blue_italic(),
+                    failure.info
+                );
+            }
+
+            CtlMsg::ConnectSuccess if self.quotes.awaits_connection(&source) => {
+                self.handle_quote_connection(endpoints, source, true)?;
+            }
+
+            CtlMsg::ConnectFailed if self.quotes.awaits_connection(&source) => {
+                self.handle_quote_connection(endpoints, source, false)?;
+            }
+
+            CtlMsg::AddDealTemplate(template) => {
+                self.handle_add_deal_template(endpoints, source, template)?;
+            }
+
+            CtlMsg::RemoveDealTemplate(template_id) => {
+                self.handle_remove_deal_template(endpoints, source, template_id)?;
+            }
+
+            CtlMsg::RequestQuote(ProtoQuoteRequest { maker, request }) => {
+                self.handle_request_quote(endpoints, source, maker, request)?;
+            }
+
+            CtlMsg::SetMaintenance(enable) => {
+                self.handle_set_maintenance(endpoints, source, enable)?;
+            }
+
+            CtlMsg::Shutdown(force) => {
+                self.handle_shutdown(endpoints, source, force)?;
+            }
+
+            CtlMsg::ShutdownReady => {
+                if let ServiceId::Swap(swap_id) = source {
+                    self.handle_shutdown_reply(endpoints, swap_id, None)?;
+                }
+            }
+
+            CtlMsg::ShutdownRefused(reason) => {
+                if let ServiceId::Swap(swap_id) = source {
+                    self.handle_shutdown_reply(endpoints, swap_id, Some(reason))?;
+                }
+            }
+
+            CtlMsg::Terminated => {
+                self.handle_service_stopped(endpoints, source)?;
+            }
+
             // Notify all swapds in case of disconnect
             req @ (CtlMsg::Disconnected | CtlMsg::Reconnected) => {
                 for swap_id in self
@@ -360,6 +658,13 @@ impl Runtime {
                 }
             }
 
+            // databased has no checkpoint for the crashed swap we requested
+            CtlMsg::Failure(_)
+                if source == ServiceId::Database && self.supervisor.awaits_checkpoint_entry() =>
+            {
+                self.handle_missing_crashed_swap_checkpoint(endpoints)?;
+            }
+
             // Add progress in queues and forward to subscribed clients
             event @ (CtlMsg::Progress(..) | CtlMsg::Success(..) | CtlMsg::Failure(..)) => {
                 if !self.progress.contains_key(&source) {
@@ -431,6 +736,9 @@ impl Runtime {
                             .filter_map(|tsm| tsm.open_deal())
                             .collect(),
                         stats: self.stats.clone(),
+                        swap_limits: self.swap_limits_usage(),
+                        maintenance: self.maintenance_status(),
+                        syncer_alerts: self.syncer_alerts.iter().cloned().collect(),
                     }),
                 )?;
             }
@@ -462,12 +770,15 @@ impl Runtime {
                         let open_deals = self
                             .trade_state_machines
                             .iter()
-                            .filter_map(|tsm| tsm.open_deal())
-                            .map(|deal| DealInfo {
+                            // the deals hidden in maintenance mode are not listed
+                            .filter(|tsm| !self.maintenance || tsm.announced_deal().is_none())
+                            .filter_map(|tsm| tsm.open_deal().map(|deal| (deal, tsm.deal_expiry())))
+                            .map(|(deal, expiry)| DealInfo {
                                 serialized_deal: deal.to_string(),
                                 deal,
                                 status: DealStatus::Open,
                                 local_trade_role: TradeRole::Maker,
+                                expiry,
                             })
                             .collect();
                         self.send_client_info(endpoints, source, InfoMsg::DealList(open_deals))?;
@@ -482,6 +793,7 @@ impl Runtime {
                                 deal,
                                 status: DealStatus::InProgress,
                                 local_trade_role: trade_role,
+                                expiry: None,
                             })
                             .collect();
                         self.send_client_info(endpoints, source, InfoMsg::DealList(pub_deals))?;
@@ -504,6 +816,56 @@ impl Runtime {
                 self.send_client_info(endpoints, source, InfoMsg::ListenList(listen_url))?;
             }
 
+            InfoMsg::ListRemoteDeals => {
+                self.send_client_info(
+                    endpoints,
+                    source,
+                    InfoMsg::RemoteDealList(self.gossip.list().into()),
+                )?;
+            }
+
+            InfoMsg::QueryDealRegistry(query) => {
+                self.handle_query_deal_registry(endpoints, source, query)?;
+            }
+
+            InfoMsg::ListDealTemplates => {
+                self.handle_list_deal_templates(endpoints, source)?;
+            }
+
+            InfoMsg::GetMarketMaker => {
+                self.handle_get_market_maker(endpoints, source)?;
+            }
+
+            InfoMsg::GetReferencePrice => {
+                self.handle_get_reference_price(endpoints, source)?;
+            }
+
+            InfoMsg::ListApprovals => {
+                self.send_client_info(
+                    endpoints,
+                    source,
+                    InfoMsg::ApprovalList(
+                        self.trade_state_machines
+                            .iter()
+                            .flat_map(|tsm| tsm.pending_approvals())
+                            .collect(),
+                    ),
+                )?;
+            }
+
+            InfoMsg::ClaimConnection(node_id, pid) => {
+                let claimed = self.claim_connection(node_id, pid);
+                self.send_client_info(endpoints, source, InfoMsg::ConnectionClaim(claimed))?;
+            }
+
+            InfoMsg::ListBans => {
+                self.send_client_info(
+                    endpoints,
+                    source,
+                    InfoMsg::PeerBanList(self.bans.list().into()),
+                )?;
+            }
+
             // Returns a unique response that contains the complete progress queue
             InfoMsg::ReadProgress(swap_id) => {
                 if let Some(queue) = self.progress.get_mut(&ServiceId::Swap(swap_id)) {
@@ -539,21 +901,26 @@ impl Runtime {
                     }
                     report_to.push((Some(source), InfoMsg::SwapProgress(swap_progress)));
                 } else {
-                    let info = if self.running_swaps_contain(&swap_id) {
-                        s!("No progress made yet on this swap")
+                    let (code, info) = if self.running_swaps_contain(&swap_id) {
+                        (
+                            FailureCode::NotFound,
+                            s!("No progress made yet on this swap"),
+                        )
                     } else {
-                        s!("Unknown swapd")
+                        (FailureCode::UnknownSwap, s!("Unknown swapd"))
                     };
-                    report_to.push((
-                        Some(source),
-                        InfoMsg::Failure(Failure {
-                            code: FailureCode::Unknown,
-                            info,
-                        }),
-                    ));
+                    report_to.push((Some(source), InfoMsg::Failure(Failure { code, info })));
                 }
             }
 
+            // Checkpoint entry requested to relaunch a crashed swapd
+            InfoMsg::CheckpointEntry(entry)
+                if source == ServiceId::Database
+                    && self.supervisor.awaits_checkpoint_entry_for(&entry.swap_id) =>
+            {
+                self.handle_crashed_swap_checkpoint(endpoints, entry)?;
+            }
+
             // From client: Request a list of checkpoints available for restore.
             // From internal: Trigger restore on a list of checkpoints.
             //
@@ -623,7 +990,7 @@ impl Runtime {
                     report_to.push((
                         Some(source),
                         InfoMsg::Failure(Failure {
-                            code: FailureCode::Unknown,
+                            code: FailureCode::UnknownSwap,
                             info: "Unknown swapd".to_string(),
                         }),
                     ));
@@ -689,6 +1056,15 @@ impl Runtime {
         source: ServiceId,
         request: SyncMsg,
     ) -> Result<(), Error> {
+        if let SyncMsg::Event(event) = &request {
+            if self
+                .watchtower
+                .as_ref()
+                .map_or(false, |watchtower| watchtower.handles_event(event))
+            {
+                return self.handle_watchtower_event(endpoints, event.clone());
+            }
+        }
         self.process_request_with_state_machines(BusMsg::Sync(request), source, endpoints)
     }
 
@@ -737,11 +1113,10 @@ impl Runtime {
         }
     }
 
-    pub fn handle_new_connection(&mut self, connection: ServiceId) {
-        if let Some(node_addr) = connection.node_addr() {
-            self.spawning_services
-                .remove(&ServiceId::dummy_peer_service_id(node_addr));
-        }
+    pub fn handle_new_connection(&mut self, endpoints: &mut Endpoints, connection: ServiceId) {
+        // Only a single connection exists per remote node, all swaps with that node are
+        // multiplexed over it
+        self.spawning_services.remove(&connection);
         if self.registered_services.insert(connection.clone()) {
             info!(
                 "Connection {} is registered; total {} connections are known",
@@ -754,6 +1129,7 @@ impl Runtime {
                 connection.bright_blue_italic()
             );
         }
+        self.sync_deal_gossip(endpoints, &connection);
     }
 
     pub fn handle_failed_connection(
@@ -765,11 +1141,11 @@ impl Runtime {
             "Connection {} failed. Removing it from our connection pool and terminating.",
             connection
         );
-        if let Some(node_addr) = connection.node_addr() {
-            self.spawning_services
-                .remove(&ServiceId::dummy_peer_service_id(node_addr));
-        }
+        self.spawning_services.remove(&connection);
         self.registered_services.remove(&connection);
+        if let Some(node_id) = connection.node_id() {
+            self.peer_processes.remove(&node_id);
+        }
         endpoints.send_to(
             ServiceBus::Ctl,
             self.identity(),
@@ -784,18 +1160,27 @@ impl Runtime {
         swap_id: &SwapId,
         endpoints: &mut Endpoints,
     ) -> Result<(), Error> {
-        endpoints.send_to(
+        // swapd is already gone if it crashed
+        if let Err(err) = endpoints.send_to(
             ServiceBus::Ctl,
             self.identity(),
             ServiceId::Swap(*swap_id),
             BusMsg::Ctl(CtlMsg::Terminate),
-        )?;
+        ) {
+            debug!("{} | Failed to terminate swapd: {}", swap_id, err);
+        }
         endpoints.send_to(
             ServiceBus::Ctl,
             self.identity(),
             ServiceId::Database,
             BusMsg::Ctl(CtlMsg::RemoveCheckpoint(*swap_id)),
         )?;
+        if let Err(err) = self.revoke_from_watchtowers(endpoints, *swap_id) {
+            warn!(
+                "{} | Failed to revoke the swap from the watchtowers: {}",
+                swap_id, err
+            );
+        }
 
         self.registered_services = self
             .registered_services
@@ -803,7 +1188,9 @@ impl Runtime {
             .drain()
             .filter(|service| {
                 if let ServiceId::Peer(..) = service {
-                    if !self.connection_has_swap_client(service) {
+                    if !self.connection_has_swap_client(service)
+                        && !self.is_watchtower_connection(service)
+                    {
                         info!("{} | Terminating {} for swap cleanup", swap_id, service);
                         endpoints
                             .send_to(
@@ -890,6 +1277,10 @@ impl Runtime {
             .values()
             .filter_map(|ssm| ssm.syncer())
             .any(|client_syncer| client_syncer == *syncerd)
+            || self
+                .watchtower
+                .as_ref()
+                .map_or(false, |watchtower| watchtower.uses_syncer(syncerd))
     }
 
     fn count_syncers(&self) -> usize {
@@ -899,6 +1290,28 @@ impl Runtime {
             .count()
     }
 
+    fn is_watchtower_connection(&self, peerd: &ServiceId) -> bool {
+        self.watchtower_client.is_watchtower(peerd)
+            || self
+                .watchtower
+                .as_ref()
+                .map_or(false, |watchtower| watchtower.is_client(peerd))
+    }
+
+    /// Keeps the alert in the node info, the oldest alerts are dropped past the limit
+    fn raise_syncer_alert(&mut self, swap_id: SwapId, stale_syncer_data: StaleSyncerData) {
+        if self.syncer_alerts.len() >= MAX_SYNCER_ALERTS {
+            self.syncer_alerts.pop_front();
+        }
+        self.syncer_alerts.push_back(SyncerAlert {
+            swap_id,
+            blockchain: stale_syncer_data.blockchain,
+            tx_label: stale_syncer_data.tx_label,
+            reason: stale_syncer_data.reason,
+            since: unix_timestamp(),
+        });
+    }
+
     fn connection_has_swap_client(&self, peerd: &ServiceId) -> bool {
         self.trade_state_machines
             .iter()
@@ -906,6 +1319,43 @@ impl Runtime {
             .any(|client_connection| client_connection == *peerd)
     }
 
+    /// The registered connection to the given remote node, if any
+    pub fn peer_connection(&self, node_id: &NodeId) -> Option<ServiceId> {
+        let service = ServiceId::Peer(*node_id);
+        if self.registered_services.contains(&service) {
+            Some(service)
+        } else {
+            None
+        }
+    }
+
+    /// Claim the connection to a remote node for a listener-forked peerd process. The claim is
+    /// refused while the connection is registered or spawning and its owning process is alive,
+    /// so that a single peerd routes the messages of a remote node.
+    pub fn claim_connection(&mut self, node_id: NodeId, pid: u32) -> bool {
+        let connection = ServiceId::Peer(node_id);
+        if self.registered_services.contains(&connection)
+            || self.spawning_services.contains(&connection)
+        {
+            match self.peer_processes.get(&node_id) {
+                Some(owner) if *owner == pid || !is_process_alive(*owner) => {}
+                _ => return false,
+            }
+        }
+        self.peer_processes.insert(node_id, pid);
+        true
+    }
+
+    /// Whether the peerd process owns the connection, failures reported by a replaced process
+    /// are ignored
+    fn owns_connection(&self, connection: &ServiceId, pid: u32) -> bool {
+        connection.node_id().map_or(true, |node_id| {
+            self.peer_processes
+                .get(&node_id)
+                .map_or(true, |owner| *owner == pid)
+        })
+    }
+
     pub fn count_connections(&self) -> usize {
         self.registered_services
             .iter()
@@ -913,10 +1363,10 @@ impl Runtime {
             .count()
     }
 
-    fn get_open_connections(&self) -> Vec<NodeAddr> {
+    fn get_open_connections(&self) -> Vec<NodeId> {
         self.registered_services
             .iter()
-            .filter_map(|s| s.node_addr())
+            .filter_map(|s| s.node_id())
             .collect()
     }
 
@@ -977,7 +1427,8 @@ impl Runtime {
             (BusMsg::Ctl(CtlMsg::MakeDeal(..)), _) => Ok(Some(TradeStateMachine::StartMaker)),
             (BusMsg::Ctl(CtlMsg::TakeDeal(..)), _) => Ok(Some(TradeStateMachine::StartTaker)),
             (BusMsg::P2p(PeerMsg::TakerCommit(TakerCommit { deal, .. })), _)
-            | (BusMsg::Ctl(CtlMsg::RevokeDeal(deal)), _) => Ok(self
+            | (BusMsg::Ctl(CtlMsg::RevokeDeal(deal)), _)
+            | (BusMsg::Bridge(BridgeMsg::DealExpired { deal }), _) => Ok(self
                 .trade_state_machines
                 .iter()
                 .position(|tsm| {
@@ -988,6 +1439,13 @@ impl Runtime {
                     }
                 })
                 .map(|pos| self.trade_state_machines.remove(pos))),
+            (BusMsg::Ctl(CtlMsg::AcceptTaker(swap_id)), _)
+            | (BusMsg::Ctl(CtlMsg::RejectTaker(swap_id)), _)
+            | (BusMsg::Bridge(BridgeMsg::ApprovalExpired { swap_id }), _) => Ok(self
+                .trade_state_machines
+                .iter()
+                .position(|tsm| tsm.awaits_approval(swap_id))
+                .map(|pos| self.trade_state_machines.remove(pos))),
             (BusMsg::Ctl(CtlMsg::SwapKeys(SwapKeys { deal, .. })), _) => Ok(self
                 .trade_state_machines
                 .iter()
@@ -999,13 +1457,13 @@ impl Runtime {
                     }
                 })
                 .map(|pos| self.trade_state_machines.remove(pos))),
-            (BusMsg::Ctl(CtlMsg::ConnectSuccess), ServiceId::Peer(_, addr))
-            | (BusMsg::Ctl(CtlMsg::ConnectFailed), ServiceId::Peer(_, addr)) => Ok(self
+            (BusMsg::Ctl(CtlMsg::ConnectSuccess), ServiceId::Peer(node_id))
+            | (BusMsg::Ctl(CtlMsg::ConnectFailed), ServiceId::Peer(node_id)) => Ok(self
                 .trade_state_machines
                 .iter()
                 .position(|tsm| {
-                    if let Some(tsm_addr) = tsm.awaiting_connect_from() {
-                        tsm_addr == *addr
+                    if let Some(tsm_node_id) = tsm.awaiting_connect_from() {
+                        tsm_node_id == *node_id
                     } else {
                         false
                     }
@@ -1031,7 +1489,7 @@ impl Runtime {
         }
     }
 
-    fn process_request_with_state_machines(
+    pub fn process_request_with_state_machines(
         &mut self,
         request: BusMsg,
         source: ServiceId,
@@ -1043,7 +1501,8 @@ impl Runtime {
             {
                 self.trade_state_machines.push(new_tsm);
             }
-            Ok(())
+            // the processed request may have taken, revoked or expired a market maker deal
+            self.handle_market_maker(endpoints)
         } else if let Some(ssm) = self.match_request_to_syncer_state_machine(&request, &source)? {
             if let Some(new_ssm) =
                 SyncerStateMachineExecutor::execute(self, endpoints, source, request, ssm)?
@@ -1063,19 +1522,34 @@ impl Runtime {
                         self.identity(),
                         source,
                         BusMsg::Ctl(CtlMsg::Failure(Failure {
-                            code: FailureCode::Unknown,
+                            code: FailureCode::UnknownDeal,
                             info: "Deal to revoke not found.".to_string(),
                         })),
                     )?;
                     Ok(())
                 }
+                BusMsg::Ctl(CtlMsg::AcceptTaker(swap_id) | CtlMsg::RejectTaker(swap_id)) => {
+                    self.send_client_ctl(
+                        endpoints,
+                        source,
+                        CtlMsg::Failure(Failure {
+                            code: FailureCode::NotFound,
+                            info: format!("No taker awaiting approval for swap {}", swap_id),
+                        }),
+                    )?;
+                    Ok(())
+                }
+                // The taker was accepted or rejected before the approval expired
+                BusMsg::Bridge(BridgeMsg::ApprovalExpired { .. }) => Ok(()),
+                // The deal was taken or revoked before it expired
+                BusMsg::Bridge(BridgeMsg::DealExpired { .. }) => Ok(()),
                 BusMsg::Ctl(CtlMsg::Connect(..)) => {
                     endpoints.send_to(
                         ServiceBus::Ctl,
                         self.identity(),
                         source,
                         BusMsg::Ctl(CtlMsg::Failure(Failure {
-                            code: FailureCode::Unknown,
+                            code: FailureCode::UnknownSwap,
                             info: "Swap to connect not found.".to_string(),
                         })),
                     )?;
@@ -1087,6 +1561,9 @@ impl Runtime {
                         commit.swap_id(),
                         deal.id(),
                     );
+                    if !self.deals.contains(&deal) {
+                        self.handle_peer_misbehavior(endpoints, &source, Misbehavior::UnknownDeal)?;
+                    }
                     endpoints.send_to(
                         ServiceBus::Msg,
                         self.identity(),
@@ -1135,6 +1612,12 @@ impl Runtime {
                 &format!("{}", peer_secret_key.display_secret()),
                 "--token",
                 &self.wallet_token.clone().to_string(),
+                "--max-inbound-connections",
+                &self.config.max_inbound_connections().to_string(),
+                "--max-inbound-connections-per-ip",
+                &self.config.max_inbound_connections_per_ip().to_string(),
+                "--max-handshakes-per-minute",
+                &self.config.max_handshakes_per_minute().to_string(),
             ],
         );
 
@@ -1159,32 +1642,27 @@ impl Runtime {
 
     pub fn connect_peer(&mut self, node_addr: &NodeAddr) -> Result<(bool, ServiceId), Error> {
         self.services_ready()?;
+        if self.bans.is_banned(&node_addr.id) {
+            return Err(Error::Farcaster(format!(
+                "Remote peer {} is banned",
+                node_addr.id
+            )));
+        }
         let (peer_secret_key, _) = self.peer_keys_ready()?;
-        if let Some(spawning_peer) = self.spawning_services.iter().find(|service| {
-            if let Some(registered_node_addr) = service.node_addr() {
-                registered_node_addr.id == node_addr.id
-            } else {
-                false
-            }
-        }) {
+        let peer_service = ServiceId::Peer(node_addr.id);
+        if let Some(spawning_peer) = self.spawning_services.get(&peer_service) {
             warn!(
                 "Already spawning a connection with remote peer {}, through a spawned connection {}, but have not received Connect from it yet.",
                 node_addr.id, spawning_peer
             );
             return Ok((false, spawning_peer.clone()));
         };
-        if let Some(existing_peer) = self.registered_services.iter().find(|service| {
-            if let Some(registered_node_addr) = service.node_addr() {
-                registered_node_addr.id == node_addr.id
-            } else {
-                false
-            }
-        }) {
+        if let Some(existing_peer) = self.peer_connection(&node_addr.id) {
             debug!(
                 "Already connected to remote peer {} through a spawned connection {}",
                 node_addr.id, existing_peer
             );
-            return Ok((true, existing_peer.clone()));
+            return Ok((true, existing_peer));
         }
 
         debug!("{} to remote peer {}", "Connecting", node_addr);
@@ -1210,12 +1688,23 @@ impl Runtime {
         }
 
         debug!("New instance of peerd launched with PID {}", child.id());
+        self.peer_processes.insert(node_addr.id, child.id());
 
-        self.spawning_services
-            .insert(ServiceId::dummy_peer_service_id(*node_addr));
+        self.spawning_services.insert(peer_service.clone());
         debug!("Awaiting for peerd to connect...");
 
-        Ok((false, ServiceId::dummy_peer_service_id(*node_addr)))
+        Ok((false, peer_service))
+    }
+
+    /// Add a progress event emitted by farcasterd on behalf of a swap in the swap's queue and
+    /// forward it to the subscribed clients
+    pub fn report_swap_progress(
+        &mut self,
+        endpoints: &mut Endpoints,
+        swap_id: SwapId,
+        event: CtlMsg,
+    ) -> Result<(), Error> {
+        self.handle_ctl(endpoints, ServiceId::Swap(swap_id), event)
     }
 
     /// Notify(forward to) the subscribed clients still online with the given request
@@ -1262,7 +1751,8 @@ pub fn syncer_up(
         ];
         args.append(&mut syncer_servers_args(config, blockchain, network)?);
         debug!("launching syncer with: {:?}", args);
-        launch("syncerd", args)?;
+        let child = launch("syncerd", args)?;
+        supervise(syncer_service.clone(), child);
         spawning_services.insert(syncer_service.clone());
     }
     if registered_services.contains(&syncer_service) {
@@ -1288,8 +1778,20 @@ pub fn launch_swapd(
             swap_config.arbitrating.finality.to_string(),
             "--arb-safety".to_string(),
             swap_config.arbitrating.safety.to_string(),
+            "--arb-max-data-age".to_string(),
+            swap_config.arbitrating.max_syncer_data_age().to_string(),
             "--acc-finality".to_string(),
             swap_config.accordant.finality.to_string(),
+            "--acc-max-data-age".to_string(),
+            swap_config.accordant.max_syncer_data_age().to_string(),
+            "--commit-timeout".to_string(),
+            swap_config.timeouts.commit.to_string(),
+            "--reveal-timeout".to_string(),
+            swap_config.timeouts.reveal.to_string(),
+            "--funding-timeout".to_string(),
+            swap_config.timeouts.funding.to_string(),
+            "--lock-timeout".to_string(),
+            swap_config.timeouts.lock.to_string(),
             "--id".to_string(),
             swap_id.to_string(),
             "--deal".to_string(),
@@ -1299,6 +1801,7 @@ pub fn launch_swapd(
         ],
     )?;
     debug!("New instance of swapd launched with PID {}", child.id());
+    supervise(ServiceId::Swap(swap_id), child);
     debug!("Awaiting for swapd to connect...");
     Ok(())
 }
@@ -1340,6 +1843,27 @@ fn syncer_servers_args(
     }
 }
 
+/// Whether the process is still running, a child of farcasterd that exited is reaped
+#[cfg(feature = "server")]
+fn is_process_alive(pid: u32) -> bool {
+    use nix::sys::signal::kill;
+    use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+    use nix::unistd::Pid;
+
+    let pid = Pid::from_raw(pid as i32);
+    match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
+        Ok(WaitStatus::StillAlive) => true,
+        Ok(_) => false,
+        // Not a child of farcasterd, e.g. forked by a peerd listener
+        Err(_) => kill(pid, None).is_ok(),
+    }
+}
+
+#[cfg(not(feature = "server"))]
+fn is_process_alive(_pid: u32) -> bool {
+    true
+}
+
 pub fn launch(
     name: &str,
     args: impl IntoIterator<Item = impl AsRef<OsStr>>,
@@ -0,0 +1,265 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use farcaster_core::swap::SwapId;
+use farcaster_core::Uuid;
+use microservices::esb::Handler;
+
+use crate::bus::bridge::BridgeMsg;
+use crate::bus::ctl::CtlMsg;
+use crate::bus::{BusMsg, Failure, FailureCode, OptionDetails, ServiceBus};
+use crate::farcasterd::runtime::Runtime;
+use crate::farcasterd::supervisor::report_over_bridge;
+use crate::service::Endpoints;
+use crate::{CtlServer, Error, LogStyle, ServiceId};
+
+/// Time the running swapds have to checkpoint and confirm before the shutdown is given up, or
+/// carried on if forced
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time the services of a stage have to unregister before the next stage is stopped anyway
+const SHUTDOWN_STAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The services are stopped in dependency order, a stage once the previous one unregistered
+const SHUTDOWN_STAGES: [fn(&ServiceId) -> bool; 6] = [
+    |service| matches!(service, ServiceId::Peer(..)),
+    |service| matches!(service, ServiceId::Swap(..)),
+    |service| matches!(service, ServiceId::Syncer(..)),
+    |service| *service == ServiceId::Grpcd,
+    |service| *service == ServiceId::Wallet,
+    |service| *service == ServiceId::Database,
+];
+
+/// A shutdown awaiting the confirmation of the running swapds, then stopping the services
+pub struct Shutdown {
+    // Tags the timeouts of this shutdown, the ones of a previous shutdown are stale
+    id: Uuid,
+    client: ServiceId,
+    force: bool,
+    // The maintenance mode before the shutdown, restored if the shutdown is refused
+    maintenance: bool,
+    pending: HashSet<SwapId>,
+    refused: Vec<String>,
+    // 0 while the swaps checkpoint, then the number of the stage being stopped
+    stage: u8,
+    // The services of the stage being stopped not unregistered yet
+    stopping: HashSet<ServiceId>,
+}
+
+impl Runtime {
+    /// Ask the running swapds to checkpoint, the services are stopped once all confirmed. No new
+    /// swap starts meanwhile.
+    pub fn handle_shutdown(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        force: bool,
+    ) -> Result<(), Error> {
+        if self.shutdown.is_some() {
+            return self.send_client_ctl(
+                endpoints,
+                source,
+                CtlMsg::Failure(Failure {
+                    code: FailureCode::InvalidState,
+                    info: "A shutdown is already in progress".to_string(),
+                }),
+            );
+        }
+        let pending: HashSet<SwapId> = self
+            .trade_state_machines
+            .iter()
+            .filter_map(|tsm| tsm.swap_id())
+            .filter(|swap_id| {
+                self.registered_services
+                    .contains(&ServiceId::Swap(*swap_id))
+            })
+            .collect();
+        info!(
+            "{}, awaiting the checkpoint of {} swaps",
+            "Shutting down".bright_yellow_bold(),
+            pending.len()
+        );
+        for swap_id in pending.iter() {
+            endpoints.send_to(
+                ServiceBus::Ctl,
+                self.identity(),
+                ServiceId::Swap(*swap_id),
+                BusMsg::Ctl(CtlMsg::PrepareShutdown(force)),
+            )?;
+        }
+        let id = Uuid::new();
+        self.shutdown = Some(Shutdown {
+            id,
+            client: source,
+            force,
+            maintenance: self.maintenance,
+            pending,
+            refused: vec![],
+            stage: 0,
+            stopping: none!(),
+        });
+        self.maintenance = true;
+        start_shutdown_timeout(id, 0, SHUTDOWN_TIMEOUT);
+        self.proceed_shutdown(endpoints)
+    }
+
+    /// Handle the reply of a swapd to the shutdown, None once ready or the reason of its refusal
+    pub fn handle_shutdown_reply(
+        &mut self,
+        endpoints: &mut Endpoints,
+        swap_id: SwapId,
+        refusal: Option<String>,
+    ) -> Result<(), Error> {
+        if let Some(shutdown) = self.shutdown.as_mut() {
+            if shutdown.pending.remove(&swap_id) {
+                if let Some(refusal) = refusal {
+                    shutdown.refused.push(refusal);
+                }
+            }
+        }
+        self.proceed_shutdown(endpoints)
+    }
+
+    /// Give up the shutdown if swapds did not confirm in time, unless forced, or stop the next
+    /// stage if the services of the current one did not unregister in time. The timeouts of a
+    /// previous shutdown or stage are ignored.
+    pub fn handle_shutdown_timeout(
+        &mut self,
+        endpoints: &mut Endpoints,
+        shutdown_id: Uuid,
+        stage: u8,
+    ) -> Result<(), Error> {
+        let shutdown = match self.shutdown.as_mut() {
+            Some(shutdown) if shutdown.id == shutdown_id && shutdown.stage == stage => shutdown,
+            _ => {
+                debug!(
+                    "Ignoring stale shutdown timeout {} stage {}",
+                    shutdown_id, stage
+                );
+                return Ok(());
+            }
+        };
+        if stage == 0 {
+            for swap_id in shutdown.pending.drain() {
+                warn!("{} | swapd did not confirm the shutdown", swap_id.swap_id());
+                if !shutdown.force {
+                    shutdown
+                        .refused
+                        .push(format!("swap {} did not checkpoint in time", swap_id));
+                }
+            }
+            return self.proceed_shutdown(endpoints);
+        }
+        for service in shutdown.stopping.drain() {
+            warn!("{} did not stop in time", service.bright_blue_italic());
+            self.registered_services.remove(&service);
+        }
+        self.stop_next_stage(endpoints)
+    }
+
+    /// Handle a service unregistering on shutdown, the next stage is stopped once all the
+    /// services of the current one unregistered
+    pub fn handle_service_stopped(
+        &mut self,
+        endpoints: &mut Endpoints,
+        service: ServiceId,
+    ) -> Result<(), Error> {
+        if !self.shutting_down() {
+            debug!("{} terminated", service);
+            return Ok(());
+        }
+        self.registered_services.remove(&service);
+        let stage_stopped = self.shutdown.as_mut().map_or(false, |shutdown| {
+            shutdown.stopping.remove(&service) && shutdown.stopping.is_empty()
+        });
+        if stage_stopped {
+            self.stop_next_stage(endpoints)?;
+        }
+        Ok(())
+    }
+
+    fn proceed_shutdown(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        match &self.shutdown {
+            Some(shutdown) if shutdown.pending.is_empty() && shutdown.stage == 0 => {}
+            _ => return Ok(()),
+        }
+        let shutdown = self.shutdown.take().expect("checked above");
+        if !shutdown.refused.is_empty() {
+            warn!("Shutdown refused: {}", shutdown.refused.join(", "));
+            self.maintenance = shutdown.maintenance;
+            return self.send_client_ctl(
+                endpoints,
+                shutdown.client,
+                CtlMsg::Failure(Failure {
+                    code: FailureCode::InvalidState,
+                    info: format!(
+                        "Shutdown refused, {}. Use --force to shut down anyway",
+                        shutdown.refused.join(", ")
+                    ),
+                }),
+            );
+        }
+        self.send_client_ctl(
+            endpoints,
+            shutdown.client.clone(),
+            CtlMsg::Success(OptionDetails::with("Shutting down".to_string())),
+        )?;
+        self.shutdown = Some(shutdown);
+        self.stop_next_stage(endpoints)
+    }
+
+    /// Terminate the services of the next stage having registered services, and exit once all
+    /// stages are stopped
+    fn stop_next_stage(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        let shutdown = match self.shutdown.as_mut() {
+            Some(shutdown) => shutdown,
+            None => return Ok(()),
+        };
+        while (shutdown.stage as usize) < SHUTDOWN_STAGES.len() {
+            let is_stage = SHUTDOWN_STAGES[shutdown.stage as usize];
+            shutdown.stage += 1;
+            shutdown.stopping = self
+                .registered_services
+                .iter()
+                .filter(|service| is_stage(service))
+                .cloned()
+                .collect();
+            if shutdown.stopping.is_empty() {
+                continue;
+            }
+            for service in shutdown.stopping.iter() {
+                info!("Terminating {}", service.bright_blue_italic());
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    ServiceId::Farcasterd,
+                    service.clone(),
+                    BusMsg::Ctl(CtlMsg::Terminate),
+                )?;
+            }
+            start_shutdown_timeout(shutdown.id, shutdown.stage, SHUTDOWN_STAGE_TIMEOUT);
+            return Ok(());
+        }
+        info!("{}", "Farcasterd stopped".bright_green_bold());
+        std::process::exit(0);
+    }
+
+    /// Whether the services are being terminated, their exit is not a crash
+    pub fn shutting_down(&self) -> bool {
+        self.shutdown
+            .as_ref()
+            .map_or(false, |shutdown| shutdown.stage > 0)
+    }
+}
+
+fn start_shutdown_timeout(shutdown_id: Uuid, stage: u8, timeout: Duration) {
+    std::thread::spawn(move || {
+        std::thread::sleep(timeout);
+        report_over_bridge(BridgeMsg::ShutdownTimeout { shutdown_id, stage });
+    });
+}
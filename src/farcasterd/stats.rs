@@ -31,6 +31,8 @@ pub struct Stats {
     funded_btc: u64,
     funding_canceled_xmr: u64,
     funding_canceled_btc: u64,
+    withheld_broadcasts: u64,
+    service_restarts: u64,
 }
 
 impl Stats {
@@ -39,7 +41,7 @@ impl Stats {
             Outcome::SuccessSwap => self.success += 1,
             Outcome::FailureRefund => self.refund += 1,
             Outcome::FailurePunish => self.punish += 1,
-            Outcome::FailureAbort => self.abort += 1,
+            Outcome::FailureAbort | Outcome::FailureTimeout => self.abort += 1,
         };
     }
 
@@ -103,6 +105,14 @@ impl Stats {
         }
     }
 
+    pub fn incr_withheld_broadcast(&mut self) {
+        self.withheld_broadcasts += 1;
+    }
+
+    pub fn incr_service_restart(&mut self) {
+        self.service_restarts += 1;
+    }
+
     pub fn success_rate(&self) -> f64 {
         let Stats {
             success,
@@ -116,11 +126,13 @@ impl Stats {
             funded_xmr,
             funding_canceled_xmr,
             funding_canceled_btc,
+            withheld_broadcasts,
+            service_restarts,
         } = self;
         let total = success + refund + punish + abort;
         let rate = *success as f64 / (total as f64);
         info!(
-            "Swapped({}) | Refunded({}) / Punished({}) | Aborted({}) | Initialized({}) / AwaitingFundingXMR({}) / AwaitingFundingBTC({}) / FundedXMR({}) / FundedBTC({}) / FundingCanceledXMR({}) / FundingCanceledBTC({})",
+            "Swapped({}) | Refunded({}) / Punished({}) | Aborted({}) | Initialized({}) / AwaitingFundingXMR({}) / AwaitingFundingBTC({}) / FundedXMR({}) / FundedBTC({}) / FundingCanceledXMR({}) / FundingCanceledBTC({}) | WithheldBroadcasts({}) | ServiceRestarts({})",
             success.label(),
             refund.label(),
             punish.label(),
@@ -132,6 +144,8 @@ impl Stats {
             funded_btc.label(),
             funding_canceled_xmr.label(),
             funding_canceled_btc.label(),
+            withheld_broadcasts.label(),
+            service_restarts.label(),
         );
         info!(
             "{} = {:>4.3}%",
@@ -0,0 +1,437 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::process;
+use std::time::{Duration, SystemTime};
+
+use farcaster_core::blockchain::{Blockchain, Network};
+use farcaster_core::role::TradeRole;
+use farcaster_core::swap::btcxmr::Deal;
+use farcaster_core::swap::SwapId;
+use internet2::session::LocalSession;
+use internet2::{zeromq::ZmqSocketType, SendRecvMessage, TypedEnum};
+use microservices::esb::Handler;
+use microservices::ZMQ_CONTEXT;
+
+use crate::bus::bridge::BridgeMsg;
+use crate::bus::ctl::CtlMsg;
+use crate::bus::info::InfoMsg;
+use crate::bus::{BusMsg, CheckpointEntry, DealInfo, DealStatus, Failure, FailureCode};
+use crate::bus::{Outcome, Progress, ServiceBus};
+use crate::farcasterd::runtime::{syncer_up, Runtime};
+use crate::service::Endpoints;
+use crate::{Error, LogStyle, ServiceId};
+
+/// The inproc socket over which the supervising and timer threads report to the runtime
+pub const SUPERVISOR_BRIDGE: &str = "inproc://farcasterdbridge";
+
+/// Restarts of a service are capped within this sliding window
+const RESTART_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Wait on a launched child process in a dedicated thread and report its exit to the runtime
+/// over the supervisor bridge.
+pub fn supervise(service_id: ServiceId, mut child: process::Child) {
+    std::thread::spawn(move || {
+        let code = match child.wait() {
+            Ok(status) => status.code(),
+            Err(err) => {
+                error!("Failed to wait on {}: {}", service_id, err);
+                return;
+            }
+        };
+        report_over_bridge(BridgeMsg::ServiceExited { service_id, code });
+    });
+}
+
+/// Send a message from a thread other than the runtime's to the runtime over the supervisor
+/// bridge.
+pub fn report_over_bridge(request: BridgeMsg) {
+    let tx = ZMQ_CONTEXT
+        .socket(zmq::PUSH)
+        .expect("Panic while creating a zmq socket");
+    tx.connect(SUPERVISOR_BRIDGE)
+        .expect("Panic while connecting to bridge socket");
+    let mut session = LocalSession::with_zmq_socket(ZmqSocketType::Push, tx);
+    let address: Vec<u8> = ServiceId::Farcasterd.into();
+    let request = BusMsg::Bridge(request);
+    if let Err(err) =
+        session.send_routed_message(&address, &address, &address, &request.serialize())
+    {
+        error!("Failed to report {} to the runtime: {}", request, err);
+    }
+}
+
+/// A swap whose swapd crashed, waiting for its checkpoint entry to be relaunched.
+struct PendingRestore {
+    swap_id: SwapId,
+    deal: Deal,
+    trade_role: TradeRole,
+}
+
+/// Keeps track of the restarts of the supervised services and of the crashed swaps being
+/// relaunched.
+#[derive(Default)]
+pub struct Supervisor {
+    restarts: HashMap<ServiceId, VecDeque<SystemTime>>,
+    // Checkpoint entries are requested from databased one swap at a time, the front entry is
+    // the one awaiting a reply
+    pending_restores: VecDeque<PendingRestore>,
+    relaunched_syncers: HashSet<ServiceId>,
+}
+
+impl Supervisor {
+    /// Record a restart of the service, returns the attempt number or None if the service
+    /// already reached the maximum number of restarts within the window
+    fn register_restart(&mut self, service_id: &ServiceId, max_restarts: u32) -> Option<usize> {
+        let now = SystemTime::now();
+        let restarts = self.restarts.entry(service_id.clone()).or_default();
+        while restarts.front().map_or(false, |restart| {
+            now.duration_since(*restart).unwrap_or_default() > RESTART_WINDOW
+        }) {
+            restarts.pop_front();
+        }
+        if restarts.len() >= max_restarts as usize {
+            None
+        } else {
+            restarts.push_back(now);
+            Some(restarts.len())
+        }
+    }
+
+    /// Returns if a checkpoint entry was requested from databased for a crashed swap
+    pub fn awaits_checkpoint_entry(&self) -> bool {
+        !self.pending_restores.is_empty()
+    }
+
+    /// Returns if the checkpoint entry is the one requested for a crashed swap
+    pub fn awaits_checkpoint_entry_for(&self, swap_id: &SwapId) -> bool {
+        self.pending_restores
+            .front()
+            .map_or(false, |pending| pending.swap_id == *swap_id)
+    }
+}
+
+impl Runtime {
+    /// Handle the exit of a supervised child process. Swapds and syncers exit cleanly once
+    /// terminated by farcasterd, an exit with a non-zero code or by a signal is a crash.
+    pub fn handle_service_exited(
+        &mut self,
+        endpoints: &mut Endpoints,
+        service_id: ServiceId,
+        code: Option<i32>,
+    ) -> Result<(), Error> {
+        if self.shutting_down() {
+            debug!("{} exited on shutdown with code {:?}", service_id, code);
+            return self.handle_service_stopped(endpoints, service_id);
+        }
+        // The code is None if the process was killed by a signal
+        if code == Some(0) {
+            debug!("{} exited cleanly", service_id);
+            return Ok(());
+        }
+        match service_id {
+            ServiceId::Swap(swap_id) => self.handle_swapd_exited(endpoints, swap_id, code),
+            ServiceId::Syncer(blockchain, network) => {
+                self.handle_syncerd_exited(endpoints, blockchain, network, code)
+            }
+            _ => {
+                debug!("{} exited with code {:?}", service_id, code);
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_swapd_exited(
+        &mut self,
+        endpoints: &mut Endpoints,
+        swap_id: SwapId,
+        code: Option<i32>,
+    ) -> Result<(), Error> {
+        let service_id = ServiceId::Swap(swap_id);
+        let position = match self
+            .trade_state_machines
+            .iter()
+            .position(|tsm| tsm.swap_id() == Some(swap_id))
+        {
+            Some(position) => position,
+            None => {
+                debug!(
+                    "{} | {} exited with code {:?}",
+                    swap_id.swap_id(),
+                    service_id,
+                    code
+                );
+                return Ok(());
+            }
+        };
+        let tsm = self.trade_state_machines.remove(position);
+        let (deal, trade_role) = match (tsm.deal(), tsm.trade_role()) {
+            (Some(deal), Some(trade_role)) => (deal.clone(), trade_role),
+            _ => {
+                error!(
+                    "{} | {} crashed in state {}, cannot relaunch it",
+                    swap_id.swap_id(),
+                    service_id,
+                    tsm
+                );
+                return Ok(());
+            }
+        };
+        error!(
+            "{} | {} {} with code {:?}",
+            swap_id.swap_id(),
+            service_id.bright_blue_italic(),
+            "crashed".err(),
+            code
+        );
+
+        let max_restarts = self.config.max_service_restarts();
+        match self.supervisor.register_restart(&service_id, max_restarts) {
+            Some(attempt) => {
+                self.stats.incr_service_restart();
+                self.report_swap_progress(
+                    endpoints,
+                    swap_id,
+                    CtlMsg::Progress(Progress::Message(format!(
+                        "swapd crashed, relaunching it from its latest checkpoint (attempt {}/{})",
+                        attempt, max_restarts
+                    ))),
+                )?;
+                self.supervisor.pending_restores.push_back(PendingRestore {
+                    swap_id,
+                    deal,
+                    trade_role,
+                });
+                if self.supervisor.pending_restores.len() == 1 {
+                    self.request_checkpoint_entry(endpoints)?;
+                }
+            }
+            None => {
+                error!(
+                    "{} | {} crashed {} times within an hour, giving up on it",
+                    swap_id.swap_id(),
+                    service_id,
+                    max_restarts
+                );
+                self.report_swap_progress(
+                    endpoints,
+                    swap_id,
+                    CtlMsg::Failure(Failure {
+                        code: FailureCode::Unavailable,
+                        info: format!(
+                            "swapd crashed {} times within an hour and is no longer relaunched, \
+                             restore the checkpoint manually once the issue is fixed",
+                            max_restarts
+                        ),
+                    }),
+                )?;
+                self.clean_up_after_syncer_usage(endpoints)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn request_checkpoint_entry(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        if let Some(pending) = self.supervisor.pending_restores.front() {
+            endpoints.send_to(
+                ServiceBus::Info,
+                self.identity(),
+                ServiceId::Database,
+                BusMsg::Info(InfoMsg::GetCheckpointEntry(pending.swap_id)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Relaunch the crashed swap from the checkpoint entry returned by databased
+    pub fn handle_crashed_swap_checkpoint(
+        &mut self,
+        endpoints: &mut Endpoints,
+        entry: CheckpointEntry,
+    ) -> Result<(), Error> {
+        self.supervisor.pending_restores.pop_front();
+        info!(
+            "{} | Relaunching swapd from its latest checkpoint",
+            entry.swap_id.swap_id()
+        );
+        self.process_request_with_state_machines(
+            BusMsg::Ctl(CtlMsg::RestoreCheckpoint(entry)),
+            ServiceId::Database,
+            endpoints,
+        )?;
+        self.request_checkpoint_entry(endpoints)
+    }
+
+    /// The crashed swap did not checkpoint yet, hence no funds are locked: abort it
+    pub fn handle_missing_crashed_swap_checkpoint(
+        &mut self,
+        endpoints: &mut Endpoints,
+    ) -> Result<(), Error> {
+        if let Some(PendingRestore {
+            swap_id,
+            deal,
+            trade_role,
+        }) = self.supervisor.pending_restores.pop_front()
+        {
+            warn!(
+                "{} | No checkpoint to relaunch the crashed swapd from, aborting the swap",
+                swap_id.swap_id()
+            );
+            endpoints.send_to(
+                ServiceBus::Ctl,
+                self.identity(),
+                ServiceId::Database,
+                BusMsg::Ctl(CtlMsg::SetDealInfo(DealInfo {
+                    serialized_deal: deal.to_string(),
+                    deal,
+                    status: DealStatus::Ended(Outcome::FailureAbort),
+                    local_trade_role: trade_role,
+                    expiry: None,
+                })),
+            )?;
+            self.report_swap_progress(
+                endpoints,
+                swap_id,
+                CtlMsg::Failure(Failure {
+                    code: FailureCode::Unavailable,
+                    info: "swapd crashed before checkpointing the swap, the swap is aborted"
+                        .to_string(),
+                }),
+            )?;
+            self.clean_up_after_swap(&swap_id, endpoints)?;
+            self.stats.incr_outcome(&Outcome::FailureAbort);
+        }
+        self.request_checkpoint_entry(endpoints)
+    }
+
+    fn handle_syncerd_exited(
+        &mut self,
+        endpoints: &mut Endpoints,
+        blockchain: Blockchain,
+        network: Network,
+        code: Option<i32>,
+    ) -> Result<(), Error> {
+        let service_id = ServiceId::Syncer(blockchain, network);
+        // farcasterd removes the syncers it terminates from the registered services
+        let registered = self.registered_services.remove(&service_id);
+        let spawning = self.spawning_services.remove(&service_id);
+        if !registered && !spawning {
+            debug!("{} exited with code {:?}", service_id, code);
+            return Ok(());
+        }
+        error!(
+            "{} {} with code {:?}",
+            service_id.bright_blue_italic(),
+            "crashed".err(),
+            code
+        );
+        if !self.syncer_has_client(&service_id) {
+            info!("{} has no client left, not relaunching it", service_id);
+            return Ok(());
+        }
+
+        let clients: Vec<SwapId> = self
+            .trade_state_machines
+            .iter()
+            .filter(|tsm| tsm.syncers().contains(&service_id))
+            .filter_map(|tsm| tsm.swap_id())
+            .collect();
+        let max_restarts = self.config.max_service_restarts();
+        match self.supervisor.register_restart(&service_id, max_restarts) {
+            Some(attempt) => {
+                info!(
+                    "Relaunching {} (attempt {}/{})",
+                    service_id.bright_blue_italic(),
+                    attempt,
+                    max_restarts
+                );
+                self.stats.incr_service_restart();
+                self.supervisor
+                    .relaunched_syncers
+                    .insert(service_id.clone());
+                if let Some(watchtower) = self.watchtower.as_mut() {
+                    watchtower.requeue_syncer_tasks(&service_id);
+                }
+                syncer_up(
+                    &mut self.spawning_services,
+                    &mut self.registered_services,
+                    blockchain,
+                    network,
+                    &self.config,
+                )?;
+                for swap_id in clients {
+                    self.report_swap_progress(
+                        endpoints,
+                        swap_id,
+                        CtlMsg::Progress(Progress::Message(format!(
+                            "{} syncer crashed, relaunching it (attempt {}/{})",
+                            blockchain, attempt, max_restarts
+                        ))),
+                    )?;
+                }
+            }
+            None => {
+                error!(
+                    "{} crashed {} times within an hour, giving up on it",
+                    service_id, max_restarts
+                );
+                for swap_id in clients {
+                    self.report_swap_progress(
+                        endpoints,
+                        swap_id,
+                        CtlMsg::Failure(Failure {
+                            code: FailureCode::SyncerUnhealthy,
+                            info: format!(
+                                "{} syncer crashed {} times within an hour and is no longer \
+                                 relaunched",
+                                blockchain, max_restarts
+                            ),
+                        }),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Once a relaunched syncer is up, let the swapds using it re-register their tasks
+    pub fn handle_syncer_relaunched(
+        &mut self,
+        endpoints: &mut Endpoints,
+        syncer: &ServiceId,
+    ) -> Result<(), Error> {
+        let blockchain = match syncer {
+            ServiceId::Syncer(blockchain, _)
+                if self.supervisor.relaunched_syncers.remove(syncer) =>
+            {
+                *blockchain
+            }
+            _ => return Ok(()),
+        };
+        let swap_ids: Vec<SwapId> = self
+            .trade_state_machines
+            .iter()
+            .filter(|tsm| tsm.syncers().contains(syncer))
+            .filter_map(|tsm| tsm.swap_id())
+            .collect();
+        for swap_id in swap_ids {
+            if let Err(err) = endpoints.send_to(
+                ServiceBus::Ctl,
+                self.identity(),
+                ServiceId::Swap(swap_id),
+                BusMsg::Ctl(CtlMsg::SyncerRestarted(blockchain)),
+            ) {
+                warn!(
+                    "{} | Failed to notify swapd of the relaunched syncer: {}",
+                    swap_id.swap_id(),
+                    err
+                );
+            }
+        }
+        Ok(())
+    }
+}
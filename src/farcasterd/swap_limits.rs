@@ -0,0 +1,494 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::VecDeque;
+
+use farcaster_core::blockchain::Network;
+use farcaster_core::swap::btcxmr::DealParameters;
+use microservices::esb::Handler;
+
+use crate::bus::ctl::CtlMsg;
+use crate::bus::info::{SwapLimitsUsage, VolumeUsage};
+use crate::bus::{unix_timestamp, BusMsg, ServiceBus, StartedSwap};
+use crate::config::Config;
+use crate::farcasterd::runtime::Runtime;
+use crate::service::Endpoints;
+use crate::{Error, ServiceId};
+
+/// Window of the daily volume, in seconds
+const DAILY_VOLUME_WINDOW: u64 = 24 * 60 * 60;
+
+/// The swaps started over the last 24 hours, persisted by databased and restored on startup
+#[derive(Default)]
+pub struct SwapVolume {
+    swaps: VecDeque<StartedSwap>,
+}
+
+impl SwapVolume {
+    /// Forget the swaps started before the window, they are returned to be removed from the
+    /// database
+    fn prune(&mut self) -> Vec<StartedSwap> {
+        let now = unix_timestamp();
+        let mut expired = vec![];
+        while self
+            .swaps
+            .front()
+            .map_or(false, |swap| swap.since + DAILY_VOLUME_WINDOW <= now)
+        {
+            expired.extend(self.swaps.pop_front());
+        }
+        expired
+    }
+
+    /// Insert a swap restored from the database, keeping the swaps ordered by start time, the
+    /// swaps that left the window are returned to be removed from the database
+    fn restore(&mut self, swap: StartedSwap) -> Vec<StartedSwap> {
+        if !self.swaps.iter().any(|known| known.deal_id == swap.deal_id) {
+            let index = self
+                .swaps
+                .iter()
+                .position(|known| known.since > swap.since)
+                .unwrap_or(self.swaps.len());
+            self.swaps.insert(index, swap);
+        }
+        self.prune()
+    }
+
+    /// Bitcoin and monero swapped on the network over the last 24 hours
+    fn daily(&self, network: Network) -> (bitcoin::Amount, monero::Amount) {
+        let now = unix_timestamp();
+        self.swaps
+            .iter()
+            .filter(|swap| swap.network == network && swap.since + DAILY_VOLUME_WINDOW > now)
+            .fold(
+                (bitcoin::Amount::from_sat(0), monero::Amount::from_pico(0)),
+                |(btc, xmr), swap| (btc + swap.btc, xmr + swap.xmr),
+            )
+    }
+}
+
+impl Runtime {
+    /// Count a new swap of the deal in the daily volume and persist it, the swaps that left the
+    /// window are removed from the database
+    pub fn record_swap_volume(
+        &mut self,
+        endpoints: &mut Endpoints,
+        deal: &DealParameters,
+    ) -> Result<(), Error> {
+        for swap in self.swap_volume.prune() {
+            self.send_to_database(endpoints, CtlMsg::RemoveStartedSwap(swap.deal_id))?;
+        }
+        let swap = StartedSwap {
+            deal_id: deal.uuid,
+            since: unix_timestamp(),
+            network: deal.network,
+            btc: deal.arbitrating_amount,
+            xmr: deal.accordant_amount,
+        };
+        self.swap_volume.swaps.push_back(swap.clone());
+        self.send_to_database(endpoints, CtlMsg::SetStartedSwap(swap))
+    }
+
+    /// Restore a swap of the daily volume persisted before a restart, removing it from the
+    /// database if it already left the window
+    pub fn handle_restore_started_swap(
+        &mut self,
+        endpoints: &mut Endpoints,
+        swap: StartedSwap,
+    ) -> Result<(), Error> {
+        for swap in self.swap_volume.restore(swap) {
+            self.send_to_database(endpoints, CtlMsg::RemoveStartedSwap(swap.deal_id))?;
+        }
+        Ok(())
+    }
+
+    fn send_to_database(&self, endpoints: &mut Endpoints, msg: CtlMsg) -> Result<(), Error> {
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(msg),
+        )?;
+        Ok(())
+    }
+
+    /// Check a new swap of the deal stays within the swap limits, given the swaps already running
+    pub fn check_swap_limits(&self, deal: &DealParameters) -> Result<(), Error> {
+        let running_swaps: Vec<_> = self.running_swaps().collect();
+        check_swap_limits(&self.config, &running_swaps, &self.swap_volume, deal)
+    }
+
+    pub fn swap_limits_usage(&self) -> SwapLimitsUsage {
+        SwapLimitsUsage {
+            max_concurrent_swaps: self.config.max_concurrent_swaps(),
+            running_swaps: self.running_swaps().count() as u32,
+            networks: [Network::Mainnet, Network::Testnet, Network::Local]
+                .iter()
+                .map(|network| self.volume_usage(*network))
+                .filter(|usage| {
+                    self.config.volume_limits(usage.network).is_some()
+                        || usage.btc_daily_volume.as_sat() > 0
+                        || usage.btc_in_flight.as_sat() > 0
+                })
+                .collect(),
+        }
+    }
+
+    // The parameters of the deals taken or consumed by a taker, their swap is about to start or
+    // is running
+    fn running_swaps(&self) -> impl Iterator<Item = DealParameters> + '_ {
+        self.trade_state_machines
+            .iter()
+            .filter_map(|tsm| tsm.consumed_deal())
+            .map(|(deal, _)| deal.parameters)
+    }
+
+    fn volume_usage(&self, network: Network) -> VolumeUsage {
+        let running_swaps: Vec<_> = self.running_swaps().collect();
+        volume_usage(&self.config, &running_swaps, &self.swap_volume, network)
+    }
+}
+
+/// Bitcoin and monero of the running swaps and of the swaps started over the last 24 hours on
+/// the network, with their limits
+fn volume_usage(
+    config: &Config,
+    running_swaps: &[DealParameters],
+    swap_volume: &SwapVolume,
+    network: Network,
+) -> VolumeUsage {
+    let (btc_in_flight, xmr_in_flight) = running_swaps
+        .iter()
+        .filter(|deal| deal.network == network)
+        .fold(
+            (bitcoin::Amount::from_sat(0), monero::Amount::from_pico(0)),
+            |(btc, xmr), deal| (btc + deal.arbitrating_amount, xmr + deal.accordant_amount),
+        );
+    let (btc_daily_volume, xmr_daily_volume) = swap_volume.daily(network);
+    let limits = config.volume_limits(network);
+    VolumeUsage {
+        network,
+        btc_in_flight,
+        max_btc_in_flight: limits.as_ref().and_then(|l| l.max_btc_in_flight),
+        xmr_in_flight,
+        max_xmr_in_flight: limits.as_ref().and_then(|l| l.max_xmr_in_flight),
+        btc_daily_volume,
+        max_btc_daily_volume: limits.as_ref().and_then(|l| l.max_btc_daily_volume),
+        xmr_daily_volume,
+        max_xmr_daily_volume: limits.as_ref().and_then(|l| l.max_xmr_daily_volume),
+    }
+}
+
+/// Check a new swap of the deal stays within the swap limits, given the swaps already running
+/// and the swaps started over the last 24 hours
+fn check_swap_limits(
+    config: &Config,
+    running_swaps: &[DealParameters],
+    swap_volume: &SwapVolume,
+    deal: &DealParameters,
+) -> Result<(), Error> {
+    if let Some(max_concurrent_swaps) = config.max_concurrent_swaps() {
+        if running_swaps.len() >= max_concurrent_swaps as usize {
+            return Err(Error::SwapLimit(format!(
+                "{} swaps already running, the maximum is {}",
+                running_swaps.len(),
+                max_concurrent_swaps
+            )));
+        }
+    }
+    let limits = match config.volume_limits(deal.network) {
+        Some(limits) => limits,
+        None => return Ok(()),
+    };
+    let usage = volume_usage(config, running_swaps, swap_volume, deal.network);
+    check_limit(
+        "bitcoin in flight",
+        usage.btc_in_flight + deal.arbitrating_amount,
+        limits.max_btc_in_flight,
+    )?;
+    check_limit(
+        "monero in flight",
+        usage.xmr_in_flight + deal.accordant_amount,
+        limits.max_xmr_in_flight,
+    )?;
+    check_limit(
+        "bitcoin daily volume",
+        usage.btc_daily_volume + deal.arbitrating_amount,
+        limits.max_btc_daily_volume,
+    )?;
+    check_limit(
+        "monero daily volume",
+        usage.xmr_daily_volume + deal.accordant_amount,
+        limits.max_xmr_daily_volume,
+    )
+}
+
+fn check_limit<T>(name: &str, amount: T, max: Option<T>) -> Result<(), Error>
+where
+    T: PartialOrd + std::fmt::Display,
+{
+    match max {
+        Some(max) if amount > max => Err(Error::SwapLimit(format!(
+            "{} would reach {}, the maximum is {}",
+            name, amount, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FarcasterdConfig, Networked, SwapLimitsConfig, VolumeLimits};
+    use farcaster_core::bitcoin::fee::SatPerKvB;
+    use farcaster_core::bitcoin::timelock::CSVTimelock;
+    use farcaster_core::blockchain::{Blockchain, FeeStrategy};
+    use farcaster_core::role::SwapRole;
+    use farcaster_core::Uuid;
+
+    const BTC: u64 = 100_000_000;
+    const XMR: u64 = 1_000_000_000_000;
+
+    fn deal(network: Network, btc: u64, xmr: u64) -> DealParameters {
+        DealParameters {
+            uuid: Uuid::new().into(),
+            network,
+            arbitrating_blockchain: Blockchain::Bitcoin,
+            accordant_blockchain: Blockchain::Monero,
+            arbitrating_amount: bitcoin::Amount::from_sat(btc),
+            accordant_amount: monero::Amount::from_pico(xmr),
+            cancel_timelock: CSVTimelock::new(10),
+            punish_timelock: CSVTimelock::new(20),
+            fee_strategy: FeeStrategy::Fixed(SatPerKvB::from_sat(1_000)),
+            maker_role: SwapRole::Bob,
+        }
+    }
+
+    // A swap of the deal started the given number of seconds ago
+    fn started_swap(deal: &DealParameters, ago: u64) -> StartedSwap {
+        StartedSwap {
+            deal_id: deal.uuid,
+            since: unix_timestamp() - ago,
+            network: deal.network,
+            btc: deal.arbitrating_amount,
+            xmr: deal.accordant_amount,
+        }
+    }
+
+    fn config(max_concurrent_swaps: Option<u32>, testnet: Option<VolumeLimits>) -> Config {
+        Config {
+            farcasterd: Some(FarcasterdConfig {
+                swap_limits: Some(SwapLimitsConfig {
+                    max_concurrent_swaps,
+                    networks: Networked {
+                        mainnet: None,
+                        testnet,
+                        local: None,
+                    },
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn limits() -> VolumeLimits {
+        VolumeLimits {
+            max_btc_in_flight: None,
+            max_xmr_in_flight: None,
+            max_btc_daily_volume: None,
+            max_xmr_daily_volume: None,
+        }
+    }
+
+    fn rejection(res: Result<(), Error>) -> String {
+        match res {
+            Err(Error::SwapLimit(reason)) => reason,
+            res => panic!("expected a swap limit rejection, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn concurrent_swaps() {
+        let config = config(Some(2), None);
+        let volume = SwapVolume::default();
+        let new_deal = deal(Network::Testnet, BTC, XMR);
+        let mut running = vec![deal(Network::Local, BTC, XMR)];
+        assert!(check_swap_limits(&config, &running, &volume, &new_deal).is_ok());
+        // the limit applies across networks
+        running.push(deal(Network::Mainnet, BTC, XMR));
+        assert_eq!(
+            rejection(check_swap_limits(&config, &running, &volume, &new_deal)),
+            "2 swaps already running, the maximum is 2"
+        );
+        // no limit, no network limits
+        let config = self::config(None, None);
+        assert!(check_swap_limits(&config, &running, &volume, &new_deal).is_ok());
+    }
+
+    #[test]
+    fn in_flight() {
+        let config = config(
+            None,
+            Some(VolumeLimits {
+                max_btc_in_flight: Some(bitcoin::Amount::from_sat(2 * BTC)),
+                max_xmr_in_flight: Some(monero::Amount::from_pico(3 * XMR)),
+                ..limits()
+            }),
+        );
+        let volume = SwapVolume::default();
+        let running = vec![
+            deal(Network::Testnet, BTC, XMR),
+            // swaps on other networks are not counted
+            deal(Network::Local, 10 * BTC, 10 * XMR),
+        ];
+        // exactly at both limits
+        assert!(check_swap_limits(
+            &config,
+            &running,
+            &volume,
+            &deal(Network::Testnet, BTC, 2 * XMR)
+        )
+        .is_ok());
+        assert_eq!(
+            rejection(check_swap_limits(
+                &config,
+                &running,
+                &volume,
+                &deal(Network::Testnet, BTC + 1, XMR)
+            )),
+            "bitcoin in flight would reach 2.00000001 BTC, the maximum is 2.00000000 BTC"
+        );
+        assert!(rejection(check_swap_limits(
+            &config,
+            &running,
+            &volume,
+            &deal(Network::Testnet, BTC, 2 * XMR + 1)
+        ))
+        .starts_with("monero in flight would reach"));
+        // the limits only apply to their network
+        assert!(check_swap_limits(
+            &config,
+            &running,
+            &volume,
+            &deal(Network::Local, 10 * BTC, 10 * XMR)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn daily_volume() {
+        let config = config(
+            None,
+            Some(VolumeLimits {
+                max_btc_daily_volume: Some(bitcoin::Amount::from_sat(3 * BTC)),
+                max_xmr_daily_volume: Some(monero::Amount::from_pico(3 * XMR)),
+                ..limits()
+            }),
+        );
+        let mut volume = SwapVolume::default();
+        volume
+            .swaps
+            .push_back(started_swap(&deal(Network::Testnet, BTC, XMR), 3600));
+        volume
+            .swaps
+            .push_back(started_swap(&deal(Network::Local, 10 * BTC, 10 * XMR), 60));
+        // the running swaps are counted in the daily volume once started, not while in flight
+        let running = vec![deal(Network::Testnet, BTC, XMR)];
+        // exactly at both limits
+        assert!(check_swap_limits(
+            &config,
+            &running,
+            &volume,
+            &deal(Network::Testnet, 2 * BTC, 2 * XMR)
+        )
+        .is_ok());
+        assert_eq!(
+            rejection(check_swap_limits(
+                &config,
+                &running,
+                &volume,
+                &deal(Network::Testnet, 2 * BTC + 1, XMR)
+            )),
+            "bitcoin daily volume would reach 3.00000001 BTC, the maximum is 3.00000000 BTC"
+        );
+        assert!(rejection(check_swap_limits(
+            &config,
+            &running,
+            &volume,
+            &deal(Network::Testnet, BTC, 2 * XMR + 1)
+        ))
+        .starts_with("monero daily volume would reach"));
+    }
+
+    #[test]
+    fn daily_volume_window() {
+        let config = config(
+            None,
+            Some(VolumeLimits {
+                max_btc_daily_volume: Some(bitcoin::Amount::from_sat(2 * BTC)),
+                ..limits()
+            }),
+        );
+        let mut volume = SwapVolume::default();
+        let old_swap = started_swap(&deal(Network::Testnet, BTC, XMR), DAILY_VOLUME_WINDOW);
+        let recent_swap = started_swap(&deal(Network::Testnet, BTC, XMR), DAILY_VOLUME_WINDOW - 60);
+        volume.swaps.push_back(old_swap.clone());
+        volume.swaps.push_back(recent_swap.clone());
+        // the swap started 24 hours ago left the window
+        assert_eq!(
+            volume.daily(Network::Testnet),
+            (
+                bitcoin::Amount::from_sat(BTC),
+                monero::Amount::from_pico(XMR)
+            )
+        );
+        assert!(
+            check_swap_limits(&config, &[], &volume, &deal(Network::Testnet, BTC, XMR)).is_ok()
+        );
+        assert!(
+            check_swap_limits(&config, &[], &volume, &deal(Network::Testnet, BTC + 1, XMR))
+                .is_err()
+        );
+        // and is pruned
+        assert_eq!(volume.prune(), vec![old_swap]);
+        assert_eq!(volume.swaps, vec![recent_swap]);
+    }
+
+    #[test]
+    fn restore_started_swaps() {
+        let config = config(
+            None,
+            Some(VolumeLimits {
+                max_btc_daily_volume: Some(bitcoin::Amount::from_sat(2 * BTC)),
+                ..limits()
+            }),
+        );
+        let mut volume = SwapVolume::default();
+        let first = started_swap(&deal(Network::Testnet, BTC, XMR), 7200);
+        let second = started_swap(&deal(Network::Testnet, BTC, XMR), 3600);
+        let expired = started_swap(&deal(Network::Testnet, BTC, XMR), DAILY_VOLUME_WINDOW + 60);
+        // restored in any order, kept ordered by start time
+        assert!(volume.restore(second.clone()).is_empty());
+        assert!(volume.restore(first.clone()).is_empty());
+        // restoring a known swap does not count it twice
+        assert!(volume.restore(second.clone()).is_empty());
+        assert_eq!(volume.swaps, vec![first.clone(), second.clone()]);
+        // a swap persisted before leaving the window is returned to be removed
+        assert_eq!(volume.restore(expired.clone()), vec![expired]);
+        assert_eq!(volume.swaps, vec![first, second]);
+        // the restored volume counts toward the daily limit
+        assert_eq!(
+            rejection(check_swap_limits(
+                &config,
+                &[],
+                &volume,
+                &deal(Network::Testnet, 1, XMR)
+            )),
+            "bitcoin daily volume would reach 2.00000001 BTC, the maximum is 2.00000000 BTC"
+        );
+    }
+}
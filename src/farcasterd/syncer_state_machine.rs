@@ -359,7 +359,7 @@ fn attempt_transition_to_end(
                 event.send_client_ctl(
                     source,
                     CtlMsg::Failure(Failure {
-                        code: FailureCode::Unknown,
+                        code: FailureCode::SyncerUnhealthy,
                         info: format!("Failed to get adddress balance {}", err),
                     }),
                 )?;
@@ -385,7 +385,7 @@ fn attempt_transition_to_end(
             event.send_client_ctl(
                 source,
                 CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
+                    code: FailureCode::SyncerUnhealthy,
                     info: format!(
                         "Failure in chain query{}",
                         error.map_or("".to_string(), |e| format!(": {}", e))
@@ -4,18 +4,23 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use crate::bus::bridge::BridgeMsg;
 use crate::bus::ctl::{
-    BitcoinFundingInfo, CtlMsg, FundingInfo, InitMakerSwap, InitTakerSwap, MoneroFundingInfo,
-    ProtoDeal, PubDeal, SwapKeys, WrappedKeyManager,
+    BitcoinFundingInfo, CtlMsg, DealApproval, FundingInfo, InitMakerSwap, InitTakerSwap,
+    MoneroFundingInfo, ProtoDeal, PubDeal, SwapKeys, WrappedKeyManager,
 };
-use crate::bus::info::{InfoMsg, MadeDeal, TookDeal, ViewableDeal};
+use crate::bus::info::{InfoMsg, MadeDeal, PendingApproval, TookDeal, ViewableDeal};
 use crate::bus::p2p::{Commit, PeerMsg};
-use crate::bus::{CheckpointEntry, DealInfo, DealStatus, Failure, FailureCode};
+use crate::bus::{
+    unix_timestamp, CheckpointEntry, DealInfo, DealStatus, Failure, FailureCode, OptionDetails,
+    SignedDeal,
+};
 use crate::farcasterd::runtime::{launch_swapd, syncer_up, Runtime};
-use crate::service::{SwapDetails, SwapLogging};
+use crate::farcasterd::supervisor::report_over_bridge;
+use crate::service::{Endpoints, SwapDetails, SwapLogging};
 use crate::LogStyle;
 use crate::{
-    bus::{BusMsg, Outcome},
+    bus::{BusMsg, Outcome, ServiceBus},
     error::Error,
     event::{Event, StateMachine, StateMachineExecutor},
     ServiceId,
@@ -28,6 +33,7 @@ use internet2::addr::{NodeAddr, NodeId};
 use microservices::esb::Handler;
 use std::convert::TryInto;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
 /// State machine for launching a swap and cleaning up once done.
 ///
@@ -124,6 +130,17 @@ pub struct MakeDeal {
     deal: Deal,
     arb_addr: bitcoin::Address,
     acc_addr: monero::Address,
+    approval: DealApproval,
+    // Takers awaiting the approval of the user, only used for deals requiring manual approval
+    pending_approvals: Vec<PendingTakerCommit>,
+    // Unix timestamp at which the deal is automatically revoked, only set for signed deals
+    expiry: Option<u64>,
+}
+
+pub struct PendingTakerCommit {
+    peerd: ServiceId,
+    commit: Commit,
+    since: SystemTime,
 }
 
 pub struct TakerCommit {
@@ -307,6 +324,69 @@ impl TradeStateMachine {
         }
     }
 
+    /// Returns the expiry of the open deal, if it is signed
+    pub fn deal_expiry(&self) -> Option<u64> {
+        if let TradeStateMachine::MakeDeal(MakeDeal { expiry, .. }) = self {
+            *expiry
+        } else {
+            None
+        }
+    }
+
+    /// Returns the open deal if it is announced to the peers, deals restricted to an allowlist
+    /// of takers are not
+    pub fn announced_deal(&self) -> Option<Deal> {
+        match self {
+            TradeStateMachine::MakeDeal(MakeDeal {
+                deal,
+                approval: DealApproval::Auto | DealApproval::Manual,
+                ..
+            }) => Some(deal.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns if the taker of the swap awaits the approval of the user
+    pub fn awaits_approval(&self, swap_id: &SwapId) -> bool {
+        if let TradeStateMachine::MakeDeal(MakeDeal {
+            pending_approvals, ..
+        }) = self
+        {
+            pending_approvals
+                .iter()
+                .any(|pending| pending.commit.swap_id() == *swap_id)
+        } else {
+            false
+        }
+    }
+
+    pub fn pending_approvals(&self) -> Vec<PendingApproval> {
+        if let TradeStateMachine::MakeDeal(MakeDeal {
+            deal,
+            pending_approvals,
+            ..
+        }) = self
+        {
+            pending_approvals
+                .iter()
+                .filter_map(|pending| {
+                    Some(PendingApproval {
+                        swap_id: pending.commit.swap_id(),
+                        deal_id: deal.id(),
+                        taker: pending.peerd.node_id()?,
+                        since: pending
+                            .since
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs(),
+                    })
+                })
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
     pub fn deal(&self) -> Option<&Deal> {
         match self {
             TradeStateMachine::MakeDeal(MakeDeal { deal, .. }) => Some(deal),
@@ -434,10 +514,10 @@ impl TradeStateMachine {
         }
     }
 
-    pub fn awaiting_connect_from(&self) -> Option<NodeAddr> {
+    pub fn awaiting_connect_from(&self) -> Option<NodeId> {
         match self {
             TradeStateMachine::TakerConnect(taker_connect) => {
-                Some(node_addr_from_deal(&taker_connect.deal))
+                Some(NodeId::from(taker_connect.deal.node_id))
             }
             TradeStateMachine::RestoringSwapd(RestoringSwapd {
                 deal,
@@ -446,7 +526,7 @@ impl TradeStateMachine {
                 ..
             }) => {
                 if *trade_role == TradeRole::Taker && *expect_connection {
-                    Some(node_addr_from_deal(deal))
+                    Some(NodeId::from(deal.node_id))
                 } else {
                     None
                 }
@@ -461,7 +541,7 @@ impl TradeStateMachine {
                 // is only possible to a listening peer as a taker, its address
                 // thus has to match the address of the deal.
                 if *trade_role == TradeRole::Taker && peerd.is_none() {
-                    Some(node_addr_from_deal(deal))
+                    Some(NodeId::from(deal.node_id))
                 } else {
                     None
                 }
@@ -511,17 +591,28 @@ fn attempt_transition_to_make_deal(
             arbitrating_addr,
             accordant_addr,
             public_addr,
-            ..
+            approval,
+            lifetime,
         })) => {
-            // validate deal parameters
-            if let Err(e) = runtime.config.validate_deal_parameters(
-                &deal_parameters,
-                &arbitrating_addr,
-                &accordant_addr,
-            ) {
+            // validate deal parameters, our policy as maker and the swap limits
+            if let Err(e) = runtime
+                .check_maintenance()
+                .and_then(|_| {
+                    runtime.config.validate_deal_parameters(
+                        &deal_parameters,
+                        &arbitrating_addr,
+                        &accordant_addr,
+                        None,
+                    )
+                })
+                .and_then(|_| {
+                    runtime.validate_deal_policy(&deal_parameters, deal_parameters.maker_role, None)
+                })
+                .and_then(|_| runtime.check_swap_limits(&deal_parameters))
+            {
                 warn!("Deal parameters validation error: {}", e);
                 event.complete_client_ctl(CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
+                    code: FailureCode::from(&e),
                     info: e.to_string(),
                 }))?;
                 return Ok(None);
@@ -530,7 +621,7 @@ fn attempt_transition_to_make_deal(
             let bind_addr = match runtime.config.get_bind_addr() {
                 Err(err) => {
                     event.complete_ctl(CtlMsg::Failure(Failure {
-                        code: FailureCode::Unknown,
+                        code: FailureCode::from(&err),
                         info: err.to_string(),
                     }))?;
                     return Ok(None);
@@ -544,14 +635,46 @@ fn attempt_transition_to_make_deal(
                         err
                     ));
                     event.complete_client_ctl(CtlMsg::Failure(Failure {
-                        code: FailureCode::Unknown,
+                        code: FailureCode::from(&err),
                         info: err.to_string(),
                     }))?;
                     Ok(None)
                 }
                 Ok(node_id) => {
                     let deal = deal_parameters.to_v1(node_id.public_key(), public_addr);
-                    let msg = s!("Deal registered, please share with taker.");
+                    // Deals with a lifetime are shared signed, so takers can check their
+                    // authenticity and expiry
+                    let signed_deal = match lifetime {
+                        Some(lifetime) => {
+                            let expiry = unix_timestamp() + lifetime;
+                            match runtime
+                                .peer_keys_ready()
+                                .and_then(|(sk, _)| SignedDeal::sign(deal.clone(), expiry, &sk))
+                            {
+                                Ok(signed_deal) => Some(signed_deal),
+                                Err(err) => {
+                                    log_helper.log_warn(format!(
+                                        "Failed to sign deal, cannot make deal: {}",
+                                        err
+                                    ));
+                                    event.complete_client_ctl(CtlMsg::Failure(Failure {
+                                        code: FailureCode::from(&err),
+                                        info: err.to_string(),
+                                    }))?;
+                                    return Ok(None);
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+                    let expiry = signed_deal.as_ref().map(|signed_deal| signed_deal.expiry);
+                    let msg = match expiry {
+                        Some(expiry) => format!(
+                            "Deal registered, please share with taker. Expires at {}.",
+                            expiry
+                        ),
+                        None => s!("Deal registered, please share with taker."),
+                    };
                     log_helper.log_info(format!(
                         "{}: {:#}",
                         "Deal registered.".bright_green_bold(),
@@ -564,20 +687,32 @@ fn attempt_transition_to_make_deal(
                             serialized_deal: deal.to_string(),
                             status: DealStatus::Open,
                             local_trade_role: TradeRole::Maker,
+                            expiry,
                         }),
                     )?;
+                    runtime.deals.insert(deal.clone());
+                    if !matches!(approval, DealApproval::Allowlist(_)) {
+                        runtime.announce_deal(event.endpoints, &deal);
+                    }
                     event.complete_client_info(InfoMsg::MadeDeal(MadeDeal {
                         message: msg,
                         viewable_deal: ViewableDeal {
-                            deal: deal.to_string(),
+                            deal: signed_deal
+                                .as_ref()
+                                .map_or_else(|| deal.to_string(), |signed| signed.to_string()),
                             details: deal.clone(),
                         },
                     }))?;
-                    runtime.deals.insert(deal.clone());
+                    if let Some(lifetime) = lifetime {
+                        schedule_deal_expiry(deal.clone(), Duration::from_secs(lifetime));
+                    }
                     Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
                         deal,
                         arb_addr: arbitrating_addr,
                         acc_addr: accordant_addr,
+                        approval,
+                        pending_approvals: vec![],
+                        expiry,
                     })))
                 }
             }
@@ -592,6 +727,59 @@ fn attempt_transition_to_make_deal(
     }
 }
 
+/// Make a deal on behalf of farcasterd, without a client to report to, e.g. a deal issued from a
+/// deal template or by the market maker. Deals with a lifetime are signed with their expiry, deals
+/// not restricted to an allowlist of takers are announced.
+pub fn make_internal_deal(
+    runtime: &mut Runtime,
+    endpoints: &mut Endpoints,
+    deal: Deal,
+    arb_addr: bitcoin::Address,
+    acc_addr: monero::Address,
+    approval: DealApproval,
+    lifetime: Option<Duration>,
+) -> Result<(TradeStateMachine, Option<SignedDeal>), Error> {
+    let signed_deal = match lifetime {
+        Some(lifetime) => {
+            let (sk, _) = runtime.peer_keys_ready()?;
+            let expiry = unix_timestamp() + lifetime.as_secs();
+            Some(SignedDeal::sign(deal.clone(), expiry, &sk)?)
+        }
+        None => None,
+    };
+    let expiry = signed_deal.as_ref().map(|signed_deal| signed_deal.expiry);
+    endpoints.send_to(
+        ServiceBus::Ctl,
+        runtime.identity(),
+        ServiceId::Database,
+        BusMsg::Ctl(CtlMsg::SetDealInfo(DealInfo {
+            deal: deal.clone(),
+            serialized_deal: deal.to_string(),
+            status: DealStatus::Open,
+            local_trade_role: TradeRole::Maker,
+            expiry,
+        })),
+    )?;
+    runtime.deals.insert(deal.clone());
+    if !matches!(approval, DealApproval::Allowlist(_)) {
+        runtime.announce_deal(endpoints, &deal);
+    }
+    if let Some(lifetime) = lifetime {
+        schedule_deal_expiry(deal.clone(), lifetime);
+    }
+    Ok((
+        TradeStateMachine::MakeDeal(MakeDeal {
+            deal,
+            arb_addr,
+            acc_addr,
+            approval,
+            pending_approvals: vec![],
+            expiry,
+        }),
+        signed_deal,
+    ))
+}
+
 fn attempt_transition_to_taker_connect_or_take_deal(
     mut event: Event,
     runtime: &mut Runtime,
@@ -600,18 +788,37 @@ fn attempt_transition_to_taker_connect_or_take_deal(
     match event.request.clone() {
         BusMsg::Ctl(CtlMsg::TakeDeal(PubDeal {
             deal,
+            origin,
             bitcoin_address: arb_addr,
             monero_address: acc_addr,
         })) => {
-            // validate deal parameters
-            if let Err(e) =
-                runtime
-                    .config
-                    .validate_deal_parameters(&deal.parameters, &arb_addr, &acc_addr)
+            // verify the deal against its signature or announcement by its maker, then validate
+            // deal parameters, our policy as taker, the swap limits and price against the
+            // reference price, if a price oracle is configured
+            if let Err(e) = runtime
+                .check_maintenance()
+                .and_then(|_| runtime.verify_taken_deal(&deal, &origin))
+                .and_then(|_| runtime.reference_price())
+                .and_then(|reference_price| {
+                    runtime.config.validate_deal_parameters(
+                        &deal.parameters,
+                        &arb_addr,
+                        &acc_addr,
+                        reference_price,
+                    )
+                })
+                .and_then(|_| {
+                    runtime.validate_deal_policy(
+                        &deal.parameters,
+                        deal.parameters.maker_role.other(),
+                        Some(NodeId::from(deal.node_id)),
+                    )
+                })
+                .and_then(|_| runtime.check_swap_limits(&deal.parameters))
             {
                 warn!("Deal parameters validation error: {}", e);
                 event.complete_client_ctl(CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
+                    code: FailureCode::from(&e),
                     info: e.to_string(),
                 }))?;
                 return Ok(None);
@@ -623,7 +830,7 @@ fn attempt_transition_to_taker_connect_or_take_deal(
                 );
                 log_helper.log_warn(format!("{}", msg.err()));
                 event.complete_client_ctl(CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
+                    code: FailureCode::InvalidDeal,
                     info: msg,
                 }))?;
                 return Ok(None);
@@ -638,12 +845,13 @@ fn attempt_transition_to_taker_connect_or_take_deal(
                         err
                     ));
                     event.complete_client_ctl(CtlMsg::Failure(Failure {
-                        code: FailureCode::Unknown,
+                        code: FailureCode::PeerUnreachable,
                         info: err.to_string(),
                     }))?;
                     Ok(None)
                 }
                 Ok((connected, peer_service_id)) => {
+                    runtime.record_swap_volume(event.endpoints, &deal.parameters)?;
                     if connected {
                         let deal_registered = "Deal registered".to_string();
                         log_helper.log_info(format!(
@@ -702,7 +910,7 @@ fn attempt_transition_to_restoring_swapd(
         })) => {
             if let Err(err) = runtime.services_ready() {
                 event.complete_client_ctl(CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
+                    code: FailureCode::Unavailable,
                     info: err.to_string(),
                 }))?;
                 return Ok(None);
@@ -714,23 +922,29 @@ fn attempt_transition_to_restoring_swapd(
                 .is_ok()
             {
                 event.complete_client_ctl(CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
+                    code: FailureCode::InvalidState,
                     info: "Cannot restore a checkpoint into a running swap.".to_string(),
                 }))?;
                 return Ok(None);
             }
 
-            // We only try to re-establish a connection if we are the Taker
-            let expect_connection = if trade_role == TradeRole::Taker {
+            // We only try to re-establish a connection if we are the Taker. The connection may
+            // still be up if swapd is relaunched after a crash.
+            let (expect_connection, peerd) = if trade_role == TradeRole::Taker {
                 let peer_node_addr = node_addr_from_deal(&deal);
-                if let Err(err) = runtime.connect_peer(&peer_node_addr) {
-                    log_helper.log_warn(format!("failed to reconnect to peer on restore: {}", err));
-                    false
-                } else {
-                    true
+                match runtime.connect_peer(&peer_node_addr) {
+                    Err(err) => {
+                        log_helper
+                            .log_warn(format!("failed to reconnect to peer on restore: {}", err));
+                        (false, None)
+                    }
+                    Ok((true, peerd)) => (false, Some(peerd)),
+                    Ok((false, _)) => (true, None),
                 }
             } else {
-                false
+                let peerd = expected_counterparty_node_id
+                    .and_then(|node_id| runtime.peer_connection(&node_id));
+                (false, peerd)
             };
 
             let swap_config = runtime.config.get_swap_config(
@@ -787,7 +1001,7 @@ fn attempt_transition_to_restoring_swapd(
                 arbitrating_syncer_up,
                 accordant_syncer_up,
                 swapd_up: false,
-                peerd: None,
+                peerd,
                 expect_connection,
                 listening,
             })))
@@ -812,34 +1026,112 @@ fn attempt_transition_to_taker_committed(
         deal,
         arb_addr,
         acc_addr,
+        approval,
+        mut pending_approvals,
+        expiry,
     } = make_deal;
     match (event.request.clone(), event.source.clone()) {
         (BusMsg::P2p(PeerMsg::TakerCommit(taker_commit)), ServiceId::Peer(..)) => {
             if deal == taker_commit.deal {
                 let source = event.source.clone();
-                log_helper.log_info(
-                    "Received TakerCommit for swap - requesting walletd to create swap keys.",
-                );
-                event.send_ctl_service(
-                    ServiceId::Wallet,
-                    CtlMsg::CreateSwapKeys(deal.clone(), runtime.wallet_token.clone()),
-                )?;
-                event.complete_ctl_service(
-                    ServiceId::Database,
-                    CtlMsg::SetDealInfo(DealInfo {
-                        deal: deal.clone(),
-                        serialized_deal: deal.to_string(),
-                        status: DealStatus::InProgress,
-                        local_trade_role: TradeRole::Maker,
-                    }),
-                )?;
-                Ok(Some(TradeStateMachine::TakerCommit(TakerCommit {
-                    peerd: source,
+                let swap_id = taker_commit.commit.swap_id();
+                if expiry.map_or(false, |expiry| unix_timestamp() >= expiry) {
+                    log_helper.log_warn(format!(
+                        "Rejecting TakerCommit from {}, deal {} has expired.",
+                        source,
+                        deal.id()
+                    ));
+                    event.send_msg_service(source, PeerMsg::DealNotFound(swap_id))?;
+                    return Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                        deal,
+                        arb_addr,
+                        acc_addr,
+                        approval,
+                        pending_approvals,
+                        expiry,
+                    })));
+                }
+                if let Err(err) = runtime
+                    .check_maintenance()
+                    .and_then(|_| runtime.check_swap_limits(&deal.parameters))
+                {
+                    log_helper.log_warn(format!(
+                        "Rejecting TakerCommit from {} for deal {}: {}",
+                        source,
+                        deal.id(),
+                        err
+                    ));
+                    event.send_msg_service(source, PeerMsg::DealNotFound(swap_id))?;
+                    return Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                        deal,
+                        arb_addr,
+                        acc_addr,
+                        approval,
+                        pending_approvals,
+                        expiry,
+                    })));
+                }
+                match &approval {
+                    DealApproval::Auto => {}
+                    DealApproval::Allowlist(node_ids) => {
+                        if !source.node_id().map_or(false, |id| node_ids.contains(&id)) {
+                            log_helper.log_warn(format!(
+                                "Rejecting TakerCommit from {}, not allowlisted for deal {}.",
+                                source,
+                                deal.id()
+                            ));
+                            event.send_msg_service(source, PeerMsg::DealNotFound(swap_id))?;
+                            return Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                                deal,
+                                arb_addr,
+                                acc_addr,
+                                approval,
+                                pending_approvals,
+                                expiry,
+                            })));
+                        }
+                    }
+                    DealApproval::Manual => {
+                        if let Some(pending) = pending_approvals
+                            .iter_mut()
+                            .find(|pending| pending.commit.swap_id() == swap_id)
+                        {
+                            // The taker resent its commit, possibly over a new connection
+                            pending.peerd = source;
+                        } else {
+                            log_helper.log_info(format!(
+                                "Received TakerCommit for swap {} from {} - awaiting approval.",
+                                swap_id.bright_blue_italic(),
+                                source
+                            ));
+                            schedule_approval_expiry(swap_id, runtime.config.approval_timeout());
+                            pending_approvals.push(PendingTakerCommit {
+                                peerd: source,
+                                commit: taker_commit.commit,
+                                since: SystemTime::now(),
+                            });
+                        }
+                        return Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                            deal,
+                            arb_addr,
+                            acc_addr,
+                            approval,
+                            pending_approvals,
+                            expiry,
+                        })));
+                    }
+                }
+                let tsm = accept_taker_commit(
+                    &mut event,
+                    runtime,
+                    &log_helper,
                     deal,
-                    commit: taker_commit.commit,
-                    target_bitcoin_address: arb_addr,
-                    target_monero_address: acc_addr,
-                })))
+                    source,
+                    taker_commit.commit,
+                    arb_addr,
+                    acc_addr,
+                )?;
+                Ok(Some(tsm))
             } else {
                 log_helper.log_error(format!(
                     "Received invalid TakerCommit for deal {}.",
@@ -849,13 +1141,126 @@ fn attempt_transition_to_taker_committed(
                     deal,
                     arb_addr,
                     acc_addr,
+                    approval,
+                    pending_approvals,
+                    expiry,
+                })))
+            }
+        }
+        (BusMsg::Ctl(CtlMsg::AcceptTaker(swap_id)), _) => {
+            if let Some(pos) = pending_approvals
+                .iter()
+                .position(|pending| pending.commit.swap_id() == swap_id)
+            {
+                let accepted = pending_approvals.remove(pos);
+                log_helper.log_info(format!(
+                    "Taker {} approved for swap {}.",
+                    accepted.peerd,
+                    swap_id.bright_blue_italic()
+                ));
+                // The deal can only be taken once, reject the other takers
+                reject_pending_takers(&mut event, &log_helper, pending_approvals)?;
+                let tsm = accept_taker_commit(
+                    &mut event,
+                    runtime,
+                    &log_helper,
+                    deal,
+                    accepted.peerd,
+                    accepted.commit,
+                    arb_addr,
+                    acc_addr,
+                )?;
+                event.complete_client_ctl(CtlMsg::Success(OptionDetails::with(format!(
+                    "Accepted taker for swap {}",
+                    swap_id
+                ))))?;
+                Ok(Some(tsm))
+            } else {
+                event.complete_client_ctl(CtlMsg::Failure(Failure {
+                    code: FailureCode::NotFound,
+                    info: format!("No taker awaiting approval for swap {}", swap_id),
+                }))?;
+                Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                    deal,
+                    arb_addr,
+                    acc_addr,
+                    approval,
+                    pending_approvals,
+                    expiry,
                 })))
             }
         }
+        (BusMsg::Ctl(CtlMsg::RejectTaker(swap_id)), _) => {
+            let (rejected, pending_approvals): (Vec<_>, Vec<_>) = pending_approvals
+                .into_iter()
+                .partition(|pending| pending.commit.swap_id() == swap_id);
+            if rejected.is_empty() {
+                event.complete_client_ctl(CtlMsg::Failure(Failure {
+                    code: FailureCode::NotFound,
+                    info: format!("No taker awaiting approval for swap {}", swap_id),
+                }))?;
+            } else {
+                reject_pending_takers(&mut event, &log_helper, rejected)?;
+                event.complete_client_ctl(CtlMsg::Success(OptionDetails::with(format!(
+                    "Rejected taker for swap {}",
+                    swap_id
+                ))))?;
+            }
+            Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                deal,
+                arb_addr,
+                acc_addr,
+                approval,
+                pending_approvals,
+                expiry,
+            })))
+        }
+        (BusMsg::Bridge(BridgeMsg::ApprovalExpired { swap_id }), _) => {
+            let (expired, pending_approvals): (Vec<_>, Vec<_>) = pending_approvals
+                .into_iter()
+                .partition(|pending| pending.commit.swap_id() == swap_id);
+            if !expired.is_empty() {
+                log_helper.log_warn(format!(
+                    "Taker for swap {} was not approved in time.",
+                    swap_id.bright_blue_italic()
+                ));
+            }
+            reject_pending_takers(&mut event, &log_helper, expired)?;
+            Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                deal,
+                arb_addr,
+                acc_addr,
+                approval,
+                pending_approvals,
+                expiry,
+            })))
+        }
+        (BusMsg::Bridge(BridgeMsg::DealExpired { deal: expired_deal }), _)
+            if expired_deal == deal =>
+        {
+            log_helper.log_info(format!("Deal {} expired, revoking it", deal.label()));
+            reject_pending_takers(&mut event, &log_helper, pending_approvals)?;
+            runtime.announce_deal_revocation(event.endpoints, &deal);
+            runtime.release_quoted_deal(&deal);
+            event.send_ctl_service(
+                ServiceId::Database,
+                CtlMsg::SetDealInfo(DealInfo {
+                    deal: deal.clone(),
+                    serialized_deal: deal.to_string(),
+                    status: DealStatus::Revoked,
+                    local_trade_role: TradeRole::Maker,
+                    expiry,
+                }),
+            )?;
+            Ok(None)
+        }
         (BusMsg::Ctl(CtlMsg::RevokeDeal(revoke_deal)), _) => {
             log_helper.log_debug(format!("attempting to revoke {}", deal));
             if revoke_deal == deal {
                 log_helper.log_info(format!("Revoked deal {}", deal.label()));
+                reject_pending_takers(&mut event, &log_helper, pending_approvals)?;
+                runtime.announce_deal_revocation(event.endpoints, &deal);
+                runtime.release_quoted_deal(&deal);
                 event.send_ctl_service(
                     ServiceId::Database,
                     CtlMsg::SetDealInfo(DealInfo {
@@ -863,6 +1268,7 @@ fn attempt_transition_to_taker_committed(
                         serialized_deal: deal.to_string(),
                         status: DealStatus::Revoked,
                         local_trade_role: TradeRole::Maker,
+                        expiry,
                     }),
                 )?;
                 event.complete_client_info(InfoMsg::String(
@@ -877,6 +1283,9 @@ fn attempt_transition_to_taker_committed(
                     deal,
                     arb_addr,
                     acc_addr,
+                    approval,
+                    pending_approvals,
+                    expiry,
                 })))
             }
         }
@@ -896,11 +1305,71 @@ fn attempt_transition_to_taker_committed(
                 deal,
                 arb_addr,
                 acc_addr,
+                approval,
+                pending_approvals,
+                expiry,
             })))
         }
     }
 }
 
+/// Consume the deal with the taker's commit, requesting walletd to create the swap keys
+#[allow(clippy::too_many_arguments)]
+fn accept_taker_commit(
+    event: &mut Event,
+    runtime: &mut Runtime,
+    log_helper: &LogHelper,
+    deal: Deal,
+    peerd: ServiceId,
+    commit: Commit,
+    arb_addr: bitcoin::Address,
+    acc_addr: monero::Address,
+) -> Result<TradeStateMachine, Error> {
+    log_helper.log_info("Received TakerCommit for swap - requesting walletd to create swap keys.");
+    // The deal is consumed, it is no longer available to the other takers
+    runtime.announce_deal_revocation(event.endpoints, &deal);
+    runtime.record_swap_volume(event.endpoints, &deal.parameters)?;
+    event.send_ctl_service(
+        ServiceId::Wallet,
+        CtlMsg::CreateSwapKeys(deal.clone(), runtime.wallet_token.clone()),
+    )?;
+    event.send_ctl_service(
+        ServiceId::Database,
+        CtlMsg::SetDealInfo(DealInfo {
+            deal: deal.clone(),
+            serialized_deal: deal.to_string(),
+            status: DealStatus::InProgress,
+            local_trade_role: TradeRole::Maker,
+            expiry: None,
+        }),
+    )?;
+    Ok(TradeStateMachine::TakerCommit(TakerCommit {
+        peerd,
+        deal,
+        commit,
+        target_bitcoin_address: arb_addr,
+        target_monero_address: acc_addr,
+    }))
+}
+
+/// Reply to the takers that were not approved as if the deal did not exist
+fn reject_pending_takers(
+    event: &mut Event,
+    log_helper: &LogHelper,
+    rejected: Vec<PendingTakerCommit>,
+) -> Result<(), Error> {
+    for pending in rejected {
+        let swap_id = pending.commit.swap_id();
+        log_helper.log_info(format!(
+            "Rejecting taker {} for swap {}.",
+            pending.peerd,
+            swap_id.bright_blue_italic()
+        ));
+        event.send_msg_service(pending.peerd, PeerMsg::DealNotFound(swap_id))?;
+    }
+    Ok(())
+}
+
 fn attempt_transition_from_taker_commit_to_swapd_launched(
     event: Event,
     runtime: &mut Runtime,
@@ -962,9 +1431,9 @@ fn attempt_transition_to_take_deal(
     } = taker_connect;
     match event.request {
         BusMsg::Ctl(CtlMsg::ConnectSuccess)
-            if Some(node_addr_from_deal(&deal)) == event.source.node_addr() =>
+            if Some(NodeId::from(deal.node_id)) == event.source.node_id() =>
         {
-            runtime.handle_new_connection(event.source.clone());
+            runtime.handle_new_connection(event.endpoints, event.source.clone());
             let deal_registered = "Deal registered".to_string();
             log_helper.log_info(format!(
                 "{}: {:#}",
@@ -991,7 +1460,7 @@ fn attempt_transition_to_take_deal(
             })))
         }
         BusMsg::Ctl(CtlMsg::ConnectFailed)
-            if Some(node_addr_from_deal(&deal)) == event.source.node_addr() =>
+            if Some(NodeId::from(deal.node_id)) == event.source.node_id() =>
         {
             log_helper.log_warn(format!(
                 "{} | Connection to the remote peer {} failed, cannot  take the deal.",
@@ -1003,7 +1472,7 @@ fn attempt_transition_to_take_deal(
                 source,
                 CtlMsg::Failure(Failure {
                     info: format!("Could not connect to remote peer {}.", event.source),
-                    code: FailureCode::Unknown,
+                    code: FailureCode::PeerUnreachable,
                 }),
             )?;
             Ok(None)
@@ -1065,6 +1534,7 @@ fn attempt_transition_from_take_deal_to_swapd_launched(
                     deal,
                     status: DealStatus::InProgress,
                     local_trade_role: TradeRole::Taker,
+                    expiry: None,
                 }),
             )?;
             Ok(Some(tsm))
@@ -1157,7 +1627,7 @@ fn attempt_transition_from_swapd_launched_to_swapd_running(
     log_helper: LogHelper,
 ) -> Result<Option<TradeStateMachine>, Error> {
     let SwapdLaunched {
-        mut peerd,
+        peerd,
         deal,
         swap_id,
         mut arbitrating_syncer_up,
@@ -1183,6 +1653,10 @@ fn attempt_transition_from_swapd_launched_to_swapd_running(
         {
             arbitrating_syncer_up = Some(source);
         }
+        (BusMsg::Ctl(CtlMsg::Hello), source) if source == peerd => {
+            // Peerd reconnected to the same remote node in the meantime
+            peerd_reconnected = true;
+        }
         (BusMsg::Ctl(CtlMsg::Hello), ServiceId::Peer(..)) => {}
         _ => {
             log_helper.log_trace(format!(
@@ -1192,18 +1666,7 @@ fn attempt_transition_from_swapd_launched_to_swapd_running(
         }
     }
 
-    let peerd_up = runtime.registered_services.iter().any(|service| {
-        if *service == peerd {
-            true
-        } else if service.node_addr() == peerd.node_addr() {
-            // Peerd might have changed after reconnect in the meantime
-            peerd = service.clone();
-            peerd_reconnected = true;
-            true
-        } else {
-            false
-        }
-    });
+    let peerd_up = runtime.registered_services.contains(&peerd);
 
     if let (Some(accordant_syncer), Some(arbitrating_syncer), true, true) = (
         accordant_syncer_up.clone(),
@@ -1314,31 +1777,27 @@ fn attempt_transition_from_restoring_swapd_to_swapd_running(
             arbitrating_syncer_up = Some(source);
         }
         (BusMsg::Ctl(CtlMsg::ConnectSuccess), source)
-            if Some(node_addr_from_deal(&deal)) == source.node_addr()
+            if Some(NodeId::from(deal.node_id)) == source.node_id()
                 && trade_role == TradeRole::Taker =>
         {
-            runtime.handle_new_connection(event.source.clone());
+            runtime.handle_new_connection(event.endpoints, event.source.clone());
 
             log_helper.log_info("Peerd connected for restored swap");
             peerd = Some(event.source.clone());
         }
         (BusMsg::Ctl(CtlMsg::ConnectFailed), source)
-            if Some(node_addr_from_deal(&deal)) == source.node_addr()
+            if Some(NodeId::from(deal.node_id)) == source.node_id()
                 && trade_role == TradeRole::Taker =>
         {
             runtime.handle_failed_connection(event.endpoints, source)?;
             expect_connection = false;
         }
         (BusMsg::Ctl(CtlMsg::Hello), source) if trade_role == TradeRole::Maker => {
-            if let Ok(bind_addr) = runtime.config.get_bind_addr() {
-                if let Some(node_id) = expected_counterparty_node_id {
-                    if source.node_addr() == Some(NodeAddr::new(node_id, bind_addr)) {
-                        log_helper.log_info("Peerd connected for restored swap");
-                        peerd = Some(source);
-                    }
+            if let Some(node_id) = expected_counterparty_node_id {
+                if source.node_id() == Some(node_id) {
+                    log_helper.log_info("Peerd connected for restored swap");
+                    peerd = Some(source);
                 }
-            } else {
-                log_helper.log_error("Invalid bind addr configuration");
             }
         }
         _ => {}
@@ -1414,14 +1873,14 @@ fn attempt_transition_to_end(
     } = swapd_running;
     match (event.request.clone(), event.source.clone()) {
         (BusMsg::Ctl(CtlMsg::Hello), source)
-            if matches!(source, ServiceId::Peer(..))
-                && source.node_addr() == peerd.as_ref().and_then(|p| p.node_addr()) =>
+            if peerd.as_ref().map_or(false, |peerd| *peerd == source) =>
         {
             let swap_service_id = ServiceId::Swap(swap_id);
             log_helper.log_debug("Letting swapd know of peer reconnection.");
-            event.complete_ctl_service(swap_service_id, CtlMsg::PeerdReconnected(source))?;
+            event
+                .complete_ctl_service(swap_service_id, CtlMsg::PeerdReconnected(source.clone()))?;
             Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
-                peerd,
+                peerd: Some(source),
                 deal,
                 swap_id,
                 arbitrating_syncer,
@@ -1436,13 +1895,15 @@ fn attempt_transition_to_end(
 
         (BusMsg::Ctl(CtlMsg::Hello), source)
             if peerd.is_none()
-                && expected_counterparty_node_id == source.node_addr().map(|a| a.id) =>
+                && source.node_id().is_some()
+                && expected_counterparty_node_id == source.node_id() =>
         {
             let swap_service_id = ServiceId::Swap(swap_id);
             log_helper.log_debug("Letting swapd know of peer reconnection.");
-            event.complete_ctl_service(swap_service_id, CtlMsg::PeerdReconnected(source))?;
+            event
+                .complete_ctl_service(swap_service_id, CtlMsg::PeerdReconnected(source.clone()))?;
             Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
-                peerd,
+                peerd: Some(source),
                 deal,
                 swap_id,
                 arbitrating_syncer,
@@ -1672,7 +2133,7 @@ fn attempt_transition_to_end(
             let mut new_peerd = peerd.clone();
             if let Some(peerd) = peerd {
                 event.complete_client_ctl(CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
+                    code: FailureCode::InvalidState,
                     info: format!(
                         "The swap already has a dedicated connection daemon {}",
                         peerd
@@ -1683,7 +2144,7 @@ fn attempt_transition_to_end(
                 match runtime.connect_peer(&peer_node_addr) {
                     Err(err) => {
                         event.complete_client_ctl(CtlMsg::Failure(Failure {
-                            code: FailureCode::Unknown,
+                            code: FailureCode::PeerUnreachable,
                             info: err.to_string(),
                         }))?;
                     }
@@ -1711,12 +2172,12 @@ fn attempt_transition_to_end(
 
         // A ConnectSuccess event can only come from a peerd connecting to a listener (maker)
         (BusMsg::Ctl(CtlMsg::ConnectSuccess), source)
-            if source.node_addr() == Some(node_addr_from_deal(&deal)) =>
+            if source.node_id() == Some(NodeId::from(deal.node_id)) =>
         {
             for client in clients_awaiting_connect_result.drain(..) {
                 event.send_client_ctl(client, CtlMsg::ConnectSuccess)?;
             }
-            runtime.handle_new_connection(source.clone());
+            runtime.handle_new_connection(event.endpoints, source.clone());
             Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
                 peerd: Some(source),
                 deal,
@@ -1733,13 +2194,13 @@ fn attempt_transition_to_end(
 
         // A ConnectFailed event can only come from a peerd connecting to a listener (maker)
         (BusMsg::Ctl(CtlMsg::ConnectFailed), source)
-            if source.node_addr() == Some(node_addr_from_deal(&deal)) =>
+            if source.node_id() == Some(NodeId::from(deal.node_id)) =>
         {
             for client in clients_awaiting_connect_result.drain(..) {
                 event.send_client_ctl(
                     client,
                     CtlMsg::Failure(Failure {
-                        code: FailureCode::Unknown,
+                        code: FailureCode::PeerUnreachable,
                         info: format!("Failed to connect to remote peer: {}", source),
                     }),
                 )?;
@@ -1789,6 +2250,7 @@ fn attempt_transition_to_end(
         (BusMsg::Ctl(CtlMsg::SwapOutcome(outcome)), source)
             if ServiceId::Swap(swap_id) == source =>
         {
+            runtime.handle_market_maker_outcome(&deal, &outcome);
             event.send_ctl_service(
                 ServiceId::Database,
                 CtlMsg::SetDealInfo(DealInfo {
@@ -1796,6 +2258,7 @@ fn attempt_transition_to_end(
                     deal,
                     status: DealStatus::Ended(outcome.clone()),
                     local_trade_role: trade_role,
+                    expiry: None,
                 }),
             )?;
             runtime.clean_up_after_swap(&swap_id, event.endpoints)?;
@@ -1813,6 +2276,9 @@ fn attempt_transition_to_end(
                 Outcome::FailureAbort => {
                     log_helper.log_warn(format!("Aborted swap {}", swap_id));
                 }
+                Outcome::FailureTimeout => {
+                    log_helper.log_warn(format!("Timed out swap {}", swap_id));
+                }
             }
             runtime.stats.success_rate();
             Ok(None)
@@ -1846,6 +2312,21 @@ fn attempt_transition_to_end(
     }
 }
 
+/// Reject the taker if it is still awaiting approval once the timeout elapsed
+fn schedule_approval_expiry(swap_id: SwapId, timeout: Duration) {
+    std::thread::spawn(move || {
+        std::thread::sleep(timeout);
+        report_over_bridge(BridgeMsg::ApprovalExpired { swap_id });
+    });
+}
+
+fn schedule_deal_expiry(deal: Deal, lifetime: Duration) {
+    std::thread::spawn(move || {
+        std::thread::sleep(lifetime);
+        report_over_bridge(BridgeMsg::DealExpired { deal });
+    });
+}
+
 fn node_addr_from_deal(deal: &Deal) -> NodeAddr {
     NodeAddr {
         id: NodeId::from(deal.node_id), // node_id is bitcoin::Pubkey
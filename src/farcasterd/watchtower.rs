@@ -0,0 +1,871 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+use bitcoin::Txid;
+use farcaster_core::blockchain::Blockchain;
+use farcaster_core::swap::SwapId;
+use farcaster_core::transaction::TxLabel;
+use internet2::addr::{NodeAddr, NodeId};
+use microservices::esb::Handler;
+
+use crate::bus::ctl::{CtlMsg, WatchedSwap};
+use crate::bus::p2p::{PeerMsg, SignedWatchtowerRequest, WatchtowerRequest, WatchtowerSwap};
+use crate::bus::sync::SyncMsg;
+use crate::bus::{BusMsg, Failure, FailureCode, OptionDetails, ServiceBus};
+use crate::farcasterd::runtime::{syncer_up, Runtime};
+use crate::service::Endpoints;
+use crate::syncerd::{
+    Abort, BroadcastTransaction, Event as SyncerEvent, Task, TaskId, TaskTarget,
+    TransactionBroadcasted, TransactionConfirmations, WatchTransaction,
+};
+use crate::{CtlServer, Error, LogStyle, ServiceId};
+
+/// Stop receiving confirmations after this many blocks, far above any punish timelock
+const WATCHTOWER_CONFIRMATION_BOUND: u32 = 50000;
+
+/// Requests signed longer ago are rejected, bounding the replay window across watchtower
+/// restarts, in milliseconds
+const WATCHTOWER_REQUEST_MAX_AGE: u64 = 10 * 60 * 1000;
+
+fn unix_timestamp_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Client side of the watchtower protocol: the watchtowers our swaps are registered with, the
+/// registered transactions, persisted in databased, and the requests waiting for a connection to
+/// one of them.
+pub struct WatchtowerClient {
+    towers: Vec<NodeAddr>,
+    registered: HashMap<SwapId, WatchtowerSwap>,
+    pending_requests: HashMap<NodeId, Vec<SignedWatchtowerRequest>>,
+    last_nonce: u64,
+}
+
+impl WatchtowerClient {
+    pub fn new(towers: Vec<NodeAddr>) -> Self {
+        WatchtowerClient {
+            towers,
+            registered: none!(),
+            pending_requests: none!(),
+            last_nonce: 0,
+        }
+    }
+
+    /// Restore a registration persisted before a restart, so the swap is still revoked when it
+    /// ends
+    pub fn restore_registration(&mut self, swap: WatchtowerSwap) {
+        self.registered.insert(swap.swap_id, swap);
+    }
+
+    /// Returns the current time in milliseconds, strictly above the previous nonce
+    fn next_nonce(&mut self) -> u64 {
+        self.last_nonce = unix_timestamp_millis().max(self.last_nonce + 1);
+        self.last_nonce
+    }
+
+    /// Returns if the service is a connection to one of our watchtowers
+    pub fn is_watchtower(&self, service: &ServiceId) -> bool {
+        service
+            .node_id()
+            .map_or(false, |id| self.towers.iter().any(|t| t.id == id))
+    }
+
+    /// Returns if requests are waiting for the connection to complete
+    pub fn awaits_connection(&self, service: &ServiceId) -> bool {
+        service
+            .node_id()
+            .map_or(false, |id| self.pending_requests.contains_key(&id))
+    }
+
+    fn queue_request(&mut self, tower: NodeAddr, request: SignedWatchtowerRequest) {
+        self.pending_requests
+            .entry(tower.id)
+            .or_default()
+            .push(request);
+    }
+
+    fn take_pending_requests(&mut self, service: &ServiceId) -> Vec<SignedWatchtowerRequest> {
+        service
+            .node_id()
+            .and_then(|id| self.pending_requests.remove(&id))
+            .unwrap_or_default()
+    }
+}
+
+/// Watchtower side of the protocol: the swaps watched on behalf of the authorized clients and
+/// the nonce of the last request accepted from each client.
+pub struct Watchtower {
+    authorized_clients: HashSet<NodeId>,
+    started: bool,
+    jobs: HashMap<SwapId, WatchtowerJob>,
+    awaiting_syncer: Vec<(ServiceId, Task)>,
+    last_nonces: HashMap<NodeId, u64>,
+}
+
+/// A swap watched by the watchtower. Cancel is broadcast as soon as the lock allows it and the
+/// buy is not seen, refund once the cancel is final and the punish is not seen, punish once the
+/// temporal safety parameters allow it and the refund is not seen. The job ends when the buy, the
+/// refund or the punish transaction is final, or when the client revokes it.
+struct WatchtowerJob {
+    client: NodeId,
+    swap: WatchtowerSwap,
+    syncer: ServiceId,
+    watched_txs: HashMap<TaskId, TxLabel>,
+    seen_txs: HashSet<TxLabel>,
+    broadcasting_txs: HashMap<TaskId, TxLabel>,
+    broadcasted_txs: HashSet<TxLabel>,
+}
+
+impl WatchtowerJob {
+    fn txid(&self, label: TxLabel) -> Option<Txid> {
+        match label {
+            TxLabel::Lock => Some(self.swap.lock_txid),
+            TxLabel::Buy => self.swap.buy_txid,
+            TxLabel::Cancel => Some(self.swap.cancel_tx.txid()),
+            TxLabel::Refund => Some(self.swap.refund_txid),
+            TxLabel::Punish => self.swap.punish_tx.as_ref().map(|tx| tx.txid()),
+            _ => None,
+        }
+    }
+}
+
+impl Watchtower {
+    pub fn new(authorized_clients: Vec<NodeId>) -> Self {
+        Watchtower {
+            authorized_clients: authorized_clients.into_iter().collect(),
+            started: false,
+            jobs: none!(),
+            awaiting_syncer: none!(),
+            last_nonces: none!(),
+        }
+    }
+
+    /// Returns if the syncer is needed by a watched swap
+    pub fn uses_syncer(&self, syncer: &ServiceId) -> bool {
+        self.jobs.values().any(|job| job.syncer == *syncer)
+    }
+
+    /// Queue the watch tasks of the swaps served by a crashed syncer, they are sent again once the
+    /// relaunched syncer is up
+    pub fn requeue_syncer_tasks(&mut self, syncer: &ServiceId) {
+        let tasks: Vec<Task> = self
+            .jobs
+            .values()
+            .filter(|job| job.syncer == *syncer)
+            .flat_map(|job| {
+                job.watched_txs.iter().filter_map(move |(id, label)| {
+                    job.txid(*label).map(|txid| {
+                        Task::WatchTransaction(WatchTransaction {
+                            id: *id,
+                            lifetime: u64::MAX,
+                            hash: txid.into(),
+                            confirmation_bound: WATCHTOWER_CONFIRMATION_BOUND,
+                        })
+                    })
+                })
+            })
+            .collect();
+        for task in tasks {
+            self.awaiting_syncer.push((syncer.clone(), task));
+        }
+    }
+
+    /// Returns if the service is a connection from one of our authorized clients
+    pub fn is_client(&self, service: &ServiceId) -> bool {
+        service
+            .node_id()
+            .map_or(false, |id| self.authorized_clients.contains(&id))
+    }
+
+    /// Returns if the syncer event belongs to a watched swap
+    pub fn handles_event(&self, event: &SyncerEvent) -> bool {
+        let id = match event {
+            SyncerEvent::TransactionConfirmations(TransactionConfirmations { id, .. })
+            | SyncerEvent::TransactionBroadcasted(TransactionBroadcasted { id, .. }) => id,
+            _ => return false,
+        };
+        self.jobs
+            .values()
+            .any(|job| job.watched_txs.contains_key(id) || job.broadcasting_txs.contains_key(id))
+    }
+}
+
+impl WatchtowerJob {
+    fn transaction(&self, label: TxLabel) -> Option<bitcoin::Transaction> {
+        match label {
+            TxLabel::Cancel => Some(self.swap.cancel_tx.clone()),
+            TxLabel::Refund => self.swap.refund_tx.clone(),
+            TxLabel::Punish => self.swap.punish_tx.clone(),
+            _ => None,
+        }
+    }
+
+    fn broadcasted(&self, label: TxLabel) -> bool {
+        self.broadcasted_txs.contains(&label) || self.broadcasting_txs.values().any(|l| *l == label)
+    }
+
+    /// Returns the transactions the temporal safety parameters allow to broadcast given the
+    /// confirmations of a watched transaction. The refund is broadcast for as long as it is
+    /// valid, even past the safe refund window, until the punish is seen.
+    fn next_broadcasts(&self, label: TxLabel, confirmations: u32) -> Vec<TxLabel> {
+        let temporal_safety = &self.swap.temporal_safety;
+        let seen = |label| self.seen_txs.contains(&label);
+        let candidates = match label {
+            TxLabel::Lock if temporal_safety.valid_cancel(confirmations) && !seen(TxLabel::Buy) => {
+                vec![TxLabel::Cancel]
+            }
+            TxLabel::Cancel => {
+                let mut candidates = vec![];
+                if temporal_safety.final_tx(confirmations, Blockchain::Bitcoin)
+                    && !seen(TxLabel::Punish)
+                {
+                    candidates.push(TxLabel::Refund);
+                }
+                if temporal_safety.valid_punish(confirmations) && !seen(TxLabel::Refund) {
+                    candidates.push(TxLabel::Punish);
+                }
+                candidates
+            }
+            _ => vec![],
+        };
+        candidates
+            .into_iter()
+            .filter(|label| self.transaction(*label).is_some() && !self.broadcasted(*label))
+            .collect()
+    }
+
+    /// Returns if the swap is settled on-chain and does not need watching anymore
+    fn settled(&self, label: TxLabel, confirmations: u32) -> bool {
+        matches!(label, TxLabel::Buy | TxLabel::Refund | TxLabel::Punish)
+            && self
+                .swap
+                .temporal_safety
+                .final_tx(confirmations, Blockchain::Bitcoin)
+    }
+}
+
+/// Check that the registered transactions chain up as expected: cancel spends the lock, refund
+/// and punish spend the cancel.
+fn validate_watchtower_swap(swap: &WatchtowerSwap) -> Result<(), Error> {
+    swap.temporal_safety.valid_params()?;
+    let spends = |tx: &bitcoin::Transaction, txid: bitcoin::Txid| {
+        tx.input
+            .iter()
+            .any(|input| input.previous_output.txid == txid)
+    };
+    let cancel_txid = swap.cancel_tx.txid();
+    if !spends(&swap.cancel_tx, swap.lock_txid)
+        || swap.refund_tx.as_ref().map_or(false, |tx| {
+            !spends(tx, cancel_txid) || tx.txid() != swap.refund_txid
+        })
+        || swap
+            .punish_tx
+            .as_ref()
+            .map_or(false, |tx| !spends(tx, cancel_txid))
+    {
+        return Err(Error::Farcaster(
+            "Watchtower transactions do not spend the lock and cancel outputs".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl Runtime {
+    /// Start serving as a watchtower once the services and the node keys are ready: listen for
+    /// client connections and restore the persisted watched swaps.
+    pub fn handle_watchtower_start(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        let ready = self.services_ready().is_ok() && self.peer_keys_ready().is_ok();
+        match self.watchtower.as_mut() {
+            Some(watchtower) if ready && !watchtower.started => {
+                watchtower.started = true;
+            }
+            _ => return Ok(()),
+        }
+        let bind_addr = self.config.get_bind_addr()?;
+        let node_id = self.listen(bind_addr)?;
+        info!(
+            "{} for clients on {}@{}",
+            "Watchtower running".bright_green_bold(),
+            node_id.bright_yellow_bold(),
+            bind_addr
+        );
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(CtlMsg::RetrieveWatchedSwaps),
+        )?;
+        Ok(())
+    }
+
+    /// Sign and send a register request for the swap to all the configured watchtowers
+    pub fn register_with_watchtowers(
+        &mut self,
+        endpoints: &mut Endpoints,
+        swap: WatchtowerSwap,
+    ) -> Result<(), Error> {
+        if self.watchtower_client.towers.is_empty() {
+            debug!(
+                "{} | No watchtower configured, not registering the swap",
+                swap.swap_id.swap_id()
+            );
+            return Ok(());
+        }
+        self.watchtower_client
+            .registered
+            .insert(swap.swap_id, swap.clone());
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(CtlMsg::SetWatchtowerRegistration(swap.clone())),
+        )?;
+        self.send_watchtower_request(endpoints, WatchtowerRequest::Register(swap))
+    }
+
+    /// Revoke the swap from all the configured watchtowers, if it was registered
+    pub fn revoke_from_watchtowers(
+        &mut self,
+        endpoints: &mut Endpoints,
+        swap_id: SwapId,
+    ) -> Result<(), Error> {
+        if self.watchtower_client.registered.remove(&swap_id).is_none() {
+            return Ok(());
+        }
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(CtlMsg::RemoveWatchtowerRegistration(swap_id)),
+        )?;
+        self.send_watchtower_request(endpoints, WatchtowerRequest::Revoke(swap_id))
+    }
+
+    /// Register the swap with the configured watchtowers again on the request of a client
+    pub fn handle_register_with_watchtowers(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        swap_id: SwapId,
+    ) -> Result<(), Error> {
+        let res = if self.watchtower_client.towers.is_empty() {
+            Err(s!("No watchtower configured"))
+        } else {
+            match self.watchtower_client.registered.get(&swap_id).cloned() {
+                Some(swap) => self
+                    .register_with_watchtowers(endpoints, swap)
+                    .map_err(|err| err.to_string()),
+                None => Err(format!(
+                    "No watchtower registration known for swap {}, the swap registers once its \
+                     cancel transaction is signed",
+                    swap_id
+                )),
+            }
+        };
+        self.report_watchtower_command(endpoints, source, res, "Registered", swap_id)
+    }
+
+    /// Revoke the swap from the configured watchtowers on the request of a client
+    pub fn handle_revoke_from_watchtowers(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        swap_id: SwapId,
+    ) -> Result<(), Error> {
+        let res = if self.watchtower_client.registered.contains_key(&swap_id) {
+            self.revoke_from_watchtowers(endpoints, swap_id)
+                .map_err(|err| err.to_string())
+        } else {
+            Err(format!(
+                "Swap {} is not registered with watchtowers",
+                swap_id
+            ))
+        };
+        self.report_watchtower_command(endpoints, source, res, "Revoked", swap_id)
+    }
+
+    fn report_watchtower_command(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        res: Result<(), String>,
+        action: &str,
+        swap_id: SwapId,
+    ) -> Result<(), Error> {
+        let msg = match res {
+            Ok(()) => CtlMsg::Success(OptionDetails::with(format!(
+                "{} swap {} with the watchtowers",
+                action, swap_id
+            ))),
+            Err(info) => CtlMsg::Failure(Failure {
+                code: FailureCode::Unknown,
+                info,
+            }),
+        };
+        self.send_client_ctl(endpoints, source, msg)
+    }
+
+    fn send_watchtower_request(
+        &mut self,
+        endpoints: &mut Endpoints,
+        request: WatchtowerRequest,
+    ) -> Result<(), Error> {
+        let (node_secret_key, _) = self.peer_keys_ready()?;
+        let nonce = self.watchtower_client.next_nonce();
+        let signed_request = SignedWatchtowerRequest::sign(request, nonce, &node_secret_key)?;
+        for tower in self.watchtower_client.towers.clone() {
+            match self.connect_peer(&tower) {
+                Ok((true, peerd)) => {
+                    info!(
+                        "{} | Sending {} to watchtower {}",
+                        signed_request.request.swap_id().swap_id(),
+                        signed_request.request.label(),
+                        tower.bright_blue_italic()
+                    );
+                    endpoints.send_to(
+                        ServiceBus::Msg,
+                        self.identity(),
+                        peerd,
+                        BusMsg::P2p(PeerMsg::WatchtowerRequest(signed_request.clone())),
+                    )?;
+                }
+                Ok((false, _)) => {
+                    debug!(
+                        "{} | Waiting for the connection to watchtower {} to send {}",
+                        signed_request.request.swap_id().swap_id(),
+                        tower,
+                        signed_request.request
+                    );
+                    self.watchtower_client
+                        .queue_request(tower, signed_request.clone());
+                }
+                Err(err) => {
+                    error!(
+                        "{} | Failed to connect to watchtower {}: {}",
+                        signed_request.request.swap_id().swap_id(),
+                        tower,
+                        err.err()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the requests waiting for the connection to the watchtower, or drop them if the
+    /// connection failed
+    pub fn handle_watchtower_connection(
+        &mut self,
+        endpoints: &mut Endpoints,
+        peerd: ServiceId,
+        connected: bool,
+    ) -> Result<(), Error> {
+        let pending_requests = self.watchtower_client.take_pending_requests(&peerd);
+        if !connected {
+            for signed_request in pending_requests {
+                error!(
+                    "{} | Connection to watchtower {} failed, dropping {}",
+                    signed_request.request.swap_id().swap_id(),
+                    peerd,
+                    signed_request.request
+                );
+            }
+            return self.handle_failed_connection(endpoints, peerd);
+        }
+        for signed_request in pending_requests {
+            info!(
+                "{} | Sending {} to watchtower {}",
+                signed_request.request.swap_id().swap_id(),
+                signed_request.request.label(),
+                peerd.bright_blue_italic()
+            );
+            endpoints.send_to(
+                ServiceBus::Msg,
+                self.identity(),
+                peerd.clone(),
+                BusMsg::P2p(PeerMsg::WatchtowerRequest(signed_request)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Process a register or revoke request received from a client
+    pub fn handle_watchtower_request(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        signed_request: SignedWatchtowerRequest,
+    ) -> Result<(), Error> {
+        let swap_id = signed_request.request.swap_id();
+        let watchtower = match self.watchtower.as_ref() {
+            Some(watchtower) => watchtower,
+            None => {
+                warn!(
+                    "{} | Received {} from {}, but watchtower mode is disabled",
+                    swap_id.swap_id(),
+                    signed_request.request,
+                    source
+                );
+                return Ok(());
+            }
+        };
+        if let Err(err) = signed_request.verify() {
+            warn!(
+                "{} | Rejecting watchtower request from {} with an invalid signature: {}",
+                swap_id.swap_id(),
+                source,
+                err
+            );
+            return Ok(());
+        }
+        if !watchtower
+            .authorized_clients
+            .contains(&signed_request.node_id)
+        {
+            warn!(
+                "{} | Rejecting watchtower request from unauthorized client {}",
+                swap_id.swap_id(),
+                signed_request.node_id
+            );
+            return Ok(());
+        }
+        if source.node_id() != Some(signed_request.node_id) {
+            warn!(
+                "{} | Rejecting watchtower request of client {} relayed by {}",
+                swap_id.swap_id(),
+                signed_request.node_id,
+                source
+            );
+            return Ok(());
+        }
+        let last_nonce = watchtower
+            .last_nonces
+            .get(&signed_request.node_id)
+            .copied()
+            .unwrap_or_default();
+        if signed_request.nonce <= last_nonce
+            || signed_request.nonce + WATCHTOWER_REQUEST_MAX_AGE < unix_timestamp_millis()
+        {
+            warn!(
+                "{} | Rejecting replayed or outdated watchtower request from client {}",
+                swap_id.swap_id(),
+                signed_request.node_id
+            );
+            return Ok(());
+        }
+        if let Some(watchtower) = self.watchtower.as_mut() {
+            watchtower
+                .last_nonces
+                .insert(signed_request.node_id, signed_request.nonce);
+        }
+        let watchtower = self.watchtower.as_ref().expect("checked above");
+
+        match signed_request.request {
+            WatchtowerRequest::Register(swap) => {
+                if let Err(err) = validate_watchtower_swap(&swap) {
+                    warn!(
+                        "{} | Rejecting watchtower registration: {}",
+                        swap_id.swap_id(),
+                        err
+                    );
+                    return Ok(());
+                }
+                let watched_swap = WatchedSwap {
+                    client: signed_request.node_id,
+                    swap,
+                };
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Database,
+                    BusMsg::Ctl(CtlMsg::SetWatchedSwap(watched_swap.clone())),
+                )?;
+                self.watch_swap(endpoints, watched_swap)
+            }
+            WatchtowerRequest::Revoke(_) => {
+                let registered_by = watchtower.jobs.get(&swap_id).map(|job| job.client);
+                match registered_by {
+                    Some(client) if client == signed_request.node_id => {
+                        info!(
+                            "{} | Swap revoked by client {}",
+                            swap_id.swap_id(),
+                            client.bright_blue_italic()
+                        );
+                        self.unwatch_swap(endpoints, swap_id)
+                    }
+                    Some(_) => {
+                        warn!(
+                            "{} | Client {} cannot revoke a swap registered by another client",
+                            swap_id.swap_id(),
+                            signed_request.node_id
+                        );
+                        Ok(())
+                    }
+                    None => {
+                        debug!("{} | Revoked swap is not watched", swap_id.swap_id());
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start watching the lock, buy, cancel, refund and punish transactions of a registered swap
+    pub fn watch_swap(
+        &mut self,
+        endpoints: &mut Endpoints,
+        watched_swap: WatchedSwap,
+    ) -> Result<(), Error> {
+        let WatchedSwap { client, swap } = watched_swap;
+        if self.watchtower.is_none() {
+            warn!(
+                "{} | Watchtower mode is disabled, not watching the swap",
+                swap.swap_id.swap_id()
+            );
+            return Ok(());
+        }
+        let swap_id = swap.swap_id;
+        if self
+            .watchtower
+            .as_ref()
+            .map_or(false, |w| w.jobs.contains_key(&swap_id))
+        {
+            debug!(
+                "{} | Swap already watched, replacing the registration",
+                swap_id.swap_id()
+            );
+            self.unwatch_swap(endpoints, swap_id)?;
+        }
+
+        let syncer = ServiceId::Syncer(Blockchain::Bitcoin, swap.network);
+        let syncer_ready = syncer_up(
+            &mut self.spawning_services,
+            &mut self.registered_services,
+            Blockchain::Bitcoin,
+            swap.network,
+            &self.config,
+        )?;
+        let mut watched_txs = HashMap::new();
+        let mut tasks = vec![];
+        for (txid, label) in [
+            (Some(swap.lock_txid), TxLabel::Lock),
+            (swap.buy_txid, TxLabel::Buy),
+            (Some(swap.cancel_tx.txid()), TxLabel::Cancel),
+            (Some(swap.refund_txid), TxLabel::Refund),
+            (swap.punish_tx.as_ref().map(|tx| tx.txid()), TxLabel::Punish),
+        ] {
+            if let Some(txid) = txid {
+                let id = TaskId(self.syncer_task_counter);
+                self.syncer_task_counter += 1;
+                watched_txs.insert(id, label);
+                tasks.push(Task::WatchTransaction(WatchTransaction {
+                    id,
+                    lifetime: u64::MAX,
+                    hash: txid.into(),
+                    confirmation_bound: WATCHTOWER_CONFIRMATION_BOUND,
+                }));
+            }
+        }
+        info!(
+            "{} | {} for client {}",
+            swap_id.swap_id(),
+            "Watching swap".bright_green_bold(),
+            client.bright_blue_italic()
+        );
+        for task in tasks {
+            if syncer_ready.is_some() {
+                endpoints.send_to(
+                    ServiceBus::Sync,
+                    self.identity(),
+                    syncer.clone(),
+                    BusMsg::Sync(SyncMsg::Task(task)),
+                )?;
+            } else if let Some(watchtower) = self.watchtower.as_mut() {
+                watchtower.awaiting_syncer.push((syncer.clone(), task));
+            }
+        }
+        if let Some(watchtower) = self.watchtower.as_mut() {
+            watchtower.jobs.insert(
+                swap_id,
+                WatchtowerJob {
+                    client,
+                    swap,
+                    syncer,
+                    watched_txs,
+                    seen_txs: none!(),
+                    broadcasting_txs: none!(),
+                    broadcasted_txs: none!(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Stop watching the swap and forget it
+    fn unwatch_swap(&mut self, endpoints: &mut Endpoints, swap_id: SwapId) -> Result<(), Error> {
+        let job = match self
+            .watchtower
+            .as_mut()
+            .and_then(|watchtower| watchtower.jobs.remove(&swap_id))
+        {
+            Some(job) => job,
+            None => return Ok(()),
+        };
+        if let Some(watchtower) = self.watchtower.as_mut() {
+            watchtower.awaiting_syncer.retain(|(_, task)| match task {
+                Task::WatchTransaction(WatchTransaction { id, .. }) => {
+                    !job.watched_txs.contains_key(id)
+                }
+                _ => true,
+            });
+        }
+        if self.registered_services.contains(&job.syncer) {
+            for id in job.watched_txs.keys() {
+                endpoints.send_to(
+                    ServiceBus::Sync,
+                    self.identity(),
+                    job.syncer.clone(),
+                    BusMsg::Sync(SyncMsg::Task(Task::Abort(Abort {
+                        task_target: TaskTarget::TaskId(*id),
+                        respond: false,
+                    }))),
+                )?;
+            }
+        }
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(CtlMsg::RemoveWatchedSwap(swap_id)),
+        )?;
+        self.clean_up_after_syncer_usage(endpoints)
+    }
+
+    /// Send the watch tasks waiting for the syncer to come up
+    pub fn handle_watchtower_syncer_up(
+        &mut self,
+        endpoints: &mut Endpoints,
+        syncer: &ServiceId,
+    ) -> Result<(), Error> {
+        let tasks = match self.watchtower.as_mut() {
+            Some(watchtower) => {
+                let (ready, awaiting) = watchtower
+                    .awaiting_syncer
+                    .drain(..)
+                    .partition(|(service, _)| service == syncer);
+                watchtower.awaiting_syncer = awaiting;
+                ready
+            }
+            None => vec![],
+        };
+        for (_, task) in tasks {
+            endpoints.send_to(
+                ServiceBus::Sync,
+                self.identity(),
+                syncer.clone(),
+                BusMsg::Sync(SyncMsg::Task(task)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Process a syncer event of a watched swap, broadcasting cancel, refund or punish when the
+    /// temporal safety parameters allow it
+    pub fn handle_watchtower_event(
+        &mut self,
+        endpoints: &mut Endpoints,
+        event: SyncerEvent,
+    ) -> Result<(), Error> {
+        let identity = self.identity();
+        let watchtower = match self.watchtower.as_mut() {
+            Some(watchtower) => watchtower,
+            None => return Ok(()),
+        };
+        match event {
+            SyncerEvent::TransactionConfirmations(TransactionConfirmations {
+                id,
+                confirmations: Some(confirmations),
+                ..
+            }) => {
+                let (swap_id, job) = match watchtower
+                    .jobs
+                    .iter_mut()
+                    .find(|(_, job)| job.watched_txs.contains_key(&id))
+                {
+                    Some((swap_id, job)) => (*swap_id, job),
+                    None => return Ok(()),
+                };
+                let label = job.watched_txs[&id];
+                job.seen_txs.insert(label);
+                if job.settled(label, confirmations) {
+                    info!(
+                        "{} | {} transaction is final, the swap is settled",
+                        swap_id.swap_id(),
+                        label.label()
+                    );
+                    return self.unwatch_swap(endpoints, swap_id);
+                }
+                for next in job.next_broadcasts(label, confirmations) {
+                    let tx = job.transaction(next).expect("filtered by next_broadcasts");
+                    let id = TaskId(self.syncer_task_counter);
+                    self.syncer_task_counter += 1;
+                    info!(
+                        "{} | Broadcasting {} transaction ({}) on behalf of client {}",
+                        swap_id.swap_id(),
+                        next.label(),
+                        tx.txid().tx_hash(),
+                        job.client.bright_blue_italic()
+                    );
+                    job.broadcasting_txs.insert(id, next);
+                    endpoints.send_to(
+                        ServiceBus::Sync,
+                        identity.clone(),
+                        job.syncer.clone(),
+                        BusMsg::Sync(SyncMsg::Task(Task::BroadcastTransaction(
+                            BroadcastTransaction {
+                                id,
+                                tx: bitcoin::consensus::serialize(&tx),
+                                broadcast_after_height: None,
+                            },
+                        ))),
+                    )?;
+                }
+            }
+            SyncerEvent::TransactionBroadcasted(TransactionBroadcasted { id, error, .. }) => {
+                if let Some((swap_id, job)) = watchtower
+                    .jobs
+                    .iter_mut()
+                    .find(|(_, job)| job.broadcasting_txs.contains_key(&id))
+                {
+                    let label = job.broadcasting_txs.remove(&id).expect("checked above");
+                    if let Some(err) = error {
+                        warn!(
+                            "{} | Error broadcasting {} transaction, will retry: {}",
+                            swap_id.swap_id(),
+                            label.label(),
+                            err
+                        );
+                    } else {
+                        info!(
+                            "{} | {} transaction broadcasted",
+                            swap_id.swap_id(),
+                            label.label()
+                        );
+                        job.broadcasted_txs.insert(label);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
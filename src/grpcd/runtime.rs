@@ -6,20 +6,29 @@
 
 use crate::bus::bridge::BridgeMsg;
 use crate::bus::ctl::FundingInfo;
-use crate::bus::ctl::ProtoDeal;
-use crate::bus::ctl::PubDeal;
+use crate::bus::ctl::{DealApproval, ProtoDeal};
+use crate::bus::ctl::{DealOrigin, PubDeal};
 use crate::bus::info::Address;
 use crate::bus::info::DealStatusSelector;
+use crate::bus::info::MaintenanceStatus as MaintenanceStatusInfo;
+use crate::bus::info::PendingApproval as PendingApprovalInfo;
 use crate::bus::info::ProgressEvent;
+use crate::bus::info::{deal_price, MarketInfo, MarketMakerInfo, RemoteDealInfo};
+use crate::bus::info::{AddressBalance, DealTemplateInfo, QuotedDeal, RegistryQuery};
+use crate::bus::p2p::QuoteRequest;
 use crate::bus::AddressSecretKey;
 use crate::bus::DealStatus;
 use crate::bus::Failure;
+use crate::bus::FailureCode;
 use crate::bus::HealthCheckSelector;
 use crate::bus::OptionDetails;
 use crate::bus::Outcome;
+use crate::bus::PeerBan as PeerBanInfo;
+use crate::bus::SignedDeal;
 use crate::grpcd::runtime::farcaster::NetworkSelector;
 use crate::service::Endpoints;
 use crate::swapd::StateReport;
+use crate::syncerd::runtime::SyncerdTask;
 use crate::syncerd::Health;
 use crate::syncerd::SweepAddressAddendum;
 use crate::syncerd::SweepBitcoinAddress;
@@ -35,7 +44,7 @@ use farcaster_core::swap::{
     btcxmr::{Deal, DealParameters},
     SwapId,
 };
-use internet2::addr::InetSocketAddr;
+use internet2::addr::{InetSocketAddr, NodeAddr, NodeId};
 use internet2::session::LocalSession;
 use internet2::SendRecvMessage;
 use std::collections::HashMap;
@@ -49,11 +58,11 @@ use tokio::sync::oneshot::error::RecvError;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
-use crate::bus::{ctl::CtlMsg, info::InfoMsg, info::SwapInfo};
+use crate::bus::{ctl, ctl::CtlMsg, info::InfoMsg, info::SwapInfo};
 use crate::bus::{BusMsg, ServiceBus};
-use crate::{CtlServer, Error, Service, ServiceConfig, ServiceId};
+use crate::{CtlServer, Error, LogStyle, Service, ServiceConfig, ServiceId};
 use internet2::{zeromq::ZmqSocketType, TypedEnum};
-use microservices::esb;
+use microservices::esb::{self, Handler};
 use microservices::ZMQ_CONTEXT;
 
 use farcaster::farcaster_server::{Farcaster, FarcasterServer};
@@ -66,6 +75,16 @@ pub mod farcaster {
     tonic::include_proto!("farcaster");
 }
 
+impl From<MaintenanceStatusInfo> for farcaster::MaintenanceStatus {
+    fn from(status: MaintenanceStatusInfo) -> farcaster::MaintenanceStatus {
+        farcaster::MaintenanceStatus {
+            enabled: status.enabled,
+            draining_swaps: status.draining_swaps,
+            safe_to_shutdown: status.safe_to_shutdown,
+        }
+    }
+}
+
 impl From<TradeRole> for farcaster::TradeRole {
     fn from(t: TradeRole) -> farcaster::TradeRole {
         match t {
@@ -177,6 +196,29 @@ impl From<Outcome> for farcaster::Outcome {
             Outcome::FailureRefund => farcaster::Outcome::FailureRefund,
             Outcome::FailurePunish => farcaster::Outcome::FailurePunish,
             Outcome::FailureAbort => farcaster::Outcome::FailureAbort,
+            Outcome::FailureTimeout => farcaster::Outcome::FailureTimeout,
+        }
+    }
+}
+
+impl From<FailureCode> for farcaster::FailureCode {
+    fn from(t: FailureCode) -> farcaster::FailureCode {
+        match t {
+            FailureCode::InvalidRequest => farcaster::FailureCode::InvalidRequest,
+            FailureCode::InvalidDeal => farcaster::FailureCode::InvalidDeal,
+            FailureCode::AmountOutOfRange => farcaster::FailureCode::AmountOutOfRange,
+            FailureCode::UnknownDeal => farcaster::FailureCode::UnknownDeal,
+            FailureCode::UnknownSwap => farcaster::FailureCode::UnknownSwap,
+            FailureCode::NotFound => farcaster::FailureCode::NotFound,
+            FailureCode::InvalidState => farcaster::FailureCode::InvalidState,
+            FailureCode::LimitReached => farcaster::FailureCode::LimitReached,
+            FailureCode::Unavailable => farcaster::FailureCode::Unavailable,
+            FailureCode::PeerUnreachable => farcaster::FailureCode::PeerUnreachable,
+            FailureCode::SyncerUnhealthy => farcaster::FailureCode::SyncerUnhealthy,
+            FailureCode::WalletLocked => farcaster::FailureCode::WalletLocked,
+            FailureCode::InsufficientFunds => farcaster::FailureCode::InsufficientFunds,
+            FailureCode::TargetServiceNotFound => farcaster::FailureCode::TargetServiceNotFound,
+            FailureCode::Unknown => farcaster::FailureCode::Unknown,
         }
     }
 }
@@ -200,6 +242,7 @@ impl From<Deal> for DeserializedDeal {
                 .into(),
             node_id: deal.node_id.to_string(),
             peer_address: deal.peer_address.to_string(),
+            price: deal_price(&deal),
         }
     }
 }
@@ -215,11 +258,89 @@ impl From<DealStatus> for farcaster::DealStatus {
                 Outcome::FailureAbort => farcaster::DealStatus::DealEndedFailureAbort,
                 Outcome::FailurePunish => farcaster::DealStatus::DealEndedFailurePunish,
                 Outcome::FailureRefund => farcaster::DealStatus::DealEndedFailureRefund,
+                Outcome::FailureTimeout => farcaster::DealStatus::DealEndedFailureTimeout,
             },
         }
     }
 }
 
+impl From<RemoteDealInfo> for RemoteDeal {
+    fn from(remote: RemoteDealInfo) -> RemoteDeal {
+        RemoteDeal {
+            price: deal_price(&remote.deal),
+            since: remote.since,
+            serialized_deal: remote.serialized_deal,
+            deserialized_deal: Some(remote.deal.into()),
+        }
+    }
+}
+
+impl From<MarketInfo> for Market {
+    fn from(market: MarketInfo) -> Market {
+        Market {
+            network: farcaster::Network::from(market.network).into(),
+            maker_role: farcaster::SwapRole::from(market.maker_role).into(),
+            status: market.status,
+            price: market.price,
+            target_open_deals: market.target_open_deals,
+            open_deals: market
+                .open_deals
+                .iter()
+                .map(|deal_id| deal_id.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl From<DealTemplateInfo> for farcaster::DealTemplate {
+    fn from(template: DealTemplateInfo) -> farcaster::DealTemplate {
+        farcaster::DealTemplate {
+            template_id: template.template_id.to_string(),
+            network: farcaster::Network::from(template.network).into(),
+            maker_role: farcaster::SwapRole::from(template.maker_role).into(),
+            min_amount: template.min_amount.as_sat(),
+            max_amount: template.max_amount.as_sat(),
+            price: template.price,
+            cancel_timelock: template.cancel_timelock,
+            punish_timelock: template.punish_timelock,
+            available_inventory: template.available.map(|available| {
+                farcaster::deal_template::AvailableInventory::Available(available.as_sat())
+            }),
+            issued_deals: template.issued_deals,
+        }
+    }
+}
+
+impl From<PeerBanInfo> for farcaster::PeerBan {
+    fn from(ban: PeerBanInfo) -> farcaster::PeerBan {
+        farcaster::PeerBan {
+            node_id: ban.node_id.to_string(),
+            reason: ban.reason,
+            since: ban.since,
+        }
+    }
+}
+
+impl From<PendingApprovalInfo> for farcaster::PendingApproval {
+    fn from(approval: PendingApprovalInfo) -> farcaster::PendingApproval {
+        farcaster::PendingApproval {
+            swap_id: approval.swap_id.to_string(),
+            deal_id: approval.deal_id.to_string(),
+            taker: approval.taker.to_string(),
+            since: approval.since,
+        }
+    }
+}
+
+impl From<SyncerdTask> for SyncerTask {
+    fn from(task: SyncerdTask) -> SyncerTask {
+        SyncerTask {
+            task: task.task.to_string(),
+            source: task.source.to_string(),
+        }
+    }
+}
+
 impl DealInfo {
     fn new(deal: Deal, local_trade_role: TradeRole, status: DealStatus) -> DealInfo {
         DealInfo {
@@ -227,6 +348,7 @@ impl DealInfo {
             deserialized_deal: Some(deal.into()),
             local_trade_role: farcaster::TradeRole::from(local_trade_role).into(),
             deal_status: farcaster::DealStatus::from(status).into(),
+            expiry: 0,
         }
     }
 }
@@ -344,12 +466,43 @@ impl FarcasterService {
 fn process_error_response<T>(msg: Result<BusMsg, RecvError>) -> Result<GrpcResponse<T>, Status> {
     match msg {
         Err(error) => Err(Status::internal(error.to_string())),
-        Ok(BusMsg::Ctl(CtlMsg::Failure(Failure { info, .. }))) => Err(Status::internal(info)),
-        Ok(BusMsg::Info(InfoMsg::Failure(Failure { info, .. }))) => Err(Status::internal(info)),
+        Ok(BusMsg::Ctl(CtlMsg::Failure(failure))) => Err(failure_status(failure)),
+        Ok(BusMsg::Info(InfoMsg::Failure(failure))) => Err(failure_status(failure)),
         _ => Err(Status::internal("received unexpected internal response")),
     }
 }
 
+/// The status of a failure, its code and info are attached as FailureDetails for the clients to
+/// tell the failures apart
+fn failure_status(failure: Failure) -> Status {
+    let code = match failure.code {
+        FailureCode::InvalidRequest | FailureCode::InvalidDeal => tonic::Code::InvalidArgument,
+        FailureCode::AmountOutOfRange => tonic::Code::OutOfRange,
+        FailureCode::UnknownDeal
+        | FailureCode::UnknownSwap
+        | FailureCode::NotFound
+        | FailureCode::TargetServiceNotFound => tonic::Code::NotFound,
+        FailureCode::InvalidState | FailureCode::InsufficientFunds => {
+            tonic::Code::FailedPrecondition
+        }
+        FailureCode::LimitReached => tonic::Code::ResourceExhausted,
+        FailureCode::Unavailable | FailureCode::PeerUnreachable | FailureCode::SyncerUnhealthy => {
+            tonic::Code::Unavailable
+        }
+        FailureCode::WalletLocked => tonic::Code::PermissionDenied,
+        FailureCode::Unknown => tonic::Code::Internal,
+    };
+    let details = FailureDetails {
+        code: farcaster::FailureCode::from(failure.code).into(),
+        info: failure.info.clone(),
+    };
+    Status::with_details(
+        code,
+        failure.info,
+        prost::bytes::Bytes::from(prost::Message::encode_to_vec(&details)),
+    )
+}
+
 #[tonic::async_trait]
 impl Farcaster for FarcasterService {
     async fn info(
@@ -377,6 +530,12 @@ impl Farcaster for FarcasterService {
                     peers: info.peers.iter().map(|peer| peer.to_string()).collect(),
                     swaps: info.swaps.iter().map(|swap| swap.to_string()).collect(),
                     deals: info.deals.iter().map(|deal| deal.to_string()).collect(),
+                    maintenance: Some(info.maintenance.into()),
+                    syncer_alerts: info
+                        .syncer_alerts
+                        .iter()
+                        .map(|alert| alert.to_string())
+                        .collect(),
                 };
                 Ok(GrpcResponse::new(reply))
             }
@@ -495,7 +654,10 @@ impl Farcaster for FarcasterService {
                             network_selector == NetworkSelector::AllNetworks
                                 || Some(d.deal.parameters.network) == network_selector.into()
                         })
-                        .map(|d| DealInfo::new(d.deal, d.local_trade_role, d.status))
+                        .map(|d| DealInfo {
+                            expiry: d.expiry.unwrap_or_default(),
+                            ..DealInfo::new(d.deal, d.local_trade_role, d.status)
+                        })
                         .collect(),
                 };
                 Ok(GrpcResponse::new(reply))
@@ -509,7 +671,10 @@ impl Farcaster for FarcasterService {
                             network_selector == NetworkSelector::AllNetworks
                                 || Some(d.deal.parameters.network) == network_selector.into()
                         })
-                        .map(|d| DealInfo::new(d.deal, d.local_trade_role, d.status))
+                        .map(|d| DealInfo {
+                            expiry: d.expiry.unwrap_or_default(),
+                            ..DealInfo::new(d.deal, d.local_trade_role, d.status)
+                        })
                         .collect(),
                 };
                 Ok(GrpcResponse::new(reply))
@@ -520,6 +685,114 @@ impl Farcaster for FarcasterService {
         }
     }
 
+    async fn list_remote_deals(
+        &self,
+        request: GrpcRequest<ListRemoteDealsRequest>,
+    ) -> Result<GrpcResponse<ListRemoteDealsResponse>, Status> {
+        debug!("Received a grpc request: {:?}", request);
+        let ListRemoteDealsRequest {
+            id,
+            network_selector: grpc_network_selector,
+        } = request.into_inner();
+        let network_selector: NetworkSelector =
+            farcaster::NetworkSelector::from_i32(grpc_network_selector)
+                .ok_or_else(|| Status::invalid_argument("network_selector"))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::ListRemoteDeals,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::RemoteDealList(mut deals))) => {
+                let reply = ListRemoteDealsResponse {
+                    id,
+                    deals: deals
+                        .drain(..)
+                        .filter(|d| {
+                            network_selector == NetworkSelector::AllNetworks
+                                || Some(d.deal.parameters.network) == network_selector.into()
+                        })
+                        .map(RemoteDeal::from)
+                        .collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            Err(error) => Err(Status::internal(format!("{}", error))),
+            Ok(BusMsg::Ctl(CtlMsg::Failure(Failure { info, .. }))) => Err(Status::internal(info)),
+            _ => Err(Status::invalid_argument("received invalid response")),
+        }
+    }
+
+    async fn market_maker(
+        &self,
+        request: GrpcRequest<MarketMakerRequest>,
+    ) -> Result<GrpcResponse<MarketMakerResponse>, Status> {
+        debug!("Received a grpc request: {:?}", request);
+        let MarketMakerRequest { id } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::GetMarketMaker,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::MarketMakerInfo(MarketMakerInfo {
+                max_concurrent_swaps,
+                running_swaps,
+                btc_inventory,
+                btc_used,
+                xmr_inventory,
+                xmr_used,
+                markets,
+            }))) => {
+                let reply = MarketMakerResponse {
+                    id,
+                    concurrency_limit: max_concurrent_swaps
+                        .map(market_maker_response::ConcurrencyLimit::MaxConcurrentSwaps),
+                    running_swaps,
+                    bitcoin_inventory: btc_inventory.map(|inventory| {
+                        market_maker_response::BitcoinInventory::BtcInventory(inventory.as_sat())
+                    }),
+                    btc_used: btc_used.as_sat(),
+                    monero_inventory: xmr_inventory.map(|inventory| {
+                        market_maker_response::MoneroInventory::XmrInventory(inventory.as_pico())
+                    }),
+                    xmr_used: xmr_used.as_pico(),
+                    markets: markets.into_iter().map(Market::from).collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            Err(error) => Err(Status::internal(format!("{}", error))),
+            Ok(BusMsg::Ctl(CtlMsg::Failure(Failure { info, .. }))) => Err(Status::internal(info)),
+            _ => Err(Status::invalid_argument("received invalid response")),
+        }
+    }
+
+    async fn maintenance(
+        &self,
+        request: GrpcRequest<MaintenanceRequest>,
+    ) -> Result<GrpcResponse<MaintenanceResponse>, Status> {
+        debug!("Received a grpc maintenance request: {:?}", request);
+        let MaintenanceRequest { id, enable } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::SetMaintenance(enable),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::MaintenanceStatus(status))) => {
+                let reply = MaintenanceResponse {
+                    id,
+                    maintenance: Some(status.into()),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
     async fn deal_info(
         &self,
         request: GrpcRequest<DealInfoRequest>,
@@ -736,6 +1009,7 @@ impl Farcaster for FarcasterService {
             maker_role: grpc_swap_role,
             public_ip_addr: str_public_ip_addr,
             public_port,
+            lifetime,
         } = request.into_inner();
 
         let network: Network = farcaster::Network::from_i32(grpc_network)
@@ -786,6 +1060,8 @@ impl Farcaster for FarcasterService {
             public_addr,
             arbitrating_addr,
             accordant_addr,
+            approval: DealApproval::Auto,
+            lifetime: if lifetime == 0 { None } else { Some(lifetime) },
         };
 
         let oneshot_rx = self
@@ -1083,6 +1359,8 @@ impl Farcaster for FarcasterService {
             id,
             source_address: str_source_address,
             destination_address: str_destination_address,
+            minimum_balance,
+            monero_from_height,
         } = request.into_inner();
 
         if let (Ok(source_address), Ok(destination_address)) = (
@@ -1137,6 +1415,11 @@ impl Farcaster for FarcasterService {
                     secret_key_info,
                     address: _,
                 }))) => {
+                    // Scan from the creation of the address unless requested otherwise
+                    let from_height = monero_from_height.map_or(
+                        secret_key_info.creation_height,
+                        |sweep_address_request::MoneroFromHeight::FromHeight(height)| height,
+                    );
                     let oneshot_rx = self
                         .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
                             request: CtlMsg::SweepAddress(SweepAddressAddendum::Monero(
@@ -1144,8 +1427,8 @@ impl Farcaster for FarcasterService {
                                     source_spend_key: secret_key_info.spend,
                                     source_view_key: secret_key_info.view,
                                     destination_address,
-                                    minimum_balance: monero::Amount::from_pico(0),
-                                    from_height: Some(secret_key_info.creation_height),
+                                    minimum_balance: monero::Amount::from_pico(minimum_balance),
+                                    from_height: Some(from_height),
                                 },
                             )),
                             service_id: ServiceId::Farcasterd,
@@ -1182,12 +1465,25 @@ impl Farcaster for FarcasterService {
             .map_err(|_| Status::invalid_argument("arbitrating address"))?;
         let monero_address = monero::Address::from_str(&str_monero_address)
             .map_err(|_| Status::invalid_argument("accordant_address"))?;
-        let deal = Deal::from_str(&str_deal).map_err(|_| Status::invalid_argument("deal"))?;
+        // Signed deals are only taken if signed by their maker and not expired yet
+        let (deal, origin) = match SignedDeal::from_str(&str_deal) {
+            Ok(signed_deal) => {
+                signed_deal
+                    .verify()
+                    .map_err(|err| Status::invalid_argument(format!("signed deal: {}", err)))?;
+                (signed_deal.deal.clone(), DealOrigin::Signed(signed_deal))
+            }
+            Err(_) => (
+                Deal::from_str(&str_deal).map_err(|_| Status::invalid_argument("deal"))?,
+                DealOrigin::Encoded,
+            ),
+        };
 
         let oneshot_rx = self
             .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
                 request: CtlMsg::TakeDeal(PubDeal {
                     deal,
+                    origin,
                     bitcoin_address,
                     monero_address,
                 }),
@@ -1203,6 +1499,670 @@ impl Farcaster for FarcasterService {
             res => process_error_response(res),
         }
     }
+
+    async fn list_swaps(
+        &self,
+        request: GrpcRequest<ListSwapsRequest>,
+    ) -> Result<GrpcResponse<ListSwapsResponse>, Status> {
+        debug!("Received a grpc list swaps request: {:?}", request);
+        let ListSwapsRequest { id } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::ListSwaps,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::SwapList(swaps))) => {
+                let reply = ListSwapsResponse {
+                    id,
+                    swaps: swaps.iter().map(|swap_id| swap_id.to_string()).collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn list_tasks(
+        &self,
+        request: GrpcRequest<ListTasksRequest>,
+    ) -> Result<GrpcResponse<ListTasksResponse>, Status> {
+        debug!("Received a grpc list tasks request: {:?}", request);
+        let ListTasksRequest {
+            id,
+            blockchain: grpc_blockchain,
+            network: grpc_network,
+        } = request.into_inner();
+        let blockchain: Blockchain = farcaster::Blockchain::from_i32(grpc_blockchain)
+            .ok_or_else(|| Status::invalid_argument("blockchain"))?
+            .into();
+        let network: Network = farcaster::Network::from_i32(grpc_network)
+            .ok_or_else(|| Status::invalid_argument("network"))?
+            .into();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::ListTasks,
+                service_id: ServiceId::Syncer(blockchain, network),
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::TaskList(mut tasks))) => {
+                let reply = ListTasksResponse {
+                    id,
+                    tasks: tasks.drain(..).map(SyncerTask::from).collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn list_listens(
+        &self,
+        request: GrpcRequest<ListListensRequest>,
+    ) -> Result<GrpcResponse<ListListensResponse>, Status> {
+        debug!("Received a grpc list listens request: {:?}", request);
+        let ListListensRequest { id } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::ListListens,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::ListenList(mut listens))) => {
+                let reply = ListListensResponse {
+                    id,
+                    listens: listens.drain(..).collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn query_registry(
+        &self,
+        request: GrpcRequest<QueryRegistryRequest>,
+    ) -> Result<GrpcResponse<QueryRegistryResponse>, Status> {
+        debug!("Received a grpc query registry request: {:?}", request);
+        let QueryRegistryRequest {
+            id,
+            network: grpc_network,
+            min_arbitrating_amount,
+            max_arbitrating_amount,
+            min_deal_price,
+            max_deal_price,
+        } = request.into_inner();
+        let network: Network = farcaster::Network::from_i32(grpc_network)
+            .ok_or_else(|| Status::invalid_argument("network"))?
+            .into();
+        let query = RegistryQuery {
+            network,
+            min_amount: min_arbitrating_amount
+                .map(|query_registry_request::MinArbitratingAmount::MinAmount(amount)| amount),
+            max_amount: max_arbitrating_amount
+                .map(|query_registry_request::MaxArbitratingAmount::MaxAmount(amount)| amount),
+            min_price: min_deal_price
+                .map(|query_registry_request::MinDealPrice::MinPrice(price)| price),
+            max_price: max_deal_price
+                .map(|query_registry_request::MaxDealPrice::MaxPrice(price)| price),
+        };
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::QueryDealRegistry(query),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::RegistryDealList(mut deals))) => {
+                let reply = QueryRegistryResponse {
+                    id,
+                    deals: deals.drain(..).map(RemoteDeal::from).collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn add_deal_template(
+        &self,
+        request: GrpcRequest<AddDealTemplateRequest>,
+    ) -> Result<GrpcResponse<AddDealTemplateResponse>, Status> {
+        debug!("Received a grpc add deal template request: {:?}", request);
+        let AddDealTemplateRequest {
+            id,
+            network: grpc_network,
+            arbitrating_blockchain: grpc_arb_blockchain,
+            accordant_blockchain: grpc_acc_blockchain,
+            min_arbitrating_amount: int_min_arb_amount,
+            max_arbitrating_amount: int_max_arb_amount,
+            price,
+            template_inventory,
+            arbitrating_addr: str_arb_addr,
+            accordant_addr: str_acc_addr,
+            cancel_timelock: int_cancel_timelock,
+            punish_timelock: int_punish_timelock,
+            min_cancel,
+            max_cancel,
+            min_punish,
+            max_punish,
+            fee_strategy: str_fee_strategy,
+            maker_role: grpc_swap_role,
+            public_ip_addr: str_public_ip_addr,
+            public_port,
+        } = request.into_inner();
+
+        let network: Network = farcaster::Network::from_i32(grpc_network)
+            .ok_or_else(|| Status::invalid_argument("network"))?
+            .into();
+        let arbitrating_blockchain: Blockchain =
+            farcaster::Blockchain::from_i32(grpc_arb_blockchain)
+                .ok_or_else(|| Status::invalid_argument("arbitrating blockchain"))?
+                .into();
+        let accordant_blockchain: Blockchain = farcaster::Blockchain::from_i32(grpc_acc_blockchain)
+            .ok_or_else(|| Status::invalid_argument("accordant blockchain"))?
+            .into();
+        if price == 0 {
+            return Err(Status::invalid_argument("price must be positive"));
+        }
+        let max_arbitrating_amount = bitcoin::Amount::from_sat(int_max_arb_amount);
+        // accordant amount of the largest deal at the price, in piconero
+        let accordant_amount = monero::Amount::from_pico(
+            (int_max_arb_amount as u128 * 1_000_000_000_000 / price as u128) as u64,
+        );
+        let arbitrating_addr = bitcoin::Address::from_str(&str_arb_addr)
+            .map_err(|_| Status::invalid_argument("arbitrating address"))?;
+        let accordant_addr = monero::Address::from_str(&str_acc_addr)
+            .map_err(|_| Status::invalid_argument("accordant_address"))?;
+        let maker_role: SwapRole = farcaster::SwapRole::from_i32(grpc_swap_role)
+            .ok_or_else(|| Status::invalid_argument("maker role"))?
+            .into();
+        let public_ip_addr = IpAddr::from_str(&str_public_ip_addr)
+            .map_err(|_| Status::invalid_argument("public ip address"))?;
+        let fee_strategy: FeeStrategy<SatPerKvB> = FeeStrategy::from_str(&str_fee_strategy)
+            .map_err(|_| {
+                Status::invalid_argument(
+                    "fee is required to be formated as a fixed value, e.g. \"1000 satoshi/kvB\"",
+                )
+            })?;
+
+        let template_id = farcaster_core::Uuid::new();
+        let template = ctl::DealTemplate {
+            id: template_id,
+            deal_parameters: DealParameters {
+                uuid: Uuid::new_v4().into(),
+                network,
+                arbitrating_blockchain,
+                accordant_blockchain,
+                arbitrating_amount: max_arbitrating_amount,
+                accordant_amount,
+                cancel_timelock: CSVTimelock::new(int_cancel_timelock),
+                punish_timelock: CSVTimelock::new(int_punish_timelock),
+                fee_strategy,
+                maker_role,
+            },
+            min_arbitrating_amount: bitcoin::Amount::from_sat(int_min_arb_amount),
+            min_cancel_timelock: min_cancel
+                .map(|add_deal_template_request::MinCancel::MinCancelTimelock(timelock)| timelock)
+                .unwrap_or(int_cancel_timelock),
+            max_cancel_timelock: max_cancel
+                .map(|add_deal_template_request::MaxCancel::MaxCancelTimelock(timelock)| timelock)
+                .unwrap_or(int_cancel_timelock),
+            min_punish_timelock: min_punish
+                .map(|add_deal_template_request::MinPunish::MinPunishTimelock(timelock)| timelock)
+                .unwrap_or(int_punish_timelock),
+            max_punish_timelock: max_punish
+                .map(|add_deal_template_request::MaxPunish::MaxPunishTimelock(timelock)| timelock)
+                .unwrap_or(int_punish_timelock),
+            inventory: template_inventory.map(
+                |add_deal_template_request::TemplateInventory::Inventory(inventory)| {
+                    bitcoin::Amount::from_sat(inventory)
+                },
+            ),
+            public_addr: InetSocketAddr::socket(public_ip_addr, public_port as u16),
+            arbitrating_addr,
+            accordant_addr,
+        };
+
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::AddDealTemplate(template),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = AddDealTemplateResponse {
+                    id,
+                    template_id: template_id.to_string(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn list_deal_templates(
+        &self,
+        request: GrpcRequest<ListDealTemplatesRequest>,
+    ) -> Result<GrpcResponse<ListDealTemplatesResponse>, Status> {
+        debug!("Received a grpc list deal templates request: {:?}", request);
+        let ListDealTemplatesRequest { id } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::ListDealTemplates,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::DealTemplateList(mut templates))) => {
+                let reply = ListDealTemplatesResponse {
+                    id,
+                    templates: templates
+                        .drain(..)
+                        .map(farcaster::DealTemplate::from)
+                        .collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn remove_deal_template(
+        &self,
+        request: GrpcRequest<RemoveDealTemplateRequest>,
+    ) -> Result<GrpcResponse<RemoveDealTemplateResponse>, Status> {
+        debug!(
+            "Received a grpc remove deal template request: {:?}",
+            request
+        );
+        let RemoveDealTemplateRequest {
+            id,
+            template_id: str_template_id,
+        } = request.into_inner();
+        let template_id = farcaster_core::Uuid::from_str(&str_template_id)
+            .map_err(|_| Status::invalid_argument("template id"))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::RemoveDealTemplate(template_id),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = RemoveDealTemplateResponse { id };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn request_quote(
+        &self,
+        request: GrpcRequest<RequestQuoteRequest>,
+    ) -> Result<GrpcResponse<RequestQuoteResponse>, Status> {
+        debug!("Received a grpc request quote request: {:?}", request);
+        let RequestQuoteRequest {
+            id,
+            maker: str_maker,
+            network: grpc_network,
+            arbitrating_amount,
+            quote_template,
+            quote_cancel_timelock,
+            quote_punish_timelock,
+        } = request.into_inner();
+        let maker =
+            NodeAddr::from_str(&str_maker).map_err(|_| Status::invalid_argument("maker"))?;
+        let network: Network = farcaster::Network::from_i32(grpc_network)
+            .ok_or_else(|| Status::invalid_argument("network"))?
+            .into();
+        let template_id = quote_template
+            .map(
+                |request_quote_request::QuoteTemplate::TemplateId(str_template_id)| {
+                    farcaster_core::Uuid::from_str(&str_template_id)
+                        .map_err(|_| Status::invalid_argument("template id"))
+                },
+            )
+            .transpose()?;
+        let quote_request = QuoteRequest {
+            id: farcaster_core::Uuid::new(),
+            template_id,
+            network,
+            arbitrating_amount: bitcoin::Amount::from_sat(arbitrating_amount),
+            cancel_timelock: quote_cancel_timelock.map(
+                |request_quote_request::QuoteCancelTimelock::CancelTimelock(timelock)| timelock,
+            ),
+            punish_timelock: quote_punish_timelock.map(
+                |request_quote_request::QuotePunishTimelock::PunishTimelock(timelock)| timelock,
+            ),
+        };
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::RequestQuote(ctl::ProtoQuoteRequest {
+                    maker,
+                    request: quote_request,
+                }),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::QuotedDeal(QuotedDeal {
+                expiry,
+                viewable_deal,
+                ..
+            }))) => {
+                let reply = RequestQuoteResponse {
+                    id,
+                    deal: viewable_deal.deal,
+                    deserialized_deal: Some(viewable_deal.details.into()),
+                    expiry,
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn reference_price(
+        &self,
+        request: GrpcRequest<ReferencePriceRequest>,
+    ) -> Result<GrpcResponse<ReferencePriceResponse>, Status> {
+        debug!("Received a grpc reference price request: {:?}", request);
+        let ReferencePriceRequest { id } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::GetReferencePrice,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::ReferencePrice(price))) => {
+                let reply = ReferencePriceResponse {
+                    id,
+                    price: price.price.as_sat(),
+                    since: price.since,
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn ban(
+        &self,
+        request: GrpcRequest<BanRequest>,
+    ) -> Result<GrpcResponse<BanResponse>, Status> {
+        debug!("Received a grpc ban request: {:?}", request);
+        let BanRequest {
+            id,
+            node_id: str_node_id,
+        } = request.into_inner();
+        let node_id =
+            NodeId::from_str(&str_node_id).map_err(|_| Status::invalid_argument("node id"))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::BanPeer(node_id),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = BanResponse { id };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn unban(
+        &self,
+        request: GrpcRequest<UnbanRequest>,
+    ) -> Result<GrpcResponse<UnbanResponse>, Status> {
+        debug!("Received a grpc unban request: {:?}", request);
+        let UnbanRequest {
+            id,
+            node_id: str_node_id,
+        } = request.into_inner();
+        let node_id =
+            NodeId::from_str(&str_node_id).map_err(|_| Status::invalid_argument("node id"))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::UnbanPeer(node_id),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = UnbanResponse { id };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn list_bans(
+        &self,
+        request: GrpcRequest<ListBansRequest>,
+    ) -> Result<GrpcResponse<ListBansResponse>, Status> {
+        debug!("Received a grpc list bans request: {:?}", request);
+        let ListBansRequest { id } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::ListBans,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::PeerBanList(mut bans))) => {
+                let reply = ListBansResponse {
+                    id,
+                    bans: bans.drain(..).map(farcaster::PeerBan::from).collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn list_approvals(
+        &self,
+        request: GrpcRequest<ListApprovalsRequest>,
+    ) -> Result<GrpcResponse<ListApprovalsResponse>, Status> {
+        debug!("Received a grpc list approvals request: {:?}", request);
+        let ListApprovalsRequest { id } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::ListApprovals,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::ApprovalList(mut approvals))) => {
+                let reply = ListApprovalsResponse {
+                    id,
+                    approvals: approvals
+                        .drain(..)
+                        .map(farcaster::PendingApproval::from)
+                        .collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn accept_taker(
+        &self,
+        request: GrpcRequest<AcceptTakerRequest>,
+    ) -> Result<GrpcResponse<AcceptTakerResponse>, Status> {
+        debug!("Received a grpc accept taker request: {:?}", request);
+        let AcceptTakerRequest {
+            id,
+            swap_id: str_swap_id,
+        } = request.into_inner();
+        let swap_id =
+            SwapId::from_str(&str_swap_id).map_err(|_| Status::invalid_argument("swap id"))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::AcceptTaker(swap_id),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = AcceptTakerResponse { id };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn reject_taker(
+        &self,
+        request: GrpcRequest<RejectTakerRequest>,
+    ) -> Result<GrpcResponse<RejectTakerResponse>, Status> {
+        debug!("Received a grpc reject taker request: {:?}", request);
+        let RejectTakerRequest {
+            id,
+            swap_id: str_swap_id,
+        } = request.into_inner();
+        let swap_id =
+            SwapId::from_str(&str_swap_id).map_err(|_| Status::invalid_argument("swap id"))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::RejectTaker(swap_id),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = RejectTakerResponse { id };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn watchtower_register(
+        &self,
+        request: GrpcRequest<WatchtowerRegisterRequest>,
+    ) -> Result<GrpcResponse<WatchtowerRegisterResponse>, Status> {
+        debug!("Received a grpc watchtower register request: {:?}", request);
+        let WatchtowerRegisterRequest {
+            id,
+            swap_id: str_swap_id,
+        } = request.into_inner();
+        let swap_id =
+            SwapId::from_str(&str_swap_id).map_err(|_| Status::invalid_argument("swap id"))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::RegisterWithWatchtowers(swap_id),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = WatchtowerRegisterResponse { id };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn watchtower_revoke(
+        &self,
+        request: GrpcRequest<WatchtowerRevokeRequest>,
+    ) -> Result<GrpcResponse<WatchtowerRevokeResponse>, Status> {
+        debug!("Received a grpc watchtower revoke request: {:?}", request);
+        let WatchtowerRevokeRequest {
+            id,
+            swap_id: str_swap_id,
+        } = request.into_inner();
+        let swap_id =
+            SwapId::from_str(&str_swap_id).map_err(|_| Status::invalid_argument("swap id"))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::RevokeFromWatchtowers(swap_id),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = WatchtowerRevokeResponse { id };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn shutdown(
+        &self,
+        request: GrpcRequest<ShutdownRequest>,
+    ) -> Result<GrpcResponse<ShutdownResponse>, Status> {
+        debug!("Received a grpc shutdown request: {:?}", request);
+        let ShutdownRequest { id, force } = request.into_inner();
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                request: CtlMsg::Shutdown(force),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Ctl(CtlMsg::Success(_))) => {
+                let reply = ShutdownResponse { id };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn get_balance(
+        &self,
+        request: GrpcRequest<GetBalanceRequest>,
+    ) -> Result<GrpcResponse<GetBalanceResponse>, Status> {
+        debug!("Received a grpc get balance request: {:?}", request);
+        let GetBalanceRequest {
+            id,
+            address: str_address,
+        } = request.into_inner();
+        let address = Address::from_str(&str_address)
+            .map_err(|_| Status::invalid_argument("address malformed".to_string()))?;
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                service_id: ServiceId::Database,
+                request: InfoMsg::GetAddressSecretKey(address),
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::AddressSecretKey(address_secret_key))) => {
+                let oneshot_rx = self
+                    .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
+                        request: CtlMsg::GetBalance(address_secret_key),
+                        service_id: ServiceId::Farcasterd,
+                    }))
+                    .await?;
+                match oneshot_rx.await {
+                    Ok(BusMsg::Info(InfoMsg::AddressBalance(AddressBalance {
+                        address,
+                        balance,
+                    }))) => {
+                        let reply = GetBalanceResponse {
+                            id,
+                            address: address.to_string(),
+                            balance,
+                        };
+                        Ok(GrpcResponse::new(reply))
+                    }
+                    res => process_error_response(res),
+                }
+            }
+            res => process_error_response(res),
+        }
+    }
 }
 
 pub struct GrpcServer {
@@ -1440,7 +2400,7 @@ impl esb::Handler<ServiceBus> for Runtime {
 impl Runtime {
     fn handle_ctl(
         &mut self,
-        _endpoints: &mut Endpoints,
+        endpoints: &mut Endpoints,
         source: ServiceId,
         request: CtlMsg,
     ) -> Result<(), Error> {
@@ -1449,6 +2409,17 @@ impl Runtime {
                 debug!("Received Hello from {}", source);
             }
 
+            CtlMsg::Terminate if source == ServiceId::Farcasterd => {
+                info!("Terminating {}", self.identity().label());
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::Ctl(CtlMsg::Terminated),
+                )?;
+                std::process::exit(0);
+            }
+
             req => {
                 if let ServiceId::GrpcdClient(id) = source {
                     self.tx_response
@@ -55,7 +55,11 @@ pub mod farcasterd;
 #[cfg(feature = "node")]
 pub mod grpcd;
 #[cfg(feature = "node")]
+pub mod oracle;
+#[cfg(feature = "node")]
 pub mod peerd;
+#[cfg(feature = "node")]
+pub mod registry;
 #[cfg(feature = "_rpc")]
 mod service;
 #[cfg(feature = "node")]
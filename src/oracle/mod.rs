@@ -0,0 +1,245 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Price oracle: pluggable sources of the reference price of one XMR in BTC, used to validate
+//! the price of the taken deals and to price the maker deals relatively to the market.
+//!
+//! Two sources are available:
+//!  - [`HttpPriceSource`] fetches a JSON document, e.g. the ticker of an exchange, and reads the
+//!    price at a JSON pointer,
+//!  - [`FilePriceSource`] reads the price from a local file, meant for tests.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use serde_json::Value;
+
+use crate::config::PriceOracleConfig;
+use crate::Error;
+
+const PRICE_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A source of the price of one XMR in BTC
+pub trait PriceSource: Send {
+    /// Fetch the current price, blocking: must not be called from the runtime thread
+    fn fetch(&self) -> Result<bitcoin::Amount, Error>;
+}
+
+/// Builds the price source configured, the HTTP source if an url is set and the file source
+/// otherwise
+pub fn price_source(config: &PriceOracleConfig) -> Result<Box<dyn PriceSource>, Error> {
+    match (&config.url, &config.file) {
+        (Some(url), _) => Ok(Box::new(HttpPriceSource::new(
+            url,
+            config.json_pointer.clone(),
+        )?)),
+        (None, Some(path)) => Ok(Box::new(FilePriceSource::new(path))),
+        (None, None) => Err(Error::PriceOracle(
+            "either an url or a file is required as price source".to_string(),
+        )),
+    }
+}
+
+/// Parses a price of one XMR in BTC, either as a number of BTC or as a bitcoin amount with its
+/// denomination, e.g. `0.0065` or `0.0065 btc`
+pub fn parse_price(s: &str) -> Result<bitcoin::Amount, Error> {
+    let s = s.trim();
+    let price = match f64::from_str(s) {
+        Ok(btc) => bitcoin::Amount::from_btc(btc),
+        Err(_) => bitcoin::Amount::from_str(s),
+    }
+    .map_err(|err| Error::PriceOracle(format!("invalid price {}: {}", s, err)))?;
+    if price.as_sat() == 0 {
+        Err(Error::PriceOracle(format!("invalid price {}", s)))
+    } else {
+        Ok(price)
+    }
+}
+
+/// Fetches the price from a JSON document served over HTTP
+pub struct HttpPriceSource {
+    url: String,
+    json_pointer: Option<String>,
+    client: Client,
+}
+
+impl HttpPriceSource {
+    pub fn new(url: &str, json_pointer: Option<String>) -> Result<Self, Error> {
+        let client = Client::builder()
+            .timeout(PRICE_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| Error::PriceOracle(err.to_string()))?;
+        Ok(HttpPriceSource {
+            url: url.to_string(),
+            json_pointer,
+            client,
+        })
+    }
+}
+
+impl PriceSource for HttpPriceSource {
+    fn fetch(&self) -> Result<bitcoin::Amount, Error> {
+        let res = self
+            .client
+            .get(&self.url)
+            .send()
+            .map_err(|err| Error::PriceOracle(err.to_string()))?;
+        if !res.status().is_success() {
+            return Err(Error::PriceOracle(format!(
+                "{} answered {}",
+                self.url,
+                res.status()
+            )));
+        }
+        let document: Value = res
+            .json()
+            .map_err(|err| Error::PriceOracle(err.to_string()))?;
+        let value = match &self.json_pointer {
+            Some(pointer) => document.pointer(pointer).ok_or_else(|| {
+                Error::PriceOracle(format!("no value at {} in the answer", pointer))
+            })?,
+            None => &document,
+        };
+        match value {
+            Value::Number(number) => parse_price(&number.to_string()),
+            Value::String(price) => parse_price(price),
+            _ => Err(Error::PriceOracle(format!("invalid price {}", value))),
+        }
+    }
+}
+
+/// Reads the price from a local file, the file can be updated while the node runs
+pub struct FilePriceSource {
+    path: String,
+}
+
+impl FilePriceSource {
+    pub fn new(path: &str) -> Self {
+        FilePriceSource {
+            path: shellexpand::tilde(path).to_string(),
+        }
+    }
+}
+
+impl PriceSource for FilePriceSource {
+    fn fetch(&self) -> Result<bitcoin::Amount, Error> {
+        let price = std::fs::read_to_string(&self.path)
+            .map_err(|err| Error::PriceOracle(format!("cannot read {}: {}", self.path, err)))?;
+        parse_price(&price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, FarcasterdConfig};
+    use farcaster_core::bitcoin::fee::SatPerKvB;
+    use farcaster_core::bitcoin::timelock::CSVTimelock;
+    use farcaster_core::blockchain::{Blockchain, FeeStrategy, Network};
+    use farcaster_core::role::SwapRole;
+    use farcaster_core::swap::btcxmr::DealParameters;
+    use farcaster_core::Uuid;
+
+    // A price file unique to the test, removed by the test
+    fn price_file(price: &str) -> String {
+        let path = std::env::temp_dir().join(format!("farcaster-price-{}", Uuid::new()));
+        std::fs::write(&path, price).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn deal(btc: &str, xmr: &str) -> DealParameters {
+        DealParameters {
+            uuid: Uuid::new().into(),
+            network: Network::Testnet,
+            arbitrating_blockchain: Blockchain::Bitcoin,
+            accordant_blockchain: Blockchain::Monero,
+            arbitrating_amount: bitcoin::Amount::from_str(btc).unwrap(),
+            accordant_amount: monero::Amount::from_str(xmr).unwrap(),
+            cancel_timelock: CSVTimelock::new(4),
+            punish_timelock: CSVTimelock::new(5),
+            fee_strategy: FeeStrategy::Fixed(SatPerKvB::from_sat(1_000)),
+            maker_role: SwapRole::Bob,
+        }
+    }
+
+    #[test]
+    fn parse_prices() {
+        let price = bitcoin::Amount::from_sat(650_000);
+        assert_eq!(parse_price("0.0065").unwrap(), price);
+        assert_eq!(parse_price(" 0.0065\n").unwrap(), price);
+        assert_eq!(parse_price("0.0065 BTC").unwrap(), price);
+        assert_eq!(parse_price("650000 satoshi").unwrap(), price);
+        assert!(parse_price("0").is_err());
+        assert!(parse_price("0 BTC").is_err());
+        assert!(parse_price("-0.0065").is_err());
+        assert!(parse_price("price").is_err());
+    }
+
+    #[test]
+    fn file_price_source() {
+        let path = price_file("0.0065\n");
+        let source = FilePriceSource::new(&path);
+        assert_eq!(source.fetch().unwrap(), bitcoin::Amount::from_sat(650_000));
+        // the file is read again on each fetch
+        std::fs::write(&path, "0.007 BTC").unwrap();
+        assert_eq!(source.fetch().unwrap(), bitcoin::Amount::from_sat(700_000));
+        std::fs::write(&path, "0").unwrap();
+        assert!(source.fetch().is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(source.fetch().is_err());
+    }
+
+    #[test]
+    fn deal_price_deviation() {
+        let path = price_file("0.005");
+        let oracle = PriceOracleConfig {
+            url: None,
+            json_pointer: None,
+            file: Some(path.clone()),
+            refresh_interval: None,
+            max_deviation: Some(5.0),
+        };
+        let reference_price = price_source(&oracle).unwrap().fetch().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let config = Config {
+            farcasterd: Some(FarcasterdConfig {
+                price_oracle: Some(oracle),
+                ..Default::default()
+            }),
+            swap: None,
+            ..Default::default()
+        };
+        let btc_addr =
+            bitcoin::Address::from_str("tb1q4gj53tuew3e6u4a32kdtle2q72su8te39dpceq").unwrap();
+        let xmr_addr = monero::Address::from_str("55LTR8KniP4LQGJSPtbYDacR7dz8RBFnsfAKMaMuwUNYX6aQbBcovzDPyrQF9KXF9tVU6Xk3K8no1BywnJX6GvZX8yJsXvt").unwrap();
+        let validate = |deal: &DealParameters| {
+            config.validate_deal_parameters(deal, &btc_addr, &xmr_addr, Some(reference_price))
+        };
+
+        assert!(validate(&deal("0.01 BTC", "2 XMR")).is_ok());
+        // 4% above and below the reference price
+        assert!(validate(&deal("0.0104 BTC", "2 XMR")).is_ok());
+        assert!(validate(&deal("0.0096 BTC", "2 XMR")).is_ok());
+        // 6% above and below the reference price
+        for deal in [deal("0.0106 BTC", "2 XMR"), deal("0.0094 BTC", "2 XMR")] {
+            match validate(&deal) {
+                Err(Error::InvalidDeal(reason)) => {
+                    assert!(
+                        reason.contains("6.00% off the reference price"),
+                        "{}",
+                        reason
+                    )
+                }
+                res => panic!("expected the deal price to be rejected, got {:?}", res),
+            }
+        }
+        // no reference price, no price validation
+        assert!(config
+            .validate_deal_parameters(&deal("0.0106 BTC", "2 XMR"), &btc_addr, &xmr_addr, None)
+            .is_ok());
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+const HANDSHAKE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits applied by the listening peerd before forking a child for an incoming connection.
+#[derive(Clone, Copy, Debug)]
+pub struct InboundLimits {
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    pub max_handshakes_per_minute: usize,
+}
+
+#[derive(Clone, Debug, Display)]
+pub enum InboundRejection {
+    #[display("limit of {0} inbound connections reached")]
+    TooManyConnections(usize),
+    #[display("limit of {1} inbound connections from {0} reached")]
+    TooManyConnectionsFromIp(IpAddr, usize),
+    #[display("limit of {0} handshakes per minute reached")]
+    TooManyHandshakes(usize),
+}
+
+/// Tracks the live inbound connections, identified by the pid of the peerd child serving them,
+/// and the recent handshakes of the listener.
+pub struct InboundLimiter {
+    limits: InboundLimits,
+    connections: HashMap<i32, IpAddr>,
+    handshakes: VecDeque<Instant>,
+}
+
+impl InboundLimiter {
+    pub fn new(limits: InboundLimits) -> Self {
+        InboundLimiter {
+            limits,
+            connections: none!(),
+            handshakes: none!(),
+        }
+    }
+
+    /// Checks whether a new connection from the given ip address is accepted, and counts its
+    /// handshake if so.
+    pub fn admit(&mut self, ip: IpAddr) -> Result<(), InboundRejection> {
+        let now = Instant::now();
+        while self
+            .handshakes
+            .front()
+            .map_or(false, |start| now.duration_since(*start) > HANDSHAKE_WINDOW)
+        {
+            self.handshakes.pop_front();
+        }
+        if self.connections.len() >= self.limits.max_connections {
+            return Err(InboundRejection::TooManyConnections(
+                self.limits.max_connections,
+            ));
+        }
+        if self
+            .connections
+            .values()
+            .filter(|addr| **addr == ip)
+            .count()
+            >= self.limits.max_connections_per_ip
+        {
+            return Err(InboundRejection::TooManyConnectionsFromIp(
+                ip,
+                self.limits.max_connections_per_ip,
+            ));
+        }
+        if self.handshakes.len() >= self.limits.max_handshakes_per_minute {
+            return Err(InboundRejection::TooManyHandshakes(
+                self.limits.max_handshakes_per_minute,
+            ));
+        }
+        self.handshakes.push_back(now);
+        Ok(())
+    }
+
+    /// Registers the child process serving an admitted connection
+    pub fn register(&mut self, pid: i32, ip: IpAddr) {
+        self.connections.insert(pid, ip);
+    }
+
+    /// Releases the connection served by a child process that exited
+    pub fn release(&mut self, pid: i32) {
+        self.connections.remove(&pid);
+    }
+
+    pub fn pids(&self) -> Vec<i32> {
+        self.connections.keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(
+        max_connections: usize,
+        max_connections_per_ip: usize,
+        max_handshakes_per_minute: usize,
+    ) -> InboundLimiter {
+        InboundLimiter::new(InboundLimits {
+            max_connections,
+            max_connections_per_ip,
+            max_handshakes_per_minute,
+        })
+    }
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    #[test]
+    fn max_connections() {
+        let mut limiter = limiter(2, 2, 10);
+        assert!(limiter.admit(ip(1)).is_ok());
+        limiter.register(100, ip(1));
+        assert!(limiter.admit(ip(2)).is_ok());
+        limiter.register(101, ip(2));
+        assert!(matches!(
+            limiter.admit(ip(3)),
+            Err(InboundRejection::TooManyConnections(2))
+        ));
+        // releasing a connection frees its slot
+        limiter.release(100);
+        assert_eq!(limiter.pids(), vec![101]);
+        assert!(limiter.admit(ip(3)).is_ok());
+    }
+
+    #[test]
+    fn max_connections_per_ip() {
+        let mut limiter = limiter(10, 1, 10);
+        assert!(limiter.admit(ip(1)).is_ok());
+        limiter.register(100, ip(1));
+        match limiter.admit(ip(1)) {
+            Err(InboundRejection::TooManyConnectionsFromIp(addr, 1)) => assert_eq!(addr, ip(1)),
+            res => panic!("expected a rejection of the ip, got {:?}", res),
+        }
+        // other addresses are still admitted
+        assert!(limiter.admit(ip(2)).is_ok());
+        // releasing an unknown pid changes nothing
+        limiter.release(200);
+        assert!(limiter.admit(ip(1)).is_err());
+        limiter.release(100);
+        assert!(limiter.admit(ip(1)).is_ok());
+    }
+
+    #[test]
+    fn max_handshakes_per_minute() {
+        let mut limiter = limiter(10, 10, 2);
+        assert!(limiter.admit(ip(1)).is_ok());
+        assert!(limiter.admit(ip(2)).is_ok());
+        // a handshake is counted when admitted, even if the connection is never registered
+        assert!(matches!(
+            limiter.admit(ip(3)),
+            Err(InboundRejection::TooManyHandshakes(2))
+        ));
+        // a rejected connection is not counted, releasing does not free handshakes
+        limiter.release(100);
+        assert_eq!(limiter.handshakes.len(), 2);
+        assert!(limiter.admit(ip(3)).is_err());
+        // the handshakes older than a minute are forgotten
+        let expired = Instant::now() - HANDSHAKE_WINDOW - Duration::from_secs(1);
+        limiter.handshakes[0] = expired;
+        assert!(limiter.admit(ip(3)).is_ok());
+        assert_eq!(limiter.handshakes.len(), 2);
+    }
+
+    #[test]
+    fn rejection_reasons() {
+        assert_eq!(
+            InboundRejection::TooManyConnections(8).to_string(),
+            "limit of 8 inbound connections reached"
+        );
+        assert_eq!(
+            InboundRejection::TooManyConnectionsFromIp(ip(1), 2).to_string(),
+            "limit of 2 inbound connections from 10.0.0.1 reached"
+        );
+        assert_eq!(
+            InboundRejection::TooManyHandshakes(30).to_string(),
+            "limit of 30 handshakes per minute reached"
+        );
+    }
+}
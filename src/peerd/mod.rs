@@ -4,11 +4,16 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+mod inbound;
 #[cfg(feature = "shell")]
 mod opts;
 mod runtime;
 
+pub use inbound::{InboundLimiter, InboundLimits, InboundRejection};
 #[cfg(feature = "shell")]
 pub use opts::{Opts, PeerKeyOpts};
+pub use runtime::claim_connection;
+pub use runtime::is_banned;
+pub use runtime::report_failure;
 pub use runtime::run_from_connect;
 pub use runtime::run_from_listener;
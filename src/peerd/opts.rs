@@ -59,6 +59,19 @@ pub struct Opts {
     #[clap(short, long, default_value = "9735")]
     pub port: u16,
 
+    /// Maximum number of inbound connections accepted in listening mode
+    #[clap(long, default_value = "64")]
+    pub max_inbound_connections: usize,
+
+    /// Maximum number of inbound connections accepted from a single ip address in listening
+    /// mode
+    #[clap(long, default_value = "4")]
+    pub max_inbound_connections_per_ip: usize,
+
+    /// Maximum number of inbound handshakes started per minute in listening mode
+    #[clap(long, default_value = "30")]
+    pub max_handshakes_per_minute: usize,
+
     /// Node key configuration
     #[clap(flatten)]
     pub peer_key_opts: PeerKeyOpts,
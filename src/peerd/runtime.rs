@@ -4,19 +4,18 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use farcaster_core::swap::SwapId;
+use bitcoin::hashes::sha256;
 use internet2::addr::LocalNode;
 use microservices::peer::RecvMessage;
 use std::sync::Arc;
 use std::thread::spawn;
 use std::time::{Duration, SystemTime};
-use uuid::Uuid;
 
 use amplify::Bipolar;
 use bitcoin::secp256k1::rand::{self, Rng, RngCore};
 use internet2::{addr::InetSocketAddr, CreateUnmarshaller, Unmarshall, Unmarshaller};
 use internet2::{
-    addr::NodeAddr,
+    addr::{NodeAddr, NodeId},
     presentation, transport,
     zeromq::{Carrier, ZmqSocketType},
     TypedEnum,
@@ -26,12 +25,14 @@ use microservices::node::TryService;
 use microservices::peer::{self, PeerConnection, PeerSender, SendMessage};
 use microservices::ZMQ_CONTEXT;
 
-use crate::bus::p2p::Receipt;
+use crate::bus::p2p::{
+    IdentityChallenge, IdentityProof, PeerFeatures, PeerInit, Receipt, PEER_PROTOCOL_VERSION,
+};
 use crate::bus::{
     ctl::CtlMsg,
     info::{InfoMsg, PeerInfo},
     p2p::PeerMsg,
-    BusMsg, ServiceBus,
+    BusMsg, Failure, FailureCode, Misbehavior, ServiceBus,
 };
 use crate::{CtlServer, Endpoints, Error, LogStyle, Service, ServiceConfig, ServiceId};
 
@@ -39,33 +40,32 @@ pub fn start_connect_peer_listener_runtime(
     remote_node_addr: NodeAddr,
     local_node: LocalNode,
     peerd_service_id: ServiceId,
-) -> Result<(PeerSender, std::sync::mpsc::Sender<()>), Error> {
+) -> Result<(PeerSender, std::sync::mpsc::Sender<()>, PeerFeatures), Error> {
     let connection = PeerConnection::connect_brontozaur(local_node, remote_node_addr)?;
     debug!("Connected to remote peer: {}", remote_node_addr);
 
     debug!("Splitting connection into receiver and sender parts");
     let (mut peer_receiver, mut peer_sender) = connection.split();
 
-    // this is hella hacky, but it serves the purpose of keeping peerd's service
-    // id constant across reconnects: <REMOTE_NODE_ID>:<REMOTE_ADDR> for taker,
-    // <REMOTE_NODE_ID>:<LOCAL_ADDR> for maker
-    // TODO: It is privacy/security critical that once the
-    // connection is encrypted, this should be replaced by a proper handshake.
-    peer_sender.send_message(PeerMsg::Identity(local_node.node_id()))?;
-    debug!(
-        "sent message with local node id {} to the maker",
-        local_node.node_id()
-    );
-    let unmarshaller: Unmarshaller<PeerMsg> = PeerMsg::create_unmarshaller();
-    let msg: &PeerMsg = &*peer_receiver.recv_message(&unmarshaller)?;
-    match msg {
-        PeerMsg::Pong(id) => {
-            debug!("Received the following pong from the maker {:?}", id);
-        }
-        _ => {
-            return Err(Error::Peer(presentation::Error::UnknownDataType));
-        }
-    };
+    // The maker has to prove it owns the node key announced in the deal
+    // The transport handshake authenticated the remote static key against the node address
+    let remote_node_id = authenticate_peer(
+        &mut peer_receiver,
+        &mut peer_sender,
+        &local_node,
+        remote_node_addr.id,
+    )?;
+    if remote_node_id != remote_node_addr.id {
+        error!(
+            "Remote peer {} failed to authenticate as {}, closing connection",
+            remote_node_id, remote_node_addr.id
+        );
+        return Err(Error::PeerIdentityMismatch {
+            expected: remote_node_addr.id,
+            found: remote_node_id,
+        });
+    }
+    let features = negotiate_features(&mut peer_receiver, &mut peer_sender)?;
 
     let tx = ZMQ_CONTEXT.socket(zmq::PUSH)?;
     tx.connect("inproc://bridge")?;
@@ -107,7 +107,121 @@ pub fn start_connect_peer_listener_runtime(
         }
     });
 
-    Ok((peer_sender, thread_flag_tx))
+    Ok((peer_sender, thread_flag_tx, features))
+}
+
+/// Runs the identity handshake over the encrypted connection. Each side sends a challenge with
+/// its claimed node id and a fresh nonce, then signs the nonce received from the remote with its
+/// node key, committing to the static keys of the transport session. The proven node id must be
+/// the remote static key of the session. Returns the node id the remote peer proved to own.
+fn authenticate_peer(
+    peer_receiver: &mut impl RecvMessage,
+    peer_sender: &mut impl SendMessage,
+    local_node: &LocalNode,
+    remote_static: NodeId,
+) -> Result<NodeId, Error> {
+    let mut nonce = vec![0u8; 32];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    peer_sender.send_message(PeerMsg::IdentityChallenge(IdentityChallenge {
+        node_id: local_node.node_id(),
+        nonce: nonce.clone(),
+    }))?;
+
+    let unmarshaller: Unmarshaller<PeerMsg> = PeerMsg::create_unmarshaller();
+    let challenge = match &*peer_receiver.recv_message(&unmarshaller)? {
+        PeerMsg::IdentityChallenge(challenge) => challenge.clone(),
+        msg => {
+            error!(
+                "Expected an identity challenge from the remote peer, received {}",
+                msg
+            );
+            return Err(Error::Misbehaving);
+        }
+    };
+    debug!(
+        "Received identity challenge from remote peer claiming node id {}",
+        challenge.node_id
+    );
+    peer_sender.send_message(PeerMsg::IdentityProof(IdentityProof::sign(
+        &challenge.nonce,
+        &local_node.private_key(),
+        &remote_static,
+    )?))?;
+
+    let proof = match &*peer_receiver.recv_message(&unmarshaller)? {
+        PeerMsg::IdentityProof(proof) => proof.clone(),
+        msg => {
+            error!(
+                "Expected an identity proof from the remote peer, received {}",
+                msg
+            );
+            return Err(Error::Misbehaving);
+        }
+    };
+    if proof.node_id != challenge.node_id {
+        error!(
+            "Remote peer claimed node id {} but proved {}, closing connection",
+            challenge.node_id, proof.node_id
+        );
+        return Err(Error::PeerIdentityMismatch {
+            expected: challenge.node_id,
+            found: proof.node_id,
+        });
+    }
+    if proof.node_id != remote_static {
+        error!(
+            "Remote peer proved node id {} over a session with {}, closing connection",
+            proof.node_id, remote_static
+        );
+        return Err(Error::PeerIdentityMismatch {
+            expected: remote_static,
+            found: proof.node_id,
+        });
+    }
+    if proof.verify(&nonce, &local_node.node_id()).is_err() {
+        error!(
+            "Remote peer failed to prove ownership of node id {}, closing connection",
+            proof.node_id
+        );
+        return Err(Error::PeerIdentityUnproven(proof.node_id));
+    }
+    debug!("Remote peer authenticated as {}", proof.node_id);
+    Ok(proof.node_id)
+}
+
+/// Exchanges the init message with the remote peer and refuses it if the peer protocols are
+/// incompatible. Returns the features supported by both nodes.
+fn negotiate_features(
+    peer_receiver: &mut impl RecvMessage,
+    peer_sender: &mut impl SendMessage,
+) -> Result<PeerFeatures, Error> {
+    let local_init = PeerInit::local();
+    peer_sender.send_message(PeerMsg::Init(local_init.clone()))?;
+
+    let unmarshaller: Unmarshaller<PeerMsg> = PeerMsg::create_unmarshaller();
+    let remote_init = match &*peer_receiver.recv_message(&unmarshaller)? {
+        PeerMsg::Init(init) => init.clone(),
+        msg => {
+            error!(
+                "Expected an init message from the remote peer, received {}",
+                msg
+            );
+            return Err(Error::Misbehaving);
+        }
+    };
+    match local_init.negotiate(&remote_init) {
+        Ok(features) => {
+            debug!(
+                "Remote peer speaks protocol {}, negotiated features: {}",
+                remote_init, features
+            );
+            Ok(features)
+        }
+        Err(err) => {
+            error!("{}, closing connection", err);
+            Err(err)
+        }
+    }
 }
 
 pub fn run_from_connect(
@@ -121,18 +235,18 @@ pub fn run_from_connect(
     rx.bind("inproc://bridge")?;
 
     let (thread_flag_tx, _thread_flag_rx) = std::sync::mpsc::channel();
-    let id = Uuid::new_v4().as_u128();
 
     debug!(
         "Starting main service runtime with identity: {}",
-        ServiceId::Peer(id, remote_node_addr)
+        ServiceId::Peer(remote_node_addr.id)
     );
     let runtime = Runtime {
-        identity: ServiceId::Peer(id, remote_node_addr),
+        identity: ServiceId::Peer(remote_node_addr.id),
         remote_node_addr: Some(remote_node_addr),
         local_socket,
         local_node,
         peer_sender: None, // As connector we create the sender on is_ready
+        negotiated_features: None,
         forked_from_listener: false,
         started: SystemTime::now(),
         messages_sent: 0,
@@ -154,37 +268,21 @@ pub fn run_from_listener(
     remote_node_addr: Option<NodeAddr>,
     local_socket: Option<InetSocketAddr>,
     local_node: LocalNode,
+    remote_static: NodeId,
 ) -> Result<(), Error> {
     debug!("Splitting connection into receiver and sender parts");
     let (mut peer_receiver, mut peer_sender) = connection.split();
 
-    // this is hella hacky, but it serves the purpose of keeping peerd's service
-    // id constant across reconnects: <REMOTE_NODE_ID>:<REMOTE_ADDR> for taker,
-    // <REMOTE_NODE_ID>:<LOCAL_ADDR> for maker
-    // TODO: It is privacy/security critical that once the
-    // connection is encrypted, this should be replaced by a proper handshake.
-    let unmarshaller: Unmarshaller<PeerMsg> = PeerMsg::create_unmarshaller();
-    let msg: Arc<PeerMsg> = peer_receiver
-        .recv_message(&unmarshaller)
-        .expect("failed to receive identity message from taker");
-    let id = match *msg {
-        PeerMsg::Identity(id) => {
-            debug!("Received the following local node id from the taker {}", id);
-            Some(id)
-        }
-        _ => None,
-    };
-    peer_sender
-        .send_message(PeerMsg::Pong(vec![0]))
-        .expect("Failed to send handshake pong");
-    let peerd_id = Uuid::new_v4().as_u128();
-    let internal_identity = ServiceId::Peer(
-        peerd_id,
-        NodeAddr {
-            id: id.expect("remote id should always be some in maker's case"),
-            addr: local_socket.expect("Checked for listener"),
-        },
-    );
+    // The authenticated node id of the taker keys peerd's service id, so all
+    // swaps with that node are routed through it, also across reconnects
+    let remote_node_id = authenticate_peer(
+        &mut peer_receiver,
+        &mut peer_sender,
+        &local_node,
+        remote_static,
+    )?;
+    let features = negotiate_features(&mut peer_receiver, &mut peer_sender)?;
+    let internal_identity = ServiceId::Peer(remote_node_id);
 
     debug!("Opening bridge between runtime and peer receiver threads");
     let rx = ZMQ_CONTEXT.socket(zmq::PULL)?;
@@ -238,6 +336,7 @@ pub fn run_from_listener(
         local_socket,
         local_node,
         peer_sender: Some(peer_sender),
+        negotiated_features: Some(features),
         forked_from_listener: true,
         started: SystemTime::now(),
         messages_sent: 0,
@@ -280,6 +379,119 @@ impl esb::Handler<ServiceBus> for BridgeHandler {
     }
 }
 
+/// Handler of the one-off controllers peerd uses to reach farcasterd outside of its runtime
+struct OneOffHandler {
+    identity: ServiceId,
+}
+
+impl esb::Handler<ServiceBus> for OneOffHandler {
+    type Request = BusMsg;
+    type Error = Error;
+
+    fn identity(&self) -> ServiceId {
+        self.identity.clone()
+    }
+
+    fn handle(
+        &mut self,
+        _endpoints: &mut Endpoints,
+        _bus: ServiceBus,
+        _addr: ServiceId,
+        _request: BusMsg,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn handle_err(&mut self, _: &mut Endpoints, err: esb::Error<ServiceId>) -> Result<(), Error> {
+        Err(Error::Esb(err))
+    }
+}
+
+/// Report to farcasterd that the peerd runtime stopped on an error, so it releases the
+/// connection. The runtime's own controller is gone, the report goes through a new one with the
+/// same identity.
+pub fn report_failure(
+    config: ServiceConfig,
+    identity: ServiceId,
+    msg: CtlMsg,
+) -> Result<(), Error> {
+    let mut reporter = esb::Controller::with(
+        map! {
+            ServiceBus::Ctl => esb::BusConfig::with_addr(
+                config.ctl_endpoint,
+                ZmqSocketType::RouterConnect,
+                Some(ServiceId::router())
+            )
+        },
+        OneOffHandler { identity },
+    )?;
+    reporter.send_to(ServiceBus::Ctl, ServiceId::Farcasterd, BusMsg::Ctl(msg))?;
+    Ok(())
+}
+
+/// Ask farcasterd whether the remote node is banned, so the listener refuses its connection
+/// before the peerd runtime registers it. The query goes through a one-off client controller.
+pub fn is_banned(config: &ServiceConfig, node_id: NodeId) -> Result<bool, Error> {
+    let mut client = esb::Controller::with(
+        map! {
+            ServiceBus::Info => esb::BusConfig::with_addr(
+                config.info_endpoint.clone(),
+                ZmqSocketType::RouterConnect,
+                Some(ServiceId::router())
+            )
+        },
+        OneOffHandler {
+            identity: ServiceId::client(),
+        },
+    )?;
+    // We have to sleep in order for ZMQ to bootstrap
+    std::thread::sleep(Duration::from_secs_f32(0.1));
+    client.send_to(
+        ServiceBus::Info,
+        ServiceId::Farcasterd,
+        BusMsg::Info(InfoMsg::ListBans),
+    )?;
+    loop {
+        for rep in client.recv_poll()? {
+            if let BusMsg::Info(InfoMsg::PeerBanList(bans)) = rep.request {
+                return Ok(bans.iter().any(|ban| ban.node_id == node_id));
+            }
+        }
+    }
+}
+
+/// Claim the connection to the remote node for this process, farcasterd refuses it while
+/// another live peerd process owns the connection. The query goes through a one-off client
+/// controller.
+pub fn claim_connection(config: &ServiceConfig, node_id: NodeId) -> Result<bool, Error> {
+    let mut client = esb::Controller::with(
+        map! {
+            ServiceBus::Info => esb::BusConfig::with_addr(
+                config.info_endpoint.clone(),
+                ZmqSocketType::RouterConnect,
+                Some(ServiceId::router())
+            )
+        },
+        OneOffHandler {
+            identity: ServiceId::client(),
+        },
+    )?;
+    // We have to sleep in order for ZMQ to bootstrap
+    std::thread::sleep(Duration::from_secs_f32(0.1));
+    client.send_to(
+        ServiceBus::Info,
+        ServiceId::Farcasterd,
+        BusMsg::Info(InfoMsg::ClaimConnection(node_id, std::process::id())),
+    )?;
+    loop {
+        for rep in client.recv_poll()? {
+            if let BusMsg::Info(InfoMsg::ConnectionClaim(claimed)) = rep.request {
+                return Ok(claimed);
+            }
+        }
+    }
+}
+
 // PeerReceiverRuntime handles incoming messages only
 pub struct PeerReceiverRuntime {
     internal_identity: ServiceId,
@@ -352,6 +564,14 @@ impl peer::Handler<PeerMsg> for PeerReceiverRuntime {
                 self.awaiting_pong = true;
                 Ok(())
             }
+            // the frame was received but could not be decoded, the connection is still usable
+            Error::Peer(ref presentation_err)
+                if !matches!(presentation_err, presentation::Error::Transport(_)) =>
+            {
+                warn!("Received a malformed message from the remote peer: {}", err);
+                self.send_over_bridge(Arc::new(PeerMsg::MalformedMessage))?;
+                Ok(())
+            }
             // for all other error types, indicating internal errors and broken
             // connections, we propagate error to the upper level (currently not
             // handled, will result in a broken peerd state)
@@ -374,6 +594,7 @@ pub struct Runtime {
     local_node: LocalNode,
 
     peer_sender: Option<PeerSender>,
+    negotiated_features: Option<PeerFeatures>,
     // TODO: make this an enum instead with a descriptive distinction of listening and connecting to a listener
     forked_from_listener: bool,
 
@@ -382,7 +603,7 @@ pub struct Runtime {
     messages_received: usize,
     awaited_pong: Option<u16>,
 
-    unchecked_msg_cache: Vec<((SwapId, internet2::TypeId), PeerMsg)>,
+    unchecked_msg_cache: Vec<(sha256::Hash, PeerMsg)>,
 
     thread_flag_tx: std::sync::mpsc::Sender<()>,
 }
@@ -400,7 +621,7 @@ impl esb::Handler<ServiceBus> for Runtime {
     fn on_ready(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
         // log iff taker
         if !self.forked_from_listener {
-            let (peer_sender, thread_flag_tx) = match start_connect_peer_listener_runtime(
+            let (peer_sender, thread_flag_tx, features) = match start_connect_peer_listener_runtime(
                 self.remote_node_addr.expect("Checked for connecter"),
                 self.local_node,
                 self.identity(),
@@ -437,6 +658,7 @@ impl esb::Handler<ServiceBus> for Runtime {
 
             self.peer_sender = Some(peer_sender);
             self.thread_flag_tx = thread_flag_tx;
+            self.negotiated_features = Some(features);
             info!(
                 "{} with the remote peer {}",
                 "Successfully connected".bright_blue_bold(),
@@ -486,9 +708,29 @@ impl Runtime {
     fn handle_msg(
         &mut self,
         endpoints: &mut Endpoints,
-        _source: ServiceId,
+        source: ServiceId,
         message: PeerMsg,
     ) -> Result<(), Error> {
+        if !self.supports(&message) {
+            debug!(
+                "{} | Remote peer did not negotiate the feature required by {}, dropping it",
+                self.identity(),
+                message
+            );
+            // The sender of a protocol message would otherwise wait for a receipt forever
+            if message.is_protocol() {
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    source,
+                    BusMsg::Ctl(CtlMsg::Failure(Failure {
+                        code: FailureCode::Unavailable,
+                        info: format!("Remote peer does not support the {} message", message),
+                    })),
+                )?;
+            }
+            return Ok(());
+        }
         // Forward to the remote peer
         debug!("{} | Message type: {}", self.identity(), message.get_type());
         debug!(
@@ -516,20 +758,13 @@ impl Runtime {
             );
             // If this is the listener-forked peerd, i.e. the maker's peerd, terminate it.
             if self.forked_from_listener {
-                for (_, cached_msg) in self.unchecked_msg_cache.drain(..) {
-                    // Draining cached messages to the various running swaps
-                    endpoints.send_to(
-                        ServiceBus::Ctl,
-                        self.identity.clone(),
-                        ServiceId::Swap(cached_msg.swap_id()),
-                        BusMsg::Ctl(CtlMsg::FailedPeerMessage(cached_msg)),
-                    )?;
-                }
+                // The swaps keep their unacknowledged messages and resend them on reconnect
+                self.unchecked_msg_cache.clear();
                 endpoints.send_to(
                     ServiceBus::Ctl,
                     self.identity(),
                     ServiceId::Farcasterd,
-                    BusMsg::Ctl(CtlMsg::PeerdTerminated),
+                    BusMsg::Ctl(CtlMsg::PeerdTerminated(std::process::id())),
                 )?;
                 warn!(
                     "Waiting for farcaster to terminate peerd instance {}",
@@ -551,14 +786,11 @@ impl Runtime {
         }
 
         if message.is_protocol() {
-            // First remove any dangling messages with the same type and origin
-            self.unchecked_msg_cache
-                .iter()
-                .position(|(key, _)| key.0 == message.swap_id() && key.1 == message.get_type())
-                .map(|pos| self.unchecked_msg_cache.remove(pos));
+            // First remove any dangling copy of the same message
+            let msg_id = message.msg_id();
+            self.unchecked_msg_cache.retain(|(id, _)| *id != msg_id);
             // Then push the message to the back of the vector
-            self.unchecked_msg_cache
-                .push(((message.swap_id(), message.get_type()), message.clone()));
+            self.unchecked_msg_cache.push((msg_id, message.clone()));
             let swap_id = message.swap_id();
             info!(
                 "{} | Sent the {} protocol message",
@@ -578,15 +810,6 @@ impl Runtime {
     ) -> Result<(), Error> {
         match request {
             CtlMsg::Terminate if source == ServiceId::Farcasterd => {
-                for (_, cached_msg) in self.unchecked_msg_cache.drain(..) {
-                    // Draining cached messages to the various running swaps
-                    endpoints.send_to(
-                        ServiceBus::Ctl,
-                        self.identity.clone(),
-                        ServiceId::Swap(cached_msg.swap_id()),
-                        BusMsg::Ctl(CtlMsg::FailedPeerMessage(cached_msg)),
-                    )?;
-                }
                 // FIXME: if persist pid logging beyond debugging, make this idiomatic across all services
                 info!(
                     "Terminating {} with PID {}",
@@ -594,6 +817,12 @@ impl Runtime {
                     std::process::id()
                 );
 
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::Ctl(CtlMsg::Terminated),
+                )?;
                 std::process::exit(0);
             }
 
@@ -638,6 +867,11 @@ impl Runtime {
                     messages_received: self.messages_received,
                     forked_from_listener: self.forked_from_listener,
                     awaits_pong: self.awaited_pong.is_some(),
+                    protocol_version: PEER_PROTOCOL_VERSION,
+                    features: self
+                        .negotiated_features
+                        .map(PeerFeatures::names)
+                        .unwrap_or_default(),
                 };
                 self.send_client_info(endpoints, source, InfoMsg::PeerInfo(info))?;
             }
@@ -677,7 +911,7 @@ impl Runtime {
                     );
                     std::thread::sleep(std::time::Duration::from_secs(attempt));
                 }
-                Ok((peer_sender, thread_flag_tx)) => {
+                Ok((peer_sender, thread_flag_tx, features)) => {
                     info!(
                         "{} | Reconnect success after {} attempts",
                         self.identity(),
@@ -685,6 +919,7 @@ impl Runtime {
                     );
                     self.peer_sender = Some(peer_sender);
                     self.thread_flag_tx = thread_flag_tx;
+                    self.negotiated_features = Some(features);
                     break;
                 }
             }
@@ -759,21 +994,10 @@ impl Runtime {
                         ServiceBus::Ctl,
                         self.identity(),
                         ServiceId::Farcasterd,
-                        BusMsg::Ctl(CtlMsg::PeerdTerminated),
+                        BusMsg::Ctl(CtlMsg::PeerdTerminated(std::process::id())),
                     )?;
-                    for ((swap_id, _), cached_msg) in self.unchecked_msg_cache.drain(..) {
-                        // Draining cached messages to the various running swaps
-                        debug!(
-                            "{} | Returning cache message {} back to swap",
-                            swap_id, cached_msg
-                        );
-                        endpoints.send_to(
-                            ServiceBus::Ctl,
-                            self.identity.clone(),
-                            ServiceId::Swap(swap_id),
-                            BusMsg::Ctl(CtlMsg::FailedPeerMessage(cached_msg)),
-                        )?;
-                    }
+                    // The swaps keep their unacknowledged messages and resend them on reconnect
+                    self.unchecked_msg_cache.clear();
                     warn!(
                         "Waiting for farcaster to terminate peerd instance {}",
                         self.identity().label()
@@ -791,19 +1015,51 @@ impl Runtime {
                 }
             }
 
+            PeerMsg::MalformedMessage => {
+                self.report_misbehavior(endpoints, Misbehavior::MalformedMessage)?;
+            }
+
             PeerMsg::MsgReceipt(receipt) => {
                 debug!("{} | received receipt: {:?}", request.swap_id(), receipt);
 
                 self.unchecked_msg_cache
-                    .iter()
-                    .position(|(key, _)| key.0 == receipt.swap_id && key.1 == receipt.msg_type)
-                    .map(|pos| self.unchecked_msg_cache.remove(pos));
+                    .retain(|(msg_id, _)| *msg_id != receipt.msg_id);
+
+                // forward the receipt to the swap so it can clear its outbox
+                if let Err(err) = endpoints.send_to(
+                    ServiceBus::Msg,
+                    self.identity(),
+                    ServiceId::Swap(receipt.swap_id),
+                    BusMsg::P2p(request.clone()),
+                ) {
+                    debug!(
+                        "{} | Could not forward receipt to swapd: {}",
+                        receipt.swap_id, err
+                    );
+                }
+            }
+
+            // deal gossip and quotes are handled by farcasterd, aborts are routed to their swap
+            // by farcasterd, none of them are acknowledged
+            PeerMsg::Abort(_)
+            | PeerMsg::DealAnnouncement(_)
+            | PeerMsg::DealRevocation(_)
+            | PeerMsg::QuoteRequest(_)
+            | PeerMsg::Quote(_)
+            | PeerMsg::QuoteRejected(_) => {
+                endpoints.send_to(
+                    ServiceBus::Msg,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::P2p(request),
+                )?;
             }
 
-            // swap initiation message
-            PeerMsg::TakerCommit(_) => {
+            // swap initiation and watchtower messages
+            PeerMsg::TakerCommit(_) | PeerMsg::WatchtowerRequest(_) => {
                 let swap_id = request.swap_id();
                 let msg_type = request.get_type();
+                let msg_id = request.msg_id();
                 debug!(
                     "{} | PID {} | Received the {} protocol message, forwarding to farcasterd",
                     swap_id.swap_id(),
@@ -821,7 +1077,11 @@ impl Runtime {
                 self.handle_msg(
                     endpoints,
                     source,
-                    PeerMsg::MsgReceipt(Receipt { swap_id, msg_type }),
+                    PeerMsg::MsgReceipt(Receipt {
+                        swap_id,
+                        msg_type,
+                        msg_id,
+                    }),
                 )?;
             }
 
@@ -834,26 +1094,45 @@ impl Runtime {
                     std::process::id(),
                     msg.label()
                 );
+                // swapd sends the receipt back once the message is checkpointed
                 endpoints.send_to(
                     ServiceBus::Msg,
                     self.identity(),
                     ServiceId::Swap(swap_id),
-                    BusMsg::P2p(request.clone()),
+                    BusMsg::P2p(request),
                 )?;
-
-                // send a receipt back to the remote peer
-                self.peer_sender
-                    .as_mut()
-                    .expect("should be connected")
-                    .send_message(PeerMsg::MsgReceipt(Receipt {
-                        swap_id: request.swap_id(),
-                        msg_type: request.get_type(),
-                    }))?;
             }
         }
         Ok(())
     }
 
+    /// Whether the remote peer negotiated the feature required by the message, if any
+    fn supports(&self, message: &PeerMsg) -> bool {
+        message.required_feature().map_or(true, |feature| {
+            self.negotiated_features
+                .map_or(false, |features| features.contains(feature))
+        })
+    }
+
+    fn report_misbehavior(
+        &mut self,
+        endpoints: &mut Endpoints,
+        misbehavior: Misbehavior,
+    ) -> Result<(), Error> {
+        warn!(
+            "{} | Reporting remote peer misbehavior to farcasterd: {}",
+            self.identity(),
+            misbehavior
+        );
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Farcasterd,
+            BusMsg::Ctl(CtlMsg::PeerMisbehaved(misbehavior)),
+        )?;
+        Ok(())
+    }
+
     fn ping(&mut self) -> Result<(), Error> {
         trace!("{} | Sending ping to the remote peer", self.identity());
         let mut rng = rand::thread_rng();
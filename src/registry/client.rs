@@ -0,0 +1,91 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::time::Duration;
+
+use farcaster_core::swap::btcxmr::Deal;
+use reqwest::blocking::{Client, Response};
+
+use crate::bus::info::RegistryQuery;
+use crate::registry::{encode_query, ListedDeal, RegistryAction, RegistryDeal};
+use crate::Error;
+
+const REGISTRY_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Blocking client of an HTTP deal registry, must not be used from the runtime thread.
+pub struct RegistryClient {
+    url: String,
+    client: Client,
+}
+
+impl RegistryClient {
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let client = Client::builder()
+            .timeout(REGISTRY_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| Error::DealRegistry(err.to_string()))?;
+        Ok(RegistryClient {
+            url: url.trim_end_matches('/').to_string(),
+            client,
+        })
+    }
+
+    pub fn publish(&self, deal: RegistryDeal) -> Result<(), Error> {
+        let res = self
+            .client
+            .post(format!("{}/deals", self.url))
+            .json(&deal)
+            .send();
+        Self::check(res).map(|_| ())
+    }
+
+    pub fn revoke(&self, deal: &Deal, signed_deal: RegistryDeal) -> Result<(), Error> {
+        let res = self
+            .client
+            .delete(format!("{}/deals/{}", self.url, deal.id()))
+            .json(&signed_deal)
+            .send();
+        Self::check(res).map(|_| ())
+    }
+
+    /// Fetch the deals matching the query, the deals not signed by their maker are discarded
+    pub fn query(&self, query: &RegistryQuery) -> Result<Vec<(Deal, u64)>, Error> {
+        let res = self
+            .client
+            .get(format!("{}/deals?{}", self.url, encode_query(query)))
+            .send();
+        let listed: Vec<ListedDeal> = Self::check(res)?
+            .json()
+            .map_err(|err| Error::DealRegistry(err.to_string()))?;
+        Ok(listed
+            .into_iter()
+            .filter_map(
+                |listed| match listed.signed_deal.verify(RegistryAction::Publish) {
+                    Ok(deal) => Some((deal, listed.since)),
+                    Err(err) => {
+                        warn!(
+                            "Discarding deal with an invalid signature from the registry: {}",
+                            err
+                        );
+                        None
+                    }
+                },
+            )
+            .filter(|(deal, _)| query.matches(deal))
+            .collect())
+    }
+
+    fn check(res: reqwest::Result<Response>) -> Result<Response, Error> {
+        let res = res.map_err(|err| Error::DealRegistry(err.to_string()))?;
+        if res.status().is_success() {
+            Ok(res)
+        } else {
+            let status = res.status();
+            let reason = res.text().unwrap_or_default();
+            Err(Error::DealRegistry(format!("{} {}", status, reason)))
+        }
+    }
+}
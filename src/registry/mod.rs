@@ -0,0 +1,190 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! HTTP deal registry: makers publish their open deals, signed with their node key, and revoke
+//! them once they are no longer available; takers query the registry for the deals matching
+//! their network, amount range and price.
+//!
+//! The registry exposes three endpoints:
+//!  - `POST /deals` publishes the [`RegistryDeal`] in the body,
+//!  - `DELETE /deals/<deal id>` revokes the deal, the body is the same deal signed for revocation,
+//!  - `GET /deals?network=..&min_amount=..` lists the [`ListedDeal`]s matching the query.
+//!
+//! Every signature commits to the time it was made at, the registry rejects the requests older
+//! than [`REGISTRY_MAX_REQUEST_AGE`] and the ones not more recent than the last accepted request
+//! for the deal, so a publication cannot be replayed after the deal was revoked.
+
+mod client;
+#[cfg(feature = "shell")]
+mod opts;
+#[cfg(feature = "server")]
+pub mod server;
+
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1, SecretKey};
+use farcaster_core::blockchain::Network;
+use farcaster_core::swap::btcxmr::Deal;
+use serde::{Deserialize, Serialize};
+
+use crate::bus::info::RegistryQuery;
+use crate::Error;
+
+pub use client::RegistryClient;
+#[cfg(feature = "shell")]
+pub use opts::Opts;
+
+/// What a deal signature authorizes, a publication signature can't be replayed to revoke the
+/// deal and the other way around.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum RegistryAction {
+    #[display("publish")]
+    Publish,
+    #[display("revoke")]
+    Revoke,
+}
+
+/// Requests signed longer ago, or further in the future, than this are rejected by the registry
+pub const REGISTRY_MAX_REQUEST_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// An encoded deal and the signature of the registry action by the maker's node key, made at
+/// the timestamp in milliseconds.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(crate = "serde_crate")]
+pub struct RegistryDeal {
+    pub deal: String,
+    pub timestamp: u64,
+    pub signature: String,
+}
+
+impl RegistryDeal {
+    pub fn sign(
+        deal: &Deal,
+        action: RegistryAction,
+        node_secret_key: &SecretKey,
+    ) -> Result<Self, Error> {
+        Self::sign_at(deal, action, timestamp_millis(), node_secret_key)
+    }
+
+    pub fn sign_at(
+        deal: &Deal,
+        action: RegistryAction,
+        timestamp: u64,
+        node_secret_key: &SecretKey,
+    ) -> Result<Self, Error> {
+        let secp = Secp256k1::new();
+        if PublicKey::from_secret_key(&secp, node_secret_key) != deal.node_id {
+            return Err(Error::DealRegistry(format!(
+                "deal {} was not made by this node",
+                deal.id()
+            )));
+        }
+        let deal = deal.to_string();
+        let msg = Self::message(&deal, action, timestamp)?;
+        Ok(RegistryDeal {
+            signature: secp.sign_ecdsa(&msg, node_secret_key).to_string(),
+            deal,
+            timestamp,
+        })
+    }
+
+    /// Decodes the deal and checks the action was signed by the node key of its maker
+    pub fn verify(&self, action: RegistryAction) -> Result<Deal, Error> {
+        let deal = Deal::from_str(&self.deal)?;
+        let signature = Signature::from_str(&self.signature)?;
+        let msg = Self::message(&self.deal, action, self.timestamp)?;
+        Secp256k1::verification_only().verify_ecdsa(&msg, &signature, &deal.node_id)?;
+        Ok(deal)
+    }
+
+    /// Whether the request was signed within the maximum request age of the given time
+    pub fn is_fresh(&self, now: u64) -> bool {
+        let max_age = REGISTRY_MAX_REQUEST_AGE.as_millis() as u64;
+        self.timestamp + max_age >= now && self.timestamp <= now + max_age
+    }
+
+    fn message(deal: &str, action: RegistryAction, timestamp: u64) -> Result<Message, Error> {
+        let mut engine = sha256::Hash::engine();
+        engine.input(b"farcaster deal registry ");
+        engine.input(action.to_string().as_bytes());
+        engine.input(deal.as_bytes());
+        engine.input(&timestamp.to_be_bytes());
+        Ok(Message::from_slice(&sha256::Hash::from_engine(engine))?)
+    }
+}
+
+/// The current time in milliseconds since the unix epoch
+pub fn timestamp_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// A deal listed by the registry, with the time it was published at
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(crate = "serde_crate")]
+pub struct ListedDeal {
+    #[serde(flatten)]
+    pub signed_deal: RegistryDeal,
+    pub since: u64,
+}
+
+/// Encodes the query as the query string of the listing endpoint
+pub fn encode_query(query: &RegistryQuery) -> String {
+    let mut params = vec![format!("network={}", query.network)];
+    let bounds = [
+        ("min_amount", query.min_amount),
+        ("max_amount", query.max_amount),
+        ("min_price", query.min_price),
+        ("max_price", query.max_price),
+    ];
+    for (name, value) in bounds {
+        if let Some(value) = value {
+            params.push(format!("{}={}", name, value));
+        }
+    }
+    params.join("&")
+}
+
+/// Decodes the query string of the listing endpoint, the network is mandatory
+pub fn decode_query(query: &str) -> Result<RegistryQuery, Error> {
+    let mut network = None;
+    let mut decoded = RegistryQuery {
+        network: Network::Local,
+        min_amount: None,
+        max_amount: None,
+        min_price: None,
+        max_price: None,
+    };
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let (name, value) = param
+            .split_once('=')
+            .ok_or_else(|| Error::DealRegistry(format!("malformed query parameter {}", param)))?;
+        let bound = || {
+            u64::from_str(value)
+                .map_err(|_| Error::DealRegistry(format!("invalid value {} for {}", value, name)))
+        };
+        match name {
+            "network" => network = Some(Network::from_str(value)?),
+            "min_amount" => decoded.min_amount = Some(bound()?),
+            "max_amount" => decoded.max_amount = Some(bound()?),
+            "min_price" => decoded.min_price = Some(bound()?),
+            "max_price" => decoded.max_price = Some(bound()?),
+            _ => {
+                return Err(Error::DealRegistry(format!(
+                    "unknown query parameter {}",
+                    name
+                )))
+            }
+        }
+    }
+    decoded.network =
+        network.ok_or_else(|| Error::DealRegistry("missing network parameter".to_string()))?;
+    Ok(decoded)
+}
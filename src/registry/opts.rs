@@ -0,0 +1,25 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::net::SocketAddr;
+
+/// Reference HTTP deal registry for Farcaster nodes
+#[derive(Parser, Clone, PartialEq, Eq, Debug)]
+#[clap(name = "deal-registry", bin_name = "deal-registry", author, version)]
+pub struct Opts {
+    /// Address the registry accepts HTTP connections on
+    #[clap(short, long, default_value = "127.0.0.1:7070")]
+    pub listen: SocketAddr,
+}
+
+impl Opts {
+    pub fn process(&mut self) {
+        let env = env_logger::Env::new().default_filter_or("error,farcaster_node=info");
+        env_logger::from_env(env)
+            .try_init()
+            .expect("Failed to initialize loggger!");
+    }
+}
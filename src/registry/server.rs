@@ -0,0 +1,227 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Reference implementation of the deal registry, keeping the published deals in memory.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use farcaster_core::swap::btcxmr::Deal;
+use farcaster_core::trade::DealId;
+use farcaster_core::Uuid;
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use tokio::runtime::Builder;
+
+use crate::registry::{
+    decode_query, timestamp_millis, ListedDeal, RegistryAction, RegistryDeal,
+    REGISTRY_MAX_REQUEST_AGE,
+};
+use crate::Error;
+
+/// Request bodies larger than this are rejected, a signed deal is a few hundred bytes
+pub const MAX_REQUEST_BODY_SIZE: usize = 16 * 1024;
+
+struct PublishedDeal {
+    deal: Deal,
+    listed: ListedDeal,
+}
+
+/// The published deals, and the timestamp of the last accepted request of every deal, kept
+/// after its revocation until the timestamp is too old to be accepted anyway.
+#[derive(Default)]
+struct Registry {
+    deals: HashMap<DealId, PublishedDeal>,
+    last_requests: HashMap<DealId, u64>,
+}
+
+impl Registry {
+    /// Accept the signed request only if it is fresh and more recent than the last accepted
+    /// request of the deal, a request cannot be replayed
+    fn check_request(
+        &mut self,
+        deal_id: DealId,
+        signed_deal: &RegistryDeal,
+    ) -> Result<(), (StatusCode, String)> {
+        let now = timestamp_millis();
+        let max_age = REGISTRY_MAX_REQUEST_AGE.as_millis() as u64;
+        self.last_requests
+            .retain(|_, timestamp| *timestamp + max_age >= now);
+        if !signed_deal.is_fresh(now) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("request for deal {} is outdated", deal_id),
+            ));
+        }
+        if self
+            .last_requests
+            .get(&deal_id)
+            .map_or(false, |last| signed_deal.timestamp <= *last)
+        {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("request for deal {} was replayed", deal_id),
+            ));
+        }
+        self.last_requests.insert(deal_id, signed_deal.timestamp);
+        Ok(())
+    }
+}
+
+type DealBook = Arc<Mutex<Registry>>;
+
+/// Serve the registry on the given address until the process is stopped
+pub fn run(listen: SocketAddr) -> Result<(), Error> {
+    let rt = Builder::new_multi_thread().enable_all().build()?;
+    rt.block_on(async move {
+        let book = DealBook::default();
+        let make_service = make_service_fn(move |_| {
+            let book = book.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| handle_request(book.clone(), req)))
+            }
+        });
+        info!("Deal registry listening on {}", listen);
+        Server::bind(&listen)
+            .serve(make_service)
+            .await
+            .map_err(|err| Error::DealRegistry(err.to_string()))
+    })
+}
+
+async fn handle_request(book: DealBook, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let query = req.uri().query().unwrap_or_default().to_string();
+    debug!("{} {}", method, req.uri());
+    let res = match (&method, path.as_str()) {
+        (&Method::GET, "/deals") => list_deals(&book, &query),
+        (&Method::POST, "/deals") => match read_signed_deal(req).await {
+            Ok(signed_deal) => publish_deal(&book, signed_deal),
+            Err(err) => Err(err),
+        },
+        (&Method::DELETE, path) if path.starts_with("/deals/") => {
+            let deal_id = path.trim_start_matches("/deals/").to_string();
+            match read_signed_deal(req).await {
+                Ok(signed_deal) => revoke_deal(&book, &deal_id, signed_deal),
+                Err(err) => Err(err),
+            }
+        }
+        _ => Err((StatusCode::NOT_FOUND, "not found".to_string())),
+    };
+    Ok(res.unwrap_or_else(|(status, reason)| {
+        debug!("{} {} failed: {} {}", method, path, status, reason);
+        let mut res = Response::new(Body::from(reason));
+        *res.status_mut() = status;
+        res
+    }))
+}
+
+async fn read_signed_deal(req: Request<Body>) -> Result<RegistryDeal, (StatusCode, String)> {
+    let too_large = || {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("request body exceeds {} bytes", MAX_REQUEST_BODY_SIZE),
+        )
+    };
+    let mut body = req.into_body();
+    if body.size_hint().lower() > MAX_REQUEST_BODY_SIZE as u64 {
+        return Err(too_large());
+    }
+    let mut bytes = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+        if bytes.len() + chunk.len() > MAX_REQUEST_BODY_SIZE {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    serde_json::from_slice(&bytes).map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))
+}
+
+fn list_deals(book: &DealBook, query: &str) -> Result<Response<Body>, (StatusCode, String)> {
+    let query = decode_query(query).map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    let listed: Vec<ListedDeal> = book
+        .lock()
+        .expect("deal book lock poisoned")
+        .deals
+        .values()
+        .filter(|published| query.matches(&published.deal))
+        .map(|published| published.listed.clone())
+        .collect();
+    let body = serde_json::to_vec(&listed)
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    Ok(Response::new(Body::from(body)))
+}
+
+fn publish_deal(
+    book: &DealBook,
+    signed_deal: RegistryDeal,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let deal = signed_deal
+        .verify(RegistryAction::Publish)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    let mut book = book.lock().expect("deal book lock poisoned");
+    if book
+        .deals
+        .get(&deal.id())
+        .map_or(false, |published| published.deal != deal)
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("deal {} was published by another maker", deal.id()),
+        ));
+    }
+    book.check_request(deal.id(), &signed_deal)?;
+    info!("Publishing deal {}", deal.id());
+    let since = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    book.deals.entry(deal.id()).or_insert(PublishedDeal {
+        deal,
+        listed: ListedDeal { signed_deal, since },
+    });
+    Ok(Response::new(Body::empty()))
+}
+
+fn revoke_deal(
+    book: &DealBook,
+    deal_id: &str,
+    signed_deal: RegistryDeal,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let deal_id = Uuid::from_str(deal_id)
+        .map(DealId::from)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    let deal = signed_deal
+        .verify(RegistryAction::Revoke)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    if deal.id() != deal_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("signed deal {} does not match {}", deal.id(), deal_id),
+        ));
+    }
+    let mut book = book.lock().expect("deal book lock poisoned");
+    match book.deals.get(&deal_id) {
+        Some(published) if published.deal != deal => Err((
+            StatusCode::FORBIDDEN,
+            format!("deal {} was published by another maker", deal_id),
+        )),
+        Some(_) => {
+            book.check_request(deal_id, &signed_deal)?;
+            info!("Revoking deal {}", deal_id);
+            book.deals.remove(&deal_id);
+            Ok(Response::new(Body::empty()))
+        }
+        None => Err((StatusCode::NOT_FOUND, format!("unknown deal {}", deal_id))),
+    }
+}
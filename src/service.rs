@@ -16,11 +16,7 @@ use colored::Colorize;
 use farcaster_core::role::{SwapRole, TradeRole};
 use farcaster_core::Uuid;
 use internet2::addr::NodeId;
-use internet2::{
-    addr::{NodeAddr, ServiceAddr},
-    zeromq,
-    zeromq::ZmqSocketType,
-};
+use internet2::{addr::ServiceAddr, zeromq, zeromq::ZmqSocketType};
 use lazy_static::lazy_static;
 use microservices::esb;
 #[cfg(feature = "node")]
@@ -133,8 +129,9 @@ pub enum ServiceId {
     #[display("farcasterd")]
     Farcasterd,
 
-    #[display("peerd<{0} {1}>")]
-    Peer(u128, NodeAddr),
+    /// A connection to a remote node, all swaps with that node are routed through it
+    #[display("peerd<{0}>")]
+    Peer(NodeId),
 
     #[display("swap<{0}>")]
     #[from]
@@ -173,24 +170,12 @@ impl ServiceId {
     }
 
     pub fn node_id(&self) -> Option<NodeId> {
-        if let ServiceId::Peer(_, addr) = self {
-            Some(addr.id)
-        } else {
-            None
-        }
-    }
-
-    pub fn node_addr(&self) -> Option<NodeAddr> {
-        if let ServiceId::Peer(_, addr) = self {
-            Some(*addr)
+        if let ServiceId::Peer(node_id) = self {
+            Some(*node_id)
         } else {
             None
         }
     }
-
-    pub fn dummy_peer_service_id(node_addr: NodeAddr) -> ServiceId {
-        ServiceId::Peer(0, node_addr)
-    }
 }
 
 impl esb::ServiceAddress for ServiceId {}
@@ -6,6 +6,7 @@
 
 #[cfg(feature = "shell")]
 mod opts;
+mod pre_lock_timeouts;
 mod runtime;
 mod state_report;
 mod swap_key_manager;
@@ -15,7 +16,10 @@ mod temporal_safety;
 
 #[cfg(feature = "shell")]
 pub use opts::Opts;
+pub use pre_lock_timeouts::PreLockTimeouts;
 pub use runtime::run;
-pub use runtime::CheckpointSwapd;
+pub use runtime::{CheckpointSwapd, LegacyCheckpointSwapd, LegacyServiceId};
 pub use state_report::StateReport;
+pub use swap_key_manager::WrappedEncryptedSignature;
 pub use swap_state::SwapStateMachine;
+pub use temporal_safety::TemporalSafety;
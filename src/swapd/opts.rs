@@ -43,11 +43,39 @@ pub struct Opts {
     #[clap(long = "arb-safety")]
     pub arbitrating_safety: u8,
 
+    /// Maximum age, in seconds, of the arbitrating syncer data; race-sensitive transactions are
+    /// not broadcasted while the syncer's view of the chain is older than this or unhealthy
+    #[clap(long = "arb-max-data-age", default_value = "120")]
+    pub arbitrating_max_data_age: u64,
+
     /// Finality argument used for the accordant blockchain; defines when transactions are
     /// considered final
     #[clap(long = "acc-finality")]
     pub accordant_finality: u8,
 
+    /// Maximum age, in seconds, of the accordant syncer data; the buy procedure signature is not
+    /// sent while the syncer's view of the chain is older than this or unhealthy
+    #[clap(long = "acc-max-data-age", default_value = "120")]
+    pub accordant_max_data_age: u64,
+
+    /// Seconds to await the commit and the reveal of the counterparty before aborting the swap,
+    /// zero disables the timeout
+    #[clap(long, default_value = "300")]
+    pub commit_timeout: u64,
+
+    /// Seconds to await the next protocol step once the parameters are revealed before aborting
+    /// the swap, zero disables the timeout
+    #[clap(long, default_value = "600")]
+    pub reveal_timeout: u64,
+
+    /// Seconds to await the funding as Bob before aborting the swap, zero disables the timeout
+    #[clap(long, default_value = "3600")]
+    pub funding_timeout: u64,
+
+    /// Seconds to await the bitcoin lock before aborting the swap, zero disables the timeout
+    #[clap(long, default_value = "3600")]
+    pub lock_timeout: u64,
+
     /// These params can be read also from the configuration file, not just
     /// Command-line args or environment variables
     #[clap(flatten)]
@@ -0,0 +1,119 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::time::Duration;
+
+use crate::swapd::SwapStateMachine;
+
+/// Time the swap waits in each state before the bitcoin lock before it is aborted, so an
+/// unresponsive counterparty does not hold the swap, its funding address and its syncer tasks
+/// forever. A zero duration disables the timeout.
+#[derive(Debug, Clone)]
+pub struct PreLockTimeouts {
+    /// Awaiting the commit and the reveal of the counterparty
+    pub commit: Duration,
+    /// Awaiting Bob's fee estimation or Alice awaiting Bob's core arbitrating setup
+    pub reveal: Duration,
+    /// Bob awaiting the funding
+    pub funding: Duration,
+    /// Bob awaiting Alice's refund procedure signatures or Alice awaiting Bob's lock
+    pub lock: Duration,
+}
+
+impl PreLockTimeouts {
+    /// Returns the timeout of the state, None if the state is past the lock or its timeout is
+    /// disabled
+    pub fn timeout(&self, state: &SwapStateMachine) -> Option<Duration> {
+        let timeout = match state {
+            SwapStateMachine::BobInitMaker(_)
+            | SwapStateMachine::AliceInitMaker(_)
+            | SwapStateMachine::BobInitTaker(_)
+            | SwapStateMachine::AliceInitTaker(_)
+            | SwapStateMachine::BobTakerMakerCommit(_)
+            | SwapStateMachine::AliceTakerMakerCommit(_) => self.commit,
+            SwapStateMachine::BobReveal(_) | SwapStateMachine::AliceReveal(_) => self.reveal,
+            SwapStateMachine::BobFeeEstimated(_) => self.funding,
+            SwapStateMachine::BobFunded(_) | SwapStateMachine::AliceCoreArbitratingSetup(_) => {
+                self.lock
+            }
+            _ => return None,
+        };
+        Some(timeout).filter(|timeout| !timeout.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swapd::swap_state::test_states;
+    use farcaster_core::role::SwapRole;
+
+    fn timeouts() -> PreLockTimeouts {
+        PreLockTimeouts {
+            commit: Duration::from_secs(1),
+            reveal: Duration::from_secs(2),
+            funding: Duration::from_secs(3),
+            lock: Duration::from_secs(4),
+        }
+    }
+
+    fn secs(timeout: Option<Duration>) -> Option<u64> {
+        timeout.map(|timeout| timeout.as_secs())
+    }
+
+    #[test]
+    fn pre_lock_states() {
+        let timeouts = timeouts();
+        let (pre_lock_states, _) = test_states();
+        let expected = [
+            ("Bob Init Maker", 1),
+            ("Alice Init Maker", 1),
+            ("Bob Init Taker", 1),
+            ("Alice Init Taker", 1),
+            ("Bob Taker Maker Commit", 1),
+            ("Alice Taker Maker Commit", 1),
+            ("Bob Reveal", 2),
+            ("Alice Reveal", 2),
+            ("Bob Fee Estimated", 3),
+            ("Bob Funded", 4),
+            ("Alice Core Arbitrating Setup", 4),
+        ];
+        assert_eq!(pre_lock_states.len(), expected.len());
+        for (state, (name, timeout)) in pre_lock_states.iter().zip(expected) {
+            assert_eq!(state.to_string(), name);
+            assert_eq!(secs(timeouts.timeout(state)), Some(timeout), "{}", state);
+        }
+    }
+
+    #[test]
+    fn disabled_timeout() {
+        let timeouts = PreLockTimeouts {
+            reveal: Duration::ZERO,
+            ..timeouts()
+        };
+        for state in test_states().0 {
+            match state {
+                SwapStateMachine::BobReveal(_) | SwapStateMachine::AliceReveal(_) => {
+                    assert_eq!(timeouts.timeout(&state), None, "{}", state)
+                }
+                _ => assert!(timeouts.timeout(&state).is_some(), "{}", state),
+            }
+        }
+    }
+
+    #[test]
+    fn post_lock_states() {
+        let timeouts = timeouts();
+        let (_, post_lock_states) = test_states();
+        let start_states = [
+            SwapStateMachine::StartTaker(SwapRole::Bob),
+            SwapStateMachine::StartMaker(SwapRole::Alice),
+        ];
+        for state in post_lock_states.iter().chain(start_states.iter()) {
+            assert_eq!(timeouts.timeout(state), None, "{}", state);
+        }
+    }
+}
@@ -5,6 +5,7 @@
 // https://opensource.org/licenses/MIT.
 
 use super::{
+    pre_lock_timeouts::PreLockTimeouts,
     swap_state::{SwapStateMachine, SwapStateMachineExecutor},
     syncer_client::{SyncerState, SyncerTasks},
     temporal_safety::TemporalSafety,
@@ -13,15 +14,18 @@ use super::{
 use crate::syncerd::types::{Event, TransactionConfirmations};
 use crate::syncerd::{Abort, Task, TaskTarget};
 use crate::{
-    bus::ctl::{Checkpoint, CtlMsg},
+    bus::ctl::{Checkpoint, CtlMsg, StaleSyncerData},
     bus::info::{InfoMsg, SwapInfo},
-    bus::p2p::PeerMsg,
+    bus::p2p::{PeerMsg, Receipt},
     bus::sync::SyncMsg,
-    bus::{BusMsg, ServiceBus},
+    bus::{BusMsg, Outcome, ServiceBus},
     syncerd::{HeightChanged, TransactionRetrieved, XmrAddressAddendum},
 };
-use crate::{service::SwapDetails, swapd::Opts};
 use crate::{service::SwapLogging, swapd::temporal_safety::SWEEP_MONERO_THRESHOLD};
+use crate::{
+    service::{ClientName, SwapDetails},
+    swapd::Opts,
+};
 use crate::{
     service::{Endpoints, Reporter},
     syncerd::AddressTransaction,
@@ -29,11 +33,13 @@ use crate::{
 use crate::{CtlServer, Error, LogStyle, Service, ServiceConfig, ServiceId};
 
 use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::{Duration, SystemTime};
 
+use bitcoin::hashes::sha256;
 use bitcoin::Txid;
 use farcaster_core::{
-    blockchain::Blockchain,
+    blockchain::{Blockchain, Network},
     role::{SwapRole, TradeRole},
     swap::btcxmr::{Deal, DealParameters},
     swap::SwapId,
@@ -41,6 +47,7 @@ use farcaster_core::{
 };
 
 use internet2::addr::{NodeAddr, NodeId};
+use internet2::TypedEnum;
 use microservices::esb::{self, Handler};
 use strict_encoding::{StrictDecode, StrictEncode};
 
@@ -51,7 +58,13 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
         trade_role: local_trade_role,
         arbitrating_finality,
         arbitrating_safety,
+        arbitrating_max_data_age,
         accordant_finality,
+        accordant_max_data_age,
+        commit_timeout,
+        reveal_timeout,
+        funding_timeout,
+        lock_timeout,
         ..
     } = opts;
 
@@ -85,6 +98,12 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
     };
 
     temporal_safety.valid_params()?;
+    let pre_lock_timeouts = PreLockTimeouts {
+        commit: Duration::from_secs(commit_timeout),
+        reveal: Duration::from_secs(reveal_timeout),
+        funding: Duration::from_secs(funding_timeout),
+        lock: Duration::from_secs(lock_timeout),
+    };
     let tasks = SyncerTasks {
         counter: 0,
         watched_addrs: none!(),
@@ -95,6 +114,7 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
         txids: none!(),
         final_txs: none!(),
         tasks: none!(),
+        blockchains: none!(),
     };
     let syncer_state = SyncerState {
         swap_id,
@@ -112,6 +132,10 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
         xmr_addr_addendum: None,
         confirmations: none!(),
         broadcasted_txs: none!(),
+        bitcoin_tip_status: None,
+        monero_tip_status: None,
+        bitcoin_max_data_age: Duration::from_secs(arbitrating_max_data_age),
+        monero_max_data_age: Duration::from_secs(accordant_max_data_age),
     };
 
     let state_report = StateReport::new("Start".to_string(), &temporal_safety, &syncer_state);
@@ -119,22 +143,29 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
     let runtime = Runtime {
         swap_id,
         identity: ServiceId::Swap(swap_id),
-        peer_service: ServiceId::dummy_peer_service_id(NodeAddr {
-            id: NodeId::from(deal.node_id), // node_id is bitcoin::Pubkey
-            addr: deal.peer_address,        // peer_address is InetSocketAddr
-        }),
+        peer_service: ServiceId::Peer(NodeId::from(deal.node_id)), // node_id is bitcoin::Pubkey
         connected: false,
         started: SystemTime::now(),
         syncer_state,
         temporal_safety,
         enquirer: None,
-        pending_peer_request: none!(),
+        outbox: none!(),
+        received_peer_msgs: none!(),
+        pending_receipts: none!(),
+        checkpointing_receipts: none!(),
         deal,
         local_trade_role,
         local_swap_role,
         latest_state_report: state_report,
         swap_state_machine,
         unhandled_peer_message: None, // The last message we received and was not handled by the state machine
+        withheld_broadcasts: none!(),
+        cooperative_refund: none!(),
+        latest_checkpoint: None,
+        pre_lock_timeouts,
+        state_since: SystemTime::now(),
+        timed_out: false,
+        shutdown_checkpointing: false,
     };
     let broker = false;
     Service::run(config, runtime, broker)
@@ -149,20 +180,39 @@ pub struct Runtime {
     pub enquirer: Option<ServiceId>,
     pub syncer_state: SyncerState,
     pub temporal_safety: TemporalSafety,
-    pub pending_peer_request: Vec<PeerMsg>, // Peer requests that failed and are waiting for reconnection
+    pub outbox: Vec<PeerMsg>, // Protocol messages sent to the peer and not yet acknowledged
+    pub received_peer_msgs: HashSet<sha256::Hash>, // Ids of the protocol messages received, used to drop duplicates
+    pub pending_receipts: Vec<Receipt>, // Receipts of the received protocol messages not yet part of a checkpoint
+    pub checkpointing_receipts: VecDeque<Vec<Receipt>>, // Receipts sent to the peer once databased saved the checkpoint containing their messages, in checkpoint order
     pub deal: Deal,
     pub local_trade_role: TradeRole,
     pub local_swap_role: SwapRole,
     pub latest_state_report: StateReport,
     pub swap_state_machine: SwapStateMachine,
     pub unhandled_peer_message: Option<PeerMsg>,
+    pub withheld_broadcasts: HashMap<TxLabel, Blockchain>, // Race-sensitive broadcasts withheld while the syncer data of the blockchain is stale
+    pub cooperative_refund: CooperativeRefundConsent,
+    pub latest_checkpoint: Option<SwapStateMachine>, // The state of the latest checkpoint, checkpointed again before a shutdown
+    pub pre_lock_timeouts: PreLockTimeouts,
+    pub state_since: SystemTime, // When the swap entered its current state, checked against the pre-lock timeouts
+    pub timed_out: bool, // Set once the swap is aborted because it did not progress before the lock in time
+    pub shutdown_checkpointing: bool, // Set while the shutdown waits for databased to save the checkpoints before replying ready
+}
+
+/// Tracks whether each swap participant agreed to settle a locked swap with a cooperative refund
+/// instead of waiting for the cancel timelock.
+#[derive(Debug, Clone, Default)]
+pub struct CooperativeRefundConsent {
+    pub local: bool,
+    pub remote: bool,
 }
 
 #[derive(Debug, Clone, Display, StrictEncode, StrictDecode)]
 #[display("checkpoint-swapd")]
 pub struct CheckpointSwapd {
     pub state: SwapStateMachine,
-    pub pending_msg: Option<PeerMsg>,
+    pub outbox: Vec<PeerMsg>,
+    pub received_msgs: Vec<sha256::Hash>,
     pub enquirer: Option<ServiceId>,
     pub xmr_addr_addendum: Option<XmrAddressAddendum>,
     pub temporal_safety: TemporalSafety,
@@ -173,6 +223,89 @@ pub struct CheckpointSwapd {
     pub deal: Deal,
 }
 
+impl CheckpointSwapd {
+    /// Decodes a checkpoint, falling back onto the legacy layout for the checkpoints written
+    /// before the outbox replaced the single pending message
+    pub fn strict_decode_with_legacy(data: &[u8]) -> Result<Self, strict_encoding::Error> {
+        CheckpointSwapd::strict_deserialize(data).or_else(|err| {
+            LegacyCheckpointSwapd::strict_deserialize(data)
+                .map(CheckpointSwapd::from)
+                .map_err(|_| err)
+        })
+    }
+}
+
+/// Layout of the checkpoints written before the outbox and the received messages were
+/// introduced, only decoded to restore these swaps
+#[derive(Debug, Clone, StrictEncode, StrictDecode)]
+pub struct LegacyCheckpointSwapd {
+    pub state: SwapStateMachine,
+    pub pending_msg: Option<PeerMsg>,
+    pub enquirer: Option<LegacyServiceId>,
+    pub xmr_addr_addendum: Option<XmrAddressAddendum>,
+    pub temporal_safety: TemporalSafety,
+    pub txids: Vec<(TxLabel, Txid)>,
+    pub pending_broadcasts: Vec<(bitcoin::Transaction, TxLabel)>,
+    pub local_trade_role: TradeRole,
+    pub connected_counterparty_node_id: Option<NodeId>,
+    pub deal: Deal,
+}
+
+impl From<LegacyCheckpointSwapd> for CheckpointSwapd {
+    fn from(legacy: LegacyCheckpointSwapd) -> Self {
+        CheckpointSwapd {
+            state: legacy.state,
+            // The pending message was resent on restore, keep it in the outbox until acknowledged
+            outbox: legacy.pending_msg.into_iter().collect(),
+            received_msgs: vec![],
+            enquirer: legacy.enquirer.map(ServiceId::from),
+            xmr_addr_addendum: legacy.xmr_addr_addendum,
+            temporal_safety: legacy.temporal_safety,
+            txids: legacy.txids,
+            pending_broadcasts: legacy.pending_broadcasts,
+            local_trade_role: legacy.local_trade_role,
+            connected_counterparty_node_id: legacy.connected_counterparty_node_id,
+            deal: legacy.deal,
+        }
+    }
+}
+
+/// Layout of the service ids in the legacy checkpoints, when peer connections were keyed by a
+/// random id and the remote node address
+#[derive(Debug, Clone, StrictEncode, StrictDecode)]
+pub enum LegacyServiceId {
+    Loopback,
+    Farcasterd,
+    Peer(u128, NodeAddr),
+    Swap(SwapId),
+    Client(u64),
+    Syncer(Blockchain, Network),
+    Wallet,
+    Grpcd,
+    GrpcdClient(u64),
+    Database,
+    Other(ClientName),
+}
+
+impl From<LegacyServiceId> for ServiceId {
+    fn from(legacy: LegacyServiceId) -> Self {
+        match legacy {
+            LegacyServiceId::Loopback => ServiceId::Loopback,
+            LegacyServiceId::Farcasterd => ServiceId::Farcasterd,
+            // Connections are now keyed by the remote node id only
+            LegacyServiceId::Peer(_, node_addr) => ServiceId::Peer(node_addr.id),
+            LegacyServiceId::Swap(swap_id) => ServiceId::Swap(swap_id),
+            LegacyServiceId::Client(id) => ServiceId::Client(id),
+            LegacyServiceId::Syncer(blockchain, network) => ServiceId::Syncer(blockchain, network),
+            LegacyServiceId::Wallet => ServiceId::Wallet,
+            LegacyServiceId::Grpcd => ServiceId::Grpcd,
+            LegacyServiceId::GrpcdClient(id) => ServiceId::GrpcdClient(id),
+            LegacyServiceId::Database => ServiceId::Database,
+            LegacyServiceId::Other(name) => ServiceId::Other(name),
+        }
+    }
+}
+
 impl CtlServer for Runtime {}
 impl Reporter for Runtime {
     fn report_to(&self) -> Option<ServiceId> {
@@ -237,7 +370,36 @@ impl esb::Handler<ServiceBus> for Runtime {
 }
 
 impl Runtime {
+    /// Sends a message to the counterparty. Protocol messages are kept in the outbox until the
+    /// counterparty acknowledges them with a receipt.
     pub fn send_peer(&mut self, endpoints: &mut Endpoints, msg: PeerMsg) -> Result<(), Error> {
+        if msg.is_protocol() {
+            let msg_id = msg.msg_id();
+            if !self.outbox.iter().any(|queued| queued.msg_id() == msg_id) {
+                self.outbox.push(msg.clone());
+            }
+        }
+        self.try_send_peer(endpoints, msg)?;
+        Ok(())
+    }
+
+    /// Resends all unacknowledged messages of the outbox, stopping at the first failure.
+    pub fn resend_outbox(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        if !self.outbox.is_empty() {
+            self.log_info(format!(
+                "Resending {} unacknowledged peer messages",
+                self.outbox.len()
+            ));
+        }
+        for msg in self.outbox.clone() {
+            if !self.try_send_peer(endpoints, msg)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn try_send_peer(&mut self, endpoints: &mut Endpoints, msg: PeerMsg) -> Result<bool, Error> {
         self.log_trace(format!(
             "sending peer message {} to {}",
             msg, self.peer_service
@@ -262,9 +424,9 @@ impl Runtime {
                 ServiceId::Farcasterd,
                 BusMsg::Ctl(CtlMsg::PeerdUnreachable(self.peer_service.clone())),
             )?;
-            self.pending_peer_request.push(msg);
+            return Ok(false);
         }
-        Ok(())
+        Ok(true)
     }
 
     pub fn swap_id(&self) -> SwapId {
@@ -325,8 +487,19 @@ impl Runtime {
 
         match request {
             // bob and alice
-            PeerMsg::Abort(_) => {
-                return Err(Error::Farcaster("Abort not yet supported".to_string()));
+            PeerMsg::Abort(abort) => {
+                let msg = match abort.error_body {
+                    Some(reason) => format!("Counterparty aborted the swap: {}", reason),
+                    None => "Counterparty aborted the swap".to_string(),
+                };
+                self.log_warn(&msg);
+                self.report_progress_message(endpoints, msg)?;
+                // Follow the counterparty as long as the swap can still be aborted
+                return self.execute_state_machine(
+                    endpoints,
+                    BusMsg::Ctl(CtlMsg::AbortSwap),
+                    ServiceId::Farcasterd,
+                );
             }
 
             PeerMsg::Ping(_) | PeerMsg::Pong(_) | PeerMsg::PingPeer => {
@@ -334,14 +507,97 @@ impl Runtime {
                     "Ping/Pong must remain in peerd, not supported in swapd".to_string(),
                 ));
             }
+
+            PeerMsg::MsgReceipt(receipt) => {
+                self.log_debug(format!("Peer acknowledged message {}", receipt.msg_id));
+                self.outbox.retain(|msg| msg.msg_id() != receipt.msg_id);
+                return Ok(());
+            }
             _ => {}
         }
 
+        // Replays of the unhandled peer message are not duplicates
+        let msg_id = request.msg_id();
+        let is_replay = self.unhandled_peer_message.as_ref().map(PeerMsg::msg_id) == Some(msg_id);
+        if !self.received_peer_msgs.insert(msg_id) && !is_replay {
+            self.log_debug(format!("Dropping duplicate peer message {}", request));
+            // The receipt of an already persisted message may have been lost, send it again
+            if !self.awaits_checkpoint(msg_id) {
+                self.send_receipt(endpoints, &request)?;
+            }
+            return Ok(());
+        }
+        if !self.awaits_checkpoint(msg_id) {
+            self.pending_receipts.push(Receipt {
+                swap_id: self.swap_id,
+                msg_type: request.get_type(),
+                msg_id,
+            });
+        }
+
         self.execute_state_machine(endpoints, BusMsg::P2p(request), source)?;
+        self.persist_received_msgs(endpoints)?;
 
         Ok(())
     }
 
+    /// Returns if the receipt of the message is sent only once a checkpoint persisted it
+    fn awaits_checkpoint(&self, msg_id: sha256::Hash) -> bool {
+        self.pending_receipts
+            .iter()
+            .chain(self.checkpointing_receipts.iter().flatten())
+            .any(|receipt| receipt.msg_id == msg_id)
+    }
+
+    fn send_receipt(&mut self, endpoints: &mut Endpoints, msg: &PeerMsg) -> Result<(), Error> {
+        let receipt = Receipt {
+            swap_id: self.swap_id,
+            msg_type: msg.get_type(),
+            msg_id: msg.msg_id(),
+        };
+        self.try_send_peer(endpoints, PeerMsg::MsgReceipt(receipt))?;
+        Ok(())
+    }
+
+    fn send_shutdown_ready(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        self.log_info("Ready to shut down");
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Farcasterd,
+            BusMsg::Ctl(CtlMsg::ShutdownReady),
+        )?;
+        Ok(())
+    }
+
+    /// Checkpoints the handled messages the state machine did not checkpoint itself, their
+    /// receipts are sent once databased saved them. Before the first checkpoint the swap cannot
+    /// be restored anyway, the receipts are sent right away.
+    fn persist_received_msgs(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        let unhandled_msg_id = self.unhandled_peer_message.as_ref().map(PeerMsg::msg_id);
+        if self
+            .pending_receipts
+            .iter()
+            .all(|receipt| Some(receipt.msg_id) == unhandled_msg_id)
+        {
+            return Ok(());
+        }
+        match self.latest_checkpoint.clone() {
+            Some(state) => self.checkpoint_state(endpoints, None, state),
+            None => {
+                let (receipts, pending) = self
+                    .pending_receipts
+                    .drain(..)
+                    .partition(|receipt| Some(receipt.msg_id) != unhandled_msg_id);
+                self.pending_receipts = pending;
+                for receipt in receipts {
+                    self.try_send_peer(endpoints, PeerMsg::MsgReceipt(receipt))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
     pub fn handle_ctl(
         &mut self,
         endpoints: &mut Endpoints,
@@ -357,9 +613,80 @@ impl Runtime {
             }
             CtlMsg::Terminate if source == ServiceId::Farcasterd => {
                 self.log_info(format!("Terminating {}", self.identity()).label());
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::Ctl(CtlMsg::Terminated),
+                )?;
                 std::process::exit(0);
             }
 
+            CtlMsg::PrepareShutdown(force) if source == ServiceId::Farcasterd => {
+                if !force && self.in_race_window() {
+                    self.log_warn(format!(
+                        "Refusing shutdown inside a race window in state {}",
+                        self.swap_state_machine
+                    ));
+                    let reason = format!(
+                        "swap {} is inside a race window in state {}",
+                        self.swap_id, self.swap_state_machine
+                    );
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        ServiceId::Farcasterd,
+                        BusMsg::Ctl(CtlMsg::ShutdownRefused(reason)),
+                    )?;
+                } else if let Some(state) = self.latest_checkpoint.clone() {
+                    // Checkpoint again the latest checkpointed state with the current outbox and
+                    // watched transactions, the shutdown is ready once databased saved it. If it
+                    // never does, farcasterd gives up on us with the shutdown timeout.
+                    self.checkpoint_state(endpoints, None, state)?;
+                    self.shutdown_checkpointing = true;
+                    self.log_info("Checkpointing before shutting down");
+                } else {
+                    self.send_shutdown_ready(endpoints)?;
+                }
+            }
+
+            // The remote peer did not negotiate the cooperative refund: withdraw the request, the
+            // swap goes on with the cancel timelock
+            CtlMsg::Failure(failure) if source == self.peer_service => {
+                self.log_warn(&failure.info);
+                self.outbox.retain(|msg| msg.required_feature().is_none());
+                self.cooperative_refund.local = false;
+                if let Some(dest) = self.report_to() {
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        dest,
+                        BusMsg::Ctl(CtlMsg::Failure(failure)),
+                    )?;
+                }
+            }
+
+            CtlMsg::SyncerRestarted(blockchain) if source == ServiceId::Farcasterd => {
+                let syncer = match blockchain {
+                    Blockchain::Bitcoin => self.syncer_state.bitcoin_syncer(),
+                    Blockchain::Monero => self.syncer_state.monero_syncer(),
+                };
+                let tasks = self.syncer_state.live_tasks(blockchain);
+                self.log_info(format!(
+                    "Syncer {} was relaunched, re-registering {} tasks",
+                    syncer.bright_blue_italic(),
+                    tasks.len()
+                ));
+                for task in tasks {
+                    endpoints.send_to(
+                        ServiceBus::Sync,
+                        self.identity(),
+                        syncer.clone(),
+                        BusMsg::Sync(SyncMsg::Task(task)),
+                    )?;
+                }
+            }
+
             CtlMsg::Disconnected => {
                 self.connected = false;
             }
@@ -376,23 +703,27 @@ impl Runtime {
                 self.log_info(format!("Peer {} reconnected", service_id));
                 self.peer_service = service_id;
                 self.connected = true;
-                for msg in self.pending_peer_request.clone().iter() {
-                    self.send_peer(endpoints, msg.clone())?;
-                }
-                self.pending_peer_request.clear();
+                self.resend_outbox(endpoints)?;
             }
 
-            CtlMsg::FailedPeerMessage(msg) => {
-                self.log_warn(format!(
-                    "Sending the peer message {} failed. Adding to pending peer requests",
-                    msg
-                ));
-                self.pending_peer_request.push(msg);
+            // Databased saved our oldest unconfirmed checkpoint, the messages it contains are
+            // acknowledged to the peer
+            CtlMsg::CheckpointSaved if source == ServiceId::Database => {
+                for receipt in self.checkpointing_receipts.pop_front().unwrap_or_default() {
+                    self.try_send_peer(endpoints, PeerMsg::MsgReceipt(receipt))?;
+                }
+                // The checkpoints are saved in order, the shutdown checkpoint is saved once none
+                // is left
+                if self.shutdown_checkpointing && self.checkpointing_receipts.is_empty() {
+                    self.shutdown_checkpointing = false;
+                    self.send_shutdown_ready(endpoints)?;
+                }
             }
 
             CtlMsg::Checkpoint(Checkpoint { swap_id: _, state }) => {
                 let CheckpointSwapd {
-                    pending_msg,
+                    outbox,
+                    received_msgs,
                     enquirer,
                     temporal_safety,
                     mut txids,
@@ -403,7 +734,9 @@ impl Runtime {
                     ..
                 } = state;
                 self.log_info("Restoring swap");
+                self.latest_checkpoint = Some(state.clone());
                 self.swap_state_machine = state;
+                self.state_since = SystemTime::now();
                 self.enquirer = enquirer;
                 self.temporal_safety = temporal_safety;
                 // We need to update the peerd for the pending requests in case of reconnect
@@ -412,6 +745,10 @@ impl Runtime {
                     .watch_height(endpoints, Blockchain::Bitcoin)?;
                 self.syncer_state
                     .watch_height(endpoints, Blockchain::Monero)?;
+                self.syncer_state
+                    .watch_tip_status(endpoints, Blockchain::Bitcoin)?;
+                self.syncer_state
+                    .watch_tip_status(endpoints, Blockchain::Monero)?;
 
                 self.log_trace("Watching transactions");
                 for (tx_label, txid) in txids.drain(..) {
@@ -455,9 +792,9 @@ impl Runtime {
                     )?;
                 }
 
-                if let Some(msg) = pending_msg {
-                    self.send_peer(endpoints, msg)?;
-                }
+                self.received_peer_msgs = received_msgs.into_iter().collect();
+                self.outbox = outbox;
+                self.resend_outbox(endpoints)?;
             }
 
             req => {
@@ -476,7 +813,7 @@ impl Runtime {
     ) -> Result<(), Error> {
         match request {
             InfoMsg::GetInfo => {
-                let connection = self.peer_service.node_addr();
+                let connection = self.peer_service.node_id();
                 let info = SwapInfo {
                     swap_id: self.swap_id,
                     connection,
@@ -523,6 +860,11 @@ impl Runtime {
                             .handle_height_change(*height, Blockchain::Monero);
                     }
 
+                    Event::TipStatus(tip_status) => {
+                        self.syncer_state
+                            .handle_tip_status(tip_status, Blockchain::Monero);
+                    }
+
                     Event::TransactionConfirmations(TransactionConfirmations {
                         id,
                         confirmations,
@@ -540,7 +882,7 @@ impl Runtime {
                         if let Some(txlabel) = self.syncer_state.tasks.watched_txs.get(id) {
                             self.syncer_state
                                 .last_tx_event
-                                .insert(*txlabel, request.clone());
+                                .insert(*txlabel, (source.clone(), request.clone()));
                         }
                     }
 
@@ -549,7 +891,7 @@ impl Runtime {
                         if let Some(txlabel) = self.syncer_state.tasks.watched_addrs.get(id) {
                             self.syncer_state
                                 .last_tx_event
-                                .insert(*txlabel, request.clone());
+                                .insert(*txlabel, (source.clone(), request.clone()));
                         }
                     }
 
@@ -572,6 +914,11 @@ impl Runtime {
                             .handle_height_change(*height, Blockchain::Bitcoin);
                     }
 
+                    Event::TipStatus(tip_status) => {
+                        self.syncer_state
+                            .handle_tip_status(tip_status, Blockchain::Bitcoin);
+                    }
+
                     // This re-triggers the tx fetch event in case the transaction was not detected yet
                     Event::TransactionRetrieved(TransactionRetrieved { id, tx: None })
                         if self.syncer_state.tasks.retrieving_txs.contains_key(id)
@@ -607,7 +954,7 @@ impl Runtime {
                         if let Some(txlabel) = self.syncer_state.tasks.watched_txs.get(id) {
                             self.syncer_state
                                 .last_tx_event
-                                .insert(*txlabel, request.clone());
+                                .insert(*txlabel, (source.clone(), request.clone()));
                         }
                     }
 
@@ -627,7 +974,7 @@ impl Runtime {
                         if let Some(txlabel) = self.syncer_state.tasks.watched_txs.get(id) {
                             self.syncer_state
                                 .last_tx_event
-                                .insert(*txlabel, request.clone());
+                                .insert(*txlabel, (source.clone(), request.clone()));
                         }
                     }
 
@@ -640,7 +987,7 @@ impl Runtime {
                         if let Some(txlabel) = self.syncer_state.tasks.watched_addrs.get(id) {
                             self.syncer_state
                                 .last_tx_event
-                                .insert(*txlabel, request.clone());
+                                .insert(*txlabel, (source.clone(), request.clone()));
                         }
                         self.log_debug(event);
                     }
@@ -671,7 +1018,17 @@ impl Runtime {
             }
             _ => {}
         }
-        self.execute_state_machine(endpoints, BusMsg::Sync(request), source)?;
+        let tip_refreshed = (source == self.syncer_state.bitcoin_syncer
+            || source == self.syncer_state.monero_syncer)
+            && matches!(
+                request,
+                SyncMsg::Event(Event::HeightChanged(_) | Event::TipStatus(_))
+            );
+        self.execute_state_machine(endpoints, BusMsg::Sync(request), source.clone())?;
+        if tip_refreshed {
+            self.reevaluate_withheld_broadcasts(endpoints)?;
+            self.check_pre_lock_timeout(endpoints)?;
+        }
 
         Ok(())
     }
@@ -691,6 +1048,9 @@ impl Runtime {
             msg.clone(),
             self.swap_state_machine.clone(),
         )? {
+            if std::mem::discriminant(&ssm) != std::mem::discriminant(&self.swap_state_machine) {
+                self.state_since = SystemTime::now();
+            }
             self.swap_state_machine = ssm;
             // On SwapEnd, report immediately to ensure the progress message goes out before the swap is terminated, then let farcasterd know of the outcome.
             if let SwapStateMachine::SwapEnd(outcome) = &self.swap_state_machine {
@@ -720,8 +1080,8 @@ impl Runtime {
                 self.handle_msg(endpoints, source.clone(), peer_msg)?;
             }
             // Replay syncer events to ensure we immediately advance through states that can be skipped
-            for event in self.syncer_state.last_tx_event.clone().values() {
-                self.handle_sync(endpoints, source.clone(), event.clone())?;
+            for (syncer, event) in self.syncer_state.last_tx_event.clone().into_values() {
+                self.handle_sync(endpoints, syncer, event)?;
             }
         } else if let BusMsg::P2p(peer_msg) = msg {
             self.unhandled_peer_message = Some(peer_msg);
@@ -746,12 +1106,101 @@ impl Runtime {
         Ok(())
     }
 
+    /// Abort the swap if it stayed in a state before the lock for longer than the timeout of the
+    /// state, Bob's funding is swept back by the abort
+    fn check_pre_lock_timeout(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        let timeout = match self.pre_lock_timeouts.timeout(&self.swap_state_machine) {
+            Some(timeout) => timeout,
+            None => return Ok(()),
+        };
+        // Once the lock is seen the swap can only be cancelled
+        if self.timed_out
+            || self.syncer_state.get_confs(TxLabel::Lock).is_some()
+            || self
+                .state_since
+                .elapsed()
+                .map_or(true, |elapsed| elapsed < timeout)
+        {
+            return Ok(());
+        }
+        let msg = format!(
+            "No progress in state {} for {} seconds, aborting the swap",
+            self.swap_state_machine,
+            timeout.as_secs()
+        );
+        self.log_warn(&msg);
+        self.report_progress_message(endpoints, msg)?;
+        self.timed_out = true;
+        self.execute_state_machine(
+            endpoints,
+            BusMsg::Ctl(CtlMsg::AbortSwap),
+            ServiceId::Farcasterd,
+        )
+    }
+
+    /// The outcome of an aborted swap, whether it was aborted on request or timed out
+    pub fn abort_outcome(&self) -> Outcome {
+        if self.timed_out {
+            Outcome::FailureTimeout
+        } else {
+            Outcome::FailureAbort
+        }
+    }
+
+    /// Whether the next transaction of the swap may race the counterparty: the lock is final and
+    /// the buy is no longer safe while the cancel is not seen, or the cancel is final and the
+    /// refund is no longer safe while neither the refund nor the punish are seen
+    pub fn in_race_window(&self) -> bool {
+        let confs = |label| self.syncer_state.get_confs(label);
+        match (confs(TxLabel::Lock), confs(TxLabel::Cancel)) {
+            (_, Some(cancel_confs)) => {
+                confs(TxLabel::Refund).is_none()
+                    && confs(TxLabel::Punish).is_none()
+                    && self
+                        .temporal_safety
+                        .final_tx(cancel_confs, Blockchain::Bitcoin)
+                    && !self.temporal_safety.safe_refund(cancel_confs)
+            }
+            (Some(lock_confs), None) => {
+                confs(TxLabel::Buy).is_none()
+                    && self
+                        .temporal_safety
+                        .final_tx(lock_confs, Blockchain::Bitcoin)
+                    && !self.temporal_safety.safe_buy(lock_confs)
+            }
+            (None, None) => false,
+        }
+    }
+
     pub fn checkpoint_state(
         &mut self,
         endpoints: &mut Endpoints,
         pending_msg: Option<PeerMsg>,
         next_state: SwapStateMachine,
     ) -> Result<(), Error> {
+        self.latest_checkpoint = Some(next_state.clone());
+        let mut outbox = self.outbox.clone();
+        if let Some(msg) = pending_msg {
+            let msg_id = msg.msg_id();
+            if !outbox.iter().any(|queued| queued.msg_id() == msg_id) {
+                outbox.push(msg);
+            }
+        }
+        // A message still waiting to be handled by the state machine has not been processed yet,
+        // so it must be accepted again if the peer resends it after a restore
+        let unhandled_msg_id = self.unhandled_peer_message.as_ref().map(PeerMsg::msg_id);
+        let received_msgs = self
+            .received_peer_msgs
+            .iter()
+            .filter(|msg_id| Some(**msg_id) != unhandled_msg_id)
+            .cloned()
+            .collect();
+        let (receipts, pending) = self
+            .pending_receipts
+            .drain(..)
+            .partition(|receipt| Some(receipt.msg_id) != unhandled_msg_id);
+        self.pending_receipts = pending;
+        self.checkpointing_receipts.push_back(receipts);
         endpoints.send_to(
             ServiceBus::Ctl,
             self.identity(),
@@ -760,7 +1209,8 @@ impl Runtime {
                 swap_id: self.swap_id,
                 state: CheckpointSwapd {
                     state: next_state,
-                    pending_msg,
+                    outbox,
+                    received_msgs,
                     enquirer: self.enquirer.clone(),
                     temporal_safety: self.temporal_safety.clone(),
                     txids: self.syncer_state.tasks.txids.clone().drain().collect(),
@@ -815,6 +1265,77 @@ impl Runtime {
         ));
     }
 
+    /// Checks whether the syncer data of the given blockchain can be trusted to take a
+    /// race-sensitive decision on the transaction. If not, the decision must be withheld: clients
+    /// receive a progress warning and farcasterd is notified. Withheld broadcasts are re-evaluated
+    /// once fresh data comes in.
+    pub fn syncer_data_fresh(
+        &mut self,
+        endpoints: &mut Endpoints,
+        tx_label: TxLabel,
+        blockchain: Blockchain,
+    ) -> Result<bool, Error> {
+        match self.syncer_state.withhold_reason(tx_label, blockchain) {
+            None => Ok(true),
+            Some(reason) => {
+                // only report once per withheld transaction
+                if self
+                    .withheld_broadcasts
+                    .insert(tx_label, blockchain)
+                    .is_none()
+                {
+                    let msg = format!(
+                        "Withholding {} transaction, {} syncer data cannot be trusted: {}",
+                        tx_label, blockchain, reason
+                    );
+                    self.log_warn(&msg);
+                    self.report_progress_message_log_fail(endpoints, msg);
+                    self.send_ctl(
+                        endpoints,
+                        ServiceId::Farcasterd,
+                        BusMsg::Ctl(CtlMsg::StaleSyncerData(StaleSyncerData {
+                            blockchain,
+                            tx_label,
+                            reason,
+                        })),
+                    )?;
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    fn reevaluate_withheld_broadcasts(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        if self.withheld_broadcasts.is_empty()
+            || self
+                .withheld_broadcasts
+                .values()
+                .any(|blockchain| self.syncer_state.stale_data(*blockchain).is_some())
+        {
+            return Ok(());
+        }
+        let withheld: Vec<String> = self
+            .withheld_broadcasts
+            .drain()
+            .map(|(tx_label, _)| tx_label.to_string())
+            .collect();
+        let msg = format!(
+            "Syncer data is fresh again, re-evaluating withheld {} transaction(s)",
+            withheld.join(", ")
+        );
+        self.log_info(&msg);
+        self.report_progress_message_log_fail(endpoints, msg);
+        // Replay the messages and events the withheld decisions were based on, each from the
+        // service it was received from
+        if let Some(peer_msg) = self.unhandled_peer_message.clone() {
+            self.handle_msg(endpoints, self.peer_service.clone(), peer_msg)?;
+        }
+        for (syncer, event) in self.syncer_state.last_tx_event.clone().into_values() {
+            self.handle_sync(endpoints, syncer, event)?;
+        }
+        Ok(())
+    }
+
     pub fn report_progress_message_log_fail(
         &mut self,
         endpoints: &mut Endpoints,
@@ -6,18 +6,25 @@
 
 use std::convert::TryInto;
 
-use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::{
+    secp256k1::{ecdsa::Signature, PublicKey, Secp256k1, SecretKey},
+    util::psbt::PartiallySignedTransaction,
+};
 use farcaster_core::{
     bitcoin::{
         segwitv0::{BuyTx, CancelTx, FundingTx, LockTx, PunishTx, RefundTx},
         BitcoinSegwitV0,
     },
-    blockchain::FeePriority,
+    blockchain::{Fee, FeePriority},
     consensus::{self, Decodable, Encodable},
-    crypto::{ArbitratingKeyId, CommitmentEngine, GenerateKey, ProveCrossGroupDleq, SharedKeyId},
+    crypto::{
+        AccordantKeyId, ArbitratingKeyId, CommitmentEngine, EncSign, GenerateKey,
+        ProveCrossGroupDleq, RecoverSecret, SharedKeyId, Sign,
+    },
     impl_strict_encoding,
     monero::{Monero, SHARED_VIEW_KEY_ID},
     role::{SwapRole, TradeRole},
+    script::{DataLock, ScriptPath, SwapRoleKeys},
     swap::btcxmr::{
         message::{
             BuyProcedureSignature, CommitAliceParameters, CommitBobParameters, CoreArbitratingSetup,
@@ -28,14 +35,14 @@ use farcaster_core::{
         message::{RefundProcedureSignatures, RevealAliceParameters, RevealBobParameters},
         Alice, Bob, Deal, EncryptedSignature, KeyManager, Parameters,
     },
-    transaction::{Broadcastable, Fundable, Transaction, TxLabel, Witnessable},
+    transaction::{Broadcastable, Buyable, Fundable, Transaction, TxLabel, Witnessable},
 };
 use strict_encoding::{StrictDecode, StrictEncode};
 
 use crate::{
     bus::{
         ctl::{CtlMsg, Tx},
-        p2p::Reveal,
+        p2p::{CooperativeRefundSignature, Reveal},
         AddressSecretKey, BitcoinSecretKeyInfo, MoneroSecretKeyInfo,
     },
     event::Event,
@@ -78,7 +85,7 @@ pub struct AliceTxs {
 
 #[derive(Display, Clone, Debug)]
 #[display("Encrypted Signature")]
-pub struct WrappedEncryptedSignature(EncryptedSignature);
+pub struct WrappedEncryptedSignature(pub(super) EncryptedSignature);
 
 impl Encodable for WrappedEncryptedSignature {
     fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
@@ -201,22 +208,118 @@ impl AliceSwapKeyManager {
         acc_lock_height_lower_bound: u64,
     ) -> Result<SweepMoneroAddress, Error> {
         let AliceSwapKeyManager {
-            alice,
+            alice, key_manager, ..
+        } = self;
+        let sk_b_btc =
+            alice.recover_accordant_key(key_manager, &bob_params, adaptor_refund.0, refund_tx);
+        self.sweep_with_bob_key(
+            event,
+            runtime,
+            sk_b_btc,
+            &bob_params,
+            TxLabel::Refund,
+            acc_lock_height_lower_bound,
+        )
+    }
+
+    /// Builds a transaction spending the lock output directly to Bob's refund address and signs
+    /// it with an adaptor signature encrypted with Bob's adaptor key. Once Bob decrypts and
+    /// publishes it, his Monero key share can be extracted with
+    /// [`Self::process_cooperative_refund_tx`].
+    pub fn sign_cooperative_refund(
+        &mut self,
+        runtime: &mut Runtime,
+        bob_params: &Parameters,
+        core_arbitrating_setup: &CoreArbitratingSetup,
+    ) -> Result<(bitcoin::Transaction, WrappedEncryptedSignature), Error> {
+        let AliceSwapKeyManager {
             local_params,
             key_manager,
-            target_monero_address,
             ..
         } = self;
+        let lock = LockTx::from_partial(core_arbitrating_setup.lock.clone());
+        let cancel_lock = DataLock {
+            timelock: runtime.deal.parameters.cancel_timelock,
+            success: SwapRoleKeys::new(local_params.buy, bob_params.buy),
+            failure: SwapRoleKeys::new(local_params.cancel, bob_params.cancel),
+        };
+        let mut refund =
+            BuyTx::initialize(&lock, cancel_lock, bob_params.destination_address.clone())?;
+        refund
+            .as_partial_mut()
+            .set_fee(&runtime.deal.parameters.fee_strategy, FeePriority::Low)
+            .map_err(|err| Error::Farcaster(err.to_string()))?;
+        let msg = refund.generate_witness_message(ScriptPath::Success)?;
+        let adaptor_sig =
+            key_manager.encrypt_sign(ArbitratingKeyId::Buy, &bob_params.adaptor, msg)?;
+        runtime.log_info("Cooperative refund transaction created");
+        Ok((
+            refund.as_partial().clone().extract_tx(),
+            WrappedEncryptedSignature(adaptor_sig),
+        ))
+    }
 
-        let sk_b_btc =
-            alice.recover_accordant_key(key_manager, &bob_params, adaptor_refund.0, refund_tx);
+    pub fn process_cooperative_refund_tx(
+        &mut self,
+        event: &mut Event,
+        runtime: &mut Runtime,
+        refund_tx: bitcoin::Transaction,
+        bob_params: Parameters,
+        adaptor_sig: WrappedEncryptedSignature,
+        acc_lock_height_lower_bound: u64,
+    ) -> Result<SweepMoneroAddress, Error> {
+        let secp = Secp256k1::new();
+        // The witness carries both signatures, only the decrypted one yields Bob's adaptor key
+        let sk_b_btc = refund_tx
+            .input
+            .first()
+            .into_iter()
+            .flat_map(|input| input.witness.iter())
+            .filter(|item| item.len() > 1)
+            .filter_map(|item| Signature::from_der(&item[..item.len() - 1]).ok())
+            .map(|sig| {
+                self.key_manager
+                    .recover_secret_key(adaptor_sig.0.clone(), &bob_params.adaptor, sig)
+            })
+            .find(|sk| PublicKey::from_secret_key(&secp, sk) == bob_params.adaptor)
+            .ok_or_else(|| {
+                Error::Farcaster(
+                    "Cooperative refund transaction does not reveal Bob's key".to_string(),
+                )
+            })?;
+        self.sweep_with_bob_key(
+            event,
+            runtime,
+            sk_b_btc,
+            &bob_params,
+            "Cooperative refund",
+            acc_lock_height_lower_bound,
+        )
+    }
+
+    fn sweep_with_bob_key(
+        &mut self,
+        event: &mut Event,
+        runtime: &mut Runtime,
+        sk_b_btc: SecretKey,
+        bob_params: &Parameters,
+        tx_name: impl std::fmt::Display,
+        acc_lock_height_lower_bound: u64,
+    ) -> Result<SweepMoneroAddress, Error> {
+        let AliceSwapKeyManager {
+            local_params,
+            key_manager,
+            target_monero_address,
+            ..
+        } = self;
         let mut sk_b_btc_buf: Vec<u8> = (*sk_b_btc.as_ref()).into();
         sk_b_btc_buf.reverse();
         let sk_b = monero::PrivateKey::from_slice(sk_b_btc_buf.as_ref())
             .expect("Valid Monero Private Key");
 
         runtime.log_info(format!(
-            "Extracted monero key from Refund tx: {}",
+            "Extracted monero key from {} tx: {}",
+            tx_name,
             sk_b.label()
         ));
 
@@ -730,6 +833,76 @@ impl BobSwapKeyManager {
         })
     }
 
+    /// Validates Alice's adaptor signature on the cooperative refund transaction, decrypts it
+    /// and co-signs, returning the transaction ready for broadcast.
+    pub fn handle_cooperative_refund_signature(
+        &mut self,
+        runtime: &mut Runtime,
+        cooperative_refund: CooperativeRefundSignature,
+        alice_params: &Parameters,
+        buy_procedure_signature: &BuyProcedureSignature,
+    ) -> Result<bitcoin::Transaction, Error> {
+        let CooperativeRefundSignature {
+            refund_tx,
+            refund_adaptor_sig,
+            ..
+        } = cooperative_refund;
+        let BobSwapKeyManager {
+            bob,
+            local_params,
+            key_manager,
+            ..
+        } = self;
+
+        // The buy transaction spends the lock output through the same script path, so its
+        // input carries everything needed to sign the cooperative refund.
+        let lock_input = &buy_procedure_signature.buy;
+        if refund_tx.input.len() != 1
+            || refund_tx.input[0].previous_output != lock_input.unsigned_tx.input[0].previous_output
+        {
+            return Err(Error::Farcaster(
+                "Cooperative refund does not spend the lock output".to_string(),
+            ));
+        }
+        if refund_tx.output.len() != 1
+            || refund_tx.output[0].script_pubkey != bob.refund_address.script_pubkey()
+        {
+            return Err(Error::Farcaster(
+                "Cooperative refund does not pay to our refund address".to_string(),
+            ));
+        }
+        let mut partial = PartiallySignedTransaction::from_unsigned_tx(refund_tx)
+            .map_err(|err| Error::Farcaster(err.to_string()))?;
+        partial.inputs[0] = lock_input.inputs[0].clone();
+        partial.inputs[0].partial_sigs.clear();
+        if !partial
+            .validate_fee(&runtime.deal.parameters.fee_strategy)
+            .map_err(|err| Error::Farcaster(err.to_string()))?
+        {
+            return Err(Error::Farcaster(
+                "Cooperative refund fee is out of the deal fee strategy".to_string(),
+            ));
+        }
+
+        let mut refund = BuyTx::from_partial(partial);
+        let msg = refund.generate_witness_message(ScriptPath::Success)?;
+        key_manager.verify_encrypted_signature(
+            &alice_params.buy,
+            &local_params.adaptor,
+            msg,
+            &refund_adaptor_sig.0,
+        )?;
+        let adapted_sig =
+            key_manager.decrypt_signature(AccordantKeyId::Spend, refund_adaptor_sig.0)?;
+        let sig = key_manager.sign(ArbitratingKeyId::Buy, msg)?;
+        refund.add_witness(alice_params.buy, adapted_sig)?;
+        refund.add_witness(local_params.buy, sig)?;
+        let finalized_refund_tx =
+            Broadcastable::<bitcoin::Transaction>::finalize_and_extract(&mut refund)?;
+        runtime.log_info("Cooperative refund transaction created");
+        Ok(finalized_refund_tx)
+    }
+
     pub fn aggregate_xmr_spend_view(
         &self,
         remote_params: &Parameters,
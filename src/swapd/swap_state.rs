@@ -31,7 +31,7 @@ use crate::{bus::p2p::Reveal, swapd::temporal_safety::SWEEP_MONERO_THRESHOLD};
 use crate::{
     bus::{
         ctl::{CtlMsg, InitMakerSwap, InitTakerSwap},
-        p2p::{Commit, PeerMsg, TakerCommit},
+        p2p::{Commit, CooperativeRefundSignature, PeerMsg, TakerCommit, WatchtowerSwap},
         BusMsg, Failure, FailureCode,
     },
     event::{Event, StateMachine},
@@ -69,7 +69,10 @@ use super::{
 /// triggers their transition. Note that the BobAbortAwaitingBitcoinSweep state
 /// is not shown in the diagram, as it would overcomplicate the diagram for little gain.
 /// So merely note that Bob can abort at any time before he transitions to
-/// BobRefundProcedureSignatures.
+/// BobRefundProcedureSignatures. The cooperative refund states BobCooperativeRefund and
+/// AliceCooperativeRefund are not shown either: if both parties abort after the lock, Bob
+/// moves there from BobRefundProcedureSignatures or BobAccordantLock, and Alice from
+/// AliceArbitratingLockFinal or AliceAccordantLock, with the cancel path as fallback.
 ///
 /// ```ignore
 ///                           StartMaker                            StartTaker
@@ -264,8 +267,9 @@ pub enum SwapStateMachine {
     #[display("Alice Reveal")]
     AliceReveal(AliceReveal),
     // AliceCoreArbitratingSetup state - transitions to
-    // AliceArbitratingLockFinal on event TransactionConfirmations, or
-    // AliceCanceled on event TransactionConfirmations. Watches Monero funding
+    // AliceArbitratingLockFinal on event TransactionConfirmations,
+    // AliceCanceled on event TransactionConfirmations, or SwapEnd on request
+    // AbortSwap as long as the Lock is not seen. Watches Monero funding
     // address.
     #[display("Alice Core Arbitrating Setup")]
     AliceCoreArbitratingSetup(AliceCoreArbitratingSetup),
@@ -308,6 +312,24 @@ pub enum SwapStateMachine {
     // End state
     #[display("Swap End: {0}")]
     SwapEnd(Outcome),
+
+    /*
+        Cooperative Refund States, appended after the end state to keep the
+        strict encoding of the states of the existing checkpoints
+    */
+    // BobCooperativeRefund state - transitions to SwapEnd on event
+    // TransactionConfirmations of the cooperative refund, or to BobCanceled
+    // on event TransactionConfirmations of Cancel if the cooperative refund
+    // lost the race against the timelock path.
+    #[display("Bob Cooperative Refund")]
+    BobCooperativeRefund(BobTxs),
+
+    // AliceCooperativeRefund state - transitions to AliceRefund or SwapEnd on
+    // event TransactionConfirmations of the cooperative refund, or to
+    // AliceCanceled on event TransactionConfirmations of Cancel. Extracts Bob's
+    // Monero key share from the cooperative refund transaction.
+    #[display("Alice Cooperative Refund")]
+    AliceCooperativeRefund(AliceCooperativeRefund),
 }
 
 #[derive(Clone, Debug, StrictEncode, StrictDecode)]
@@ -442,12 +464,37 @@ pub struct AliceCanceled {
     acc_lock_height_lower_bound: u64,
 }
 
+#[derive(Clone, Debug, StrictEncode, StrictDecode)]
+pub struct AliceCooperativeRefund {
+    remote_params: Parameters,
+    adaptor_refund: WrappedEncryptedSignature,
+    cooperative_refund_adaptor_sig: WrappedEncryptedSignature,
+    cooperative_refund_txid: bitcoin::Txid,
+    refund_txid: bitcoin::Txid,
+    swap_key_manager: AliceSwapKeyManager,
+    alice_txs: AliceTxs,
+    acc_lock_height_lower_bound: u64,
+}
+
 impl StateMachine<Runtime, Error> for SwapStateMachine {
     fn next(self, event: Event, runtime: &mut Runtime) -> Result<Option<Self>, Error> {
         runtime.log_debug(format!(
             "Checking event request {} from {} for state transition",
             event.request, event.source
         ));
+        if is_cooperative_refund_request(&event.request) {
+            match self {
+                SwapStateMachine::BobRefundProcedureSignatures(_)
+                | SwapStateMachine::BobAccordantLock(_) => {
+                    return try_bob_cooperative_refund(event, runtime, self);
+                }
+                SwapStateMachine::AliceArbitratingLockFinal(_)
+                | SwapStateMachine::AliceAccordantLock(_) => {
+                    return try_alice_cooperative_refund(event, runtime, self);
+                }
+                _ => {}
+            }
+        }
         match self {
             SwapStateMachine::StartTaker(swap_role) => {
                 attempt_transition_to_init_taker(event, runtime, swap_role)
@@ -519,6 +566,9 @@ impl StateMachine<Runtime, Error> for SwapStateMachine {
                 try_bob_canceled_to_bob_cancel_final(event, runtime, bob_txs)
             }
             SwapStateMachine::BobCancelFinal => try_bob_cancel_final_to_swap_end(event, runtime),
+            SwapStateMachine::BobCooperativeRefund(bob_txs) => {
+                try_bob_cooperative_refund_to_swap_end(event, runtime, bob_txs)
+            }
 
             SwapStateMachine::BobAbortAwaitingBitcoinSweep => {
                 try_awaiting_sweep_to_swap_end(event, runtime)
@@ -561,6 +611,13 @@ impl StateMachine<Runtime, Error> for SwapStateMachine {
             SwapStateMachine::AliceRefundSweeping => {
                 try_alice_refund_sweeping_to_swap_end(event, runtime)
             }
+            SwapStateMachine::AliceCooperativeRefund(alice_cooperative_refund) => {
+                try_alice_cooperative_refund_to_alice_refund(
+                    event,
+                    runtime,
+                    alice_cooperative_refund,
+                )
+            }
             SwapStateMachine::SwapEnd(_) => Ok(None),
         }
     }
@@ -639,12 +696,14 @@ fn attempt_transition_to_init_taker(
             runtime
                 .syncer_state
                 .watch_height(event.endpoints, Blockchain::Monero)?;
+            runtime
+                .syncer_state
+                .watch_tip_status(event.endpoints, Blockchain::Bitcoin)?;
+            runtime
+                .syncer_state
+                .watch_tip_status(event.endpoints, Blockchain::Monero)?;
             runtime.peer_service = peerd.clone();
-            if let ServiceId::Peer(0, _) = runtime.peer_service {
-                runtime.connected = false;
-            } else {
-                runtime.connected = true;
-            }
+            runtime.connected = true;
             runtime.enquirer = Some(report_to.clone());
 
             match swap_role {
@@ -661,7 +720,7 @@ fn attempt_transition_to_init_taker(
                         runtime.report_failure(
                             event.endpoints,
                             Failure {
-                                code: FailureCode::Unknown,
+                                code: FailureCode::from(&err),
                                 info: err.to_string(),
                             },
                         )
@@ -701,7 +760,7 @@ fn attempt_transition_to_init_taker(
                         runtime.report_failure(
                             event.endpoints,
                             Failure {
-                                code: FailureCode::Unknown,
+                                code: FailureCode::from(&err),
                                 info: err.to_string(),
                             },
                         )
@@ -758,6 +817,12 @@ fn attempt_transition_to_init_maker(
             runtime
                 .syncer_state
                 .watch_height(event.endpoints, Blockchain::Monero)?;
+            runtime
+                .syncer_state
+                .watch_tip_status(event.endpoints, Blockchain::Bitcoin)?;
+            runtime
+                .syncer_state
+                .watch_tip_status(event.endpoints, Blockchain::Monero)?;
             runtime.peer_service = peerd;
             if runtime.peer_service != ServiceId::Loopback {
                 runtime.connected = true;
@@ -786,7 +851,7 @@ fn attempt_transition_to_init_maker(
                         runtime.report_failure(
                             event.endpoints,
                             Failure {
-                                code: FailureCode::Unknown,
+                                code: FailureCode::from(&err),
                                 info: err.to_string(),
                             },
                         )
@@ -834,7 +899,7 @@ fn attempt_transition_to_init_maker(
                         runtime.report_failure(
                             event.endpoints,
                             Failure {
-                                code: FailureCode::Unknown,
+                                code: FailureCode::from(&err),
                                 info: err.to_string(),
                             },
                         )
@@ -1206,6 +1271,17 @@ fn try_bob_funded_to_bob_refund_procedure_signature(
                 .syncer_state
                 .watch_tx_btc(buy_tx.txid(), TxLabel::Buy);
             event.send_sync_service(runtime.syncer_state.bitcoin_syncer(), SyncMsg::Task(task))?;
+            let watchtower_swap = WatchtowerSwap {
+                swap_id: runtime.swap_id(),
+                network: runtime.syncer_state.network,
+                lock_txid: lock_tx.txid(),
+                refund_txid: bob_txs.refund_tx.txid(),
+                buy_txid: Some(buy_tx.txid()),
+                cancel_tx: bob_txs.cancel_tx.clone(),
+                refund_tx: Some(bob_txs.refund_tx.clone()),
+                punish_tx: None,
+                temporal_safety: runtime.temporal_safety.clone(),
+            };
             // Checkpoint BobRefundProcedureSignatures
             let new_ssm =
                 SwapStateMachine::BobRefundProcedureSignatures(BobRefundProcedureSignatures {
@@ -1221,6 +1297,11 @@ fn try_bob_funded_to_bob_refund_procedure_signature(
                 .syncer_state
                 .broadcast(lock_tx.clone(), TxLabel::Lock);
             runtime.checkpoint_state(event.endpoints, None, new_ssm.clone())?;
+            // hand cancel and refund over to the watchtowers before locking the funds
+            event.send_ctl_service(
+                ServiceId::Farcasterd,
+                CtlMsg::WatchtowerRegister(watchtower_swap),
+            )?;
             runtime.broadcast(lock_tx, TxLabel::Lock, event.endpoints)?;
             Ok(Some(new_ssm))
         }
@@ -1310,6 +1391,18 @@ fn try_bob_accordant_lock_to_bob_accordant_lock_final(
             .final_tx(confirmations, Blockchain::Monero)
             && runtime.syncer_state.tasks.watched_txs.get(&id) == Some(&TxLabel::AccLock) =>
         {
+            // Do not hand out the buy signature while a cooperative refund is pending, Alice
+            // could otherwise race the cooperative refund with the buy
+            if runtime.cooperative_refund.local {
+                runtime
+                    .log_debug("Cooperative refund requested, withholding buy procedure signature");
+                return Ok(None);
+            }
+            // The buy signature lets Alice take the bitcoin, do not hand it out on an outdated
+            // view of the accordant lock
+            if !runtime.syncer_data_fresh(event.endpoints, TxLabel::Buy, Blockchain::Monero)? {
+                return Ok(None);
+            }
             runtime.send_peer(
                 event.endpoints,
                 PeerMsg::BuyProcedureSignature(buy_procedure_signature.clone()),
@@ -1482,6 +1575,10 @@ fn try_bob_canceled_to_bob_cancel_final(
             && runtime.syncer_state.tasks.watched_txs.get(&id) == Some(&TxLabel::Cancel)
             && !runtime.syncer_state.broadcasted_tx(&TxLabel::Refund) =>
         {
+            // The refund is never withheld, see SyncerState::withhold_reason
+            if !runtime.syncer_data_fresh(event.endpoints, TxLabel::Refund, Blockchain::Bitcoin)? {
+                return Ok(None);
+            }
             runtime.log_trace("Bob publishes refund tx");
             if !runtime.temporal_safety.safe_refund(confirmations) {
                 runtime.log_warn("Publishing refund tx, but we might already have been punished");
@@ -1506,7 +1603,7 @@ fn try_awaiting_sweep_to_swap_end(
                 CtlMsg::FundingCanceled(Blockchain::Bitcoin),
             )?;
             runtime.log_info("Aborted swap.");
-            Ok(Some(SwapStateMachine::SwapEnd(Outcome::FailureAbort)))
+            Ok(Some(SwapStateMachine::SwapEnd(runtime.abort_outcome())))
         }
 
         BusMsg::Sync(SyncMsg::Event(SyncEvent::SweepSuccess(SweepSuccess { id, .. })))
@@ -1517,7 +1614,7 @@ fn try_awaiting_sweep_to_swap_end(
                 CtlMsg::FundingCanceled(Blockchain::Bitcoin),
             )?;
             runtime.log_info("Aborted swap.");
-            Ok(Some(SwapStateMachine::SwapEnd(Outcome::FailureAbort)))
+            Ok(Some(SwapStateMachine::SwapEnd(runtime.abort_outcome())))
         }
         _ => Ok(None),
     }
@@ -1567,6 +1664,22 @@ fn try_alice_reveal_to_alice_core_arbitrating_setup(
                     runtime.syncer_state.height(Blockchain::Monero),
                 );
 
+            // hand cancel and punish over to the watchtowers
+            event.send_ctl_service(
+                ServiceId::Farcasterd,
+                CtlMsg::WatchtowerRegister(WatchtowerSwap {
+                    swap_id: runtime.swap_id(),
+                    network: runtime.syncer_state.network,
+                    lock_txid: setup.lock.clone().extract_tx().txid(),
+                    refund_txid: setup.refund.clone().extract_tx().txid(),
+                    buy_txid: None,
+                    cancel_tx: alice_txs.cancel_tx.clone(),
+                    refund_tx: None,
+                    punish_tx: Some(alice_txs.punish_tx.clone()),
+                    temporal_safety: runtime.temporal_safety.clone(),
+                }),
+            )?;
+
             // checkpoint alice pre lock bob
             let new_ssm = SwapStateMachine::AliceCoreArbitratingSetup(AliceCoreArbitratingSetup {
                 remote_params,
@@ -1660,6 +1773,11 @@ fn try_alice_core_arbitrating_setup_to_alice_arbitrating_lock_final(
                 },
             )))
         }
+        BusMsg::Ctl(CtlMsg::AbortSwap)
+            if runtime.syncer_state.get_confs(TxLabel::Lock).is_none() =>
+        {
+            handle_abort_swap(event, runtime)
+        }
         _ => handle_alice_swap_interrupt_after_lock(
             event,
             runtime,
@@ -1872,6 +1990,11 @@ fn try_alice_accordant_lock_to_alice_buy_procedure_signature(
 
     match event.request.clone() {
         BusMsg::P2p(PeerMsg::BuyProcedureSignature(buy_procedure_signature)) => {
+            // Buy must not race with cancel, do not decide on outdated heights. The message is
+            // handled again once fresh syncer data comes in.
+            if !runtime.syncer_data_fresh(event.endpoints, TxLabel::Buy, Blockchain::Bitcoin)? {
+                return Ok(None);
+            }
             // register a watch task for buy
             runtime.log_debug("Registering watch buy tx task");
             let txid = buy_procedure_signature.buy.clone().extract_tx().txid();
@@ -1879,6 +2002,17 @@ fn try_alice_accordant_lock_to_alice_buy_procedure_signature(
             event.send_sync_service(runtime.syncer_state.bitcoin_syncer(), SyncMsg::Task(task))?;
             // Handle the received buy procedure signature message with the swap_key_manager
             runtime.log_debug("Handling buy procedure signature with swap_key_manager");
+            let watchtower_swap = WatchtowerSwap {
+                swap_id: runtime.swap_id(),
+                network: runtime.syncer_state.network,
+                lock_txid: core_arbitrating_setup.lock.clone().extract_tx().txid(),
+                refund_txid: core_arbitrating_setup.refund.clone().extract_tx().txid(),
+                buy_txid: Some(txid),
+                cancel_tx: alice_txs.cancel_tx.clone(),
+                refund_tx: None,
+                punish_tx: Some(alice_txs.punish_tx.clone()),
+                temporal_safety: runtime.temporal_safety.clone(),
+            };
             let HandleBuyProcedureSignatureRes { cancel_tx, buy_tx } = swap_key_manager
                 .handle_buy_procedure_signature(
                     runtime,
@@ -1889,12 +2023,13 @@ fn try_alice_accordant_lock_to_alice_buy_procedure_signature(
                 )?;
 
             // Check if we should cancel the swap
-            if let Some(SyncMsg::Event(SyncEvent::TransactionConfirmations(
-                TransactionConfirmations {
+            if let Some((
+                _,
+                SyncMsg::Event(SyncEvent::TransactionConfirmations(TransactionConfirmations {
                     confirmations: Some(confirmations),
                     ..
-                },
-            ))) = runtime.syncer_state.last_tx_event.get(&TxLabel::Lock)
+                })),
+            )) = runtime.syncer_state.last_tx_event.get(&TxLabel::Lock)
             {
                 if runtime.temporal_safety.valid_cancel(*confirmations) {
                     runtime.broadcast(cancel_tx, TxLabel::Cancel, event.endpoints)?;
@@ -1908,8 +2043,13 @@ fn try_alice_accordant_lock_to_alice_buy_procedure_signature(
                 }
             }
 
-            // Broadcast the Buy transaction
+            // Broadcast the Buy transaction and update the watchtower registration so the
+            // watchtowers stop watching the swap once it is final
             runtime.broadcast(buy_tx, TxLabel::Buy, event.endpoints)?;
+            event.send_ctl_service(
+                ServiceId::Farcasterd,
+                CtlMsg::WatchtowerRegister(watchtower_swap),
+            )?;
 
             // checkpoint swap alice pre buy
             let new_ssm = SwapStateMachine::AliceBuyProcedureSignature;
@@ -1981,6 +2121,13 @@ fn try_alice_canceled_to_alice_refund_or_alice_punish(
                         && runtime.temporal_safety.valid_punish(confirmations)
                         && !runtime.syncer_state.broadcasted_tx(&TxLabel::Punish) =>
                 {
+                    if !runtime.syncer_data_fresh(
+                        event.endpoints,
+                        TxLabel::Punish,
+                        Blockchain::Bitcoin,
+                    )? {
+                        return Ok(None);
+                    }
                     runtime.log_debug("Publishing punish tx");
                     // syncer's watch punish tx task
                     let txid = alice_txs.punish_tx.txid();
@@ -2023,6 +2170,13 @@ fn try_alice_canceled_to_alice_refund_or_alice_punish(
                         && runtime.temporal_safety.valid_cancel(confirmations)
                         && !runtime.syncer_state.broadcasted_tx(&TxLabel::Cancel) =>
                 {
+                    if !runtime.syncer_data_fresh(
+                        event.endpoints,
+                        TxLabel::Cancel,
+                        Blockchain::Bitcoin,
+                    )? {
+                        return Ok(None);
+                    }
                     runtime.log_debug("Publishing cancel tx");
                     // syncer's watch cancel tx task
                     let txid = alice_txs.cancel_tx.txid();
@@ -2273,6 +2427,9 @@ fn handle_bob_swap_interrupt_after_lock(
             && runtime.temporal_safety.valid_cancel(confirmations)
             && !runtime.syncer_state.broadcasted_tx(&TxLabel::Cancel) =>
         {
+            if !runtime.syncer_data_fresh(event.endpoints, TxLabel::Cancel, Blockchain::Bitcoin)? {
+                return Ok(None);
+            }
             watch_cancel_address(runtime, &mut event, &bob_txs)?;
 
             runtime.broadcast(bob_txs.cancel_tx, TxLabel::Cancel, event.endpoints)?;
@@ -2339,6 +2496,9 @@ fn handle_alice_swap_interrupt_after_lock(
             && runtime.temporal_safety.valid_cancel(confirmations)
             && !runtime.syncer_state.broadcasted_tx(&TxLabel::Cancel) =>
         {
+            if !runtime.syncer_data_fresh(event.endpoints, TxLabel::Cancel, Blockchain::Bitcoin)? {
+                return Ok(None);
+            }
             runtime.broadcast(alice_txs.cancel_tx, TxLabel::Cancel, event.endpoints)?;
             Ok(None)
         }
@@ -2363,13 +2523,435 @@ fn handle_alice_swap_interrupt_after_lock(
     }
 }
 
+fn is_cooperative_refund_request(request: &BusMsg) -> bool {
+    matches!(
+        request,
+        BusMsg::Ctl(CtlMsg::AbortSwap)
+            | BusMsg::P2p(PeerMsg::CooperativeRefundRequest(_))
+            | BusMsg::P2p(PeerMsg::CooperativeRefundSignature(_))
+    )
+}
+
+const COOPERATIVE_REFUND_REQUESTED: &str = "Requested a cooperative refund from the counterparty. \
+    If they do not agree, the swap is cancelled once the cancel timelock expires.";
+
+fn notify_cooperative_refund_request(
+    event: &mut Event,
+    runtime: &mut Runtime,
+) -> Result<(), Error> {
+    let msg = format!(
+        "Counterparty requests a cooperative refund, abort swap {} to agree.",
+        runtime.swap_id()
+    );
+    runtime.log_warn(&msg);
+    runtime.report_progress_message(event.endpoints, msg)
+}
+
+/// Collects the consent of both parties to refund the locked bitcoin right away. Bob only
+/// settles once Alice sent her adaptor signature on the cooperative refund, until then the
+/// cancel timelock path stays armed. Bob does not agree anymore after having sent the
+/// BuyProcedureSignature, as Alice could then race the cooperative refund with the buy.
+fn try_bob_cooperative_refund(
+    mut event: Event,
+    runtime: &mut Runtime,
+    state: SwapStateMachine,
+) -> Result<Option<SwapStateMachine>, Error> {
+    let (remote_params, mut swap_key_manager, buy_procedure_signature, bob_txs) =
+        match state.clone() {
+            SwapStateMachine::BobRefundProcedureSignatures(BobRefundProcedureSignatures {
+                remote_params,
+                swap_key_manager,
+                buy_procedure_signature,
+                bob_txs,
+                ..
+            })
+            | SwapStateMachine::BobAccordantLock(BobAccordantLock {
+                remote_params,
+                swap_key_manager,
+                buy_procedure_signature,
+                bob_txs,
+                ..
+            }) => (
+                remote_params,
+                swap_key_manager,
+                buy_procedure_signature,
+                bob_txs,
+            ),
+            _ => return Ok(None),
+        };
+    match event.request.clone() {
+        BusMsg::Ctl(CtlMsg::AbortSwap) => {
+            runtime.cooperative_refund.local = true;
+            runtime.send_peer(
+                event.endpoints,
+                PeerMsg::CooperativeRefundRequest(runtime.swap_id()),
+            )?;
+            runtime.log_info(COOPERATIVE_REFUND_REQUESTED);
+            event
+                .complete_client_info(InfoMsg::String(COOPERATIVE_REFUND_REQUESTED.to_string()))?;
+            Ok(Some(state))
+        }
+        BusMsg::P2p(PeerMsg::CooperativeRefundRequest(_)) => {
+            runtime.cooperative_refund.remote = true;
+            if !runtime.cooperative_refund.local {
+                notify_cooperative_refund_request(&mut event, runtime)?;
+            }
+            Ok(Some(state))
+        }
+        BusMsg::P2p(PeerMsg::CooperativeRefundSignature(cooperative_refund)) => {
+            if !runtime.cooperative_refund.local {
+                runtime.log_warn(
+                    "Received a cooperative refund signature without having requested a \
+                    cooperative refund, ignoring it",
+                );
+                return Ok(Some(state));
+            }
+            let refund_tx = match swap_key_manager.handle_cooperative_refund_signature(
+                runtime,
+                cooperative_refund,
+                &remote_params,
+                &buy_procedure_signature,
+            ) {
+                Ok(refund_tx) => refund_tx,
+                Err(err) => {
+                    runtime.log_error(format!(
+                        "Invalid cooperative refund: {}. Waiting for the cancel timelock instead.",
+                        err
+                    ));
+                    return Ok(Some(state));
+                }
+            };
+            let task = runtime
+                .syncer_state
+                .watch_tx_btc(refund_tx.txid(), TxLabel::Refund);
+            event.send_sync_service(runtime.syncer_state.bitcoin_syncer(), SyncMsg::Task(task))?;
+            let new_ssm = SwapStateMachine::BobCooperativeRefund(bob_txs);
+            runtime.log_debug("Checkpointing bob cooperative refund swapd state.");
+            // manually add the refund to pending broadcasts to ensure it's checkpointed
+            runtime
+                .syncer_state
+                .broadcast(refund_tx.clone(), TxLabel::Refund);
+            runtime.checkpoint_state(event.endpoints, None, new_ssm.clone())?;
+            runtime.broadcast(refund_tx, TxLabel::Refund, event.endpoints)?;
+            Ok(Some(new_ssm))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Collects the consent of both parties to refund the locked bitcoin right away. Once both
+/// agreed, Alice sends her adaptor signature on the cooperative refund and stops funding the
+/// swap: Bob publishing the refund reveals his Monero key share, so Alice can sweep anything
+/// already locked.
+fn try_alice_cooperative_refund(
+    mut event: Event,
+    runtime: &mut Runtime,
+    state: SwapStateMachine,
+) -> Result<Option<SwapStateMachine>, Error> {
+    match event.request {
+        BusMsg::Ctl(CtlMsg::AbortSwap) => {
+            runtime.cooperative_refund.local = true;
+            if !runtime.cooperative_refund.remote {
+                runtime.send_peer(
+                    event.endpoints,
+                    PeerMsg::CooperativeRefundRequest(runtime.swap_id()),
+                )?;
+                runtime.log_info(COOPERATIVE_REFUND_REQUESTED);
+                event.complete_client_info(InfoMsg::String(
+                    COOPERATIVE_REFUND_REQUESTED.to_string(),
+                ))?;
+                return Ok(Some(state));
+            }
+            let source = event.source.clone();
+            event.send_client_info(
+                source,
+                InfoMsg::String("Agreed to the cooperative refund, sending signature".to_string()),
+            )?;
+        }
+        BusMsg::P2p(PeerMsg::CooperativeRefundRequest(_)) => {
+            runtime.cooperative_refund.remote = true;
+            if !runtime.cooperative_refund.local {
+                notify_cooperative_refund_request(&mut event, runtime)?;
+                return Ok(Some(state));
+            }
+        }
+        _ => return Ok(None),
+    }
+
+    let (
+        remote_params,
+        core_arbitrating_setup,
+        adaptor_refund,
+        mut swap_key_manager,
+        alice_txs,
+        acc_lock_height_lower_bound,
+    ) = match state {
+        SwapStateMachine::AliceArbitratingLockFinal(AliceArbitratingLockFinal {
+            remote_params,
+            core_arbitrating_setup,
+            adaptor_refund,
+            swap_key_manager,
+            alice_txs,
+            acc_lock_height_lower_bound,
+            ..
+        })
+        | SwapStateMachine::AliceAccordantLock(AliceAccordantLock {
+            remote_params,
+            core_arbitrating_setup,
+            adaptor_refund,
+            swap_key_manager,
+            alice_txs,
+            acc_lock_height_lower_bound,
+            ..
+        }) => (
+            remote_params,
+            core_arbitrating_setup,
+            adaptor_refund,
+            swap_key_manager,
+            alice_txs,
+            acc_lock_height_lower_bound,
+        ),
+        _ => return Ok(None),
+    };
+    let (refund_tx, cooperative_refund_adaptor_sig) = swap_key_manager.sign_cooperative_refund(
+        runtime,
+        &remote_params,
+        &core_arbitrating_setup,
+    )?;
+    let cooperative_refund_txid = refund_tx.txid();
+    let refund_txid = core_arbitrating_setup.refund.extract_tx().txid();
+    let task = runtime
+        .syncer_state
+        .watch_tx_btc(cooperative_refund_txid, TxLabel::Refund);
+    event.send_sync_service(runtime.syncer_state.bitcoin_syncer(), SyncMsg::Task(task))?;
+    if runtime.syncer_state.awaiting_funding {
+        runtime.log_warn("Swap is being refunded cooperatively. Do not fund anymore.");
+        event.send_ctl_service(
+            ServiceId::Farcasterd,
+            CtlMsg::FundingCanceled(Blockchain::Monero),
+        )?;
+        runtime.syncer_state.awaiting_funding = false;
+    }
+    let new_ssm = SwapStateMachine::AliceCooperativeRefund(AliceCooperativeRefund {
+        remote_params,
+        adaptor_refund,
+        cooperative_refund_adaptor_sig: cooperative_refund_adaptor_sig.clone(),
+        cooperative_refund_txid,
+        refund_txid,
+        swap_key_manager,
+        alice_txs,
+        acc_lock_height_lower_bound,
+    });
+    runtime.log_debug("Checkpointing alice cooperative refund swapd state.");
+    runtime.checkpoint_state(event.endpoints, None, new_ssm.clone())?;
+    runtime.send_peer(
+        event.endpoints,
+        PeerMsg::CooperativeRefundSignature(CooperativeRefundSignature {
+            swap_id: runtime.swap_id(),
+            refund_tx,
+            refund_adaptor_sig: cooperative_refund_adaptor_sig,
+        }),
+    )?;
+    Ok(Some(new_ssm))
+}
+
+fn try_bob_cooperative_refund_to_swap_end(
+    mut event: Event,
+    runtime: &mut Runtime,
+    bob_txs: BobTxs,
+) -> Result<Option<SwapStateMachine>, Error> {
+    match event.request {
+        BusMsg::Sync(SyncMsg::Event(SyncEvent::TransactionConfirmations(
+            TransactionConfirmations {
+                id,
+                confirmations: Some(confirmations),
+                ..
+            },
+        ))) if runtime
+            .temporal_safety
+            .final_tx(confirmations, Blockchain::Bitcoin)
+            && runtime.syncer_state.tasks.watched_txs.get(&id) == Some(&TxLabel::Refund) =>
+        {
+            Ok(Some(SwapStateMachine::SwapEnd(Outcome::FailureRefund)))
+        }
+
+        // The cancel won the race against the cooperative refund, fall back on the regular
+        // refund transaction
+        BusMsg::Sync(SyncMsg::Event(SyncEvent::TransactionConfirmations(
+            TransactionConfirmations {
+                id,
+                confirmations: Some(_),
+                ..
+            },
+        ))) if runtime.syncer_state.tasks.watched_txs.get(&id) == Some(&TxLabel::Cancel) => {
+            runtime.log_warn("Cooperative refund failed, refunding through the cancel path");
+            runtime
+                .syncer_state
+                .broadcasted_txs
+                .remove(&TxLabel::Refund);
+            let task = runtime
+                .syncer_state
+                .watch_tx_btc(bob_txs.refund_tx.txid(), TxLabel::Refund);
+            event.send_sync_service(runtime.syncer_state.bitcoin_syncer(), SyncMsg::Task(task))?;
+            handle_bob_swap_interrupt_after_lock(event, runtime, bob_txs)
+        }
+
+        BusMsg::Ctl(CtlMsg::AbortSwap) => handle_cooperative_refund_in_progress(event, runtime),
+        _ => handle_bob_swap_interrupt_after_lock(event, runtime, bob_txs),
+    }
+}
+
+fn try_alice_cooperative_refund_to_alice_refund(
+    mut event: Event,
+    runtime: &mut Runtime,
+    alice_cooperative_refund: AliceCooperativeRefund,
+) -> Result<Option<SwapStateMachine>, Error> {
+    let AliceCooperativeRefund {
+        remote_params,
+        adaptor_refund,
+        cooperative_refund_adaptor_sig,
+        cooperative_refund_txid,
+        refund_txid,
+        mut swap_key_manager,
+        alice_txs,
+        acc_lock_height_lower_bound,
+    } = alice_cooperative_refund;
+    match event.request.clone() {
+        // When Alice learns of the cooperative refund, immediately extract the Monero keys from
+        // its adaptor signature
+        BusMsg::Sync(SyncMsg::Event(SyncEvent::TransactionConfirmations(
+            TransactionConfirmations {
+                id,
+                confirmations: Some(_),
+                ref tx,
+                ..
+            },
+        ))) if runtime.syncer_state.tasks.watched_txs.get(&id) == Some(&TxLabel::Refund) => {
+            let tx = bitcoin::Transaction::deserialize(
+                &tx.iter().flatten().copied().collect::<Vec<u8>>(),
+            )?;
+            if tx.txid() != cooperative_refund_txid {
+                return Ok(None);
+            }
+            runtime.log_info("Cooperative refund transaction in mempool or block");
+            let sweep_xmr = swap_key_manager.process_cooperative_refund_tx(
+                &mut event,
+                runtime,
+                tx,
+                remote_params,
+                cooperative_refund_adaptor_sig,
+                acc_lock_height_lower_bound,
+            )?;
+            runtime
+                .syncer_state
+                .tasks
+                .txids
+                .remove_entry(&TxLabel::Refund);
+
+            // Only sweep if monero were locked, otherwise the swap is over
+            if runtime
+                .syncer_state
+                .confirmations
+                .contains_key(&TxLabel::AccLock)
+            {
+                let task = runtime.syncer_state.sweep_xmr(sweep_xmr.clone(), true);
+                let sweep_address = if let Task::SweepAddress(sweep_address) = task {
+                    sweep_address
+                } else {
+                    return Ok(None);
+                };
+                runtime.log_monero_maturity(sweep_xmr.destination_address);
+                Ok(Some(SwapStateMachine::AliceRefund(sweep_address)))
+            } else {
+                Ok(Some(SwapStateMachine::SwapEnd(Outcome::FailureRefund)))
+            }
+        }
+
+        // Monero sent before agreeing on the cooperative refund, watch it so it gets swept
+        BusMsg::Sync(SyncMsg::Event(SyncEvent::AddressTransaction(AddressTransaction {
+            id,
+            ref hash,
+            incoming,
+            ..
+        }))) if runtime.syncer_state.tasks.watched_addrs.get(&id) == Some(&TxLabel::AccLock)
+            && incoming =>
+        {
+            let task = runtime.syncer_state.watch_tx_xmr(*hash, TxLabel::AccLock);
+            event.send_sync_service(runtime.syncer_state.monero_syncer(), SyncMsg::Task(task))?;
+            if runtime
+                .syncer_state
+                .tasks
+                .watched_addrs
+                .remove(&id)
+                .is_some()
+            {
+                let abort_task = runtime.syncer_state.abort_task(id);
+                event.send_sync_service(
+                    runtime.syncer_state.monero_syncer(),
+                    SyncMsg::Task(abort_task),
+                )?;
+            }
+            Ok(None)
+        }
+
+        BusMsg::Ctl(CtlMsg::AbortSwap) => handle_cooperative_refund_in_progress(event, runtime),
+
+        _ => {
+            // The cancel won the race against the cooperative refund, fall back on the regular
+            // refund transaction
+            if let BusMsg::Sync(SyncMsg::Event(SyncEvent::TransactionConfirmations(
+                TransactionConfirmations {
+                    id,
+                    confirmations: Some(_),
+                    ..
+                },
+            ))) = event.request
+            {
+                if runtime.syncer_state.tasks.watched_txs.get(&id) == Some(&TxLabel::Cancel) {
+                    runtime
+                        .log_warn("Cooperative refund failed, refunding through the cancel path");
+                    let task = runtime
+                        .syncer_state
+                        .watch_tx_btc(refund_txid, TxLabel::Refund);
+                    event.send_sync_service(
+                        runtime.syncer_state.bitcoin_syncer(),
+                        SyncMsg::Task(task),
+                    )?;
+                }
+            }
+            handle_alice_swap_interrupt_after_lock(
+                event,
+                runtime,
+                remote_params,
+                adaptor_refund,
+                swap_key_manager,
+                alice_txs,
+                acc_lock_height_lower_bound,
+            )
+        }
+    }
+}
+
+fn handle_cooperative_refund_in_progress(
+    event: Event,
+    runtime: &mut Runtime,
+) -> Result<Option<SwapStateMachine>, Error> {
+    let msg = "Swap is already being refunded cooperatively.".to_string();
+    runtime.log_warn(&msg);
+    event.complete_client_ctl(CtlMsg::Failure(Failure {
+        code: FailureCode::InvalidState,
+        info: msg,
+    }))?;
+    Ok(None)
+}
+
 fn handle_abort_swap(
     event: Event,
     runtime: &mut Runtime,
 ) -> Result<Option<SwapStateMachine>, Error> {
     event.complete_client_info(InfoMsg::String("Aborted swap".to_string()))?;
     runtime.log_info("Aborted swap.");
-    Ok(Some(SwapStateMachine::SwapEnd(Outcome::FailureAbort)))
+    Ok(Some(SwapStateMachine::SwapEnd(runtime.abort_outcome())))
 }
 
 fn handle_abort_impossible(
@@ -2379,7 +2961,7 @@ fn handle_abort_impossible(
     let msg = "Swap is already locked-in, cannot manually abort anymore.".to_string();
     runtime.log_warn(&msg);
     event.complete_client_ctl(CtlMsg::Failure(Failure {
-        code: FailureCode::Unknown,
+        code: FailureCode::InvalidState,
         info: msg,
     }))?;
     Ok(None)
@@ -2429,3 +3011,232 @@ fn watch_cancel_address(
     )?;
     Ok(())
 }
+
+/// States of a swap for both roles built by running the protocol with fixed seeds up to the
+/// lock: the states before the bitcoin lock, and some states after it
+#[cfg(test)]
+pub(super) fn test_states() -> (Vec<SwapStateMachine>, Vec<SwapStateMachine>) {
+    use std::str::FromStr;
+
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use farcaster_core::bitcoin::segwitv0::{CancelTx, FundingTx, PunishTx};
+    use farcaster_core::bitcoin::BitcoinSegwitV0;
+    use farcaster_core::blockchain::FeePriority;
+    use farcaster_core::crypto::{ArbitratingKeyId, CommitmentEngine, GenerateKey};
+    use farcaster_core::monero::Monero;
+    use farcaster_core::swap::btcxmr::{Alice, Bob, Deal, FullySignedPunish, KeyManager};
+    use farcaster_core::swap::SwapId;
+    use farcaster_core::transaction::{Broadcastable, Fundable, Transaction, Witnessable};
+    use farcaster_core::Uuid;
+
+    let deal = Deal::from_str("Deal:Cke4ftrP5A781Vq85dgBQJNwYgBS4nuUV1LQM2fvVdFMNR4h5TrWhRR11111uMFuZTAsNgpdK8DiK11111TB9zym113GTvtvqfD1111114A4TTfFfmZoWyvpcjDBtTZCdWFSUWcRKYfEC3Y17hqaXZ3dWz11111111111111111111111111111111111111111AfZ113SEBTEspU3a").unwrap();
+    let swap_id = SwapId(Uuid::new());
+    let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+    let target_bitcoin_address = bitcoin::Address::p2wpkh(
+        &bitcoin::PublicKey::new(PublicKey::from_secret_key(&Secp256k1::new(), &secret_key)),
+        bitcoin::Network::Testnet,
+    )
+    .unwrap();
+    let target_monero_address = monero::Address::from_str("55LTR8KniP4LQGJSPtbYDacR7dz8RBFnsfAKMaMuwUNYX6aQbBcovzDPyrQF9KXF9tVU6Xk3K8no1BywnJX6GvZX8yJsXvt").unwrap();
+
+    let alice = Alice::new(
+        BitcoinSegwitV0::new(),
+        Monero,
+        target_bitcoin_address.clone(),
+        FeePriority::Low,
+    );
+    let mut alice_key_manager = KeyManager::new([2; 32], 1).unwrap();
+    let alice_params = alice
+        .generate_parameters(&mut alice_key_manager, &deal)
+        .unwrap();
+    let mut alice_swap_key_manager = AliceSwapKeyManager {
+        alice,
+        local_params: alice_params.clone(),
+        key_manager: alice_key_manager,
+        target_bitcoin_address: target_bitcoin_address.clone(),
+        target_monero_address,
+    };
+
+    let bob = Bob::new(
+        BitcoinSegwitV0::new(),
+        Monero,
+        target_bitcoin_address.clone(),
+        FeePriority::Low,
+    );
+    let mut bob_key_manager = KeyManager::new([3; 32], 1).unwrap();
+    let bob_params = bob
+        .generate_parameters(&mut bob_key_manager, &deal)
+        .unwrap();
+    let mut funding_tx = FundingTx::initialize(
+        bob_key_manager.get_pubkey(ArbitratingKeyId::Lock).unwrap(),
+        deal.parameters.network,
+    )
+    .unwrap();
+    let mut bob_swap_key_manager = BobSwapKeyManager {
+        bob,
+        local_params: bob_params.clone(),
+        key_manager: bob_key_manager,
+        funding_tx: funding_tx.clone(),
+        target_bitcoin_address,
+        target_monero_address,
+    };
+
+    // Bob funds the swap and creates the core arbitrating setup
+    funding_tx
+        .update(bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![bitcoin::TxOut {
+                value: deal.parameters.arbitrating_amount.as_sat(),
+                script_pubkey: funding_tx.get_address().unwrap().script_pubkey(),
+            }],
+        })
+        .unwrap();
+    let core_arbitrating_txs = bob_swap_key_manager
+        .bob
+        .core_arbitrating_transactions(
+            &alice_params,
+            &bob_params,
+            funding_tx.clone(),
+            deal.to_arbitrating_params(),
+        )
+        .unwrap();
+    let cancel_sig = bob_swap_key_manager
+        .bob
+        .cosign_arbitrating_cancel(&mut bob_swap_key_manager.key_manager, &core_arbitrating_txs)
+        .unwrap();
+    let core_arbitrating_setup = core_arbitrating_txs
+        .clone()
+        .into_arbitrating_setup(swap_id, cancel_sig);
+    bob_swap_key_manager.funding_tx = funding_tx;
+
+    // Alice signs the refund procedure and the cancel and punish transactions
+    let AliceSwapKeyManager {
+        alice, key_manager, ..
+    } = &mut alice_swap_key_manager;
+    let adaptor_refund = alice
+        .sign_adaptor_refund(
+            key_manager,
+            &alice_params,
+            &bob_params,
+            &core_arbitrating_txs,
+            deal.to_arbitrating_params(),
+        )
+        .unwrap();
+    let alice_cancel_signature = alice
+        .cosign_arbitrating_cancel(
+            key_manager,
+            &alice_params,
+            &bob_params,
+            &core_arbitrating_txs,
+            deal.to_arbitrating_params(),
+        )
+        .unwrap();
+    let mut cancel_tx = CancelTx::from_partial(core_arbitrating_setup.cancel.clone());
+    cancel_tx
+        .add_witness(alice_params.cancel, alice_cancel_signature)
+        .unwrap();
+    cancel_tx
+        .add_witness(bob_params.cancel, core_arbitrating_setup.cancel_sig)
+        .unwrap();
+    let FullySignedPunish { punish, punish_sig } = alice
+        .fully_sign_punish(
+            key_manager,
+            &alice_params,
+            &bob_params,
+            &core_arbitrating_txs,
+            deal.to_arbitrating_params(),
+        )
+        .unwrap();
+    let mut punish_tx = PunishTx::from_partial(punish);
+    punish_tx
+        .add_witness(alice_params.punish.unwrap(), punish_sig)
+        .unwrap();
+    let alice_txs = AliceTxs {
+        cancel_tx: Broadcastable::<bitcoin::Transaction>::finalize_and_extract(&mut cancel_tx)
+            .unwrap(),
+        punish_tx: Broadcastable::<bitcoin::Transaction>::finalize_and_extract(&mut punish_tx)
+            .unwrap(),
+    };
+
+    let alice_commit = alice_params.commit_alice(swap_id, &CommitmentEngine);
+    let bob_commit = bob_params.commit_bob(swap_id, &CommitmentEngine);
+    let pre_lock_states = vec![
+        SwapStateMachine::BobInitMaker(BobInitMaker {
+            remote_commit: alice_commit.clone(),
+            swap_key_manager: bob_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::AliceInitMaker(AliceInitMaker {
+            remote_commit: bob_commit.clone(),
+            swap_key_manager: alice_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::BobInitTaker(BobInitTaker {
+            swap_key_manager: bob_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::AliceInitTaker(AliceInitTaker {
+            swap_key_manager: alice_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::BobTakerMakerCommit(BobTakerMakerCommit {
+            remote_commit: alice_commit,
+            swap_key_manager: bob_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::AliceTakerMakerCommit(AliceTakerMakerCommit {
+            remote_commit: bob_commit,
+            swap_key_manager: alice_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::BobReveal(BobReveal {
+            remote_params: alice_params.clone(),
+            swap_key_manager: bob_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::AliceReveal(AliceReveal {
+            remote_params: bob_params.clone(),
+            swap_key_manager: alice_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::BobFeeEstimated(BobFeeEstimated {
+            required_funding_amount: deal.parameters.arbitrating_amount,
+            remote_params: alice_params.clone(),
+            swap_key_manager: bob_swap_key_manager.clone(),
+        }),
+        SwapStateMachine::BobFunded(BobFunded {
+            remote_params: alice_params,
+            core_arbitrating_setup: core_arbitrating_setup.clone(),
+            swap_key_manager: bob_swap_key_manager,
+            acc_lock_height_lower_bound: 0,
+        }),
+        SwapStateMachine::AliceCoreArbitratingSetup(AliceCoreArbitratingSetup {
+            remote_params: bob_params.clone(),
+            core_arbitrating_setup: core_arbitrating_setup.clone(),
+            alice_cancel_signature,
+            adaptor_refund: WrappedEncryptedSignature(adaptor_refund.clone()),
+            swap_key_manager: alice_swap_key_manager.clone(),
+            alice_txs: alice_txs.clone(),
+            acc_lock_height_lower_bound: 0,
+        }),
+    ];
+    let post_lock_states = vec![
+        SwapStateMachine::AliceAccordantLock(AliceAccordantLock {
+            remote_params: bob_params.clone(),
+            core_arbitrating_setup,
+            alice_cancel_signature,
+            adaptor_refund: WrappedEncryptedSignature(adaptor_refund.clone()),
+            swap_key_manager: alice_swap_key_manager.clone(),
+            alice_txs: alice_txs.clone(),
+            acc_lock_height_lower_bound: 0,
+        }),
+        SwapStateMachine::AliceCanceled(AliceCanceled {
+            remote_params: bob_params,
+            adaptor_refund: WrappedEncryptedSignature(adaptor_refund),
+            swap_key_manager: alice_swap_key_manager,
+            alice_txs,
+            acc_lock_height_lower_bound: 0,
+        }),
+        SwapStateMachine::BobBuySweeping,
+        SwapStateMachine::BobCancelFinal,
+        SwapStateMachine::AliceBuyProcedureSignature,
+        SwapStateMachine::AliceRefundSweeping,
+        SwapStateMachine::SwapEnd(Outcome::SuccessSwap),
+        SwapStateMachine::SwapEnd(Outcome::FailureRefund),
+    ];
+    (pre_lock_states, post_lock_states)
+}
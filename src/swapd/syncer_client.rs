@@ -8,10 +8,10 @@ use crate::{
     bus::ServiceBus,
     service::{Endpoints, LogStyle, SwapDetails, SwapLogging},
     syncerd::{
-        Abort, AddressAddendum, BroadcastTransaction, BtcAddressAddendum, GetTx, SweepAddress,
-        SweepAddressAddendum, SweepBitcoinAddress, SweepMoneroAddress, TaskTarget,
-        TransactionBroadcasted, TxFilter, Txid, WatchAddress, WatchEstimateFee, WatchHeight,
-        WatchTransaction, XmrAddressAddendum,
+        Abort, AddressAddendum, BroadcastTransaction, BtcAddressAddendum, GetTx, Health,
+        SweepAddress, SweepAddressAddendum, SweepBitcoinAddress, SweepMoneroAddress, TaskTarget,
+        TipStatus, TransactionBroadcasted, TxFilter, Txid, WatchAddress, WatchEstimateFee,
+        WatchHeight, WatchTipStatus, WatchTransaction, XmrAddressAddendum,
     },
     Error,
 };
@@ -23,6 +23,7 @@ use farcaster_core::{
     transaction::TxLabel,
 };
 use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 
 use crate::{
     bus::sync::SyncMsg,
@@ -41,6 +42,7 @@ pub struct SyncerTasks {
     pub sweeping_addr: Option<TaskId>,
     pub txids: HashMap<TxLabel, bitcoin::Txid>,
     pub tasks: HashMap<TaskId, Task>,
+    pub blockchains: HashMap<TaskId, Blockchain>,
 }
 
 impl SyncerTasks {
@@ -48,6 +50,33 @@ impl SyncerTasks {
         self.counter += 1;
         TaskId(self.counter)
     }
+
+    /// Register a task along with the blockchain whose syncer serves it
+    pub fn insert(&mut self, id: TaskId, blockchain: Blockchain, task: Task) {
+        self.tasks.insert(id, task);
+        self.blockchains.insert(id, blockchain);
+    }
+
+    pub fn remove(&mut self, id: &TaskId) {
+        self.tasks.remove(id);
+        self.blockchains.remove(id);
+    }
+}
+
+/// Latest freshness and health of a syncer's view of the chain, timestamped at reception
+#[derive(Clone, Debug)]
+pub struct SyncerTipStatus {
+    pub tip_age: Option<Duration>,
+    pub health: Health,
+    pub received: SystemTime,
+}
+
+impl SyncerTipStatus {
+    /// Age of the syncer's view of the chain, None if the syncer never confirmed its tip
+    pub fn data_age(&self) -> Option<Duration> {
+        self.tip_age
+            .map(|tip_age| tip_age + self.received.elapsed().unwrap_or_default())
+    }
 }
 
 pub struct SyncerState {
@@ -58,7 +87,7 @@ pub struct SyncerState {
     pub bitcoin_height: u64,
     pub monero_height: u64,
     pub confirmation_bound: u32,
-    pub last_tx_event: HashMap<TxLabel, SyncMsg>,
+    pub last_tx_event: HashMap<TxLabel, (ServiceId, SyncMsg)>, // Latest event of each transaction with the syncer it came from, replayed on state changes
     pub network: farcaster_core::blockchain::Network,
     pub bitcoin_syncer: ServiceId,
     pub monero_syncer: ServiceId,
@@ -66,6 +95,10 @@ pub struct SyncerState {
     pub confirmations: HashMap<TxLabel, Option<u32>>,
     pub awaiting_funding: bool,
     pub broadcasted_txs: HashMap<TxLabel, bitcoin::Transaction>,
+    pub bitcoin_tip_status: Option<SyncerTipStatus>,
+    pub monero_tip_status: Option<SyncerTipStatus>,
+    pub bitcoin_max_data_age: Duration,
+    pub monero_max_data_age: Duration,
 }
 
 impl SwapLogging for SyncerState {
@@ -100,6 +133,12 @@ impl SyncerState {
         }
     }
     pub fn handle_height_change(&mut self, new_height: u64, blockchain: Blockchain) {
+        // a new height is proof the syncer just talked to its backend
+        *self.tip_status_mut(blockchain) = Some(SyncerTipStatus {
+            tip_age: Some(Duration::from_secs(0)),
+            health: Health::Healthy,
+            received: SystemTime::now(),
+        });
         let height = match blockchain {
             Blockchain::Bitcoin => &mut self.bitcoin_height,
             Blockchain::Monero => &mut self.monero_height,
@@ -111,7 +150,88 @@ impl SyncerState {
             self.log_warn("block height did not increment, maybe syncer sends multiple events");
         }
     }
+    pub fn handle_tip_status(&mut self, tip_status: &TipStatus, blockchain: Blockchain) {
+        if tip_status.health != Health::Healthy {
+            self.log_warn(format!(
+                "{} syncer reports unhealthy backend: {}",
+                blockchain, tip_status.health
+            ));
+        }
+        *self.tip_status_mut(blockchain) = Some(SyncerTipStatus {
+            tip_age: tip_status.tip_age.map(Duration::from_secs),
+            health: tip_status.health.clone(),
+            received: SystemTime::now(),
+        });
+    }
+
+    fn tip_status_mut(&mut self, blockchain: Blockchain) -> &mut Option<SyncerTipStatus> {
+        match blockchain {
+            Blockchain::Bitcoin => &mut self.bitcoin_tip_status,
+            Blockchain::Monero => &mut self.monero_tip_status,
+        }
+    }
+
+    /// Returns the reason why the data received from the syncer cannot be trusted to take a
+    /// race-sensitive decision, None if the data is fresh and the syncer healthy.
+    pub fn stale_data(&self, blockchain: Blockchain) -> Option<String> {
+        let (tip_status, max_data_age) = match blockchain {
+            Blockchain::Bitcoin => (&self.bitcoin_tip_status, self.bitcoin_max_data_age),
+            Blockchain::Monero => (&self.monero_tip_status, self.monero_max_data_age),
+        };
+        match tip_status {
+            None => Some(s!("no tip status received from the syncer yet")),
+            Some(SyncerTipStatus { health, .. }) if *health != Health::Healthy => {
+                Some(format!("syncer is unhealthy: {}", health))
+            }
+            Some(tip_status) => match tip_status.data_age() {
+                None => Some(s!("syncer never confirmed its tip")),
+                Some(age) if age > max_data_age => Some(format!(
+                    "syncer data is {}s old, above the {}s threshold",
+                    age.as_secs(),
+                    max_data_age.as_secs()
+                )),
+                Some(_) => None,
+            },
+        }
+    }
+
+    /// Returns the reason why the race-sensitive broadcast of the transaction must be withheld,
+    /// None if it can go out. The refund is never withheld: delaying it on stale syncer data risks
+    /// Bob getting punished, which is worse than broadcasting it.
+    pub fn withhold_reason(&self, tx_label: TxLabel, blockchain: Blockchain) -> Option<String> {
+        match tx_label {
+            TxLabel::Refund => None,
+            _ => self.stale_data(blockchain),
+        }
+    }
+
+    pub fn watch_tip_status(
+        &mut self,
+        endpoints: &mut Endpoints,
+        blockchain: Blockchain,
+    ) -> Result<(), Error> {
+        let swap_id = ServiceId::Swap(self.swap_id);
+        let task_id = self.tasks.new_taskid();
+        self.log_trace(format!("Watch tip status {}", blockchain));
+        let task = Task::WatchTipStatus(WatchTipStatus {
+            id: task_id,
+            lifetime: self.task_lifetime(blockchain),
+        });
+        self.tasks.insert(task_id, blockchain, task.clone());
+        endpoints.send_to(
+            ServiceBus::Sync,
+            swap_id,
+            match blockchain {
+                Blockchain::Bitcoin => self.bitcoin_syncer(),
+                Blockchain::Monero => self.monero_syncer(),
+            },
+            BusMsg::Sync(SyncMsg::Task(task)),
+        )?;
+        Ok(())
+    }
+
     pub fn abort_task(&mut self, id: TaskId) -> Task {
+        self.tasks.remove(&id);
         Task::Abort(Abort {
             task_target: TaskTarget::TaskId(id),
             respond: false,
@@ -129,7 +249,7 @@ impl SyncerState {
             id,
             lifetime: self.task_lifetime(Blockchain::Bitcoin),
         });
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Bitcoin, task.clone());
         task
     }
 
@@ -154,7 +274,7 @@ impl SyncerState {
             hash: txid.into(),
             confirmation_bound: self.confirmation_bound,
         });
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Bitcoin, task.clone());
         task
     }
     pub fn is_watched_tx(&self, tx_label: &TxLabel) -> bool {
@@ -182,14 +302,14 @@ impl SyncerState {
             hash,
             confirmation_bound: self.confirmation_bound,
         });
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Monero, task.clone());
         task
     }
     pub fn retrieve_tx_btc(&mut self, txid: Txid, tx_label: TxLabel) -> Task {
         let id = self.tasks.new_taskid();
         let task = Task::GetTx(GetTx { id, hash: txid });
         self.tasks.retrieving_txs.insert(id, tx_label);
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Bitcoin, task.clone());
         task
     }
     pub fn watch_addr_btc(&mut self, address: bitcoin::Address, tx_label: TxLabel) -> Task {
@@ -220,7 +340,7 @@ impl SyncerState {
             include_tx: true,
             filter,
         });
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Bitcoin, task.clone());
         task
     }
 
@@ -276,7 +396,7 @@ impl SyncerState {
             filter: TxFilter::Incoming,
         };
         let task = Task::WatchAddress(watch_addr);
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Monero, task.clone());
         task
     }
 
@@ -292,7 +412,7 @@ impl SyncerState {
             id: task_id,
             lifetime: self.task_lifetime(blockchain),
         });
-        self.tasks.tasks.insert(task_id, task.clone());
+        self.tasks.insert(task_id, blockchain, task.clone());
         endpoints.send_to(
             ServiceBus::Sync,
             swap_id,
@@ -316,7 +436,7 @@ impl SyncerState {
             retry,
         };
         let task = Task::SweepAddress(sweep_task);
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Bitcoin, task.clone());
         task
     }
 
@@ -331,7 +451,7 @@ impl SyncerState {
             retry,
         };
         let task = Task::SweepAddress(sweep_task);
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Monero, task.clone());
         task
     }
 
@@ -342,13 +462,13 @@ impl SyncerState {
             tx: bitcoin::consensus::serialize(&tx),
             broadcast_after_height: None,
         });
-        self.tasks.tasks.insert(id, task.clone());
+        self.tasks.insert(id, Blockchain::Bitcoin, task.clone());
         self.tasks.broadcasting_txs.insert(id, label);
         task
     }
     pub fn transaction_broadcasted(&mut self, event: &TransactionBroadcasted) {
         if let Some(txlabel) = self.tasks.broadcasting_txs.remove(&event.id) {
-            self.tasks.tasks.remove(&event.id);
+            self.tasks.remove(&event.id);
             if let Some(ref err) = event.error {
                 self.log_warn(format!(
                     "Error broadcasting {} transaction: {}",
@@ -391,6 +511,23 @@ impl SyncerState {
             .collect()
     }
 
+    /// Tasks still expected to be served by the syncer of the given blockchain, in the order they
+    /// were issued. Used to re-register them with a relaunched syncer.
+    pub fn live_tasks(&self, blockchain: Blockchain) -> Vec<Task> {
+        let mut tasks: Vec<(&TaskId, &Task)> = self
+            .tasks
+            .tasks
+            .iter()
+            .filter(|(id, _)| self.tasks.blockchains.get(id) == Some(&blockchain))
+            .filter(|(id, task)| match task {
+                Task::SweepAddress(_) => self.tasks.sweeping_addr == Some(**id),
+                _ => true,
+            })
+            .collect();
+        tasks.sort_by_key(|(id, _)| **id);
+        tasks.into_iter().map(|(_, task)| task.clone()).collect()
+    }
+
     pub fn acc_lock_watched(&self) -> bool {
         self.tasks
             .watched_addrs
@@ -489,3 +626,162 @@ impl SyncerState {
         self.confirmations.get(&label).copied().flatten()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use farcaster_core::blockchain::Network;
+    use farcaster_core::Uuid;
+
+    const BITCOIN_MAX_DATA_AGE: Duration = Duration::from_secs(600);
+    const MONERO_MAX_DATA_AGE: Duration = Duration::from_secs(1200);
+
+    fn syncer_state() -> SyncerState {
+        let network = Network::Testnet;
+        SyncerState {
+            swap_id: SwapId(Uuid::new()),
+            local_swap_role: SwapRole::Bob,
+            local_trade_role: TradeRole::Taker,
+            tasks: SyncerTasks {
+                counter: 0,
+                watched_txs: none!(),
+                final_txs: none!(),
+                watched_addrs: none!(),
+                retrieving_txs: none!(),
+                broadcasting_txs: none!(),
+                sweeping_addr: None,
+                txids: none!(),
+                tasks: none!(),
+                blockchains: none!(),
+            },
+            bitcoin_height: 0,
+            monero_height: 0,
+            confirmation_bound: 50000,
+            last_tx_event: none!(),
+            network,
+            bitcoin_syncer: ServiceId::Syncer(Blockchain::Bitcoin, network),
+            monero_syncer: ServiceId::Syncer(Blockchain::Monero, network),
+            xmr_addr_addendum: None,
+            confirmations: none!(),
+            awaiting_funding: false,
+            broadcasted_txs: none!(),
+            bitcoin_tip_status: None,
+            monero_tip_status: None,
+            bitcoin_max_data_age: BITCOIN_MAX_DATA_AGE,
+            monero_max_data_age: MONERO_MAX_DATA_AGE,
+        }
+    }
+
+    // A healthy tip status whose data age stays the given tip age: received in the future, no
+    // time elapses since its reception
+    fn tip_status(tip_age: Duration) -> Option<SyncerTipStatus> {
+        Some(SyncerTipStatus {
+            tip_age: Some(tip_age),
+            health: Health::Healthy,
+            received: SystemTime::now() + Duration::from_secs(3600),
+        })
+    }
+
+    #[test]
+    fn stale_tip_data() {
+        let mut state = syncer_state();
+        assert_eq!(
+            state.stale_data(Blockchain::Bitcoin).unwrap(),
+            "no tip status received from the syncer yet"
+        );
+
+        state.bitcoin_tip_status = tip_status(Duration::ZERO);
+        assert_eq!(state.stale_data(Blockchain::Bitcoin), None);
+        // up to the max data age included
+        state.bitcoin_tip_status = tip_status(BITCOIN_MAX_DATA_AGE);
+        assert_eq!(state.stale_data(Blockchain::Bitcoin), None);
+        state.bitcoin_tip_status = tip_status(BITCOIN_MAX_DATA_AGE + Duration::from_secs(1));
+        assert_eq!(
+            state.stale_data(Blockchain::Bitcoin).unwrap(),
+            "syncer data is 601s old, above the 600s threshold"
+        );
+
+        // the time elapsed since the reception of the tip status counts in the data age
+        state.bitcoin_tip_status = Some(SyncerTipStatus {
+            tip_age: Some(BITCOIN_MAX_DATA_AGE - Duration::from_secs(60)),
+            health: Health::Healthy,
+            received: SystemTime::now() - Duration::from_secs(120),
+        });
+        assert!(state.stale_data(Blockchain::Bitcoin).is_some());
+
+        state.bitcoin_tip_status = Some(SyncerTipStatus {
+            tip_age: None,
+            health: Health::Healthy,
+            received: SystemTime::now(),
+        });
+        assert_eq!(
+            state.stale_data(Blockchain::Bitcoin).unwrap(),
+            "syncer never confirmed its tip"
+        );
+
+        state.bitcoin_tip_status = Some(SyncerTipStatus {
+            tip_age: Some(Duration::ZERO),
+            health: Health::FaultyElectrum(s!("timeout")),
+            received: SystemTime::now(),
+        });
+        assert!(state
+            .stale_data(Blockchain::Bitcoin)
+            .unwrap()
+            .starts_with("syncer is unhealthy"));
+    }
+
+    #[test]
+    fn accordant_max_data_age() {
+        let mut state = syncer_state();
+        let tip_age = BITCOIN_MAX_DATA_AGE + Duration::from_secs(1);
+        state.bitcoin_tip_status = tip_status(tip_age);
+        state.monero_tip_status = tip_status(tip_age);
+        // the same data age is stale for bitcoin but fresh for monero
+        assert!(state.stale_data(Blockchain::Bitcoin).is_some());
+        assert_eq!(state.stale_data(Blockchain::Monero), None);
+
+        state.monero_tip_status = tip_status(MONERO_MAX_DATA_AGE);
+        assert_eq!(state.stale_data(Blockchain::Monero), None);
+        state.monero_tip_status = tip_status(MONERO_MAX_DATA_AGE + Duration::from_secs(1));
+        assert_eq!(
+            state.stale_data(Blockchain::Monero).unwrap(),
+            "syncer data is 1201s old, above the 1200s threshold"
+        );
+        // the bitcoin tip status does not make monero data fresh
+        state.bitcoin_tip_status = tip_status(Duration::ZERO);
+        assert!(state.stale_data(Blockchain::Monero).is_some());
+    }
+
+    #[test]
+    fn refund_never_withheld() {
+        let mut state = syncer_state();
+        for tx_label in [TxLabel::Buy, TxLabel::Cancel, TxLabel::Punish] {
+            assert!(state
+                .withhold_reason(tx_label, Blockchain::Bitcoin)
+                .is_some());
+        }
+        assert_eq!(
+            state.withhold_reason(TxLabel::Refund, Blockchain::Bitcoin),
+            None
+        );
+
+        state.bitcoin_tip_status = tip_status(BITCOIN_MAX_DATA_AGE + Duration::from_secs(1));
+        assert!(state
+            .withhold_reason(TxLabel::Buy, Blockchain::Bitcoin)
+            .is_some());
+        assert_eq!(
+            state.withhold_reason(TxLabel::Refund, Blockchain::Bitcoin),
+            None
+        );
+
+        state.bitcoin_tip_status = tip_status(Duration::ZERO);
+        assert_eq!(
+            state.withhold_reason(TxLabel::Buy, Blockchain::Bitcoin),
+            None
+        );
+        assert_eq!(
+            state.withhold_reason(TxLabel::Refund, Blockchain::Bitcoin),
+            None
+        );
+    }
+}
@@ -699,6 +699,13 @@ async fn run_syncerd_task_receiver(
                             state_guard.watch_height(task, syncerd_task.source).await;
                             drop(state_guard);
                         }
+                        Task::WatchTipStatus(task) => {
+                            let mut state_guard = state.lock().await;
+                            state_guard
+                                .watch_tip_status(task, syncerd_task.source)
+                                .await;
+                            drop(state_guard);
+                        }
                         Task::WatchTransaction(task) => {
                             debug!("received new watch tx task for txid: {}", task.hash);
                             let mut state_guard = state.lock().await;
@@ -845,6 +852,11 @@ fn height_polling(
                         "failed to spawn electrum rpc client {} in height polling: {}",
                         &electrum_server, err
                     );
+                    let mut state_guard = state.lock().await;
+                    state_guard
+                        .tip_check_failed(Health::FaultyElectrum(err.to_string()))
+                        .await;
+                    drop(state_guard);
                     // wait a bit before retrying the connection
                     tokio::time::sleep(std::time::Duration::from_secs(RETRY_TIMEOUT)).await;
                     continue;
@@ -860,6 +872,11 @@ fn height_polling(
             loop {
                 if let Err(err) = rpc.ping() {
                     error!("error ping electrum client in height polling: {}", err);
+                    let mut state_guard = state.lock().await;
+                    state_guard
+                        .tip_check_failed(Health::FaultyElectrum(err.to_string()))
+                        .await;
+                    drop(state_guard);
                     // break this loop and retry, since the electrum rpc client is probably
                     // broken
                     break;
@@ -868,12 +885,18 @@ fn height_polling(
                     Ok(blks) => blks,
                     Err(err) => {
                         error!("error polling bitcoin block height: {}", err);
+                        let mut state_guard = state.lock().await;
+                        state_guard
+                            .tip_check_failed(Health::FaultyElectrum(err.to_string()))
+                            .await;
+                        drop(state_guard);
                         // break this loop and retry, since the electrum rpc client is probably
                         // broken
                         break;
                     }
                 };
                 let mut state_guard = state.lock().await;
+                state_guard.tip_checked().await;
                 let mut block_change = false;
                 for block_notif in blocks.drain(..) {
                     block_change = state_guard
@@ -518,6 +518,12 @@ async fn run_syncerd_task_receiver(
                             let mut state_guard = state.lock().await;
                             state_guard.watch_height(task, syncerd_task.source).await;
                         }
+                        Task::WatchTipStatus(task) => {
+                            let mut state_guard = state.lock().await;
+                            state_guard
+                                .watch_tip_status(task, syncerd_task.source)
+                                .await;
+                        }
                         Task::WatchTransaction(task) => {
                             debug!("received new watch tx task: {}", task.hash);
                             let mut state_guard = state.lock().await;
@@ -702,14 +708,21 @@ fn height_polling(
     tokio::task::spawn(async move {
         let mut rpc = MoneroRpc::new(syncer_servers.monero_daemon, proxy_address);
         loop {
-            let block_notif = match rpc.check_block().await {
-                Ok(notif) => Some(notif),
-                Err(Error::Syncer(SyncerError::NoIncrementToHeight)) => None,
+            let (block_notif, health) = match rpc.check_block().await {
+                Ok(notif) => (Some(notif), Health::Healthy),
+                Err(Error::Syncer(SyncerError::NoIncrementToHeight)) => (None, Health::Healthy),
                 Err(err) => {
                     error!("error processing height polling: {}", err);
-                    None
+                    (None, Health::FaultyMoneroDaemon(err.to_string()))
                 }
             };
+            let mut state_guard = state.lock().await;
+            if health == Health::Healthy {
+                state_guard.tip_checked().await;
+            } else {
+                state_guard.tip_check_failed(health).await;
+            }
+            drop(state_guard);
             if let Some(block_notif) = block_notif {
                 let mut state_guard = state.lock().await;
                 state_guard
@@ -134,7 +134,7 @@ impl esb::Handler<ServiceBus> for Runtime {
 impl Runtime {
     fn handle_ctl(
         &mut self,
-        _endpoints: &mut Endpoints,
+        endpoints: &mut Endpoints,
         source: ServiceId,
         request: CtlMsg,
     ) -> Result<(), Error> {
@@ -151,6 +151,12 @@ impl Runtime {
             (CtlMsg::Terminate, ServiceId::Farcasterd) => {
                 // terminate all runtimes
                 info!("Received terminate on {}", self.identity());
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::Ctl(CtlMsg::Terminated),
+                )?;
                 std::process::exit(0);
             }
 
@@ -10,6 +10,7 @@ use crate::Error;
 use crate::ServiceId;
 use farcaster_core::blockchain::Blockchain;
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
 use tokio::sync::mpsc::Sender as TokioSender;
 
 use crate::service::LogStyle;
@@ -20,6 +21,9 @@ pub type BalanceServiceIdPair = (GetAddressBalance, ServiceId);
 pub type TransactionServiceIdPair = (BroadcastTransaction, ServiceId);
 pub type GetTxServiceIdPair = (GetTx, ServiceId);
 
+/// Interval at which the tip status is re-emitted to the watchers if nothing changed
+pub const TIP_STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Hash, Display)]
 #[display(Debug)]
 pub struct InternalId(u32);
@@ -47,6 +51,7 @@ pub struct SyncerState {
     tasks_sources: HashMap<InternalId, ServiceId>,
     watch_height: HashMap<InternalId, WatchHeight>,
     watch_fee_estimation: HashMap<InternalId, WatchEstimateFee>,
+    watch_tip_status: HashMap<InternalId, WatchTipStatus>,
     lifetimes: HashMap<u64, HashSet<InternalId>>,
     pub addresses: HashMap<InternalId, AddressTransactions>,
     pub transactions: HashMap<InternalId, WatchedTransaction>,
@@ -57,6 +62,9 @@ pub struct SyncerState {
     pub subscribed_addresses: HashSet<AddressAddendum>,
     pub fee_estimation: Option<FeeEstimations>,
     pub pending_broadcasts: HashSet<(BroadcastTransaction, ServiceId)>,
+    tip_checked: Option<SystemTime>,
+    tip_status_sent: Option<SystemTime>,
+    health: Health,
 }
 
 #[derive(Clone, Debug)]
@@ -93,6 +101,7 @@ impl SyncerState {
             tasks_sources: HashMap::new(),
             watch_height: HashMap::new(),
             watch_fee_estimation: HashMap::new(),
+            watch_tip_status: HashMap::new(),
             lifetimes: HashMap::new(),
             addresses: HashMap::new(),
             transactions: HashMap::new(),
@@ -104,6 +113,9 @@ impl SyncerState {
             subscribed_addresses: HashSet::new(),
             fee_estimation: None,
             pending_broadcasts: HashSet::new(),
+            tip_checked: None,
+            tip_status_sent: None,
+            health: Health::Healthy,
         }
     }
 
@@ -205,6 +217,33 @@ impl SyncerState {
                 .collect(),
         );
 
+        // check tip status tasks
+        let ids: Vec<(InternalId, TaskId)> = self
+            .watch_tip_status
+            .iter()
+            .filter_map(|(id, watch_tip_status)| {
+                if task_id.is_none() || watch_tip_status.id == task_id.unwrap() {
+                    Some((*id, watch_tip_status.id))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        aborted_ids.append(
+            &mut ids
+                .iter()
+                .filter_map(|(internal_id, found_task_id)| {
+                    if let Some(source_id) = self.tasks_sources.get(internal_id) {
+                        if *source_id == source {
+                            self.remove_tip_status(internal_id);
+                            return Some(*found_task_id);
+                        }
+                    }
+                    None
+                })
+                .collect(),
+        );
+
         // check sweep address tasks
         let ids: Vec<(InternalId, TaskId)> = self
             .sweep_addresses
@@ -295,6 +334,80 @@ impl SyncerState {
         }
     }
 
+    pub async fn watch_tip_status(&mut self, task: WatchTipStatus, source: ServiceId) {
+        // increment the count to use it as a unique internal id
+        self.task_count.increment();
+        if let Err(e) = self.add_lifetime(task.lifetime, self.task_count.into()) {
+            error!("{}", e);
+            return;
+        }
+        self.watch_tip_status
+            .insert(self.task_count.into(), task.clone());
+        self.tasks_sources
+            .insert(self.task_count.into(), source.clone());
+
+        // immediately let the watcher know where we stand
+        send_event(
+            &self.tx_event,
+            &mut vec![(Event::TipStatus(self.tip_status(task.id)), source)],
+        )
+        .await;
+    }
+
+    /// Register a successful round-trip with the blockchain backend, the tip we hold is current.
+    pub async fn tip_checked(&mut self) {
+        self.tip_checked = Some(SystemTime::now());
+        self.update_health(Health::Healthy).await;
+    }
+
+    /// Register a failed round-trip with the blockchain backend, the tip we hold is aging.
+    pub async fn tip_check_failed(&mut self, health: Health) {
+        self.update_health(health).await;
+    }
+
+    async fn update_health(&mut self, health: Health) {
+        let health_changed = self.health != health;
+        if health_changed {
+            warn!(
+                "{} syncer health changed from {} to {}",
+                self.blockchain, self.health, health
+            );
+            self.health = health;
+        }
+        let interval_elapsed = self
+            .tip_status_sent
+            .and_then(|sent| sent.elapsed().ok())
+            .map_or(true, |elapsed| elapsed >= TIP_STATUS_INTERVAL);
+        if health_changed || interval_elapsed {
+            self.emit_tip_status().await;
+        }
+    }
+
+    fn tip_status(&self, id: TaskId) -> TipStatus {
+        TipStatus {
+            id,
+            height: self.block_height,
+            tip_age: self
+                .tip_checked
+                .map(|checked| checked.elapsed().unwrap_or_default().as_secs()),
+            health: self.health.clone(),
+        }
+    }
+
+    async fn emit_tip_status(&mut self) {
+        self.tip_status_sent = Some(SystemTime::now());
+        for (id, task) in self.watch_tip_status.iter() {
+            send_event(
+                &self.tx_event,
+                &mut vec![(
+                    Event::TipStatus(self.tip_status(task.id)),
+                    self.tasks_sources.get(id).unwrap().clone(),
+                )],
+            )
+            .await;
+        }
+    }
+
     pub fn watch_address(&mut self, task: WatchAddress, source: ServiceId) {
         // increment the count to use it as a unique internal id
         self.task_count.increment();
@@ -736,6 +849,7 @@ impl SyncerState {
                 self.unseen_transactions.remove(task);
                 self.watch_height.remove(task);
                 self.watch_fee_estimation.remove(task);
+                self.watch_tip_status.remove(task);
                 self.sweep_addresses.remove(task);
                 self.tasks_sources.remove(task);
             }
@@ -784,6 +898,19 @@ impl SyncerState {
         self.tasks_sources.remove(id);
     }
 
+    fn remove_tip_status(&mut self, id: &InternalId) {
+        if let Some(watch_tip_status) = self.watch_tip_status.get(id) {
+            if let Some(ids) = self.lifetimes.get_mut(&watch_tip_status.lifetime) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.lifetimes.remove(&watch_tip_status.lifetime);
+                }
+            }
+        }
+        self.watch_tip_status.remove(id);
+        self.tasks_sources.remove(id);
+    }
+
     fn remove_sweep_address(&mut self, id: &InternalId) {
         if let Some(sweep_address) = self.sweep_addresses.get(id) {
             if let Some(ids) = self.lifetimes.get_mut(&sweep_address.lifetime) {
@@ -267,6 +267,18 @@ pub struct HealthCheck {
     pub id: TaskId,
 }
 
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(Debug)]
+pub struct WatchTipStatus {
+    pub id: TaskId,
+    pub lifetime: u64,
+}
+
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
 #[cfg_attr(
     feature = "serde",
@@ -310,6 +322,8 @@ pub enum Task {
     HealthCheck(HealthCheck),
     #[display("Terminate")]
     Terminate,
+    #[display("{0}")]
+    WatchTipStatus(WatchTipStatus),
 }
 
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
@@ -498,6 +512,17 @@ pub enum Health {
     ConfigUnavailable(String),
 }
 
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
+#[display(Debug)]
+pub struct TipStatus {
+    pub id: TaskId,
+    pub height: u64,
+    /// Seconds elapsed since the syncer last confirmed its tip against the blockchain backend,
+    /// None if it never managed to
+    pub tip_age: Option<u64>,
+    pub health: Health,
+}
+
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
 #[display(Debug)]
 // the sats per kvB is because we need u64 for Eq, PartialEq and Hash
@@ -540,4 +565,8 @@ pub enum Event {
     HealthResult(HealthResult),
     #[display("{0}")]
     AddressBalance(AddressBalance),
+    /// Periodically notify the daemon of the age of the syncer's view of the chain tip and of
+    /// the health of its backend.
+    #[display("{0}")]
+    TipStatus(TipStatus),
 }
@@ -6,12 +6,12 @@
 
 use crate::bus::{
     ctl::{CtlMsg, GetKeys, Keys, SwapKeys, Token, WrappedKeyManager},
-    BusMsg, ServiceBus,
+    BusMsg, Failure, ServiceBus,
 };
 
 use crate::service::Endpoints;
 use crate::walletd::NodeSecrets;
-use crate::{CtlServer, Error, Service, ServiceConfig, ServiceId};
+use crate::{CtlServer, Error, LogStyle, Service, ServiceConfig, ServiceId};
 
 use farcaster_core::swap::btcxmr::KeyManager;
 use microservices::esb::{self, Handler};
@@ -81,9 +81,20 @@ impl Runtime {
                 debug!("Received Hello from {}", source);
             }
 
+            CtlMsg::Terminate if source == ServiceId::Farcasterd => {
+                info!("Terminating {}", self.identity().label());
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::Ctl(CtlMsg::Terminated),
+                )?;
+                std::process::exit(0);
+            }
+
             CtlMsg::CreateSwapKeys(deal, wallet_token) => {
                 if wallet_token != self.wallet_token {
-                    return Err(Error::InvalidToken);
+                    return self.reject_invalid_token(endpoints, source);
                 }
                 let wallet_index = self.node_secrets.increment_wallet_counter();
                 let key_manager = KeyManager::new(self.node_secrets.wallet_seed, wallet_index)?;
@@ -101,7 +112,7 @@ impl Runtime {
 
             CtlMsg::GetKeys(GetKeys(wallet_token)) => {
                 if wallet_token != self.wallet_token {
-                    return Err(Error::InvalidToken);
+                    return self.reject_invalid_token(endpoints, source);
                 }
                 trace!("sent Secret request to farcasterd");
                 endpoints.send_to(
@@ -125,4 +136,15 @@ impl Runtime {
 
         Ok(())
     }
+
+    /// Tell the source its wallet token is invalid before failing with the same error
+    fn reject_invalid_token(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+    ) -> Result<(), Error> {
+        let err = Error::InvalidToken;
+        self.send_client_ctl(endpoints, source, CtlMsg::Failure(Failure::from(&err)))?;
+        Err(err)
+    }
 }
@@ -3,12 +3,25 @@ extern crate log;
 
 use crate::farcaster::{
     farcaster_client::FarcasterClient, AbortSwapRequest, CheckpointSelector, CheckpointsRequest,
-    DealInfoRequest, DealSelector, InfoResponse, ListDealsRequest, MakeRequest,
-    NeedsFundingRequest, NetworkSelector, PeersRequest, ProgressRequest, RestoreCheckpointRequest,
-    RevokeDealRequest, SwapInfoRequest, SweepAddressRequest, TakeRequest,
+    DealInfoRequest, DealSelector, InfoResponse, ListBansRequest, ListDealTemplatesRequest,
+    ListDealsRequest, ListListensRequest, ListSwapsRequest, MakeRequest, NeedsFundingRequest,
+    NetworkSelector, PeersRequest, ProgressRequest, RestoreCheckpointRequest, RevokeDealRequest,
+    SwapInfoRequest, SweepAddressRequest, TakeRequest,
 };
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
 use bitcoincore_rpc::RpcApi;
+use clap::Parser;
 use farcaster::{InfoRequest, MakeResponse, NeedsFundingResponse};
+use farcaster_core::bitcoin::fee::SatPerKvB;
+use farcaster_core::bitcoin::timelock::CSVTimelock;
+use farcaster_core::blockchain::{Blockchain, FeeStrategy, Network};
+use farcaster_core::role::SwapRole;
+use farcaster_core::swap::btcxmr::DealParameters;
+use farcaster_core::Uuid;
+use farcaster_node::cli::{
+    ApprovalsCommand, Command, MaintenanceCommand, Opts, TemplatesCommand, WatchtowerCommand,
+};
+use internet2::addr::InetSocketAddr;
 use std::{str::FromStr, sync::Arc, time};
 use tonic::transport::Endpoint;
 use utils::{config, fc::*};
@@ -56,6 +69,26 @@ async fn grpc_server_functional_test() {
     let response = farcaster_client_1.peers(request).await;
     assert_eq!(response.unwrap().into_inner().id, 1);
 
+    // Test list swaps
+    let request = tonic::Request::new(ListSwapsRequest { id: 30 });
+    let response = farcaster_client_1.list_swaps(request).await;
+    assert_eq!(response.unwrap().into_inner().id, 30);
+
+    // Test list listens
+    let request = tonic::Request::new(ListListensRequest { id: 31 });
+    let response = farcaster_client_1.list_listens(request).await;
+    assert_eq!(response.unwrap().into_inner().id, 31);
+
+    // Test list bans
+    let request = tonic::Request::new(ListBansRequest { id: 32 });
+    let response = farcaster_client_1.list_bans(request).await;
+    assert_eq!(response.unwrap().into_inner().id, 32);
+
+    // Test list deal templates
+    let request = tonic::Request::new(ListDealTemplatesRequest { id: 33 });
+    let response = farcaster_client_1.list_deal_templates(request).await;
+    assert_eq!(response.unwrap().into_inner().id, 33);
+
     // Test list checkpoints
     let request = tonic::Request::new(CheckpointsRequest {
         id: 2,
@@ -88,6 +121,7 @@ async fn grpc_server_functional_test() {
         maker_role: farcaster::SwapRole::Bob.into(),
         public_ip_addr: "127.0.0.1".to_string(),
         public_port: 7067,
+        lifetime: 0,
     };
     let request = tonic::Request::new(make_request.clone());
     let response = farcaster_client_1.make(request).await;
@@ -180,6 +214,8 @@ async fn grpc_server_functional_test() {
         id: 13,
         source_address: address.to_string(),
         destination_address: btc_address.to_string(),
+        minimum_balance: 0,
+        monero_from_height: None,
     });
     let response = farcaster_client_1.sweep_address(request).await;
     assert_eq!(response.unwrap().into_inner().id, 13);
@@ -287,3 +323,209 @@ async fn retry_until_progress(
         tokio::time::sleep(time::Duration::from_secs(1)).await;
     }
 }
+
+/// The rpc of a swap-cli command, None if the command only acts on the client side. The match is
+/// exhaustive, a new command does not build without its rpc.
+fn cli_command_rpc(command: &Command) -> Option<&'static str> {
+    match command {
+        Command::Info { .. } => Some("Info"),
+        Command::Peers => Some("Peers"),
+        Command::ListSwaps => Some("ListSwaps"),
+        Command::ListDeals { .. } => Some("ListDeals"),
+        Command::ListRemoteDeals => Some("ListRemoteDeals"),
+        Command::QueryRegistry { .. } => Some("QueryRegistry"),
+        Command::DealInfo { .. } => Some("DealInfo"),
+        Command::ListListens => Some("ListListens"),
+        Command::ListTasks { .. } => Some("ListTasks"),
+        Command::ListCheckpoints { .. } => Some("Checkpoints"),
+        Command::HealthCheck { .. } => Some("HealthCheck"),
+        Command::RestoreCheckpoint { .. } => Some("RestoreCheckpoint"),
+        Command::Connect { .. } => Some("ConnectSwap"),
+        Command::Make { .. } => Some("Make"),
+        Command::Take { .. } => Some("Take"),
+        Command::Templates { command } => Some(match command {
+            TemplatesCommand::Add { .. } => "AddDealTemplate",
+            TemplatesCommand::List => "ListDealTemplates",
+            TemplatesCommand::Remove { .. } => "RemoveDealTemplate",
+        }),
+        Command::RequestQuote { .. } => Some("RequestQuote"),
+        Command::ReferencePrice => Some("ReferencePrice"),
+        Command::MarketMaker => Some("MarketMaker"),
+        Command::RevokeDeal { .. } => Some("RevokeDeal"),
+        Command::AbortSwap { .. } => Some("AbortSwap"),
+        Command::Ban { .. } => Some("Ban"),
+        Command::Unban { .. } => Some("Unban"),
+        Command::ListBans => Some("ListBans"),
+        Command::Approvals { command } => Some(match command {
+            ApprovalsCommand::List => "ListApprovals",
+            ApprovalsCommand::Accept { .. } => "AcceptTaker",
+            ApprovalsCommand::Reject { .. } => "RejectTaker",
+        }),
+        Command::Watchtower { command } => Some(match command {
+            WatchtowerCommand::Register { .. } => "WatchtowerRegister",
+            WatchtowerCommand::Revoke { .. } => "WatchtowerRevoke",
+        }),
+        Command::Maintenance { command } => Some(match command {
+            MaintenanceCommand::On | MaintenanceCommand::Off => "Maintenance",
+        }),
+        Command::Shutdown { .. } => Some("Shutdown"),
+        Command::Progress { .. } => Some("Progress"),
+        Command::NeedsFunding { .. } => Some("NeedsFunding"),
+        Command::ListFundingAddresses { .. } => Some("FundingAddresses"),
+        Command::SweepBitcoinAddress { .. } => Some("SweepAddress"),
+        Command::SweepMoneroAddress { .. } => Some("SweepAddress"),
+        Command::GetBalance { .. } => Some("GetBalance"),
+        Command::Dashboard { .. } => None,
+        Command::Completion { .. } => None,
+    }
+}
+
+/// The names of the swap-cli commands without subcommands, nested ones joined with a space
+fn leaf_commands(command: &clap::Command, prefix: &str, names: &mut Vec<String>) {
+    for subcommand in command.get_subcommands() {
+        let name = format!("{}{}", prefix, subcommand.get_name());
+        if subcommand.has_subcommands() {
+            leaf_commands(subcommand, &format!("{} ", name), names);
+        } else {
+            names.push(name);
+        }
+    }
+}
+
+#[test]
+fn every_cli_command_has_an_rpc() {
+    let rpcs: Vec<&str> = include_str!("../src/grpcd/proto/farcaster.proto")
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("rpc "))
+        .filter_map(|rpc| rpc.split('(').next())
+        .collect();
+
+    let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+    let node_id = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key).to_string();
+    let node_addr = format!("{}@127.0.0.1:7067", node_id);
+    let deal = DealParameters {
+        uuid: Uuid::new().into(),
+        network: Network::Testnet,
+        arbitrating_blockchain: Blockchain::Bitcoin,
+        accordant_blockchain: Blockchain::Monero,
+        arbitrating_amount: bitcoin::Amount::from_sat(100_000),
+        accordant_amount: monero::Amount::from_pico(1_000_000_000_000),
+        cancel_timelock: CSVTimelock::new(4),
+        punish_timelock: CSVTimelock::new(5),
+        fee_strategy: FeeStrategy::Fixed(SatPerKvB::from_sat(1_000)),
+        maker_role: SwapRole::Bob,
+    }
+    .to_v1(
+        PublicKey::from_secret_key(&Secp256k1::new(), &secret_key),
+        InetSocketAddr::from_str("127.0.0.1:7067").unwrap(),
+    )
+    .to_string();
+    let id = Uuid::new().to_string();
+    let btc_addr = "tb1q4gj53tuew3e6u4a32kdtle2q72su8te39dpceq";
+    let xmr_addr = "55LTR8KniP4LQGJSPtbYDacR7dz8RBFnsfAKMaMuwUNYX6aQbBcovzDPyrQF9KXF9tVU6Xk3K8no1BywnJX6GvZX8yJsXvt";
+
+    // One invocation of every swap-cli command
+    let invocations: Vec<Vec<&str>> = vec![
+        vec!["info"],
+        vec!["peers"],
+        vec!["list-swaps"],
+        vec!["list-deals"],
+        vec!["list-remote-deals"],
+        vec!["query-registry"],
+        vec!["deal-info", &deal],
+        vec!["list-listens"],
+        vec!["list-tasks", "bitcoin", "testnet"],
+        vec!["list-checkpoints"],
+        vec!["health-check"],
+        vec!["restore-checkpoint", &id],
+        vec!["connect", &id],
+        vec![
+            "make",
+            "--btc-addr",
+            btc_addr,
+            "--xmr-addr",
+            xmr_addr,
+            "--btc-amount",
+            "1 BTC",
+            "--xmr-amount",
+            "100 XMR",
+        ],
+        vec![
+            "take",
+            "--btc-addr",
+            btc_addr,
+            "--xmr-addr",
+            xmr_addr,
+            "--deal",
+            &deal,
+        ],
+        vec![
+            "templates",
+            "add",
+            "--btc-addr",
+            btc_addr,
+            "--xmr-addr",
+            xmr_addr,
+            "--min-btc-amount",
+            "0.1 BTC",
+            "--max-btc-amount",
+            "1 BTC",
+            "--price",
+            "0.01 BTC",
+        ],
+        vec!["templates", "list"],
+        vec!["templates", "remove", &id],
+        vec!["request-quote", &node_addr, "--btc-amount", "1 BTC"],
+        vec!["reference-price"],
+        vec!["market-maker"],
+        vec!["revoke-deal", &deal],
+        vec!["abort-swap", &id],
+        vec!["ban", &node_id],
+        vec!["unban", &node_id],
+        vec!["list-bans"],
+        vec!["approvals", "list"],
+        vec!["approvals", "accept", &id],
+        vec!["approvals", "reject", &id],
+        vec!["watchtower", "register", &id],
+        vec!["watchtower", "revoke", &id],
+        vec!["maintenance", "on"],
+        vec!["maintenance", "off"],
+        vec!["shutdown"],
+        vec!["progress", &id],
+        vec!["needs-funding", "bitcoin"],
+        vec!["list-funding-addresses", "monero"],
+        vec!["sweep-bitcoin-address", btc_addr, btc_addr],
+        vec!["sweep-monero-address", xmr_addr, xmr_addr],
+        vec!["get-balance", btc_addr],
+        vec!["dashboard"],
+        vec!["completion", "bash"],
+    ];
+
+    let mut commands = vec![];
+    leaf_commands(&<Opts as clap::IntoApp>::command(), "", &mut commands);
+    let mut invoked = vec![];
+    for args in invocations.iter() {
+        let opts = Opts::try_parse_from(std::iter::once("swap-cli").chain(args.iter().copied()))
+            .unwrap_or_else(|err| panic!("swap-cli {} does not parse: {}", args.join(" "), err));
+        let name = commands
+            .iter()
+            .find(|command| args.join(" ").starts_with(command.as_str()))
+            .unwrap_or_else(|| panic!("swap-cli {} is not a command", args.join(" ")));
+        if let Some(rpc) = cli_command_rpc(&opts.command) {
+            assert!(
+                rpcs.contains(&rpc),
+                "rpc {} of swap-cli {} is missing in farcaster.proto",
+                rpc,
+                name
+            );
+        }
+        invoked.push(name);
+    }
+    for command in commands.iter() {
+        assert!(
+            invoked.contains(&command),
+            "swap-cli {} is not checked against farcaster.proto",
+            command
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::net::TcpListener;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+use farcaster_core::bitcoin::fee::SatPerKvB;
+use farcaster_core::bitcoin::timelock::CSVTimelock;
+use farcaster_core::blockchain::{Blockchain, FeeStrategy, Network};
+use farcaster_core::role::SwapRole;
+use farcaster_core::swap::btcxmr::{Deal, DealParameters};
+use farcaster_core::Uuid;
+use farcaster_node::bus::info::RegistryQuery;
+use farcaster_node::registry::{
+    self, timestamp_millis, RegistryAction, RegistryClient, RegistryDeal, REGISTRY_MAX_REQUEST_AGE,
+};
+use internet2::addr::InetSocketAddr;
+
+fn launch_registry() -> String {
+    let listen = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    thread::spawn(move || registry::server::run(listen));
+    let url = format!("http://{}", listen);
+    let client = RegistryClient::new(&url).unwrap();
+    for _ in 0..50 {
+        if client.query(&query()).is_ok() {
+            return url;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    panic!("deal registry did not start on {}", listen);
+}
+
+fn new_deal(secret_key: &SecretKey) -> Deal {
+    DealParameters {
+        uuid: Uuid::new().into(),
+        network: Network::Local,
+        arbitrating_blockchain: Blockchain::Bitcoin,
+        accordant_blockchain: Blockchain::Monero,
+        arbitrating_amount: bitcoin::Amount::from_sat(100_000),
+        accordant_amount: monero::Amount::from_pico(1_000_000_000_000),
+        cancel_timelock: CSVTimelock::new(4),
+        punish_timelock: CSVTimelock::new(5),
+        fee_strategy: FeeStrategy::Fixed(SatPerKvB::from_sat(1_000)),
+        maker_role: SwapRole::Bob,
+    }
+    .to_v1(
+        PublicKey::from_secret_key(&Secp256k1::new(), secret_key),
+        InetSocketAddr::from_str("127.0.0.1:7067").unwrap(),
+    )
+}
+
+fn query() -> RegistryQuery {
+    RegistryQuery {
+        network: Network::Local,
+        min_amount: None,
+        max_amount: None,
+        min_price: None,
+        max_price: None,
+    }
+}
+
+fn listed(client: &RegistryClient) -> Vec<Deal> {
+    client
+        .query(&query())
+        .unwrap()
+        .into_iter()
+        .map(|(deal, _)| deal)
+        .collect()
+}
+
+#[test]
+fn registry_publish_query_revoke() {
+    let url = launch_registry();
+    let client = RegistryClient::new(&url).unwrap();
+    let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+    let deal = new_deal(&secret_key);
+
+    let publication = RegistryDeal::sign(&deal, RegistryAction::Publish, &secret_key).unwrap();
+    client.publish(publication.clone()).unwrap();
+    assert_eq!(listed(&client), vec![deal.clone()]);
+
+    let revocation = RegistryDeal::sign(&deal, RegistryAction::Revoke, &secret_key).unwrap();
+    // a publication signature cannot be used to revoke the deal
+    assert!(client.revoke(&deal, publication.clone()).is_err());
+    client.revoke(&deal, revocation).unwrap();
+    assert!(listed(&client).is_empty());
+
+    // the publication cannot be replayed once the deal is revoked
+    assert!(client.publish(publication).is_err());
+    assert!(listed(&client).is_empty());
+}
+
+#[test]
+fn registry_rejects_foreign_and_stale_requests() {
+    let url = launch_registry();
+    let client = RegistryClient::new(&url).unwrap();
+    let secret_key = SecretKey::from_slice(&[2; 32]).unwrap();
+    let deal = new_deal(&secret_key);
+
+    // signed by another key
+    let other_key = SecretKey::from_slice(&[3; 32]).unwrap();
+    let mut forged = RegistryDeal::sign(&deal, RegistryAction::Publish, &secret_key).unwrap();
+    forged.signature =
+        RegistryDeal::sign(&new_deal(&other_key), RegistryAction::Publish, &other_key)
+            .unwrap()
+            .signature;
+    assert!(client.publish(forged).is_err());
+
+    // signed too long ago
+    let outdated = timestamp_millis() - 2 * REGISTRY_MAX_REQUEST_AGE.as_millis() as u64;
+    let stale =
+        RegistryDeal::sign_at(&deal, RegistryAction::Publish, outdated, &secret_key).unwrap();
+    assert!(client.publish(stale).is_err());
+    assert!(listed(&client).is_empty());
+
+    // an older request than the last accepted one is refused
+    let now = timestamp_millis();
+    let earlier =
+        RegistryDeal::sign_at(&deal, RegistryAction::Publish, now - 1_000, &secret_key).unwrap();
+    let later = RegistryDeal::sign_at(&deal, RegistryAction::Revoke, now, &secret_key).unwrap();
+    client
+        .publish(
+            RegistryDeal::sign_at(&deal, RegistryAction::Publish, now - 500, &secret_key).unwrap(),
+        )
+        .unwrap();
+    assert!(client.publish(earlier).is_err());
+    client.revoke(&deal, later).unwrap();
+    assert!(listed(&client).is_empty());
+}
+
+#[test]
+fn registry_rejects_large_requests() {
+    let url = launch_registry();
+    let body = vec![b' '; registry::server::MAX_REQUEST_BODY_SIZE + 1];
+    let res = reqwest::blocking::Client::new()
+        .post(format!("{}/deals", url))
+        .body(body)
+        .send()
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[test]
+fn registry_deal_freshness() {
+    let secret_key = SecretKey::from_slice(&[4; 32]).unwrap();
+    let deal = new_deal(&secret_key);
+    let max_age = REGISTRY_MAX_REQUEST_AGE.as_millis() as u64;
+    let now = timestamp_millis();
+    let signed = RegistryDeal::sign_at(&deal, RegistryAction::Publish, now, &secret_key).unwrap();
+    assert!(signed.is_fresh(now));
+    assert!(signed.is_fresh(now + max_age));
+    assert!(!signed.is_fresh(now + max_age + 1));
+    assert!(!signed.is_fresh(now - max_age - 1));
+    assert_eq!(signed.verify(RegistryAction::Publish).unwrap(), deal);
+    assert!(signed.verify(RegistryAction::Revoke).is_err());
+}
@@ -486,6 +486,41 @@ async fn swap_bob_maker_refund_alice_does_not_fund() {
     kill_all();
 }
 
+#[tokio::test]
+#[timeout(600000)]
+#[ignore]
+async fn swap_bob_maker_cooperative_refund() {
+    setup_logging();
+    let execution_mutex = Arc::new(Mutex::new(0));
+    let bitcoin_rpc = Arc::new(bitcoin_setup());
+    let (_monero_regtest, monero_wallet) = monero_setup().await;
+
+    let (_, data_dir_maker, _, data_dir_taker) = launch_farcasterd_pair().await;
+
+    let (_xmr_dest_wallet_name, bitcoin_address, swap_id) = make_and_take_deal(
+        data_dir_maker.clone(),
+        data_dir_taker.clone(),
+        "Bob".to_string(),
+        Arc::clone(&bitcoin_rpc),
+        Arc::clone(&monero_wallet),
+        bitcoin::Amount::from_str("1 BTC").unwrap(),
+        monero::Amount::from_str_with_denomination("1 XMR").unwrap(),
+    )
+    .await;
+
+    run_cooperative_refund_swap(
+        swap_id,
+        data_dir_taker,
+        data_dir_maker,
+        Arc::clone(&bitcoin_rpc),
+        bitcoin_address,
+        execution_mutex,
+    )
+    .await;
+
+    kill_all();
+}
+
 #[tokio::test]
 #[timeout(600000)]
 #[ignore]
@@ -1873,6 +1908,98 @@ async fn run_refund_swap_alice_does_not_fund(
     drop(lock);
 }
 
+async fn run_cooperative_refund_swap(
+    swap_id: SwapId,
+    data_dir_alice: Vec<String>,
+    data_dir_bob: Vec<String>,
+    bitcoin_rpc: Arc<bitcoincore_rpc::Client>,
+    funding_btc_address: bitcoin::Address,
+    execution_mutex: Arc<Mutex<u8>>,
+) {
+    let cli_alice_progress_args: Vec<String> = progress_args(data_dir_alice.clone(), swap_id);
+    let cli_bob_progress_args: Vec<String> = progress_args(data_dir_bob.clone(), swap_id);
+    let cli_bob_needs_funding_args: Vec<String> =
+        needs_funding_args(data_dir_bob.clone(), "bitcoin".to_string());
+    let cli_alice_needs_funding_args: Vec<String> =
+        needs_funding_args(data_dir_alice.clone(), "monero".to_string());
+
+    bitcoin_rpc
+        .generate_to_address(1, &reusable_btc_address())
+        .unwrap();
+
+    // run until bob has the btc funding address
+    let (address, amount) =
+        retry_until_bitcoin_funding_address(swap_id, cli_bob_needs_funding_args.clone()).await;
+
+    // fund the bitcoin address
+    let lock = execution_mutex.lock().await;
+    bitcoin_rpc
+        .send_to_address(&address, amount, None, None, None, None, None, None)
+        .unwrap();
+
+    // run until Bob Refund Procedure Signatures is received
+    info!("waiting for Bob Refund Procedure Signatures");
+    retry_until_state_transition(
+        cli_bob_progress_args.clone(),
+        "Bob Refund Procedure Signatures".to_string(),
+    )
+    .await;
+
+    tokio::time::sleep(time::Duration::from_secs(10)).await;
+
+    // generate some bitcoin blocks to finalize the bitcoin arb lock tx
+    bitcoin_rpc
+        .generate_to_address(3, &reusable_btc_address())
+        .unwrap();
+
+    // run until the alice has the monero funding address, but do not fund it
+    retry_until_monero_funding_address(swap_id, cli_alice_needs_funding_args.clone()).await;
+
+    // both parties agree to refund right away, without waiting for the cancel timelock
+    abort_swap(swap_id, data_dir_bob);
+    abort_swap(swap_id, data_dir_alice);
+
+    retry_until_state_transition(
+        cli_alice_progress_args.clone(),
+        "Alice Cooperative Refund".to_string(),
+    )
+    .await;
+    retry_until_state_transition(
+        cli_bob_progress_args.clone(),
+        "Bob Cooperative Refund".to_string(),
+    )
+    .await;
+
+    // the funding info for monero is cleared once Alice agreed
+    retry_until_funding_info_cleared(swap_id, cli_alice_needs_funding_args.clone()).await;
+
+    // Wait a bit for the cooperative refund transaction to be broadcasted
+    tokio::time::sleep(time::Duration::from_secs(20)).await;
+
+    // fewer blocks than the cancel timelock, the refund does not go through cancel
+    bitcoin_rpc
+        .generate_to_address(3, &reusable_btc_address())
+        .unwrap();
+
+    // run until FailureRefund is received
+    retry_until_finish_transition(cli_bob_progress_args.clone(), "Failure Refund".to_string())
+        .await;
+
+    // run until FailureRefund is received
+    retry_until_finish_transition(
+        cli_alice_progress_args.clone(),
+        "Failure Refund".to_string(),
+    )
+    .await;
+
+    // check that btc was received in the destination address
+    let balance = bitcoin_rpc
+        .get_received_by_address(&funding_btc_address, None)
+        .unwrap();
+    assert!(balance.as_sat() > 90000000);
+    drop(lock);
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn run_punish_swap_kill_bob_before_monero_funding(
     swap_id: SwapId,